@@ -1,9 +1,16 @@
 //! Analysis data structures
 
+use std::collections::HashMap;
 use std::ffi::CStr;
 use std::slice::from_raw_parts;
 
 use crate::shuriken;
+use crate::dex_file::{ self, DexFile, EncodedMethod };
+use crate::call_sites::DynamicXref;
+use crate::exceptions::{ self, CatchHandler, ThrownException, TryRange };
+use crate::hierarchy::{ ClassHierarchy, HierarchyMethod };
+use crate::nesting::{ ClassNesting, NestingTree };
+use crate::annotations::Annotation;
 use crate::disassembler::DvmInstruction;
 use crate::dvm_access_flags::{ DvmAccessFlag, DvmAccessFlagType };
 
@@ -74,6 +81,15 @@ impl DvmClassMethodIdx {
         }
     }
 
+    /// Create a cross-ref to a method, not reported by Shuriken
+    pub(crate) fn new(class: &str, method: &str, idx: u64) -> Self {
+        Self {
+            class: class.to_string(),
+            method: method.to_string(),
+            idx
+        }
+    }
+
     /// Return a reference to the class of the struct
     pub fn class(&self) -> &str {
         &self.class
@@ -345,6 +361,8 @@ pub struct DvmBasicBlock {
     catch_block: bool,
     /// String value of the handler type
     handler_type: String,
+    /// Handlers starting at the block
+    catch_handlers: Vec<CatchHandler>,
     /// Name of the basic block
     name: String,
     /// Whole representation of a basic block in string format
@@ -367,6 +385,7 @@ impl DvmBasicBlock {
         // reference which leads to a segmentation fault. Need to open an issue
         // upstream to investigate if this is a bug in Shuriken or something else.
         // Issue ref: https://github.com/Shuriken-Group/Shuriken-Analyzer/issues/153
+        // In the meantime, the handler type is resolved from the DEX file.
         //
         // let handler_type = unsafe {
         //     CStr::from_ptr(ptr.handler_type)
@@ -396,11 +415,41 @@ impl DvmBasicBlock {
             try_block,
             catch_block,
             handler_type,
+            catch_handlers: Vec::new(),
             name,
             block_string
         }
     }
 
+    /// Return the address of the first instruction of the block
+    fn start_address(&self) -> Option<u64> {
+        self.instructions.first().map(DvmInstruction::address)
+    }
+
+    /// Record the handlers starting at the block
+    fn set_catch_handlers(&mut self, try_ranges: &[TryRange]) {
+        let Some(start_address) = self.start_address() else {
+            return;
+        };
+
+        self.catch_handlers = try_ranges
+            .iter()
+            .flat_map(|try_range| try_range.handlers())
+            .filter(|handler| handler.address() == start_address)
+            .fold(Vec::new(), |mut handlers, handler| {
+                if !handlers.contains(handler) {
+                    handlers.push(handler.clone());
+                }
+                handlers
+            });
+
+        if let Some(handler) = self.catch_handlers.first() {
+            self.handler_type = handler.exception_type()
+                .unwrap_or(exceptions::THROWABLE)
+                .to_string();
+        }
+    }
+
     /// Return the number of instructions in the block
     pub fn n_of_instructions(&self) -> usize {
         self.n_of_instructions
@@ -422,10 +471,20 @@ impl DvmBasicBlock {
     }
 
     /// Return a reference to the string value of the handler type
+    ///
+    /// This is the descriptor of the exception caught by the first handler
+    /// starting at the block, `Ljava/lang/Throwable;` for a catch-all handler,
+    /// and empty for other blocks. Only available for methods analyzed through
+    /// a `DexContext` or an `ApkContext`.
     pub fn handler_type(&self) -> &str {
         &self.handler_type
     }
 
+    /// Return a reference to the handlers starting at the block
+    pub fn catch_handlers(&self) -> &[CatchHandler] {
+        &self.catch_handlers
+    }
+
     /// Return a reference to the name of the basic block
     pub fn name(&self) -> &str {
         &self.name
@@ -473,6 +532,13 @@ impl DvmBasicBlocks {
     pub fn blocks(&self) -> &[DvmBasicBlock] {
         &self.blocks
     }
+
+    /// Get the block starting at a given address
+    pub fn block_at(&self, address: u64) -> Option<&DvmBasicBlock> {
+        self.blocks
+            .iter()
+            .find(|block| block.start_address() == Some(address))
+    }
 }
 
 /// Type alias for Shuriken's `hdvmfieldanalysis_t`
@@ -635,6 +701,12 @@ pub struct DvmMethodAnalysis {
     xrefconstclass: Vec<DvmClassIdx>,
     /// Cache of method string
     method_string: String,
+    /// Uses of call sites, method handles and method types
+    xrefdynamic: Vec<DynamicXref>,
+    /// Try ranges of the method
+    try_ranges: Vec<TryRange>,
+    /// Exceptions thrown by `throw` instructions
+    thrown_exceptions: Vec<ThrownException>,
 }
 
 impl DvmMethodAnalysis {
@@ -741,10 +813,85 @@ impl DvmMethodAnalysis {
             xrefnewinstance,
             n_of_xrefconstclass: ptr.n_of_xrefconstclass,
             xrefconstclass,
-            method_string
+            method_string,
+            xrefdynamic: Vec::new(),
+            try_ranges: Vec::new(),
+            thrown_exceptions: Vec::new()
         }
     }
 
+    /// Complete the method analysis with the information missing from Shuriken's C API
+    pub(crate) fn resolve_from_dex(&mut self, dex: &DexFile) {
+        let encoded = dex.class_def_by_name(&self.class_name)
+            .and_then(|class_def| dex.encoded_method(&class_def, &self.full_name));
+
+        if let Some(encoded) = encoded {
+            self.resolve_from_encoded_method(dex, &encoded);
+        }
+    }
+
+    /// Record the uses of call sites, method handles and method types, which
+    /// Shuriken skips, and add the methods they reach to the `xrefto`
+    fn resolve_from_encoded_method(&mut self, dex: &DexFile, encoded: &EncodedMethod) {
+        let Some(code) = dex.code_item(encoded.code_off) else {
+            return;
+        };
+        for block in self.basic_blocks.blocks.iter_mut() {
+            for instruction in block.instructions.iter_mut() {
+                instruction.resolve_from_code(dex, &code);
+            }
+        }
+
+        self.xrefdynamic = self.basic_blocks
+            .blocks()
+            .iter()
+            .flat_map(|block| block.instructions())
+            .filter_map(|instruction| DynamicXref::read(dex, &code, instruction.op(), instruction.address()))
+            .collect();
+
+        for xref in &self.xrefdynamic {
+            let Some(target) = xref.target() else {
+                continue;
+            };
+            let Some((class, _, _)) = dex_file::split_method_name(target) else {
+                continue;
+            };
+
+            let known = self.xrefto
+                .iter()
+                .any(|known| known.idx() == xref.idx() && known.method() == target);
+            if !known {
+                let class = dex_file::same_notation_as(&self.class_name, class);
+                self.xrefto.push(DvmClassMethodIdx::new(&class, target, xref.idx()));
+            }
+        }
+        self.n_of_xrefto = self.xrefto.len();
+
+        self.try_ranges = TryRange::read_all(dex, &code);
+        for block in self.basic_blocks.blocks.iter_mut() {
+            block.set_catch_handlers(&self.try_ranges);
+        }
+        self.thrown_exceptions = self.basic_blocks
+            .blocks()
+            .iter()
+            .flat_map(|block| {
+                let caught = match block.catch_handlers() {
+                    [handler] => Some(handler),
+                    _ => None
+                };
+                block.instructions()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, instruction)| instruction.op() == exceptions::THROW)
+                    .map(move |(n, instruction)| {
+                        let preceding = block.instructions()[..n].iter().rev().map(DvmInstruction::address);
+                        let exception_type = exceptions::thrown_type(dex, &code, instruction.address(), preceding, caught);
+                        ThrownException::new(instruction.address(), exception_type)
+                    })
+            })
+            .collect();
+    }
+
     /// Return a reference to the name of the method
     pub fn name(&self) -> &str {
         &self.name
@@ -849,6 +996,109 @@ impl DvmMethodAnalysis {
     pub fn method_string(&self) -> &str {
         &self.method_string
     }
+
+    /// Return a reference to the uses of call sites, method handles and
+    /// method types
+    ///
+    /// The methods they reach are also part of [`xrefto`](#method.xrefto).
+    /// Only available for methods analyzed through a `DexContext` or an
+    /// `ApkContext`.
+    pub fn xrefdynamic(&self) -> &[DynamicXref] {
+        &self.xrefdynamic
+    }
+
+    /// Return a reference to the try ranges of the method
+    ///
+    /// Only available for methods analyzed through a `DexContext` or an
+    /// `ApkContext`, like the other exception information.
+    pub fn try_ranges(&self) -> &[TryRange] {
+        &self.try_ranges
+    }
+
+    /// Get the handlers covering a block, in the order they are tried
+    pub fn handlers_covering(&self, block: &DvmBasicBlock) -> Vec<&CatchHandler> {
+        let Some(start_address) = block.start_address() else {
+            return Vec::new();
+        };
+
+        self.try_ranges
+            .iter()
+            .filter(|try_range| try_range.covers(start_address))
+            .flat_map(|try_range| try_range.handlers())
+            .collect()
+    }
+
+    /// Get the blocks covered by a handler
+    pub fn blocks_covered_by(&self, handler: &CatchHandler) -> Vec<&DvmBasicBlock> {
+        self.basic_blocks
+            .blocks()
+            .iter()
+            .filter(|block| self.handlers_covering(block).contains(&handler))
+            .collect()
+    }
+
+    /// Get the block where a handler starts
+    pub fn handler_block(&self, handler: &CatchHandler) -> Option<&DvmBasicBlock> {
+        self.basic_blocks.block_at(handler.address())
+    }
+
+    /// Return a reference to the exceptions thrown by `throw` instructions
+    pub fn thrown_exceptions(&self) -> &[ThrownException] {
+        &self.thrown_exceptions
+    }
+
+    /// Get the exceptions thrown by `throw` instructions that no handler of
+    /// the method catches
+    ///
+    /// Only the exceptions of [`thrown_exceptions`](#method.thrown_exceptions)
+    /// are considered: exceptions raised by the invoked methods, even when
+    /// they declare them, or by the VM, like a `NullPointerException`, are
+    /// not included.
+    pub fn escaping_thrown_exceptions(&self, hierarchy: &ClassHierarchy) -> Vec<&ThrownException> {
+        self.thrown_exceptions
+            .iter()
+            .filter(|thrown| {
+                !self.try_ranges
+                    .iter()
+                    .filter(|try_range| try_range.covers(thrown.address()))
+                    .any(|try_range| try_range.handler_for(thrown.exception_type(), hierarchy).is_some())
+            })
+            .collect()
+    }
+
+    /// Whether the method takes part in virtual dispatch
+    ///
+    /// Static methods, private methods and constructors are never dispatched.
+    pub fn is_virtual(&self) -> bool {
+        !self.access_flags.iter().any(|flag| matches!(flag,
+            DvmAccessFlag::ACC_STATIC | DvmAccessFlag::ACC_PRIVATE | DvmAccessFlag::ACC_CONSTRUCTOR
+        )) && !self.name.starts_with('<')
+    }
+
+    /// Get the methods from the super types that this method overrides,
+    /// none if the method is not virtual
+    pub fn overridden_methods<'a>(&self, hierarchy: &'a ClassHierarchy) -> Vec<&'a HierarchyMethod> {
+        if !self.is_virtual() {
+            return Vec::new();
+        }
+
+        hierarchy.overridden_methods(&self.class_name, &self.name, &self.descriptor)
+    }
+
+    /// Get the methods from the subtypes that override this method, none if
+    /// the method is not virtual
+    pub fn overriding_methods<'a>(&self, hierarchy: &'a ClassHierarchy) -> Vec<&'a HierarchyMethod> {
+        if !self.is_virtual() {
+            return Vec::new();
+        }
+
+        hierarchy.overriding_methods(&self.class_name, &self.name, &self.descriptor)
+    }
+
+    /// Get the methods of the same class with the same name but a different descriptor
+    pub fn overloads<'a>(&self, hierarchy: &'a ClassHierarchy) -> Vec<&'a HierarchyMethod> {
+        hierarchy.overloads(&self.class_name, &self.name, &self.descriptor)
+    }
 }
 
 /// Type alias for Shuriken's `hdvmclassanalysis_t`
@@ -860,6 +1110,8 @@ pub struct DvmClassAnalysis {
     is_external: bool,
     /// Name of the class it extends
     extends: String,
+    /// Names of the interfaces it implements
+    interfaces: Vec<String>,
     /// name of the class
     name: String,
     /// number of methods
@@ -886,6 +1138,8 @@ pub struct DvmClassAnalysis {
     n_of_xreffrom: usize,
     /// Classes that call this class
     xreffrom: Vec<DvmClassXref>,
+    /// Outer class, nested classes and lambda target
+    nesting: ClassNesting,
 }
 
 impl DvmClassAnalysis {
@@ -946,9 +1200,18 @@ impl DvmClassAnalysis {
                 .collect::<Vec<DvmClassXref>>()
         };
 
+        let mut nesting = ClassNesting::new(&name);
+        nesting.set_lambda_target(
+            &[],
+            methods.iter()
+                .flat_map(|method| method.xrefto())
+                .map(|xref| xref.method())
+        );
+
         DvmClassAnalysis {
             is_external: ptr.is_external == 0,
             extends,
+            interfaces: Vec::new(),
             name,
             n_of_methods: ptr.n_of_methods,
             methods,
@@ -962,6 +1225,37 @@ impl DvmClassAnalysis {
             xrefto,
             n_of_xreffrom: ptr.n_of_xreffrom,
             xreffrom,
+            nesting,
+        }
+    }
+
+    /// Complete the class analysis with the information missing from Shuriken's C API
+    pub(crate) fn resolve_from_dex(&mut self, dex: &DexFile) {
+        if let Some(class_def) = dex.class_def_by_name(&self.name) {
+            self.interfaces = dex.interfaces(&class_def)
+                .iter()
+                .map(|interface| dex_file::same_notation_as(&self.extends, interface))
+                .collect();
+
+            let encoded_methods = dex.class_methods(&class_def)
+                .into_iter()
+                .filter_map(|method| Some((dex.method_full_name(method.method_idx)?, method)))
+                .collect::<HashMap<String, EncodedMethod>>();
+            for method in self.methods.iter_mut() {
+                if let Some(encoded) = encoded_methods.get(&method.full_name) {
+                    method.resolve_from_encoded_method(dex, encoded);
+                }
+            }
+
+            let directory = dex.annotations_directory(&class_def).unwrap_or_default();
+            self.nesting.apply_annotations(&Annotation::read_set(dex, directory.class_annotations_off));
+            self.nesting.set_lambda_target(
+                &self.interfaces,
+                self.methods.iter()
+                    .flat_map(|method| method.xrefto())
+                    .map(|xref| xref.method())
+            );
+            self.nesting.resolve_lambda_enclosing_method(dex);
         }
     }
 
@@ -975,6 +1269,14 @@ impl DvmClassAnalysis {
         &self.extends
     }
 
+    /// Return a reference to the names of the interfaces it implements
+    ///
+    /// Names use the same notation as [`extends`](#method.extends). Only available
+    /// for classes analyzed through a `DexContext` or an `ApkContext`.
+    pub fn interfaces(&self) -> &[String] {
+        &self.interfaces
+    }
+
     /// Return a reference to the name of the class
     pub fn name(&self) -> &str {
         &self.name
@@ -1039,4 +1341,26 @@ impl DvmClassAnalysis {
     pub fn xreffrom(&self) -> &[DvmClassXref] {
         &self.xreffrom
     }
+
+    /// Return a reference to the nesting information of the class
+    ///
+    /// Lambda classes are recognized from their name and linked to the method
+    /// they call, which requires the xrefs. The annotations of inner, local
+    /// and anonymous classes, as well as the functional interface and the
+    /// enclosing method of lambdas, are only available for classes analyzed
+    /// through a `DexContext` or an `ApkContext`.
+    pub fn nesting(&self) -> &ClassNesting {
+        &self.nesting
+    }
+
+    /// Return a reference to the descriptor of the outer class, if the class is nested
+    pub fn outer_class(&self) -> Option<&str> {
+        self.nesting.outer_class()
+    }
+
+    /// Get the classes directly nested in this class, including its local,
+    /// anonymous and lambda classes
+    pub fn nested_classes<'a>(&self, tree: &'a NestingTree) -> Vec<&'a ClassNesting> {
+        tree.nested_classes(&self.name)
+    }
 }
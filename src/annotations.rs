@@ -0,0 +1,316 @@
+//! Annotations
+//!
+//! Annotations of the classes, methods, fields and parameters, read from the
+//! DEX file since Shuriken does not expose them. Besides the annotations of
+//! the source code, the compilers store metadata as system annotations in the
+//! `dalvik.annotation` package: generic signatures, inner classes, enclosing
+//! methods and checked exceptions. Those are decoded by the getters of
+//! [`DvmClass`], [`DvmMethod`] and [`DvmField`].
+//!
+//! [`DvmClass`]: crate::parser::DvmClass
+//! [`DvmMethod`]: crate::parser::DvmMethod
+//! [`DvmField`]: crate::parser::DvmField
+
+use std::fmt;
+
+use crate::dex_file::DexFile;
+use crate::dvm_access_flags::{ DvmAccessFlag, DvmAccessFlagType };
+use crate::encoded_value::{ AnnotationElement, EncodedAnnotation, EncodedValue };
+
+pub(crate) const SIGNATURE: &str = "Ldalvik/annotation/Signature;";
+pub(crate) const INNER_CLASS: &str = "Ldalvik/annotation/InnerClass;";
+pub(crate) const ENCLOSING_CLASS: &str = "Ldalvik/annotation/EnclosingClass;";
+pub(crate) const ENCLOSING_METHOD: &str = "Ldalvik/annotation/EnclosingMethod;";
+pub(crate) const MEMBER_CLASSES: &str = "Ldalvik/annotation/MemberClasses;";
+pub(crate) const THROWS: &str = "Ldalvik/annotation/Throws;";
+
+/// Visibility of an annotation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnnotationVisibility {
+    /// Only visible at build time
+    Build,
+    /// Visible at runtime
+    Runtime,
+    /// Visible at runtime to the system only
+    System
+}
+
+impl fmt::Display for AnnotationVisibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnnotationVisibility::Build => write!(f, "build"),
+            AnnotationVisibility::Runtime => write!(f, "runtime"),
+            AnnotationVisibility::System => write!(f, "system")
+        }
+    }
+}
+
+/// Annotation of a class, method, field or parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    visibility: AnnotationVisibility,
+    annotation: EncodedAnnotation
+}
+
+impl Annotation {
+    /// Read the annotations of an `annotation_set_item`
+    ///
+    /// Returns an empty vector for a null offset.
+    pub(crate) fn read_set(dex: &DexFile, offset: u32) -> Vec<Self> {
+        if offset == 0 {
+            return Vec::new();
+        }
+
+        let mut reader = dex.reader(offset as usize);
+        let size = reader.read_u32().unwrap_or(0);
+
+        (0..size)
+            .map_while(|_| reader.read_u32())
+            .filter_map(|annotation_off| Self::read(dex, annotation_off))
+            .collect()
+    }
+
+    /// Read the annotations of the parameters from an `annotation_set_ref_list`
+    pub(crate) fn read_set_ref_list(dex: &DexFile, offset: u32) -> Vec<Vec<Self>> {
+        let mut reader = dex.reader(offset as usize);
+        let size = reader.read_u32().unwrap_or(0);
+
+        (0..size)
+            .map_while(|_| reader.read_u32())
+            .map(|set_off| Self::read_set(dex, set_off))
+            .collect()
+    }
+
+    /// Read an `annotation_item`
+    fn read(dex: &DexFile, offset: u32) -> Option<Self> {
+        let mut reader = dex.reader(offset as usize);
+        let visibility = match reader.read_u8()? {
+            0 => AnnotationVisibility::Build,
+            1 => AnnotationVisibility::Runtime,
+            2 => AnnotationVisibility::System,
+            _ => return None
+        };
+
+        Some(Self {
+            visibility,
+            annotation: EncodedAnnotation::read(dex, &mut reader)?
+        })
+    }
+
+    /// Return the visibility of the annotation
+    pub fn visibility(&self) -> AnnotationVisibility {
+        self.visibility
+    }
+
+    /// Return a reference to the descriptor of the annotation type, like
+    /// `Landroid/webkit/JavascriptInterface;`
+    pub fn type_descriptor(&self) -> &str {
+        self.annotation.type_descriptor()
+    }
+
+    /// Return a reference to the elements of the annotation
+    pub fn elements(&self) -> &[AnnotationElement] {
+        self.annotation.elements()
+    }
+
+    /// Get the value of an element given its name
+    pub fn element(&self, name: &str) -> Option<&EncodedValue> {
+        self.annotation.element(name)
+    }
+}
+
+/// Inner class information, from the `InnerClass` system annotation
+#[derive(Debug, Clone, PartialEq)]
+pub struct InnerClass {
+    /// Simple name, `None` for anonymous classes
+    name: Option<String>,
+    /// Access flags declared in the source code
+    access_flags: Vec<DvmAccessFlag>
+}
+
+impl InnerClass {
+    /// Return a reference to the simple name of the class, `None` for anonymous classes
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Return a reference to the access flags declared in the source code
+    pub fn access_flags(&self) -> &[DvmAccessFlag] {
+        &self.access_flags
+    }
+
+    /// Whether the class is anonymous
+    pub fn is_anonymous(&self) -> bool {
+        self.name.is_none()
+    }
+}
+
+/// Find an annotation given its type
+pub(crate) fn find<'a>(annotations: &'a [Annotation], type_descriptor: &str) -> Option<&'a Annotation> {
+    annotations
+        .iter()
+        .find(|annotation| annotation.type_descriptor() == type_descriptor)
+}
+
+/// Get the `value` of a system annotation
+fn system_value<'a>(annotations: &'a [Annotation], type_descriptor: &str) -> Option<&'a EncodedValue> {
+    find(annotations, type_descriptor)
+        .filter(|annotation| annotation.visibility == AnnotationVisibility::System)?
+        .element("value")
+}
+
+/// Decode the `Signature` system annotation, whose parts are joined
+pub(crate) fn signature(annotations: &[Annotation]) -> Option<String> {
+    system_value(annotations, SIGNATURE)?
+        .as_array()?
+        .iter()
+        .map(|part| part.as_str())
+        .collect()
+}
+
+/// Decode the types of a system annotation holding an array of types,
+/// like `Throws` and `MemberClasses`
+pub(crate) fn types(annotations: &[Annotation], type_descriptor: &str) -> Vec<String> {
+    system_value(annotations, type_descriptor)
+        .and_then(|value| value.as_array())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|value| value.as_type().map(String::from))
+        .collect()
+}
+
+/// Decode the `EnclosingClass` system annotation
+pub(crate) fn enclosing_class(annotations: &[Annotation]) -> Option<String> {
+    system_value(annotations, ENCLOSING_CLASS)?
+        .as_type()
+        .map(String::from)
+}
+
+/// Decode the `EnclosingMethod` system annotation
+pub(crate) fn enclosing_method(annotations: &[Annotation]) -> Option<String> {
+    match system_value(annotations, ENCLOSING_METHOD)? {
+        EncodedValue::Method(method) => Some(method.clone()),
+        _ => None
+    }
+}
+
+/// Decode the `InnerClass` system annotation
+pub(crate) fn inner_class(annotations: &[Annotation]) -> Option<InnerClass> {
+    let annotation = find(annotations, INNER_CLASS)
+        .filter(|annotation| annotation.visibility == AnnotationVisibility::System)?;
+
+    let name = match annotation.element("name")? {
+        EncodedValue::String(name) => Some(name.clone()),
+        _ => None
+    };
+    let access_flags = annotation.element("accessFlags")?.as_i64()?;
+
+    Some(InnerClass {
+        name,
+        access_flags: DvmAccessFlag::parse(access_flags as u32, DvmAccessFlagType::Class)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const BRIDGE: &str = "Lcom/example/annotations/Bridge;";
+
+    fn dex() -> DexFile {
+        DexFile::from_path(Path::new("test_files/DexAnnotationsTest.dex")).unwrap()
+    }
+
+    fn class_annotations(dex: &DexFile, name: &str) -> Vec<Annotation> {
+        let class_def = dex.class_def_by_name(name).unwrap();
+        let directory = dex.annotations_directory(&class_def).unwrap();
+
+        Annotation::read_set(dex, directory.class_annotations_off)
+    }
+
+    #[test]
+    fn test_class_annotations() {
+        let dex = dex();
+        let annotations = class_annotations(&dex, BRIDGE);
+
+        let found = annotations.iter()
+            .map(|annotation| (annotation.type_descriptor(), annotation.visibility()))
+            .collect::<Vec<(&str, AnnotationVisibility)>>();
+        assert_eq!(found, vec![
+            ("Lcom/example/annotations/Internal;", AnnotationVisibility::Build),
+            ("Lcom/example/annotations/Tag;", AnnotationVisibility::Runtime),
+            (MEMBER_CLASSES, AnnotationVisibility::System),
+            (SIGNATURE, AnnotationVisibility::System)
+        ]);
+
+        assert_eq!(signature(&annotations).as_deref(), Some("<T:Ljava/lang/Object;>Ljava/lang/Object;"));
+        assert_eq!(types(&annotations, MEMBER_CLASSES), vec!["Lcom/example/annotations/Bridge$Inner;"]);
+
+        let tag = find(&annotations, "Lcom/example/annotations/Tag;").unwrap();
+        assert_eq!(tag.element("value"), Some(&EncodedValue::String(String::from("bridge"))));
+        assert_eq!(tag.element("level"), Some(&EncodedValue::Int(-2)));
+        assert_eq!(tag.element("big"), Some(&EncodedValue::Long(0x1_2345_6789)));
+        assert_eq!(tag.element("ratio"), Some(&EncodedValue::Float(1.5)));
+        assert_eq!(tag.element("precise"), Some(&EncodedValue::Double(-0.25)));
+        assert_eq!(tag.element("letter"), Some(&EncodedValue::Char(0x41)));
+        assert_eq!(tag.element("small"), Some(&EncodedValue::Short(-300)));
+        assert_eq!(tag.element("tiny"), Some(&EncodedValue::Byte(-1)));
+        assert_eq!(tag.element("flag"), Some(&EncodedValue::Boolean(true)));
+        assert_eq!(tag.element("nothing"), Some(&EncodedValue::Null));
+        assert_eq!(
+            tag.element("kind"),
+            Some(&EncodedValue::Enum(String::from("Lcom/example/annotations/Level;->HIGH:Lcom/example/annotations/Level;")))
+        );
+        assert_eq!(tag.element("target").and_then(EncodedValue::as_type), Some("Lcom/example/annotations/Bridge$Inner;"));
+        assert_eq!(tag.element("names").and_then(EncodedValue::as_array).map(|names| names.len()), Some(2));
+
+        let Some(EncodedValue::Annotation(nested)) = tag.element("nested") else {
+            panic!("nested annotation expected");
+        };
+        assert_eq!(nested.type_descriptor(), "Lcom/example/annotations/Tag;");
+        assert_eq!(nested.element("value").and_then(EncodedValue::as_str), Some("inner"));
+    }
+
+    #[test]
+    fn test_inner_classes() {
+        let dex = dex();
+
+        let annotations = class_annotations(&dex, "Lcom/example/annotations/Bridge$Inner;");
+        let inner = inner_class(&annotations).unwrap();
+        assert_eq!(inner.name(), Some("Inner"));
+        assert_eq!(inner.access_flags(), &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_STATIC]);
+        assert_eq!(enclosing_class(&annotations).as_deref(), Some(BRIDGE));
+
+        let annotations = class_annotations(&dex, "Lcom/example/annotations/Bridge$1;");
+        assert!(inner_class(&annotations).unwrap().is_anonymous());
+        assert_eq!(
+            enclosing_method(&annotations).as_deref(),
+            Some("Lcom/example/annotations/Bridge;->exposed(Ljava/lang/String;)V")
+        );
+        assert!(enclosing_class(&annotations).is_none());
+    }
+
+    #[test]
+    fn test_member_annotations() {
+        let dex = dex();
+        let class_def = dex.class_def_by_name(BRIDGE).unwrap();
+        let directory = dex.annotations_directory(&class_def).unwrap();
+
+        assert_eq!(directory.fields.len(), 1);
+        let field = Annotation::read_set(&dex, directory.fields[0].1);
+        assert_eq!(signature(&field).as_deref(), Some("Ljava/util/List<Ljava/lang/String;>;"));
+
+        let methods = directory.methods
+            .iter()
+            .map(|(method_idx, annotations_off)| (dex.method_full_name(*method_idx).unwrap(), Annotation::read_set(&dex, *annotations_off)))
+            .collect::<Vec<(String, Vec<Annotation>)>>();
+        assert_eq!(methods[0].0, "Lcom/example/annotations/Bridge;->exposed(Ljava/lang/String;)V");
+        assert_eq!(types(&methods[0].1, THROWS), vec!["Ljava/io/IOException;"]);
+        assert_eq!(signature(&methods[1].1).as_deref(), Some("(TT;)TT;"));
+
+        let parameters = Annotation::read_set_ref_list(&dex, directory.parameters[0].1);
+        assert_eq!(parameters.len(), 1);
+        assert_eq!(parameters[0][0].element("value").and_then(EncodedValue::as_str), Some("message"));
+    }
+}
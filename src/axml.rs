@@ -0,0 +1,749 @@
+//! Android binary XML
+//!
+//! XML files in APKs (`AndroidManifest.xml`, layouts, ...) are compiled to a
+//! binary format (AXML) made of resource chunks: a string pool, a map of the
+//! attribute resource IDs, and the XML nodes themselves. This module decodes
+//! them into a tree of [`XmlElement`].
+//!
+//! The decoder is lenient with the malformed files produced by some packers,
+//! as long as Android itself would accept them.
+//!
+//! Android App Bundles use the aapt2 protobuf format instead, see
+//! [`XmlDocument::parse_proto`].
+
+use std::fmt;
+use std::collections::HashMap;
+
+use crate::dex_file::Reader;
+use crate::protobuf::{ ProtoFields, ProtoValue };
+
+/// Type of the string pool chunk
+pub(crate) const RES_STRING_POOL_TYPE: u16 = 0x0001;
+/// Type of the binary XML chunk
+const RES_XML_TYPE: u16 = 0x0003;
+/// Type of the namespace start chunk
+const RES_XML_START_NAMESPACE_TYPE: u16 = 0x0100;
+/// Type of the element start chunk
+const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+/// Type of the element end chunk
+const RES_XML_END_ELEMENT_TYPE: u16 = 0x0103;
+/// Type of the resource map chunk
+const RES_XML_RESOURCE_MAP_TYPE: u16 = 0x0180;
+
+/// Header of a resource chunk
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ChunkHeader {
+    pub(crate) chunk_type: u16,
+    pub(crate) header_size: u16,
+    pub(crate) size: u32,
+    /// Offset of the chunk in the data
+    pub(crate) offset: usize
+}
+
+impl ChunkHeader {
+    /// Read a chunk header, checking the sizes are consistent with the data
+    pub(crate) fn read(data: &[u8], offset: usize) -> Option<Self> {
+        let mut reader = Reader::new(data, offset);
+        let chunk_type = reader.read_u16()?;
+        let header_size = reader.read_u16()?;
+        let size = reader.read_u32()?;
+
+        if header_size < 8 || size < header_size as u32 || offset.checked_add(size as usize)? > data.len() {
+            return None;
+        }
+
+        Some(Self { chunk_type, header_size, size, offset })
+    }
+
+    /// Offset of the data following the chunk header
+    pub(crate) fn body(&self) -> usize {
+        self.offset + self.header_size as usize
+    }
+
+    /// Offset of the next chunk
+    pub(crate) fn end(&self) -> usize {
+        self.offset + self.size as usize
+    }
+}
+
+/// Resource string pool
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct StringPool {
+    strings: Vec<String>
+}
+
+impl StringPool {
+    /// Strings are encoded in UTF-8 instead of UTF-16
+    const UTF8_FLAG: u32 = 1 << 8;
+
+    /// Decode the strings of a string pool chunk
+    pub(crate) fn parse(data: &[u8], header: &ChunkHeader) -> Option<Self> {
+        let mut reader = Reader::new(data, header.offset + 8);
+        let string_count = reader.read_u32()? as usize;
+        let _style_count = reader.read_u32()?;
+        let flags = reader.read_u32()?;
+        let strings_start = header.offset + reader.read_u32()? as usize;
+
+        let mut offsets = Reader::new(data, header.body());
+        let chunk = data.get(..header.end())?;
+
+        let strings = (0..string_count)
+            .map_while(|_| offsets.read_u32())
+            .map(|offset| {
+                let offset = strings_start.saturating_add(offset as usize);
+                if flags & Self::UTF8_FLAG != 0 {
+                    Self::read_utf8(chunk, offset)
+                } else {
+                    Self::read_utf16(chunk, offset)
+                }
+                .unwrap_or_default()
+            })
+            .collect();
+
+        Some(Self { strings })
+    }
+
+    fn read_utf8(data: &[u8], offset: usize) -> Option<String> {
+        let mut reader = Reader::new(data, offset);
+        let read_len = |reader: &mut Reader| -> Option<usize> {
+            let first = reader.read_u8()? as usize;
+            if first & 0x80 != 0 {
+                Some(((first & 0x7f) << 8) | reader.read_u8()? as usize)
+            } else {
+                Some(first)
+            }
+        };
+
+        let _utf16_len = read_len(&mut reader)?;
+        let len = read_len(&mut reader)?;
+
+        Some(String::from_utf8_lossy(reader.read_bytes(len)?).into_owned())
+    }
+
+    fn read_utf16(data: &[u8], offset: usize) -> Option<String> {
+        let mut reader = Reader::new(data, offset);
+        let first = reader.read_u16()? as usize;
+        let len = if first & 0x8000 != 0 {
+            ((first & 0x7fff) << 16) | reader.read_u16()? as usize
+        } else {
+            first
+        };
+
+        let units = (0..len)
+            .map(|_| reader.read_u16())
+            .collect::<Option<Vec<u16>>>()?;
+
+        Some(String::from_utf16_lossy(&units))
+    }
+
+    /// Get a string given its index, `None` for `0xffffffff` or out of range indexes
+    pub(crate) fn get(&self, idx: u32) -> Option<&str> {
+        self.strings
+            .get(idx as usize)
+            .map(|string| string.as_str())
+    }
+
+    pub(crate) fn strings(&self) -> &[String] {
+        &self.strings
+    }
+}
+
+/// Name of the `android:` attributes used in manifests, given their resource ID
+///
+/// Attribute names can be obfuscated in the string pool, Android only uses
+/// the resource IDs of the attributes.
+pub(crate) fn android_attribute_name(id: u32) -> Option<&'static str> {
+    let name = match id {
+        0x0101_0000 => "theme",
+        0x0101_0001 => "label",
+        0x0101_0002 => "icon",
+        0x0101_0003 => "name",
+        0x0101_0006 => "permission",
+        0x0101_0007 => "readPermission",
+        0x0101_0008 => "writePermission",
+        0x0101_0009 => "protectionLevel",
+        0x0101_000b => "sharedUserId",
+        0x0101_000e => "enabled",
+        0x0101_000f => "debuggable",
+        0x0101_0010 => "exported",
+        0x0101_0011 => "process",
+        0x0101_0018 => "authorities",
+        0x0101_001b => "grantUriPermissions",
+        0x0101_001c => "priority",
+        0x0101_001d => "launchMode",
+        0x0101_0020 => "description",
+        0x0101_0024 => "value",
+        0x0101_0025 => "resource",
+        0x0101_0026 => "mimeType",
+        0x0101_0027 => "scheme",
+        0x0101_0028 => "host",
+        0x0101_0029 => "port",
+        0x0101_002a => "path",
+        0x0101_002b => "pathPrefix",
+        0x0101_002c => "pathPattern",
+        0x0101_020c => "minSdkVersion",
+        0x0101_021b => "versionCode",
+        0x0101_021c => "versionName",
+        0x0101_0202 => "targetActivity",
+        0x0101_0270 => "targetSdkVersion",
+        0x0101_0271 => "maxSdkVersion",
+        0x0101_0280 => "allowBackup",
+        0x0101_04ec => "usesCleartextTraffic",
+        0x0101_0527 => "networkSecurityConfig",
+        _ => return None
+    };
+
+    Some(name)
+}
+
+/// Typed value of an attribute
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlValue {
+    /// No value
+    Null,
+    /// Reference to a resource (`@0x7f...`)
+    Reference(u32),
+    /// Reference to a theme attribute (`?0x7f...`)
+    Attribute(u32),
+    /// String
+    String(String),
+    /// Floating point number
+    Float(f32),
+    /// Dimension, in the complex format of the resources
+    Dimension(u32),
+    /// Fraction, in the complex format of the resources
+    Fraction(u32),
+    /// Decimal integer
+    Int(i32),
+    /// Hexadecimal integer
+    Hex(u32),
+    /// Boolean
+    Bool(bool),
+    /// ARGB color
+    Color(u32),
+    /// Value of an unknown type
+    Other {
+        data_type: u8,
+        data: u32
+    }
+}
+
+impl XmlValue {
+    /// Decode a `Res_value`
+    ///
+    /// `raw_value` is the original string of the attribute, if any.
+    pub(crate) fn from_res_value(data_type: u8, data: u32, raw_value: Option<&str>) -> Self {
+        match data_type {
+            0x00 => XmlValue::Null,
+            0x01 => XmlValue::Reference(data),
+            0x02 => XmlValue::Attribute(data),
+            0x03 => XmlValue::String(raw_value.unwrap_or_default().to_string()),
+            0x04 => XmlValue::Float(f32::from_bits(data)),
+            0x05 => XmlValue::Dimension(data),
+            0x06 => XmlValue::Fraction(data),
+            0x10 => XmlValue::Int(data as i32),
+            0x11 => XmlValue::Hex(data),
+            0x12 => XmlValue::Bool(data != 0),
+            0x1c..=0x1f => XmlValue::Color(data),
+            _ => XmlValue::Other { data_type, data }
+        }
+    }
+
+    /// Decode a compiled `Item` of the aapt2 protobuf format
+    ///
+    /// `raw_value` is the original string of the attribute.
+    pub(crate) fn from_proto_item(data: &[u8], raw_value: &str) -> Self {
+        let Some((field, value)) = ProtoFields::new(data).next() else {
+            return XmlValue::String(raw_value.to_string());
+        };
+        let fields = value.as_bytes()
+            .map(|bytes| ProtoFields::new(bytes).collect::<Vec<(u32, ProtoValue)>>())
+            .unwrap_or_default();
+        let number = |number: u32| fields.iter()
+            .find(|(field, _)| *field == number)
+            .and_then(|(_, value)| value.as_u64())
+            .unwrap_or(0);
+
+        match field {
+            // Reference, whose type is 0 for resources and 1 for attributes
+            1 => match number(1) {
+                1 => XmlValue::Attribute(number(2) as u32),
+                _ => XmlValue::Reference(number(2) as u32)
+            },
+            // String, raw string, styled string and file reference
+            2..=5 => fields.iter()
+                .find(|(field, _)| *field == 1)
+                .and_then(|(_, value)| value.as_string())
+                .map_or_else(|| XmlValue::String(raw_value.to_string()), XmlValue::String),
+            6 => XmlValue::Null,
+            // Primitive
+            7 => {
+                let Some(&(kind, value)) = fields.first() else {
+                    return XmlValue::Null;
+                };
+                let data = value.as_u64().unwrap_or(0) as u32;
+
+                match kind {
+                    3..=5 => XmlValue::Float(f32::from_bits(data)),
+                    6 => XmlValue::Int(data as i32),
+                    7 => XmlValue::Hex(data),
+                    8 => XmlValue::Bool(data != 0),
+                    9..=12 => XmlValue::Color(data),
+                    13 => XmlValue::Dimension(data),
+                    14 => XmlValue::Fraction(data),
+                    _ => XmlValue::Null
+                }
+            },
+            _ => XmlValue::String(raw_value.to_string())
+        }
+    }
+
+    /// Get the value as a string, if it is one
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            XmlValue::String(string) => Some(string),
+            _ => None
+        }
+    }
+
+    /// Get the value as an integer
+    ///
+    /// Strings holding a number are converted as well.
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            XmlValue::Int(value) => Some(*value as i64),
+            XmlValue::Hex(value) => Some(*value as i64),
+            XmlValue::String(string) => string.trim().parse().ok(),
+            _ => None
+        }
+    }
+
+    /// Get the value as a boolean
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            XmlValue::Bool(value) => Some(*value),
+            XmlValue::String(string) => string.parse().ok(),
+            _ => None
+        }
+    }
+
+    /// Decode a value in the complex format of dimensions and fractions
+    fn complex_value(data: u32) -> f32 {
+        const RADIX_MULTIPLIERS: [f32; 4] = [
+            1.0 / 256.0,
+            1.0 / 32768.0,
+            1.0 / 8388608.0,
+            1.0 / 2147483648.0
+        ];
+
+        ((data & 0xffff_ff00) as i32) as f32 * RADIX_MULTIPLIERS[((data >> 4) & 3) as usize]
+    }
+}
+
+impl fmt::Display for XmlValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XmlValue::Null => Ok(()),
+            XmlValue::Reference(id) => write!(f, "@0x{id:08x}"),
+            XmlValue::Attribute(id) => write!(f, "?0x{id:08x}"),
+            XmlValue::String(string) => write!(f, "{string}"),
+            XmlValue::Float(value) => write!(f, "{value}"),
+            XmlValue::Dimension(data) => {
+                const UNITS: [&str; 6] = ["px", "dp", "sp", "pt", "in", "mm"];
+                let unit = UNITS.get((data & 0xf) as usize).unwrap_or(&"");
+                write!(f, "{}{unit}", Self::complex_value(*data))
+            },
+            XmlValue::Fraction(data) => {
+                let unit = if data & 0xf == 1 { "%p" } else { "%" };
+                write!(f, "{}{unit}", Self::complex_value(*data) * 100.0)
+            },
+            XmlValue::Int(value) => write!(f, "{value}"),
+            XmlValue::Hex(value) => write!(f, "0x{value:08x}"),
+            XmlValue::Bool(value) => write!(f, "{value}"),
+            XmlValue::Color(value) => write!(f, "#{value:08x}"),
+            XmlValue::Other { data_type, data } => write!(f, "(type 0x{data_type:02x})0x{data:08x}")
+        }
+    }
+}
+
+/// Attribute of an element
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlAttribute {
+    /// URI of the namespace of the attribute
+    namespace: Option<String>,
+    /// Name of the attribute
+    name: String,
+    /// Resource ID of the attribute, for framework and library attributes
+    resource_id: Option<u32>,
+    /// Value of the attribute
+    value: XmlValue
+}
+
+impl XmlAttribute {
+    /// Return a reference to the URI of the namespace of the attribute
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Return a reference to the name of the attribute
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the resource ID of the attribute
+    pub fn resource_id(&self) -> Option<u32> {
+        self.resource_id
+    }
+
+    /// Return a reference to the value of the attribute
+    pub fn value(&self) -> &XmlValue {
+        &self.value
+    }
+}
+
+/// Element of a binary XML document
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlElement {
+    /// URI of the namespace of the element
+    namespace: Option<String>,
+    /// Name of the element
+    name: String,
+    /// Line of the element in the original file
+    line: u32,
+    /// Attributes of the element
+    attributes: Vec<XmlAttribute>,
+    /// Child elements
+    children: Vec<XmlElement>
+}
+
+impl XmlElement {
+    /// Return a reference to the URI of the namespace of the element
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Return a reference to the name of the element
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the line of the element in the original file
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Return a reference to the attributes of the element
+    pub fn attributes(&self) -> &[XmlAttribute] {
+        &self.attributes
+    }
+
+    /// Return a reference to the child elements
+    pub fn children(&self) -> &[XmlElement] {
+        &self.children
+    }
+
+    /// Get the value of an attribute given its name, ignoring the namespace
+    pub fn attribute(&self, name: &str) -> Option<&XmlValue> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| &attribute.value)
+    }
+
+    /// Get the child elements with a given name
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> + 'a {
+        self.children
+            .iter()
+            .filter(move |child| child.name == name)
+    }
+
+    fn write_xml(&self, f: &mut fmt::Formatter, prefixes: &HashMap<String, String>, depth: usize) -> fmt::Result {
+        let indent = "    ".repeat(depth);
+        let qualified = |namespace: &Option<String>, name: &str| match namespace.as_ref().and_then(|uri| prefixes.get(uri)) {
+            Some(prefix) => format!("{prefix}:{name}"),
+            None => name.to_string()
+        };
+
+        write!(f, "{indent}<{}", qualified(&self.namespace, &self.name))?;
+
+        if depth == 0 {
+            let mut namespaces = prefixes.iter().collect::<Vec<(&String, &String)>>();
+            namespaces.sort();
+            for (uri, prefix) in namespaces {
+                write!(f, " xmlns:{prefix}=\"{}\"", escape(uri))?;
+            }
+        }
+
+        for attribute in &self.attributes {
+            write!(f, " {}=\"{}\"", qualified(&attribute.namespace, &attribute.name), escape(&attribute.value.to_string()))?;
+        }
+
+        if self.children.is_empty() {
+            return writeln!(f, "/>");
+        }
+
+        writeln!(f, ">")?;
+        for child in &self.children {
+            child.write_xml(f, prefixes, depth + 1)?;
+        }
+        writeln!(f, "{indent}</{}>", qualified(&self.namespace, &self.name))
+    }
+}
+
+/// Escape the XML special characters of a text
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Decoded binary XML document
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlDocument {
+    /// Root element
+    root: XmlElement,
+    /// Prefixes of the namespaces, indexed by URI
+    prefixes: HashMap<String, String>
+}
+
+impl XmlDocument {
+    /// Decode a binary XML document
+    ///
+    /// Returns `None` if the data is not binary XML or has no element.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let header = ChunkHeader::read(data, 0)
+            .or_else(|| {
+                // Some packers set a wrong size in the file header
+                let mut reader = Reader::new(data, 0);
+                let chunk_type = reader.read_u16()?;
+                let header_size = reader.read_u16()?;
+                Some(ChunkHeader { chunk_type, header_size, size: data.len() as u32, offset: 0 })
+            })?;
+
+        if header.chunk_type != RES_XML_TYPE {
+            return None;
+        }
+
+        let mut strings = StringPool::default();
+        let mut resource_ids: Vec<u32> = Vec::new();
+        let mut prefixes: HashMap<String, String> = HashMap::new();
+        let mut stack: Vec<XmlElement> = Vec::new();
+        let mut root = None;
+
+        let mut offset = header.body();
+        while let Some(chunk) = ChunkHeader::read(data, offset) {
+            match chunk.chunk_type {
+                RES_STRING_POOL_TYPE => {
+                    strings = StringPool::parse(data, &chunk)?;
+                },
+                RES_XML_RESOURCE_MAP_TYPE => {
+                    let mut reader = Reader::new(data, chunk.body());
+                    resource_ids = (chunk.body()..chunk.end())
+                        .step_by(4)
+                        .map_while(|_| reader.read_u32())
+                        .collect();
+                },
+                RES_XML_START_NAMESPACE_TYPE => {
+                    let mut reader = Reader::new(data, chunk.body());
+                    let prefix = reader.read_u32().and_then(|idx| strings.get(idx));
+                    let uri = reader.read_u32().and_then(|idx| strings.get(idx));
+
+                    if let (Some(prefix), Some(uri)) = (prefix, uri) {
+                        prefixes.insert(uri.to_string(), prefix.to_string());
+                    }
+                },
+                RES_XML_START_ELEMENT_TYPE => {
+                    if let Some(element) = Self::parse_element(data, &chunk, &strings, &resource_ids) {
+                        stack.push(element);
+                    }
+                },
+                RES_XML_END_ELEMENT_TYPE => {
+                    if let Some(element) = stack.pop() {
+                        match stack.last_mut() {
+                            Some(parent) => parent.children.push(element),
+                            None => if root.is_none() {
+                                root = Some(element);
+                            }
+                        }
+                    }
+                },
+                _ => { }
+            }
+
+            offset = chunk.end();
+        }
+
+        // Close the elements left open by truncated files
+        while let Some(element) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(element),
+                None => root = root.or(Some(element))
+            }
+        }
+
+        Some(Self { root: root?, prefixes })
+    }
+
+    fn parse_element(data: &[u8], chunk: &ChunkHeader, strings: &StringPool, resource_ids: &[u32]) -> Option<XmlElement> {
+        let line = Reader::new(data, chunk.offset + 8).read_u32()?;
+
+        let mut reader = Reader::new(data, chunk.body());
+        let namespace = strings.get(reader.read_u32()?).map(String::from);
+        let name = strings.get(reader.read_u32()?).unwrap_or_default().to_string();
+        let attribute_start = reader.read_u16()? as usize;
+        let attribute_size = reader.read_u16()? as usize;
+        let attribute_count = reader.read_u16()? as usize;
+
+        let attributes = (0..attribute_count)
+            .map_while(|idx| {
+                let mut reader = Reader::new(data, chunk.body() + attribute_start + idx * attribute_size.max(20));
+                let namespace = strings.get(reader.read_u32()?).map(String::from);
+                let name_idx = reader.read_u32()?;
+                let raw_value = strings.get(reader.read_u32()?);
+                let _size = reader.read_u16()?;
+                let _res0 = reader.read_u8()?;
+                let data_type = reader.read_u8()?;
+                let value = reader.read_u32()?;
+
+                let resource_id = resource_ids.get(name_idx as usize).copied();
+                let name = resource_id
+                    .and_then(android_attribute_name)
+                    .or_else(|| strings.get(name_idx))
+                    .unwrap_or_default()
+                    .to_string();
+
+                let value = match (data_type, raw_value) {
+                    // The typed value wins over the raw string, like in Android
+                    (0x03, _) => XmlValue::String(strings.get(value).or(raw_value).unwrap_or_default().to_string()),
+                    _ => XmlValue::from_res_value(data_type, value, raw_value)
+                };
+
+                Some(XmlAttribute { namespace, name, resource_id, value })
+            })
+            .collect();
+
+        Some(XmlElement {
+            namespace,
+            name,
+            line,
+            attributes,
+            children: Vec::new()
+        })
+    }
+
+    /// Decode a protobuf XML document, the format of the XML files of
+    /// Android App Bundles
+    ///
+    /// Returns `None` if the data has no root element.
+    pub fn parse_proto(data: &[u8]) -> Option<Self> {
+        let mut prefixes = HashMap::new();
+        let root = Self::parse_proto_node(data, &mut prefixes)?;
+
+        Some(Self { root, prefixes })
+    }
+
+    /// Decode an `XmlNode` holding an element, text nodes are skipped
+    fn parse_proto_node(data: &[u8], prefixes: &mut HashMap<String, String>) -> Option<XmlElement> {
+        let mut element = None;
+        let mut line = 0;
+
+        for (field, value) in ProtoFields::new(data) {
+            match field {
+                1 => element = value.as_bytes(),
+                3 => line = ProtoFields::new(value.as_bytes()?)
+                    .find(|(field, _)| *field == 1)
+                    .and_then(|(_, line)| line.as_u64())
+                    .unwrap_or(0) as u32,
+                _ => { }
+            }
+        }
+
+        let mut element = Self::parse_proto_element(element?, prefixes);
+        element.line = line;
+        Some(element)
+    }
+
+    fn parse_proto_element(data: &[u8], prefixes: &mut HashMap<String, String>) -> XmlElement {
+        let mut element = XmlElement {
+            namespace: None,
+            name: String::new(),
+            line: 0,
+            attributes: Vec::new(),
+            children: Vec::new()
+        };
+
+        for (field, value) in ProtoFields::new(data) {
+            let Some(bytes) = value.as_bytes() else { continue };
+
+            match field {
+                1 => {
+                    let strings = |number: u32| ProtoFields::new(bytes)
+                        .find(|(field, _)| *field == number)
+                        .and_then(|(_, value)| value.as_string());
+
+                    if let (Some(prefix), Some(uri)) = (strings(1), strings(2)) {
+                        prefixes.insert(uri, prefix);
+                    }
+                },
+                2 => element.namespace = value.as_string().filter(|namespace| !namespace.is_empty()),
+                3 => element.name = value.as_string().unwrap_or_default(),
+                4 => element.attributes.push(Self::parse_proto_attribute(bytes)),
+                5 => element.children.extend(Self::parse_proto_node(bytes, prefixes)),
+                _ => { }
+            }
+        }
+
+        element
+    }
+
+    fn parse_proto_attribute(data: &[u8]) -> XmlAttribute {
+        let mut namespace = None;
+        let mut name = String::new();
+        let mut raw_value = String::new();
+        let mut resource_id = None;
+        let mut compiled_item = None;
+
+        for (field, value) in ProtoFields::new(data) {
+            match field {
+                1 => namespace = value.as_string().filter(|namespace| !namespace.is_empty()),
+                2 => name = value.as_string().unwrap_or_default(),
+                3 => raw_value = value.as_string().unwrap_or_default(),
+                5 => resource_id = value.as_u64().map(|id| id as u32).filter(|id| *id != 0),
+                6 => compiled_item = value.as_bytes(),
+                _ => { }
+            }
+        }
+
+        let name = resource_id
+            .and_then(android_attribute_name)
+            .map(String::from)
+            .unwrap_or(name);
+        let value = match compiled_item {
+            Some(item) => XmlValue::from_proto_item(item, &raw_value),
+            None => XmlValue::String(raw_value)
+        };
+
+        XmlAttribute { namespace, name, resource_id, value }
+    }
+
+    /// Return a reference to the root element
+    pub fn root(&self) -> &XmlElement {
+        &self.root
+    }
+
+    /// Get the prefix of a namespace given its URI
+    pub fn prefix(&self, uri: &str) -> Option<&str> {
+        self.prefixes
+            .get(uri)
+            .map(|prefix| prefix.as_str())
+    }
+}
+
+impl fmt::Display for XmlDocument {
+    /// Write the document as text XML
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "<?xml version=\"1.0\" encoding=\"utf-8\"?>")?;
+        self.root.write_xml(f, &self.prefixes, 0)
+    }
+}
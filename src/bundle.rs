@@ -0,0 +1,565 @@
+//! Split APKs and app bundles
+//!
+//! Applications from stores often come as a base APK with splits (ABIs,
+//! screen densities, languages, dynamic features), either as separate files,
+//! packed in an `.apks` (bundletool) or `.xapk` archive, or as the `.aab`
+//! Android App Bundle uploaded to the Play Store. [`ApkBundle`] loads every
+//! part and merges their classes and resources into one view.
+//!
+//! Shuriken only parses files, so the APKs and DEX files packed in an
+//! archive are extracted to a temporary directory, removed with the bundle.
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::hash::{ BuildHasher, Hasher, RandomState };
+use std::io::ErrorKind;
+use std::path::{ Path, PathBuf };
+use std::process;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::time::SystemTime;
+
+use crate::{ ApkContext, DexContext };
+use crate::analysis::DvmClassAnalysis;
+use crate::axml::XmlDocument;
+use crate::call_graph::CallGraph;
+use crate::dead_code::DeadCodeReport;
+use crate::dex_file::DexFile;
+use crate::entry_points::EntryPoints;
+use crate::hierarchy::{ ClassHierarchy, DispatchResolution };
+use crate::keep_rules::KeepRule;
+use crate::manifest::AndroidManifest;
+use crate::resources::ResourceTable;
+use crate::zip_file::ZipArchive;
+
+/// Format of the input of an [`ApkBundle`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BundleFormat {
+    /// Single APK
+    Apk,
+    /// Base and split APKs given as separate files
+    SplitApks,
+    /// `.apks` archive produced by bundletool
+    Apks,
+    /// `.xapk` archive, with a `manifest.json` next to the APKs
+    Xapk,
+    /// Android App Bundle, with one directory per module
+    Aab
+}
+
+impl BundleFormat {
+    /// Detect the format of an archive from its entries
+    fn detect(archive: &ZipArchive) -> Option<Self> {
+        let mut names = archive.entries()
+            .iter()
+            .map(|entry| entry.name());
+
+        if archive.entry("BundleConfig.pb").is_some()
+            || names.clone().any(|name| name.ends_with("/manifest/AndroidManifest.xml"))
+        {
+            Some(BundleFormat::Aab)
+        } else if archive.entry("AndroidManifest.xml").is_some() {
+            Some(BundleFormat::Apk)
+        } else if !names.any(|name| name.ends_with(".apk")) {
+            None
+        } else if archive.entry("manifest.json").is_some() {
+            Some(BundleFormat::Xapk)
+        } else {
+            Some(BundleFormat::Apks)
+        }
+    }
+}
+
+/// Temporary directory holding the files extracted from an archive
+#[derive(Debug)]
+pub(crate) struct ExtractDir(PathBuf);
+
+impl ExtractDir {
+    /// Number of names tried before giving up
+    const ATTEMPTS: usize = 16;
+
+    /// Create a new directory with a random name in the temporary directory
+    ///
+    /// An existing directory is never reused, as it may have been created by
+    /// another user to feed files to the analysis.
+    pub(crate) fn create() -> Option<Self> {
+        for _ in 0..Self::ATTEMPTS {
+            let path = env::temp_dir().join(format!(
+                "shuriken-bundle-{}-{:016x}",
+                process::id(),
+                Self::random_suffix()
+            ));
+
+            match fs::create_dir(&path) {
+                Ok(()) => return Some(Self(path)),
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+                Err(_) => return None
+            }
+        }
+
+        None
+    }
+
+    /// Get a random number, from the random keys of the standard hasher
+    fn random_suffix() -> u64 {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_usize(COUNTER.fetch_add(1, Ordering::Relaxed));
+        if let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+            hasher.write_u128(elapsed.as_nanos());
+        }
+        hasher.finish()
+    }
+
+    /// Write an entry of the archive, flattening its path
+    pub(crate) fn write(&self, name: &str, data: &[u8]) -> Option<PathBuf> {
+        let path = self.0.join(Self::escape(name));
+        fs::write(&path, data).ok()?;
+        Some(path)
+    }
+
+    /// Escape an entry name into a single file name, distinct for every
+    /// entry name
+    ///
+    /// The bytes other than ASCII letters, digits, `-`, `_` and `.` are
+    /// percent-encoded, as are the dots of `.` and `..`.
+    fn escape(name: &str) -> String {
+        let only_dots = name.bytes().all(|byte| byte == b'.');
+
+        name.bytes()
+            .map(|byte| match byte {
+                b'.' if !only_dots => String::from("."),
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => char::from(byte).to_string(),
+                _ => format!("%{byte:02X}")
+            })
+            .collect()
+    }
+}
+
+impl Drop for ExtractDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Part of an [`ApkBundle`]: the base or a split APK, or a module of an app bundle
+#[derive(Debug)]
+pub struct ApkSplit {
+    /// Name of the split, `base` for the base APK
+    name: String,
+    manifest: Option<AndroidManifest>,
+    /// Context of the APK, `None` for the modules of an app bundle
+    apk_context: Option<ApkContext>,
+    /// Contexts of the DEX files of an app bundle module, with their entry names
+    dex_contexts: Vec<(String, DexContext)>
+}
+
+impl ApkSplit {
+    /// Name of the base split
+    const BASE: &'static str = "base";
+
+    fn from_apk(filepath: &Path, create_xrefs: bool) -> Self {
+        let context = ApkContext::parse_apk(filepath, create_xrefs);
+        let manifest = context.get_android_manifest();
+
+        Self {
+            name: manifest.as_ref()
+                .and_then(|manifest| manifest.split_name())
+                .unwrap_or(Self::BASE)
+                .to_string(),
+            manifest,
+            apk_context: Some(context),
+            dex_contexts: Vec::new()
+        }
+    }
+
+    fn from_module(archive: &ZipArchive, extract_dir: &ExtractDir, module: &str, create_xrefs: bool) -> Self {
+        let manifest = archive.read_by_name(&format!("{module}/manifest/AndroidManifest.xml"))
+            .and_then(|data| AndroidManifest::from_document(XmlDocument::parse_proto(&data)?));
+
+        let dex_prefix = format!("{module}/dex/");
+        let dex_contexts = archive.entries()
+            .iter()
+            .filter(|entry| entry.name().starts_with(&dex_prefix) && entry.name().ends_with(".dex"))
+            .filter_map(|entry| {
+                let path = extract_dir.write(entry.name(), &archive.read(entry)?)?;
+
+                let context = DexContext::parse_dex(&path);
+                context.disassemble_dex();
+                context.create_dex_analysis(create_xrefs);
+                context.analyze_classes();
+
+                Some((entry.name().to_string(), context))
+            })
+            .collect();
+
+        Self {
+            name: manifest.as_ref()
+                .and_then(|manifest| manifest.split_name())
+                .unwrap_or(module)
+                .to_string(),
+            manifest,
+            apk_context: None,
+            dex_contexts
+        }
+    }
+
+    /// Return a reference to the name of the split, `base` for the base APK
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether this is the base APK or the base module
+    pub fn is_base(&self) -> bool {
+        self.name == Self::BASE
+    }
+
+    /// Return a reference to the manifest of the split
+    pub fn manifest(&self) -> Option<&AndroidManifest> {
+        self.manifest.as_ref()
+    }
+
+    /// Return a reference to the context of the APK
+    ///
+    /// `None` for the modules of an app bundle, see [`ApkSplit::dex_contexts`].
+    pub fn apk_context(&self) -> Option<&ApkContext> {
+        self.apk_context.as_ref()
+    }
+
+    /// Return a reference to the contexts of the DEX files of an app bundle
+    /// module, with their entry names
+    pub fn dex_contexts(&self) -> &[(String, DexContext)] {
+        &self.dex_contexts
+    }
+
+    /// Obtain the `DvmClassAnalysis` of every class defined in the split
+    pub fn get_analyzed_classes(&self) -> Vec<DvmClassAnalysis> {
+        let mut classes = self.apk_context
+            .as_ref()
+            .map(|context| context.get_analyzed_classes_from_apk())
+            .unwrap_or_default();
+
+        for (_, context) in &self.dex_contexts {
+            classes.extend(context.get_analyzed_classes());
+        }
+
+        classes
+    }
+
+    /// Get the raw DEX files of the split, in the order they are loaded
+    fn dex_files(&self) -> Vec<&DexFile> {
+        let mut dex_files = self.apk_context
+            .as_ref()
+            .map(|context| context.loaded_dex_files())
+            .unwrap_or_default();
+        dex_files.extend(self.dex_contexts.iter().filter_map(|(_, context)| context.dex_file.as_ref()));
+
+        dex_files
+    }
+
+    /// Obtain a `DvmClassAnalysis` given its name, preferring the split
+    /// defining the class over the ones only referencing it
+    fn get_analyzed_class(&self, class_name: &str) -> Option<DvmClassAnalysis> {
+        let apk_class = self.apk_context
+            .as_ref()
+            .and_then(|context| context.get_analyzed_class_from_apk(class_name));
+
+        let mut external = None;
+        for class in apk_class.into_iter().chain(self.dex_contexts
+            .iter()
+            .filter_map(|(_, context)| context.get_analyzed_class(class_name)))
+        {
+            if !class.is_external() {
+                return Some(class);
+            }
+            external = external.or(Some(class));
+        }
+
+        external
+    }
+}
+
+/// Application made of a base APK and its splits
+#[derive(Debug)]
+pub struct ApkBundle {
+    format: BundleFormat,
+    /// Splits of the application, base first
+    splits: Vec<ApkSplit>,
+    /// Files extracted from the archive, declared last so the contexts are
+    /// destroyed before their files are removed
+    _extract_dir: Option<ExtractDir>
+}
+
+impl ApkBundle {
+    /// Parse an APK, an `.apks` or `.xapk` archive or an `.aab` app bundle
+    ///
+    /// The format is detected from the entries of the archive. For `.apks`
+    /// archives holding both, the split APKs are preferred over the
+    /// standalone ones. Returns `None` if the file is not one of those
+    /// formats.
+    pub fn parse(filepath: &Path, create_xrefs: bool) -> Option<Self> {
+        let archive = ZipArchive::from_path(filepath)?;
+        let format = BundleFormat::detect(&archive)?;
+
+        if format == BundleFormat::Apk {
+            return Some(Self::from_splits(format, vec![ApkSplit::from_apk(filepath, create_xrefs)], None));
+        }
+
+        let extract_dir = ExtractDir::create()?;
+
+        let splits = if format == BundleFormat::Aab {
+            let mut modules: Vec<&str> = Vec::new();
+            for entry in archive.entries() {
+                if let Some(module) = entry.name().strip_suffix("/manifest/AndroidManifest.xml")
+                    && !module.contains('/')
+                    && !modules.contains(&module)
+                {
+                    modules.push(module);
+                }
+            }
+
+            modules.iter()
+                .map(|module| ApkSplit::from_module(&archive, &extract_dir, module, create_xrefs))
+                .collect()
+        } else {
+            let apks = archive.entries()
+                .iter()
+                .filter(|entry| entry.name().ends_with(".apk"))
+                .collect::<Vec<_>>();
+            let has_splits = apks.iter().any(|entry| entry.name().starts_with("splits/"));
+
+            apks.into_iter()
+                .filter(|entry| !has_splits || entry.name().starts_with("splits/"))
+                .filter_map(|entry| extract_dir.write(entry.name(), &archive.read(entry)?))
+                .map(|path| ApkSplit::from_apk(&path, create_xrefs))
+                .collect()
+        };
+
+        Some(Self::from_splits(format, splits, Some(extract_dir)))
+    }
+
+    /// Parse a base APK and its splits given as separate files, in any order
+    ///
+    /// Returns `None` if no file is given.
+    pub fn from_split_apks(filepaths: &[&Path], create_xrefs: bool) -> Option<Self> {
+        if filepaths.is_empty() {
+            return None;
+        }
+
+        let splits = filepaths.iter()
+            .map(|filepath| ApkSplit::from_apk(filepath, create_xrefs))
+            .collect();
+
+        Some(Self::from_splits(BundleFormat::SplitApks, splits, None))
+    }
+
+    fn from_splits(format: BundleFormat, mut splits: Vec<ApkSplit>, extract_dir: Option<ExtractDir>) -> Self {
+        // Stable sort, the other splits keep their order
+        splits.sort_by_key(|split| !split.is_base());
+
+        Self { format, splits, _extract_dir: extract_dir }
+    }
+
+    /// Return the format of the input
+    pub fn format(&self) -> BundleFormat {
+        self.format
+    }
+
+    /// Return a reference to the splits, base first
+    pub fn splits(&self) -> &[ApkSplit] {
+        &self.splits
+    }
+
+    /// Return a reference to the base split
+    pub fn base(&self) -> Option<&ApkSplit> {
+        self.splits
+            .first()
+            .filter(|split| split.is_base())
+    }
+
+    /// Get a split given its name
+    pub fn split(&self, name: &str) -> Option<&ApkSplit> {
+        self.splits
+            .iter()
+            .find(|split| split.name == name)
+    }
+
+    /// Return a reference to the manifest of the base split
+    pub fn get_android_manifest(&self) -> Option<&AndroidManifest> {
+        self.base()?.manifest()
+    }
+
+    /// Decode the resource tables of the splits and merge them
+    ///
+    /// Returns `None` if no split has a resource table. The resources of app
+    /// bundles are stored as protobuf and are not decoded.
+    pub fn get_resource_table(&self) -> Option<ResourceTable> {
+        let mut tables = self.splits
+            .iter()
+            .filter_map(|split| split.apk_context.as_ref()?.get_resource_table());
+
+        let mut table = tables.next()?;
+        for split_table in tables {
+            table.merge(split_table);
+        }
+
+        Some(table)
+    }
+
+    /// Obtain the `DvmClassAnalysis` of every class defined in the splits
+    ///
+    /// A class defined by several splits is returned once, from the first one.
+    pub fn get_analyzed_classes(&self) -> Vec<DvmClassAnalysis> {
+        let mut names = HashSet::new();
+
+        self.splits
+            .iter()
+            .flat_map(|split| split.get_analyzed_classes())
+            .filter(|class| names.insert(class.name().to_string()))
+            .collect()
+    }
+
+    /// Obtain a `DvmClassAnalysis` given its name, from the split defining it
+    pub fn get_analyzed_class(&self, class_name: &str) -> Option<DvmClassAnalysis> {
+        let mut external = None;
+        for class in self.splits.iter().filter_map(|split| split.get_analyzed_class(class_name)) {
+            if !class.is_external() {
+                return Some(class);
+            }
+            external = external.or(Some(class));
+        }
+
+        external
+    }
+
+    /// Build the class hierarchy of the classes defined in the splits
+    pub fn get_class_hierarchy(&self) -> ClassHierarchy {
+        self.class_hierarchy_of(&self.get_analyzed_classes())
+    }
+
+    /// Build the class hierarchy of already analyzed classes
+    fn class_hierarchy_of(&self, classes: &[DvmClassAnalysis]) -> ClassHierarchy {
+        let mut hierarchy = ClassHierarchy::from_classes(classes);
+        hierarchy.add_missing_supertypes(|class_name| self.get_analyzed_class(class_name));
+        // The first definition of a class, from the first split, wins
+        let dex_files = self.splits
+            .iter()
+            .flat_map(ApkSplit::dex_files)
+            .collect::<Vec<&DexFile>>();
+        for dex_file in dex_files.into_iter().rev() {
+            hierarchy.resolve_from_dex(dex_file);
+        }
+        hierarchy
+    }
+
+    /// Build the call graph of the splits
+    ///
+    /// Calls from a split to the classes of another one are resolved as
+    /// well. The splits must have been parsed with xrefs.
+    pub fn get_call_graph(&self, resolution: DispatchResolution) -> CallGraph {
+        let classes = self.get_analyzed_classes();
+        let hierarchy = self.class_hierarchy_of(&classes);
+
+        CallGraph::build(&classes, &hierarchy, resolution)
+    }
+
+    /// Find the Android entry points of the classes defined in the splits
+    pub fn get_entry_points(&self) -> EntryPoints {
+        EntryPoints::find(&self.get_class_hierarchy())
+    }
+
+    /// Find the dead code of the classes defined in the splits
+    ///
+    /// Code of a split only used by another split is not reported. The
+    /// splits must have been parsed with xrefs.
+    pub fn get_dead_code_report(&self, keep_rules: &[KeepRule]) -> DeadCodeReport {
+        let classes = self.get_analyzed_classes();
+        let hierarchy = self.class_hierarchy_of(&classes);
+        let call_graph = CallGraph::build(&classes, &hierarchy, DispatchResolution::Cha);
+        let entry_points = EntryPoints::find(&hierarchy);
+
+        DeadCodeReport::analyze(&hierarchy, &call_graph, &entry_points.roots(), keep_rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::axml::XmlValue;
+    use crate::resources::ResourceValue;
+
+    #[test]
+    fn test_detect_format() {
+        let format = |path: &str| BundleFormat::detect(&ZipArchive::from_path(Path::new(path)).unwrap());
+
+        assert_eq!(format("test_files/test_resources.apk"), Some(BundleFormat::Apk));
+        assert_eq!(format("test_files/test_bundle.apks"), Some(BundleFormat::Apks));
+        assert_eq!(format("test_files/test_bundle.xapk"), Some(BundleFormat::Xapk));
+        assert_eq!(format("test_files/test_bundle.aab"), Some(BundleFormat::Aab));
+        assert_eq!(format("test_files/test_zip.apk"), None);
+    }
+
+    #[test]
+    fn test_extract_dir_names() {
+        assert_eq!(ExtractDir::escape("splits/base-master.apk"), "splits%2Fbase-master.apk");
+        assert_ne!(ExtractDir::escape("a/b_c.apk"), ExtractDir::escape("a_b/c.apk"));
+        assert_ne!(ExtractDir::escape("a%2Fb.apk"), ExtractDir::escape("a/b.apk"));
+        assert_eq!(ExtractDir::escape(".."), "%2E%2E");
+
+        let extract_dir = ExtractDir::create().unwrap();
+        let first = extract_dir.write("a/b_c.apk", b"first").unwrap();
+        let second = extract_dir.write("a_b/c.apk", b"second").unwrap();
+        assert_eq!(fs::read(first).unwrap(), b"first");
+        assert_eq!(fs::read(second).unwrap(), b"second");
+
+        // Every directory is new and empty
+        let other = ExtractDir::create().unwrap();
+        assert_ne!(other.0, extract_dir.0);
+        assert!(fs::read_dir(&other.0).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_aab_manifest() {
+        let archive = ZipArchive::from_path(Path::new("test_files/test_bundle.aab")).unwrap();
+
+        let data = archive.read_by_name("base/manifest/AndroidManifest.xml").unwrap();
+        let manifest = AndroidManifest::from_document(XmlDocument::parse_proto(&data).unwrap()).unwrap();
+        assert_eq!(manifest.package(), "com.example.test");
+        assert_eq!(manifest.split_name(), None);
+        assert_eq!(manifest.version_code(), Some(42));
+        assert_eq!(manifest.version_name(), Some("1.2.3"));
+        assert_eq!(manifest.min_sdk_version(), Some(21));
+        assert_eq!(manifest.application_name(), Some("com.example.test.App"));
+        assert_eq!(manifest.activities()[0].name(), "com.example.test.MainActivity");
+        assert_eq!(manifest.activities()[0].exported(), Some(false));
+        assert_eq!(manifest.document().root().children()[0].line(), 2);
+        assert_eq!(manifest.document().prefix("http://schemas.android.com/apk/res/android"), Some("android"));
+
+        let data = archive.read_by_name("dynamic/manifest/AndroidManifest.xml").unwrap();
+        let manifest = AndroidManifest::from_document(XmlDocument::parse_proto(&data).unwrap()).unwrap();
+        assert_eq!(manifest.split_name(), Some("dynamic"));
+    }
+
+    #[test]
+    fn test_merge_resources() {
+        let archive = ZipArchive::from_path(Path::new("test_files/test_bundle.apks")).unwrap();
+        let table = |name: &str| {
+            let apk = ZipArchive::from_bytes(archive.read_by_name(name).unwrap()).unwrap();
+            ResourceTable::parse(&apk.read_by_name("resources.arsc").unwrap()).unwrap()
+        };
+
+        let mut resources = table("splits/base-master.apk");
+        resources.merge(table("splits/base-de.apk"));
+
+        assert_eq!(resources.packages().len(), 1);
+        let entries = resources.get_entries(0x7f020000);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].config().language(), "de");
+        assert_eq!(
+            entries[2].value(),
+            &ResourceValue::Simple(XmlValue::String(String::from("Test DE")))
+        );
+        assert_eq!(resources.get_name(0x7f020001).unwrap().to_java(), "R.string.api_key");
+    }
+}
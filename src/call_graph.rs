@@ -0,0 +1,202 @@
+//! Call graph
+//!
+//! Build a call graph from the `xrefto` cross-references of the analyzed
+//! methods. Calls through `invoke-virtual` and `invoke-interface` are
+//! additionally resolved to their possible concrete targets using the
+//! [`ClassHierarchy`].
+
+use std::collections::{ HashMap, HashSet, VecDeque };
+
+use crate::dex_file;
+use crate::analysis::{ DvmClassAnalysis, DvmMethodAnalysis };
+use crate::hierarchy::{ ClassHierarchy, DispatchResolution };
+
+/// Kind of edge in the call graph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallEdgeKind {
+    /// Static, direct or super call, the target is the declared one
+    Direct,
+    /// Virtual or interface call to the declared target
+    Virtual,
+    /// Possible concrete target of a virtual or interface call
+    Dispatched
+}
+
+/// Edge of the call graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEdge {
+    /// Full name of the calling method
+    caller: String,
+    /// Full name of the called method
+    callee: String,
+    /// Address of the call instruction in the caller
+    idx: u64,
+    /// Kind of edge
+    kind: CallEdgeKind
+}
+
+impl CallEdge {
+    /// Return a reference to the full name of the calling method
+    pub fn caller(&self) -> &str {
+        &self.caller
+    }
+
+    /// Return a reference to the full name of the called method
+    pub fn callee(&self) -> &str {
+        &self.callee
+    }
+
+    /// Return the address of the call instruction in the caller
+    pub fn idx(&self) -> u64 {
+        self.idx
+    }
+
+    /// Return the kind of edge
+    pub fn kind(&self) -> CallEdgeKind {
+        self.kind
+    }
+}
+
+/// Call graph of a set of analyzed classes
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    /// Every edge of the graph
+    edges: Vec<CallEdge>,
+    /// Indexes of the edges leaving a method
+    by_caller: HashMap<String, Vec<usize>>,
+    /// Indexes of the edges reaching a method
+    by_callee: HashMap<String, Vec<usize>>,
+    /// Every method present in the graph
+    methods: HashSet<String>
+}
+
+impl CallGraph {
+    /// Opcodes of `invoke-virtual`, `invoke-interface` and their range variants
+    const VIRTUAL_INVOKES: [u32; 4] = [0x6e, 0x72, 0x74, 0x78];
+
+    /// Build the call graph of a list of analyzed classes
+    ///
+    /// The xrefs must have been created during the analysis for the graph to
+    /// contain any edge.
+    pub fn build(
+        classes: &[DvmClassAnalysis],
+        hierarchy: &ClassHierarchy,
+        resolution: DispatchResolution
+    ) -> Self {
+        let mut graph = Self::default();
+
+        for method in classes.iter().flat_map(|class| class.methods()) {
+            graph.add_method(method, hierarchy, resolution);
+        }
+
+        graph
+    }
+
+    /// Add the outgoing calls of a method to the graph
+    pub fn add_method(
+        &mut self,
+        method: &DvmMethodAnalysis,
+        hierarchy: &ClassHierarchy,
+        resolution: DispatchResolution
+    ) {
+        self.methods.insert(method.full_name().to_string());
+
+        for xref in method.xrefto() {
+            let op = method.basic_blocks()
+                .blocks()
+                .iter()
+                .flat_map(|block| block.instructions())
+                .find(|instruction| instruction.address() == xref.idx())
+                .map(|instruction| instruction.op());
+
+            if !op.is_some_and(|op| Self::VIRTUAL_INVOKES.contains(&op)) {
+                self.add_edge(method.full_name(), xref.method(), xref.idx(), CallEdgeKind::Direct);
+                continue;
+            }
+
+            self.add_edge(method.full_name(), xref.method(), xref.idx(), CallEdgeKind::Virtual);
+
+            let Some((class_name, method_name, descriptor)) = dex_file::split_method_name(xref.method()) else {
+                continue;
+            };
+
+            for target in hierarchy.dispatch_targets(class_name, method_name, descriptor, resolution) {
+                if target.full_name() != xref.method() {
+                    self.add_edge(method.full_name(), target.full_name(), xref.idx(), CallEdgeKind::Dispatched);
+                }
+            }
+        }
+    }
+
+    /// Add an edge to the graph
+    pub fn add_edge(&mut self, caller: &str, callee: &str, idx: u64, kind: CallEdgeKind) {
+        let edge_idx = self.edges.len();
+
+        self.edges.push(CallEdge {
+            caller: caller.to_string(),
+            callee: callee.to_string(),
+            idx,
+            kind
+        });
+
+        self.by_caller.entry(caller.to_string()).or_default().push(edge_idx);
+        self.by_callee.entry(callee.to_string()).or_default().push(edge_idx);
+        self.methods.insert(caller.to_string());
+        self.methods.insert(callee.to_string());
+    }
+
+    /// Return a reference to every edge of the graph
+    pub fn edges(&self) -> &[CallEdge] {
+        &self.edges
+    }
+
+    /// Get every method of the graph
+    pub fn methods(&self) -> impl Iterator<Item = &str> {
+        self.methods.iter().map(|method| method.as_str())
+    }
+
+    /// Get the edges leaving a method
+    pub fn callees(&self, method: &str) -> Vec<&CallEdge> {
+        self.by_caller
+            .get(method)
+            .into_iter()
+            .flatten()
+            .map(|&idx| &self.edges[idx])
+            .collect()
+    }
+
+    /// Get the edges reaching a method
+    pub fn callers(&self, method: &str) -> Vec<&CallEdge> {
+        self.by_callee
+            .get(method)
+            .into_iter()
+            .flatten()
+            .map(|&idx| &self.edges[idx])
+            .collect()
+    }
+
+    /// Get the methods reachable from a set of root methods, roots included
+    pub fn reachable_from<'a, I>(&self, roots: I) -> HashSet<String>
+    where
+        I: IntoIterator<Item = &'a str>
+    {
+        let mut reachable: HashSet<String> = HashSet::new();
+        let mut worklist: VecDeque<String> = VecDeque::new();
+
+        for root in roots {
+            if reachable.insert(root.to_string()) {
+                worklist.push_back(root.to_string());
+            }
+        }
+
+        while let Some(method) = worklist.pop_front() {
+            for edge in self.callees(&method) {
+                if reachable.insert(edge.callee.clone()) {
+                    worklist.push_back(edge.callee.clone());
+                }
+            }
+        }
+
+        reachable
+    }
+}
@@ -0,0 +1,267 @@
+//! Call sites and method handles
+//!
+//! DEX 038 added the `call_site_ids` and `method_handles` sections, used by
+//! `invoke-custom`, `invoke-polymorphic`, `const-method-handle` and
+//! `const-method-type`. Shuriken does not expose them and does not record the
+//! methods these instructions reach, so they are read from the DEX file.
+//!
+//! See the [`documentation`] for the format of the sections.
+//!
+//! [`documentation`]: https://source.android.com/docs/core/runtime/dex-format#call-site-id-item
+
+use crate::dex_file::{ CodeItem, DexFile };
+use crate::encoded_value::EncodedValue;
+
+/// Opcode of `invoke-polymorphic`
+pub(crate) const INVOKE_POLYMORPHIC: u32 = 0xfa;
+/// Opcode of `invoke-polymorphic/range`
+pub(crate) const INVOKE_POLYMORPHIC_RANGE: u32 = 0xfb;
+/// Opcode of `invoke-custom`
+pub(crate) const INVOKE_CUSTOM: u32 = 0xfc;
+/// Opcode of `invoke-custom/range`
+pub(crate) const INVOKE_CUSTOM_RANGE: u32 = 0xfd;
+/// Opcode of `const-method-handle`
+pub(crate) const CONST_METHOD_HANDLE: u32 = 0xfe;
+/// Opcode of `const-method-type`
+pub(crate) const CONST_METHOD_TYPE: u32 = 0xff;
+
+/// Kind of a method handle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MethodHandleKind {
+    StaticPut,
+    StaticGet,
+    InstancePut,
+    InstanceGet,
+    InvokeStatic,
+    InvokeInstance,
+    InvokeConstructor,
+    InvokeDirect,
+    InvokeInterface
+}
+
+impl MethodHandleKind {
+    fn from_value(value: u16) -> Option<Self> {
+        match value {
+            0x00 => Some(MethodHandleKind::StaticPut),
+            0x01 => Some(MethodHandleKind::StaticGet),
+            0x02 => Some(MethodHandleKind::InstancePut),
+            0x03 => Some(MethodHandleKind::InstanceGet),
+            0x04 => Some(MethodHandleKind::InvokeStatic),
+            0x05 => Some(MethodHandleKind::InvokeInstance),
+            0x06 => Some(MethodHandleKind::InvokeConstructor),
+            0x07 => Some(MethodHandleKind::InvokeDirect),
+            0x08 => Some(MethodHandleKind::InvokeInterface),
+            _ => None
+        }
+    }
+
+    /// Whether the handle reads or writes a field, instead of invoking a method
+    pub fn is_field_accessor(&self) -> bool {
+        matches!(
+            self,
+            MethodHandleKind::StaticPut | MethodHandleKind::StaticGet
+                | MethodHandleKind::InstancePut | MethodHandleKind::InstanceGet
+        )
+    }
+}
+
+/// Method handle of the `method_handles` section
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodHandle {
+    kind: MethodHandleKind,
+    /// Full name of the field or of the method
+    target: String
+}
+
+impl MethodHandle {
+    /// Read a method handle given its index in `method_handles`
+    pub(crate) fn read(dex: &DexFile, idx: u32) -> Option<Self> {
+        let (size, off) = dex.method_handles();
+        if idx >= size {
+            return None;
+        }
+
+        let mut reader = dex.reader(off + idx as usize * 8);
+        let kind = MethodHandleKind::from_value(reader.read_u16()?)?;
+        reader.read_u16()?;
+        let target_idx = reader.read_u16()? as u32;
+
+        let target = match kind.is_field_accessor() {
+            true => dex.field_full_name(target_idx)?,
+            false => dex.method_full_name(target_idx)?
+        };
+
+        Some(Self { kind, target })
+    }
+
+    /// Read every method handle of a DEX file
+    pub(crate) fn read_all(dex: &DexFile) -> Vec<Self> {
+        (0..dex.method_handles().0)
+            .filter_map(|idx| Self::read(dex, idx))
+            .collect()
+    }
+
+    /// Return the kind of handle
+    pub fn kind(&self) -> MethodHandleKind {
+        self.kind
+    }
+
+    /// Return a reference to the full name of the field or of the method
+    /// referenced by the handle
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+}
+
+/// Call site of the `call_site_ids` section, the target of an `invoke-custom`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSite {
+    bootstrap: MethodHandle,
+    method_name: String,
+    method_type: String,
+    arguments: Vec<EncodedValue>,
+    /// Method handles passed in `arguments`, in order
+    argument_handles: Vec<MethodHandle>
+}
+
+impl CallSite {
+    /// Read a call site given its index in `call_site_ids`
+    pub(crate) fn read(dex: &DexFile, idx: u32) -> Option<Self> {
+        let (size, off) = dex.call_site_ids();
+        if idx >= size {
+            return None;
+        }
+
+        let call_site_off = dex.u32_at(off + idx as usize * 4)? as usize;
+        let mut values = EncodedValue::read_array(dex, &mut dex.reader(call_site_off))?.into_iter();
+
+        let bootstrap = match values.next()? {
+            EncodedValue::MethodHandle(handle_idx) => MethodHandle::read(dex, handle_idx)?,
+            _ => return None
+        };
+        let method_name = values.next()?.as_str()?.to_string();
+        let method_type = match values.next()? {
+            EncodedValue::MethodType(method_type) => method_type,
+            _ => return None
+        };
+
+        let arguments = values.collect::<Vec<EncodedValue>>();
+        let argument_handles = arguments.iter()
+            .filter_map(|argument| match argument {
+                EncodedValue::MethodHandle(handle_idx) => MethodHandle::read(dex, *handle_idx),
+                _ => None
+            })
+            .collect();
+
+        Some(Self { bootstrap, method_name, method_type, arguments, argument_handles })
+    }
+
+    /// Read every call site of a DEX file
+    pub(crate) fn read_all(dex: &DexFile) -> Vec<Self> {
+        (0..dex.call_site_ids().0)
+            .filter_map(|idx| Self::read(dex, idx))
+            .collect()
+    }
+
+    /// Return a reference to the bootstrap method handle, like
+    /// `LambdaMetafactory.metafactory`
+    pub fn bootstrap(&self) -> &MethodHandle {
+        &self.bootstrap
+    }
+
+    /// Return a reference to the name of the method linked by the call site,
+    /// like `run` for a `Runnable` lambda
+    pub fn method_name(&self) -> &str {
+        &self.method_name
+    }
+
+    /// Return a reference to the descriptor of the prototype of the call site
+    pub fn method_type(&self) -> &str {
+        &self.method_type
+    }
+
+    /// Return a reference to the additional arguments of the bootstrap method
+    pub fn arguments(&self) -> &[EncodedValue] {
+        &self.arguments
+    }
+
+    /// Return a reference to the method handles passed as arguments, with
+    /// their index resolved
+    pub fn argument_handles(&self) -> &[MethodHandle] {
+        &self.argument_handles
+    }
+
+    /// Whether the bootstrap method is `LambdaMetafactory`, used for lambdas
+    /// and method references
+    pub fn is_lambda_metafactory(&self) -> bool {
+        self.bootstrap.target.starts_with("Ljava/lang/invoke/LambdaMetafactory;->")
+    }
+
+    /// Get the method invoked through the call site: the first method handle
+    /// argument, like the body of a lambda, if any
+    pub fn implementation(&self) -> Option<&MethodHandle> {
+        self.argument_handles
+            .iter()
+            .find(|handle| !handle.kind.is_field_accessor())
+    }
+}
+
+/// Reference of an instruction using a call site, a method handle or a
+/// method type
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicXref {
+    /// `invoke-custom`, with the index of the call site
+    CallSite { idx: u64, call_site_idx: u32, call_site: CallSite },
+    /// `invoke-polymorphic` of a signature polymorphic method, like
+    /// `MethodHandle.invokeExact`, with the prototype of the call
+    Polymorphic { idx: u64, method: String, prototype: String },
+    /// `const-method-handle`
+    MethodHandle { idx: u64, handle: MethodHandle },
+    /// `const-method-type`
+    MethodType { idx: u64, prototype: String }
+}
+
+impl DynamicXref {
+    /// Decode the reference of an instruction given its opcode and its address
+    /// in bytes, `None` for other instructions
+    pub(crate) fn read(dex: &DexFile, code: &CodeItem, op: u32, idx: u64) -> Option<Self> {
+        let unit = |n: u64| dex.u16_at(code.insns_off + idx as usize + n as usize * 2).map(|unit| unit as u32);
+
+        Some(match op {
+            INVOKE_CUSTOM | INVOKE_CUSTOM_RANGE => {
+                let call_site_idx = unit(1)?;
+                DynamicXref::CallSite { idx, call_site_idx, call_site: CallSite::read(dex, call_site_idx)? }
+            },
+            INVOKE_POLYMORPHIC | INVOKE_POLYMORPHIC_RANGE => DynamicXref::Polymorphic {
+                idx,
+                method: dex.method_full_name(unit(1)?)?,
+                prototype: dex.proto_descriptor(unit(3)?)?
+            },
+            CONST_METHOD_HANDLE => DynamicXref::MethodHandle { idx, handle: MethodHandle::read(dex, unit(1)?)? },
+            CONST_METHOD_TYPE => DynamicXref::MethodType { idx, prototype: dex.proto_descriptor(unit(1)?)? },
+            _ => return None
+        })
+    }
+
+    /// Return the address of the instruction
+    pub fn idx(&self) -> u64 {
+        match self {
+            DynamicXref::CallSite { idx, .. } | DynamicXref::Polymorphic { idx, .. }
+                | DynamicXref::MethodHandle { idx, .. } | DynamicXref::MethodType { idx, .. } => *idx
+        }
+    }
+
+    /// Get the full name of the method reached by the instruction: the
+    /// implementation of a call site, or its bootstrap method if there is
+    /// none, the polymorphic method, or the method of a method handle
+    pub fn target(&self) -> Option<&str> {
+        match self {
+            DynamicXref::CallSite { call_site, .. } => Some(call_site.implementation()
+                .unwrap_or(&call_site.bootstrap)
+                .target()),
+            DynamicXref::Polymorphic { method, .. } => Some(method),
+            DynamicXref::MethodHandle { handle, .. } => (!handle.kind.is_field_accessor()).then_some(handle.target()),
+            DynamicXref::MethodType { .. } => None
+        }
+    }
+}
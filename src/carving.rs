@@ -0,0 +1,201 @@
+//! DEX carving
+//!
+//! Locate the DEX files inside arbitrary data, such as the memory dumps of
+//! unpacking sandboxes, firmware images or unknown files. Candidates are
+//! found by their `dex\n0xx\0` magic, then validated with the fields of their
+//! header so random matches are discarded.
+//!
+//! The checksum is reported but not required: unpackers and dumps often
+//! leave patched DEX files whose checksum was not updated.
+
+use crate::dex_file::Reader;
+
+/// Size of a DEX header
+const HEADER_SIZE: usize = 0x70;
+/// Endian tag of little-endian DEX files
+const ENDIAN_CONSTANT: u32 = 0x1234_5678;
+
+/// DEX file found in a buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarvedDex {
+    /// Offset of the DEX file in the buffer
+    offset: usize,
+    /// Version from the magic, like `035`
+    version: String,
+    checksum_valid: bool,
+    data: Vec<u8>
+}
+
+impl CarvedDex {
+    /// Return the offset of the DEX file in the buffer
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Return the size of the DEX file, from its header
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Return a reference to the version of the DEX file, like `035`
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Whether the Adler-32 checksum of the header matches the content
+    pub fn has_valid_checksum(&self) -> bool {
+        self.checksum_valid
+    }
+
+    /// Return a reference to the bytes of the DEX file
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Check the header of a candidate DEX file and copy it out of the buffer
+    fn validate(buffer: &[u8], offset: usize) -> Option<Self> {
+        let data = &buffer[offset..];
+        let version = data.get(4..8)?;
+        if !version[..3].iter().all(u8::is_ascii_digit) || version[3] != 0 {
+            return None;
+        }
+
+        let mut reader = Reader::new(data, 8);
+        let checksum = reader.read_u32()?;
+        let _signature = reader.read_bytes(20)?;
+        let file_size = reader.read_u32()? as usize;
+        let header_size = reader.read_u32()? as usize;
+        let endian_tag = reader.read_u32()?;
+
+        if header_size != HEADER_SIZE || endian_tag != ENDIAN_CONSTANT || file_size < HEADER_SIZE || file_size > data.len() {
+            return None;
+        }
+
+        // (size, offset, item size) of the link, map, string IDs, type IDs,
+        // proto IDs, field IDs, method IDs, class definitions and data sections
+        let mut sections = Vec::new();
+        let map_offset = Reader::new(data, 0x34).read_u32()? as usize;
+        for item_size in [1, 0, 4, 4, 12, 8, 8, 32, 1] {
+            // The map is required and has an offset but no size
+            let (size, item_size) = match item_size {
+                0 => (1, 4),
+                _ => (reader.read_u32()? as usize, item_size)
+            };
+            sections.push((size, reader.read_u32()? as usize, item_size));
+        }
+
+        for (size, section_offset, item_size) in sections {
+            if size == 0 {
+                continue;
+            }
+
+            let end = size.checked_mul(item_size).and_then(|len| section_offset.checked_add(len))?;
+            if section_offset < HEADER_SIZE || end > file_size {
+                return None;
+            }
+        }
+
+        // The map list must fit in the file and start with the header item
+        let mut map = Reader::new(&data[..file_size], map_offset);
+        let map_size = map.read_u32()? as usize;
+        let (item_type, _, _, item_offset) = (map.read_u16()?, map.read_u16()?, map.read_u32()?, map.read_u32()?);
+        if item_type != 0 || item_offset != 0 || map_offset + 4 + map_size.checked_mul(12)? > file_size {
+            return None;
+        }
+
+        let data = data[..file_size].to_vec();
+
+        Some(Self {
+            offset,
+            version: String::from_utf8_lossy(&version[..3]).into_owned(),
+            checksum_valid: adler32(&data[12..]) == checksum,
+            data
+        })
+    }
+}
+
+/// Find the valid DEX files in a buffer
+///
+/// The search resumes after the end of every DEX file found, so the DEX
+/// files are not overlapping.
+pub fn carve_dex(buffer: &[u8]) -> Vec<CarvedDex> {
+    let mut carved = Vec::new();
+    let mut offset = 0;
+
+    while let Some(found) = buffer.get(offset..)
+        .and_then(|rest| rest.windows(4).position(|window| window == b"dex\n"))
+    {
+        let start = offset + found;
+
+        match CarvedDex::validate(buffer, start) {
+            Some(dex) => {
+                offset = start + dex.size();
+                carved.push(dex);
+            },
+            None => offset = start + 1
+        }
+    }
+
+    carved
+}
+
+/// Adler-32 checksum, as used by the DEX header
+fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    // Reducing every 5552 bytes keeps the sums below 2^32
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_carve_dex() {
+        let int_dex = fs::read("test_files/_int.dex").unwrap();
+        let pi_dex = fs::read("test_files/_pi.dex").unwrap();
+
+        let mut buffer = vec![0xcc; 100];
+        buffer.extend_from_slice(&int_dex);
+        // Magic with a garbage header
+        buffer.extend_from_slice(b"dex\n035\0garbage");
+        buffer.extend_from_slice(&[0x41; 33]);
+        let pi_offset = buffer.len();
+        buffer.extend_from_slice(&pi_dex);
+        // Truncated DEX file
+        buffer.extend_from_slice(&int_dex[..200]);
+
+        // Patched DEX file, whose checksum is wrong
+        let patched_offset = buffer.len();
+        let mut patched = pi_dex.clone();
+        let last = patched.len() - 1;
+        patched[last] ^= 0xff;
+        buffer.extend_from_slice(&patched);
+
+        let carved = carve_dex(&buffer);
+        let found = carved.iter()
+            .map(|dex| (dex.offset(), dex.size(), dex.has_valid_checksum()))
+            .collect::<Vec<(usize, usize, bool)>>();
+        assert_eq!(found, vec![
+            (100, int_dex.len(), true),
+            (pi_offset, pi_dex.len(), true),
+            (patched_offset, pi_dex.len(), false)
+        ]);
+
+        assert_eq!(carved[0].version(), "035");
+        assert_eq!(carved[1].data(), &pi_dex[..]);
+        assert!(carve_dex(b"dex\n").is_empty());
+    }
+}
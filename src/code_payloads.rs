@@ -0,0 +1,285 @@
+//! Switch and array payloads
+//!
+//! `packed-switch`, `sparse-switch` and `fill-array-data` point to a payload
+//! pseudo-instruction stored in the code of the method. Shuriken only reports
+//! the payload as a [`DexInstType::DexPackedSwitch`],
+//! [`DexInstType::DexSparseSwitch`] or [`DexInstType::DexFillArrayData`]
+//! instruction, so its contents are read from the DEX file.
+//!
+//! See the [`documentation`] for the format of the payloads.
+//!
+//! [`DexInstType::DexPackedSwitch`]: ../disassembler/enum.DexInstType.html#variant.DexPackedSwitch
+//! [`DexInstType::DexSparseSwitch`]: ../disassembler/enum.DexInstType.html#variant.DexSparseSwitch
+//! [`DexInstType::DexFillArrayData`]: ../disassembler/enum.DexInstType.html#variant.DexFillArrayData
+//! [`documentation`]: https://source.android.com/docs/core/runtime/dalvik-bytecode#packed-switch
+
+use crate::dex_file::{ CodeItem, DexFile, Reader };
+
+/// Opcode of `fill-array-data`
+pub(crate) const FILL_ARRAY_DATA: u32 = 0x26;
+/// Opcode of `packed-switch`
+pub(crate) const PACKED_SWITCH: u32 = 0x2b;
+/// Opcode of `sparse-switch`
+pub(crate) const SPARSE_SWITCH: u32 = 0x2c;
+
+/// Identifier of a `packed-switch-payload`
+const PACKED_SWITCH_PAYLOAD: u16 = 0x0100;
+/// Identifier of a `sparse-switch-payload`
+const SPARSE_SWITCH_PAYLOAD: u16 = 0x0200;
+/// Identifier of a `fill-array-data-payload`
+const FILL_ARRAY_DATA_PAYLOAD: u16 = 0x0300;
+
+/// Read the address of the payload of a 31t instruction given its address
+fn payload_address(dex: &DexFile, code: &CodeItem, address: u64) -> Option<u64> {
+    let offset = dex.u32_at(code.insns_off + address as usize + 2)? as i32;
+    address.checked_add_signed(offset as i64 * 2)
+}
+
+/// Kind of switch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SwitchKind {
+    /// `packed-switch`, with consecutive keys
+    Packed,
+    /// `sparse-switch`, with sorted keys
+    Sparse
+}
+
+/// Case of a switch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwitchCase {
+    key: i32,
+    target: u64
+}
+
+impl SwitchCase {
+    /// Return the value of the case
+    pub fn key(&self) -> i32 {
+        self.key
+    }
+
+    /// Return the address of the first instruction of the case
+    pub fn target(&self) -> u64 {
+        self.target
+    }
+}
+
+/// Key to target table of a `packed-switch` or a `sparse-switch`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchTable {
+    kind: SwitchKind,
+    /// Address of the switch instruction
+    address: u64,
+    /// Address of the payload
+    payload_address: u64,
+    cases: Vec<SwitchCase>
+}
+
+impl SwitchTable {
+    /// Decode the payload of a switch instruction given its opcode and its
+    /// address in bytes, `None` for other instructions
+    pub(crate) fn read(dex: &DexFile, code: &CodeItem, op: u32, address: u64) -> Option<Self> {
+        let kind = match op {
+            PACKED_SWITCH => SwitchKind::Packed,
+            SPARSE_SWITCH => SwitchKind::Sparse,
+            _ => return None
+        };
+        let payload_address = payload_address(dex, code, address)?;
+        let cases = Self::parse(&mut dex.reader(code.insns_off + payload_address as usize), kind, address)?;
+
+        Some(Self { kind, address, payload_address, cases })
+    }
+
+    /// Parse a switch payload, whose targets are relative to `address`
+    fn parse(reader: &mut Reader, kind: SwitchKind, address: u64) -> Option<Vec<SwitchCase>> {
+        let ident = match kind {
+            SwitchKind::Packed => PACKED_SWITCH_PAYLOAD,
+            SwitchKind::Sparse => SPARSE_SWITCH_PAYLOAD
+        };
+        if reader.read_u16()? != ident {
+            return None;
+        }
+
+        let size = reader.read_u16()? as usize;
+        let keys = match kind {
+            SwitchKind::Packed => {
+                let first_key = reader.read_u32()? as i32;
+                (0..size).map(|n| first_key.wrapping_add(n as i32)).collect::<Vec<i32>>()
+            },
+            SwitchKind::Sparse => (0..size)
+                .map(|_| reader.read_u32().map(|key| key as i32))
+                .collect::<Option<Vec<i32>>>()?
+        };
+
+        keys.into_iter()
+            .map(|key| {
+                let offset = reader.read_u32()? as i32;
+                let target = address.checked_add_signed(offset as i64 * 2)?;
+                Some(SwitchCase { key, target })
+            })
+            .collect()
+    }
+
+    /// Return the kind of switch
+    pub fn kind(&self) -> SwitchKind {
+        self.kind
+    }
+
+    /// Return the address of the switch instruction
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Return the address of the payload
+    pub fn payload_address(&self) -> u64 {
+        self.payload_address
+    }
+
+    /// Return a reference to the cases, in the order of the payload
+    pub fn cases(&self) -> &[SwitchCase] {
+        &self.cases
+    }
+
+    /// Get the address of the case of a given value, `None` if the switch
+    /// falls through
+    pub fn target(&self, key: i32) -> Option<u64> {
+        self.cases
+            .iter()
+            .find(|case| case.key == key)
+            .map(|case| case.target)
+    }
+}
+
+/// Elements of a `fill-array-data`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayData {
+    /// Address of the `fill-array-data` instruction
+    address: u64,
+    /// Address of the payload
+    payload_address: u64,
+    element_width: u16,
+    data: Vec<u8>
+}
+
+impl ArrayData {
+    /// Decode the payload of a `fill-array-data` given its address in bytes,
+    /// `None` for other instructions
+    pub(crate) fn read(dex: &DexFile, code: &CodeItem, op: u32, address: u64) -> Option<Self> {
+        if op != FILL_ARRAY_DATA {
+            return None;
+        }
+        let payload_address = payload_address(dex, code, address)?;
+        let (element_width, data) = Self::parse(&mut dex.reader(code.insns_off + payload_address as usize))?;
+
+        Some(Self { address, payload_address, element_width, data })
+    }
+
+    /// Parse a `fill-array-data-payload`
+    fn parse(reader: &mut Reader) -> Option<(u16, Vec<u8>)> {
+        if reader.read_u16()? != FILL_ARRAY_DATA_PAYLOAD {
+            return None;
+        }
+
+        let element_width = reader.read_u16()?;
+        let size = reader.read_u32()? as usize;
+        let data = reader.read_bytes(size.checked_mul(element_width as usize)?)?;
+
+        Some((element_width, data.to_vec()))
+    }
+
+    /// Return the address of the `fill-array-data` instruction
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Return the address of the payload
+    pub fn payload_address(&self) -> u64 {
+        self.payload_address
+    }
+
+    /// Return the size in bytes of each element
+    pub fn element_width(&self) -> u16 {
+        self.element_width
+    }
+
+    /// Return the number of elements
+    pub fn size(&self) -> usize {
+        match self.element_width {
+            0 => 0,
+            width => self.data.len() / width as usize
+        }
+    }
+
+    /// Return a reference to the raw little-endian data of the elements
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get the elements, zero-extended to 64 bits
+    ///
+    /// The type of the array is not part of the payload: a `-1` byte is
+    /// returned as `0xff`, and the bits of a `float` or a `double` as is.
+    pub fn elements(&self) -> Vec<u64> {
+        if self.element_width == 0 || self.element_width > 8 {
+            return Vec::new();
+        }
+
+        self.data
+            .chunks_exact(self.element_width as usize)
+            .map(|element| element
+                .iter()
+                .rev()
+                .fold(0, |value, byte| value << 8 | *byte as u64))
+            .collect()
+    }
+
+    /// Get the elements, sign-extended to 64 bits
+    pub fn signed_elements(&self) -> Vec<i64> {
+        let shift = 64 - 8 * self.element_width.min(8) as u32;
+        self.elements()
+            .into_iter()
+            .map(|element| ((element << shift) as i64) >> shift)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(units: &[u16]) -> Vec<u8> {
+        units.iter().flat_map(|unit| unit.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_switch_payloads() {
+        // packed-switch at 0x10, keys 5 to 7
+        let data = units(&[0x0100, 3, 5, 0, 0x10, 0, 0x14, 0, 0xfff8, 0xffff]);
+        let cases = SwitchTable::parse(&mut Reader::new(&data, 0), SwitchKind::Packed, 0x10).unwrap();
+        assert_eq!(cases, vec![
+            SwitchCase { key: 5, target: 0x30 },
+            SwitchCase { key: 6, target: 0x38 },
+            SwitchCase { key: 7, target: 0x00 }
+        ]);
+
+        // sparse-switch at 0, keys -1 and 1000
+        let data = units(&[0x0200, 2, 0xffff, 0xffff, 1000, 0, 3, 0, 9, 0]);
+        let cases = SwitchTable::parse(&mut Reader::new(&data, 0), SwitchKind::Sparse, 0).unwrap();
+        assert_eq!(cases, vec![
+            SwitchCase { key: -1, target: 6 },
+            SwitchCase { key: 1000, target: 18 }
+        ]);
+
+        assert!(SwitchTable::parse(&mut Reader::new(&data, 0), SwitchKind::Packed, 0).is_none());
+    }
+
+    #[test]
+    fn test_array_payload() {
+        let data = units(&[0x0300, 2, 3, 0, 0x0001, 0xffff, 0x0300]);
+        let (element_width, data) = ArrayData::parse(&mut Reader::new(&data, 0)).unwrap();
+        let array = ArrayData { address: 0, payload_address: 8, element_width, data };
+
+        assert_eq!(array.size(), 3);
+        assert_eq!(array.elements(), vec![1, 0xffff, 0x300]);
+        assert_eq!(array.signed_elements(), vec![1, -1, 0x300]);
+    }
+}
@@ -0,0 +1,555 @@
+//! Register data flow
+//!
+//! Build the control flow graph of a disassembled method from the
+//! [`semantics`] of its instructions, and compute over its registers:
+//!
+//! * the live registers at the entry and at the exit of each block,
+//! * the definitions reaching each block,
+//! * the def-use and use-def chains, the base of backward slicing.
+//!
+//! Exceptions thrown by an instruction covered by a try range flow to the
+//! handlers of the range, before the instruction writes its registers.
+//!
+//! [`semantics`]: ../semantics/index.html
+
+use std::collections::{ BTreeMap, BTreeSet, HashMap };
+use std::ops::Range;
+
+use crate::dex_file;
+use crate::disassembler::{ DvmDisassembledMethod, DvmInstruction };
+use crate::dvm_access_flags::DvmAccessFlag;
+use crate::semantics::{ self, ControlFlow };
+
+/// Block of the control flow graph
+#[derive(Debug, Clone, PartialEq)]
+pub struct CfgBlock {
+    /// Indexes of the instructions of the block in the method
+    instructions: Range<usize>,
+    /// Address of the first instruction of the block
+    start_address: u64,
+    /// Blocks reached by the normal flow
+    successors: Vec<usize>,
+    /// Blocks reaching this one by the normal flow
+    predecessors: Vec<usize>,
+    /// Handler blocks reached by an exception thrown in the block
+    handlers: Vec<usize>
+}
+
+impl CfgBlock {
+    /// Return the indexes of the instructions of the block in
+    /// [`DvmDisassembledMethod::instructions`]
+    ///
+    /// [`DvmDisassembledMethod::instructions`]: ../disassembler/struct.DvmDisassembledMethod.html#method.instructions
+    pub fn instructions(&self) -> Range<usize> {
+        self.instructions.clone()
+    }
+
+    /// Return the address of the first instruction of the block
+    pub fn start_address(&self) -> u64 {
+        self.start_address
+    }
+
+    /// Return a reference to the indexes of the blocks reached by the normal flow
+    pub fn successors(&self) -> &[usize] {
+        &self.successors
+    }
+
+    /// Return a reference to the indexes of the blocks reaching this one by
+    /// the normal flow
+    pub fn predecessors(&self) -> &[usize] {
+        &self.predecessors
+    }
+
+    /// Return a reference to the indexes of the handler blocks reached by an
+    /// exception thrown in the block
+    pub fn handlers(&self) -> &[usize] {
+        &self.handlers
+    }
+}
+
+/// Control flow graph of a disassembled method
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlFlowGraph {
+    /// Blocks in the order of their addresses, the entry first
+    blocks: Vec<CfgBlock>
+}
+
+impl ControlFlowGraph {
+    /// Build the control flow graph of a method
+    ///
+    /// The method must have been disassembled through a `DexContext` or an
+    /// `ApkContext` for the semantics of its instructions to be known.
+    pub fn build(method: &DvmDisassembledMethod) -> Self {
+        let instructions = method.instructions();
+        let next_address = |n: usize| instructions.get(n + 1).map(DvmInstruction::address);
+
+        // Blocks start at the entry, at jump targets, after jumps, and at the
+        // boundaries of try ranges
+        let mut leaders = BTreeSet::from([0]);
+        for (n, instruction) in instructions.iter().enumerate() {
+            let Some(semantics) = instruction.semantics() else {
+                continue;
+            };
+            leaders.extend(semantics.branch_target());
+            if semantics.control_flow() != ControlFlow::FallThrough {
+                leaders.extend(next_address(n));
+            }
+        }
+        for table in method.switch_tables() {
+            leaders.extend(table.cases().iter().map(|case| case.target()));
+        }
+        for try_range in method.try_ranges() {
+            leaders.extend([try_range.start_address(), try_range.end_address()]);
+            leaders.extend(try_range.handlers().iter().map(|handler| handler.address()));
+        }
+
+        let starts = instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| leaders.contains(&instruction.address()))
+            .map(|(n, _)| n)
+            .collect::<Vec<usize>>();
+        let block_at = starts
+            .iter()
+            .enumerate()
+            .map(|(block, n)| (instructions[*n].address(), block))
+            .collect::<HashMap<u64, usize>>();
+
+        let mut blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(block, start)| {
+                let end = starts.get(block + 1).copied().unwrap_or(instructions.len());
+                CfgBlock {
+                    instructions: *start..end,
+                    start_address: instructions[*start].address(),
+                    successors: Vec::new(),
+                    predecessors: Vec::new(),
+                    handlers: Vec::new()
+                }
+            })
+            .collect::<Vec<CfgBlock>>();
+
+        for block in 0..blocks.len() {
+            let last = blocks[block].instructions.end - 1;
+            let following = next_address(last).and_then(|address| block_at.get(&address).copied());
+            let control_flow = instructions[last].semantics()
+                .map(|semantics| semantics.control_flow())
+                .unwrap_or(ControlFlow::FallThrough);
+
+            let mut successors = match control_flow {
+                ControlFlow::FallThrough => following.into_iter().collect(),
+                ControlFlow::Goto(target) => block_at.get(&target).copied().into_iter().collect(),
+                ControlFlow::Branch(target) => block_at.get(&target).copied().into_iter().chain(following).collect(),
+                ControlFlow::Switch => method.switch_table(instructions[last].address())
+                    .into_iter()
+                    .flat_map(|table| table.cases())
+                    .filter_map(|case| block_at.get(&case.target()).copied())
+                    .chain(following)
+                    .collect(),
+                ControlFlow::Return | ControlFlow::Throw => Vec::new()
+            };
+            successors.sort_unstable();
+            successors.dedup();
+
+            let can_throw = instructions[blocks[block].instructions.clone()]
+                .iter()
+                .any(|instruction| instruction.semantics().is_some_and(|semantics| semantics.can_throw()));
+            if can_throw {
+                let start_address = blocks[block].start_address;
+                for try_range in method.try_ranges().iter().filter(|try_range| try_range.covers(start_address)) {
+                    for handler in try_range.handlers() {
+                        if let Some(handler_block) = block_at.get(&handler.address())
+                            && !blocks[block].handlers.contains(handler_block)
+                        {
+                            blocks[block].handlers.push(*handler_block);
+                        }
+                    }
+                }
+            }
+
+            for successor in &successors {
+                blocks[*successor].predecessors.push(block);
+            }
+            blocks[block].successors = successors;
+        }
+
+        Self { blocks }
+    }
+
+    /// Return a reference to the blocks, the entry first
+    pub fn blocks(&self) -> &[CfgBlock] {
+        &self.blocks
+    }
+
+    /// Get the index of the block containing the instruction at a given address
+    pub fn block_containing(&self, method: &DvmDisassembledMethod, address: u64) -> Option<usize> {
+        let n = method.instructions()
+            .iter()
+            .position(|instruction| instruction.address() == address)?;
+
+        self.blocks
+            .iter()
+            .position(|block| block.instructions.contains(&n))
+    }
+}
+
+/// Registers holding a parameter of a method at its entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterRegisters {
+    /// Descriptor of the parameter, the class for `this`
+    descriptor: String,
+    /// One register, or two for a `long` or a `double`
+    registers: Vec<u16>
+}
+
+impl ParameterRegisters {
+    /// Map the parameters of a method, `this` first for instance methods, to
+    /// the last registers of its frame
+    pub fn of(method: &DvmDisassembledMethod) -> Vec<Self> {
+        let method_id = method.method_id();
+        let Some((class, _, descriptor)) = dex_file::split_method_name(method_id.dalvik_name()) else {
+            return Vec::new();
+        };
+        let Some((parameters, _)) = dex_file::split_method_descriptor(descriptor) else {
+            return Vec::new();
+        };
+
+        let mut descriptors = parameters;
+        if !method_id.access_flags().contains(&DvmAccessFlag::ACC_STATIC) {
+            descriptors.insert(0, class.to_string());
+        }
+
+        let width = |descriptor: &str| if matches!(descriptor, "J" | "D") { 2 } else { 1 };
+        let ins_size = descriptors.iter().map(|descriptor| width(descriptor)).sum::<usize>();
+        let Some(mut register) = method.n_of_registers().checked_sub(ins_size) else {
+            return Vec::new();
+        };
+
+        descriptors.into_iter()
+            .map(|descriptor| {
+                let registers = (register..register + width(&descriptor)).map(|register| register as u16).collect();
+                register += width(&descriptor);
+                Self { descriptor, registers }
+            })
+            .collect()
+    }
+
+    /// Return a reference to the descriptor of the parameter, the class for
+    /// `this`
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// Return a reference to the registers of the parameter
+    pub fn registers(&self) -> &[u16] {
+        &self.registers
+    }
+}
+
+/// Live registers at the entry and at the exit of each block
+#[derive(Debug, Clone, PartialEq)]
+pub struct Liveness {
+    live_in: Vec<BTreeSet<u16>>,
+    live_out: Vec<BTreeSet<u16>>
+}
+
+impl Liveness {
+    /// Compute the live registers of the blocks of a method
+    pub fn build(method: &DvmDisassembledMethod, cfg: &ControlFlowGraph) -> Self {
+        let blocks = cfg.blocks();
+        let mut live_in = vec![BTreeSet::new(); blocks.len()];
+        let mut live_out = vec![BTreeSet::new(); blocks.len()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for block in (0..blocks.len()).rev() {
+                let out = blocks[block].successors
+                    .iter()
+                    .flat_map(|successor| live_in[*successor].iter().copied())
+                    .collect::<BTreeSet<u16>>();
+                let handlers_in = blocks[block].handlers
+                    .iter()
+                    .flat_map(|handler| live_in[*handler].iter().copied())
+                    .collect::<BTreeSet<u16>>();
+
+                let mut live = out.clone();
+                for instruction in method.instructions()[blocks[block].instructions.clone()].iter().rev() {
+                    let Some(semantics) = instruction.semantics() else {
+                        continue;
+                    };
+                    for register in semantics.writes() {
+                        live.remove(register);
+                    }
+                    live.extend(semantics.reads());
+                    if semantics.can_throw() {
+                        live.extend(&handlers_in);
+                    }
+                }
+
+                if live != live_in[block] || out != live_out[block] {
+                    live_in[block] = live;
+                    live_out[block] = out;
+                    changed = true;
+                }
+            }
+        }
+
+        Self { live_in, live_out }
+    }
+
+    /// Return a reference to the registers live at the entry of a block
+    pub fn live_in(&self, block: usize) -> &BTreeSet<u16> {
+        &self.live_in[block]
+    }
+
+    /// Return a reference to the registers live at the exit of a block, for
+    /// its normal successors
+    pub fn live_out(&self, block: usize) -> &BTreeSet<u16> {
+        &self.live_out[block]
+    }
+}
+
+/// Instruction or parameter defining a register
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DefinitionSite {
+    /// Parameter of the method, at its entry
+    Parameter,
+    /// Instruction at the address
+    Instruction(u64)
+}
+
+/// Definition of a register
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Definition {
+    register: u16,
+    site: DefinitionSite
+}
+
+impl Definition {
+    /// Return the register defined
+    pub fn register(&self) -> u16 {
+        self.register
+    }
+
+    /// Return the parameter or the instruction defining the register
+    pub fn site(&self) -> DefinitionSite {
+        self.site
+    }
+}
+
+/// Read of a register by an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Use {
+    /// Address of the instruction
+    address: u64,
+    register: u16
+}
+
+impl Use {
+    /// Return the address of the instruction reading the register
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Return the register read
+    pub fn register(&self) -> u16 {
+        self.register
+    }
+}
+
+/// Definitions reaching the entry and the exit of each block
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReachingDefinitions {
+    reach_in: Vec<BTreeSet<Definition>>,
+    reach_out: Vec<BTreeSet<Definition>>
+}
+
+impl ReachingDefinitions {
+    /// Compute the definitions reaching the blocks of a method, the
+    /// parameters being defined at the entry
+    pub fn build(method: &DvmDisassembledMethod, cfg: &ControlFlowGraph) -> Self {
+        let blocks = cfg.blocks();
+        let entry = ParameterRegisters::of(method)
+            .iter()
+            .flat_map(|parameter| parameter.registers().to_vec())
+            .map(|register| Definition { register, site: DefinitionSite::Parameter })
+            .collect::<BTreeSet<Definition>>();
+
+        let mut reach_in = vec![BTreeSet::new(); blocks.len()];
+        let mut reach_out = vec![BTreeSet::new(); blocks.len()];
+        let mut thrown_in = vec![BTreeSet::new(); blocks.len()];
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for block in 0..blocks.len() {
+                let mut reaching = blocks[block].predecessors
+                    .iter()
+                    .flat_map(|predecessor| reach_out[*predecessor].iter().copied())
+                    .chain(thrown_in[block].iter().copied())
+                    .collect::<BTreeSet<Definition>>();
+                if block == 0 {
+                    reaching.extend(&entry);
+                }
+                let block_in = reaching.clone();
+
+                for instruction in &method.instructions()[blocks[block].instructions.clone()] {
+                    let Some(semantics) = instruction.semantics() else {
+                        continue;
+                    };
+                    if semantics.can_throw() {
+                        for handler in &blocks[block].handlers {
+                            let before = thrown_in[*handler].len();
+                            thrown_in[*handler].extend(reaching.iter().copied());
+                            changed |= thrown_in[*handler].len() != before;
+                        }
+                    }
+                    define(&mut reaching, semantics.writes(), instruction.address());
+                }
+
+                if block_in != reach_in[block] || reaching != reach_out[block] {
+                    reach_in[block] = block_in;
+                    reach_out[block] = reaching;
+                    changed = true;
+                }
+            }
+        }
+
+        Self { reach_in, reach_out }
+    }
+
+    /// Return a reference to the definitions reaching the entry of a block
+    pub fn reach_in(&self, block: usize) -> &BTreeSet<Definition> {
+        &self.reach_in[block]
+    }
+
+    /// Return a reference to the definitions reaching the exit of a block
+    pub fn reach_out(&self, block: usize) -> &BTreeSet<Definition> {
+        &self.reach_out[block]
+    }
+}
+
+/// Replace the definitions of registers written by an instruction
+fn define(reaching: &mut BTreeSet<Definition>, registers: &[u16], address: u64) {
+    reaching.retain(|definition| !registers.contains(&definition.register));
+    reaching.extend(registers.iter().map(|register| Definition {
+        register: *register,
+        site: DefinitionSite::Instruction(address)
+    }));
+}
+
+/// Def-use and use-def chains of the registers of a method
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DefUseChains {
+    /// Uses reached by each definition
+    def_use: BTreeMap<Definition, Vec<Use>>,
+    /// Definitions reaching each use
+    use_def: BTreeMap<Use, Vec<Definition>>,
+    /// Address of the instruction producing the value of each `move-result`
+    results: BTreeMap<u64, u64>
+}
+
+impl DefUseChains {
+    /// Link the uses of the registers of a method to the definitions
+    /// reaching them
+    pub fn build(method: &DvmDisassembledMethod, cfg: &ControlFlowGraph) -> Self {
+        let reaching_definitions = ReachingDefinitions::build(method, cfg);
+        let mut chains = Self::default();
+
+        for (block_idx, block) in cfg.blocks().iter().enumerate() {
+            let mut reaching = reaching_definitions.reach_in(block_idx).clone();
+
+            for instruction in &method.instructions()[block.instructions.clone()] {
+                let Some(semantics) = instruction.semantics() else {
+                    continue;
+                };
+
+                for register in semantics.reads() {
+                    let register_use = Use { address: instruction.address(), register: *register };
+                    let definitions = reaching
+                        .iter()
+                        .filter(|definition| definition.register == *register)
+                        .copied()
+                        .collect::<Vec<Definition>>();
+
+                    for definition in &definitions {
+                        chains.def_use.entry(*definition).or_default().push(register_use);
+                    }
+                    chains.use_def.insert(register_use, definitions);
+                }
+
+                for register in semantics.writes() {
+                    chains.def_use.entry(Definition {
+                        register: *register,
+                        site: DefinitionSite::Instruction(instruction.address())
+                    }).or_default();
+                }
+                define(&mut reaching, semantics.writes(), instruction.address());
+            }
+        }
+
+        for pair in method.instructions().windows(2) {
+            if let [producer, result] = pair
+                && semantics::is_move_result(result.op() as u16)
+                && semantics::has_result(producer.op() as u16)
+            {
+                chains.results.insert(result.address(), producer.address());
+            }
+        }
+
+        chains
+    }
+
+    /// Get the uses reached by a definition
+    pub fn uses_of(&self, definition: &Definition) -> &[Use] {
+        self.def_use.get(definition).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Get the definitions reaching the read of a register by the
+    /// instruction at a given address
+    pub fn definitions_of(&self, address: u64, register: u16) -> &[Definition] {
+        self.use_def.get(&Use { address, register }).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Get every definition of the method, parameters included
+    pub fn definitions(&self) -> impl Iterator<Item = &Definition> {
+        self.def_use.keys()
+    }
+
+    /// Get the addresses of the instructions a register read by an
+    /// instruction depends on, following the use-def chains through the
+    /// registers read by each definition
+    ///
+    /// A `move-result` depends on the invoke or the `filled-new-array` before
+    /// it, and so on its arguments. Parameters the value depends on are
+    /// reported by their definitions.
+    pub fn backward_slice(&self, address: u64, register: u16) -> (BTreeSet<u64>, BTreeSet<Definition>) {
+        let mut instructions = BTreeSet::new();
+        let mut parameters = BTreeSet::new();
+        let mut worklist = self.definitions_of(address, register).to_vec();
+
+        while let Some(definition) = worklist.pop() {
+            let DefinitionSite::Instruction(address) = definition.site else {
+                parameters.insert(definition);
+                continue;
+            };
+
+            for address in std::iter::once(address).chain(self.results.get(&address).copied()) {
+                if !instructions.insert(address) {
+                    continue;
+                }
+
+                let uses = Use { address, register: 0 }..=Use { address, register: u16::MAX };
+                for definitions in self.use_def.range(uses).map(|(_, definitions)| definitions) {
+                    worklist.extend(definitions);
+                }
+            }
+        }
+
+        (instructions, parameters)
+    }
+}
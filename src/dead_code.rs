@@ -0,0 +1,246 @@
+//! Dead code detection
+//!
+//! Find the methods and classes of an application that are never referenced
+//! by any other code, and the ones that cannot be reached from a set of root
+//! methods, typically the [`EntryPoints`](crate::entry_points::EntryPoints).
+//!
+//! Classes and methods matched by ProGuard [`KeepRule`]s are considered
+//! reachable, which allows whitelisting the targets of reflection.
+
+use std::collections::HashSet;
+
+use crate::dex_file;
+use crate::call_graph::CallGraph;
+use crate::hierarchy::{ ClassHierarchy, HierarchyClass, HierarchyMethod };
+use crate::keep_rules::KeepRule;
+
+/// Dead code found in the internal classes of a hierarchy
+#[derive(Debug, Default, PartialEq)]
+pub struct DeadCodeReport {
+    /// Full names of the methods no other method calls
+    unreferenced_methods: Vec<String>,
+    /// Full names of the methods not reachable from the roots
+    unreachable_methods: Vec<String>,
+    /// Descriptors of the classes no other class references
+    unreferenced_classes: Vec<String>,
+    /// Descriptors of the classes with no reachable method
+    unreachable_classes: Vec<String>,
+    /// Number of internal methods analyzed
+    total_methods: usize,
+    /// Number of internal classes analyzed
+    total_classes: usize
+}
+
+impl DeadCodeReport {
+    /// Find the dead code of the internal classes of a hierarchy
+    ///
+    /// Besides `roots` and the methods kept by `keep_rules`, the methods
+    /// overriding an external method are considered reachable since the
+    /// framework may call them, as well as the static initializers of the
+    /// classes with a reachable method.
+    pub fn analyze(
+        hierarchy: &ClassHierarchy,
+        call_graph: &CallGraph,
+        roots: &[&str],
+        keep_rules: &[KeepRule]
+    ) -> Self {
+        let mut classes = hierarchy.classes()
+            .filter(|class| !class.is_external())
+            .collect::<Vec<&HierarchyClass>>();
+        classes.sort_by(|a, b| a.name().cmp(b.name()));
+
+        let mut report = Self {
+            total_classes: classes.len(),
+            total_methods: classes.iter().map(|class| class.methods().len()).sum(),
+            ..Self::default()
+        };
+
+        let mut kept_classes: HashSet<&str> = HashSet::new();
+        let mut all_roots: Vec<&str> = roots.to_vec();
+
+        for class in &classes {
+            if keep_rules.iter().any(|rule| rule.keeps_class(hierarchy, class)) {
+                kept_classes.insert(class.name());
+            }
+
+            for method in class.methods() {
+                if keep_rules.iter().any(|rule| rule.keeps_method(hierarchy, class, method))
+                    || Self::overrides_external(hierarchy, class, method)
+                {
+                    all_roots.push(method.full_name());
+                }
+            }
+        }
+
+        // Static initializers run as soon as their class is used, iterate
+        // until no new initializer becomes reachable
+        let mut reachable = call_graph.reachable_from(all_roots.iter().copied());
+        loop {
+            let initializers = classes.iter()
+                .filter(|class| kept_classes.contains(class.name())
+                    || class.methods().iter().any(|method| reachable.contains(method.full_name())))
+                .filter_map(|class| class.methods().iter().find(|method| method.name() == "<clinit>"))
+                .map(|method| method.full_name())
+                .filter(|method| !reachable.contains(*method))
+                .collect::<Vec<&str>>();
+
+            if initializers.is_empty() {
+                break;
+            }
+
+            all_roots.extend(initializers);
+            reachable = call_graph.reachable_from(all_roots.iter().copied());
+        }
+
+        for class in classes {
+            let mut has_reachable_method = false;
+
+            for method in class.methods() {
+                let is_referenced = call_graph.callers(method.full_name())
+                    .iter()
+                    .any(|edge| edge.caller() != method.full_name());
+
+                if !is_referenced {
+                    report.unreferenced_methods.push(method.full_name().to_string());
+                }
+
+                if reachable.contains(method.full_name()) {
+                    has_reachable_method = true;
+                } else {
+                    report.unreachable_methods.push(method.full_name().to_string());
+                }
+            }
+
+            let is_referenced = class.referenced_by()
+                .iter()
+                .any(|referencing| referencing != class.name());
+
+            if !is_referenced {
+                report.unreferenced_classes.push(class.name().to_string());
+            }
+
+            if !has_reachable_method && !kept_classes.contains(class.name()) {
+                report.unreachable_classes.push(class.name().to_string());
+            }
+        }
+
+        report
+    }
+
+    /// Whether a method overrides a method of an external super type
+    fn overrides_external(hierarchy: &ClassHierarchy, class: &HierarchyClass, method: &HierarchyMethod) -> bool {
+        method.is_virtual()
+            && hierarchy.overridden_methods(class.name(), method.name(), method.descriptor())
+                .iter()
+                .filter_map(|overridden| dex_file::split_method_name(overridden.full_name()))
+                .filter_map(|(class_name, _, _)| hierarchy.class(class_name))
+                .any(|parent| parent.is_external())
+    }
+
+    /// Return a reference to the full names of the methods no other method calls
+    pub fn unreferenced_methods(&self) -> &[String] {
+        &self.unreferenced_methods
+    }
+
+    /// Return a reference to the full names of the methods not reachable from the roots
+    pub fn unreachable_methods(&self) -> &[String] {
+        &self.unreachable_methods
+    }
+
+    /// Return a reference to the descriptors of the classes no other class references
+    pub fn unreferenced_classes(&self) -> &[String] {
+        &self.unreferenced_classes
+    }
+
+    /// Return a reference to the descriptors of the classes with no reachable method
+    pub fn unreachable_classes(&self) -> &[String] {
+        &self.unreachable_classes
+    }
+
+    /// Return the number of internal methods analyzed
+    pub fn total_methods(&self) -> usize {
+        self.total_methods
+    }
+
+    /// Return the number of internal classes analyzed
+    pub fn total_classes(&self) -> usize {
+        self.total_classes
+    }
+
+    /// Get the ratio of unreachable methods, between 0 and 1
+    pub fn unreachable_ratio(&self) -> f64 {
+        match self.total_methods {
+            0 => 0.0,
+            total => self.unreachable_methods.len() as f64 / total as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::call_graph::CallEdgeKind;
+    use crate::dvm_access_flags::DvmAccessFlag;
+
+    fn class(name: &str, super_class: &str, methods: &[&str]) -> HierarchyClass {
+        let mut class = HierarchyClass::new(name, Some(super_class), &[], false);
+
+        for method in methods {
+            let (method_name, descriptor) = method.split_at(method.find('(').unwrap());
+            let flags = match method_name {
+                "<init>" => vec![DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_CONSTRUCTOR],
+                "<clinit>" => vec![DvmAccessFlag::ACC_STATIC, DvmAccessFlag::ACC_CONSTRUCTOR],
+                _ => vec![DvmAccessFlag::ACC_PUBLIC]
+            };
+            class.add_method(HierarchyMethod::new(method_name, descriptor, &format!("{name}->{method}"), &flags));
+        }
+
+        class
+    }
+
+    #[test]
+    fn test_dead_code() {
+        let mut hierarchy = ClassHierarchy::new();
+
+        let mut external = HierarchyClass::new("Ljava/lang/Object;", None, &[], true);
+        external.add_method(HierarchyMethod::new("toString", "()Ljava/lang/String;", "Ljava/lang/Object;->toString()Ljava/lang/String;", &[DvmAccessFlag::ACC_PUBLIC]));
+        hierarchy.add_class(external);
+
+        let mut main = class("Lcom/app/Main;", "Ljava/lang/Object;", &["main()V", "helper()V", "<clinit>()V"]);
+        main.add_reference("Lcom/app/Main;");
+        hierarchy.add_class(main);
+
+        let mut util = class("Lcom/app/Util;", "Ljava/lang/Object;", &["used()V", "unused()V", "toString()Ljava/lang/String;"]);
+        util.add_reference("Lcom/app/Main;");
+        hierarchy.add_class(util);
+
+        hierarchy.add_class(class("Lcom/app/Payload;", "Ljava/lang/Object;", &["run()V", "decrypt()V"]));
+        hierarchy.add_class(class("Lcom/app/Plugin;", "Ljava/lang/Object;", &["<init>()V"]));
+
+        let mut call_graph = CallGraph::default();
+        call_graph.add_edge("Lcom/app/Main;->main()V", "Lcom/app/Util;->used()V", 0, CallEdgeKind::Direct);
+        call_graph.add_edge("Lcom/app/Main;->helper()V", "Lcom/app/Main;->helper()V", 0, CallEdgeKind::Direct);
+        call_graph.add_edge("Lcom/app/Payload;->run()V", "Lcom/app/Payload;->decrypt()V", 0, CallEdgeKind::Direct);
+
+        let rules = KeepRule::parse_rules("-keep class com.app.Plugin { <init>(); }");
+        let report = DeadCodeReport::analyze(&hierarchy, &call_graph, &["Lcom/app/Main;->main()V"], &rules);
+
+        assert_eq!(report.total_classes(), 4);
+        assert_eq!(report.total_methods(), 9);
+        assert_eq!(report.unreachable_methods(), &[
+            "Lcom/app/Main;->helper()V",
+            "Lcom/app/Payload;->run()V",
+            "Lcom/app/Payload;->decrypt()V",
+            "Lcom/app/Util;->unused()V"
+        ]);
+        assert_eq!(report.unreachable_classes(), &["Lcom/app/Payload;"]);
+        assert_eq!(report.unreferenced_classes(), &[
+            "Lcom/app/Main;",
+            "Lcom/app/Payload;",
+            "Lcom/app/Plugin;"
+        ]);
+        assert!(report.unreferenced_methods().contains(&String::from("Lcom/app/Main;->helper()V")));
+        assert!(!report.unreferenced_methods().contains(&String::from("Lcom/app/Payload;->decrypt()V")));
+        assert_eq!(report.unreachable_ratio(), 4.0 / 9.0);
+    }
+}
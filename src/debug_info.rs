@@ -0,0 +1,413 @@
+//! Debug information of the methods
+//!
+//! Decoding of the `debug_info_item` of a method: the line number table, the
+//! local variables with the range of addresses where they are live, and the
+//! names of the parameters. Shuriken does not expose it, so it is read from
+//! the DEX file.
+//!
+//! Addresses are in 16-bit code units from the start of the method, like the
+//! addresses of the disassembled instructions.
+
+use crate::dex_file::{ CodeItem, DexFile, NO_INDEX, split_method_descriptor };
+
+const DBG_END_SEQUENCE: u8 = 0x00;
+const DBG_ADVANCE_PC: u8 = 0x01;
+const DBG_ADVANCE_LINE: u8 = 0x02;
+const DBG_START_LOCAL: u8 = 0x03;
+const DBG_START_LOCAL_EXTENDED: u8 = 0x04;
+const DBG_END_LOCAL: u8 = 0x05;
+const DBG_RESTART_LOCAL: u8 = 0x06;
+const DBG_SET_PROLOGUE_END: u8 = 0x07;
+const DBG_SET_EPILOGUE_BEGIN: u8 = 0x08;
+const DBG_SET_FILE: u8 = 0x09;
+/// First special opcode, which advance both the address and the line
+const DBG_FIRST_SPECIAL: u8 = 0x0a;
+const DBG_LINE_BASE: i64 = -4;
+const DBG_LINE_RANGE: u8 = 15;
+
+/// Entry of the line number table
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineNumber {
+    address: u32,
+    line: u32,
+    /// Source file, when it differs from the one of the class
+    source_file: Option<String>
+}
+
+impl LineNumber {
+    /// Return the address of the first instruction of the line
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Return the line in the source file
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Return a reference to the source file, when it differs from the one of the class
+    pub fn source_file(&self) -> Option<&str> {
+        self.source_file.as_deref()
+    }
+}
+
+/// Local variable and the range of addresses where it is live
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalVariable {
+    register: u32,
+    name: String,
+    /// Type descriptor
+    descriptor: String,
+    /// Generic signature
+    signature: Option<String>,
+    start_address: u32,
+    end_address: u32
+}
+
+impl LocalVariable {
+    /// Return the register holding the variable
+    pub fn register(&self) -> u32 {
+        self.register
+    }
+
+    /// Return a reference to the name of the variable
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the type descriptor of the variable
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// Return a reference to the generic signature of the variable, like
+    /// `Ljava/util/List<Ljava/lang/String;>;`
+    pub fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    /// Return the address where the variable becomes live
+    pub fn start_address(&self) -> u32 {
+        self.start_address
+    }
+
+    /// Return the address where the variable stops being live, excluded
+    pub fn end_address(&self) -> u32 {
+        self.end_address
+    }
+
+    /// Whether the variable is live at an address
+    pub fn is_live_at(&self, address: u32) -> bool {
+        self.start_address <= address && address < self.end_address
+    }
+}
+
+/// Close the live variable of a register at an address
+fn end_local(
+    live: &mut [Option<LocalVariable>],
+    last: &mut [Option<LocalVariable>],
+    ended: &mut Vec<LocalVariable>,
+    register: usize,
+    address: u32
+) {
+    if let Some(mut variable) = live.get_mut(register).and_then(Option::take) {
+        variable.end_address = address;
+        ended.push(variable.clone());
+        last[register] = Some(variable);
+    }
+}
+
+/// Decoded `debug_info_item` of a method
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DebugInfo {
+    line_numbers: Vec<LineNumber>,
+    local_variables: Vec<LocalVariable>,
+    /// Names of the parameters, without `this`
+    parameter_names: Vec<Option<String>>,
+    /// Address of the end of the prologue
+    prologue_end: Option<u32>,
+    /// Addresses of the beginning of the epilogues
+    epilogue_begins: Vec<u32>
+}
+
+impl DebugInfo {
+    /// Decode the debug information of a method given its code item
+    ///
+    /// `full_name` is used for the types of the parameters, which are local
+    /// variables from the start of the method, and `is_static` tells whether
+    /// the first register of the parameters holds `this`.
+    pub(crate) fn parse(dex: &DexFile, code: &CodeItem, full_name: &str, is_static: bool) -> Option<Self> {
+        if code.debug_info_off == 0 {
+            return None;
+        }
+
+        let string = |idx: u32| (idx != NO_INDEX).then(|| dex.string(idx)).flatten();
+        let type_descriptor = |idx: u32| (idx != NO_INDEX).then(|| dex.type_descriptor(idx)).flatten();
+
+        let mut reader = dex.reader(code.debug_info_off as usize);
+        let mut line = reader.read_uleb128()? as i64;
+        let parameters_size = reader.read_uleb128()?;
+        let parameter_names = (0..parameters_size)
+            .map(|_| reader.read_uleb128p1().map(string))
+            .collect::<Option<Vec<Option<String>>>>()?;
+
+        let mut info = Self { parameter_names, ..Self::default() };
+
+        // The parameters are live from the start, in the last registers
+        let (class, descriptor) = full_name.split_once("->").unwrap_or_default();
+        let parameter_types = split_method_descriptor(descriptor.get(descriptor.find('(').unwrap_or(0)..).unwrap_or_default())
+            .map(|(parameters, _)| parameters)
+            .unwrap_or_default();
+
+        // Live variables by register, and the last one of each register for restarts
+        let mut live: Vec<Option<LocalVariable>> = vec![None; code.registers_size as usize];
+        let mut last: Vec<Option<LocalVariable>> = vec![None; code.registers_size as usize];
+        let mut register = code.registers_size.saturating_sub(code.ins_size) as usize;
+
+        if !is_static {
+            live.get_mut(register)?.replace(LocalVariable {
+                register: register as u32,
+                name: String::from("this"),
+                descriptor: class.to_string(),
+                signature: None,
+                start_address: 0,
+                end_address: 0
+            });
+            register += 1;
+        }
+
+        for (idx, descriptor) in parameter_types.iter().enumerate() {
+            if let Some(Some(name)) = info.parameter_names.get(idx)
+                && let Some(slot) = live.get_mut(register)
+            {
+                *slot = Some(LocalVariable {
+                    register: register as u32,
+                    name: name.clone(),
+                    descriptor: descriptor.clone(),
+                    signature: None,
+                    start_address: 0,
+                    end_address: 0
+                });
+            }
+            register += if descriptor == "J" || descriptor == "D" { 2 } else { 1 };
+        }
+
+        let mut address = 0u32;
+        let mut source_file = None;
+
+        loop {
+            match reader.read_u8()? {
+                DBG_END_SEQUENCE => break,
+                DBG_ADVANCE_PC => address = address.checked_add(reader.read_uleb128()?)?,
+                DBG_ADVANCE_LINE => line += reader.read_sleb128()? as i64,
+                opcode @ (DBG_START_LOCAL | DBG_START_LOCAL_EXTENDED) => {
+                    let register = reader.read_uleb128()? as usize;
+                    let name = string(reader.read_uleb128p1()?);
+                    let descriptor = type_descriptor(reader.read_uleb128p1()?);
+                    let signature = match opcode {
+                        DBG_START_LOCAL_EXTENDED => string(reader.read_uleb128p1()?),
+                        _ => None
+                    };
+
+                    if register >= live.len() {
+                        return None;
+                    }
+
+                    end_local(&mut live, &mut last, &mut info.local_variables, register, address);
+                    live[register] = Some(LocalVariable {
+                        register: register as u32,
+                        name: name.unwrap_or_default(),
+                        descriptor: descriptor.unwrap_or_default(),
+                        signature,
+                        start_address: address,
+                        end_address: address
+                    });
+                },
+                DBG_END_LOCAL => end_local(&mut live, &mut last, &mut info.local_variables, reader.read_uleb128()? as usize, address),
+                DBG_RESTART_LOCAL => {
+                    let register = reader.read_uleb128()? as usize;
+                    if register >= live.len() {
+                        return None;
+                    }
+
+                    if live[register].is_none() {
+                        live[register] = last[register].clone().map(|variable| LocalVariable {
+                            start_address: address,
+                            end_address: address,
+                            ..variable
+                        });
+                    }
+                },
+                DBG_SET_PROLOGUE_END => info.prologue_end = Some(address),
+                DBG_SET_EPILOGUE_BEGIN => info.epilogue_begins.push(address),
+                DBG_SET_FILE => source_file = string(reader.read_uleb128p1()?),
+                special => {
+                    let adjusted = special - DBG_FIRST_SPECIAL;
+                    line += DBG_LINE_BASE + (adjusted % DBG_LINE_RANGE) as i64;
+                    address = address.checked_add((adjusted / DBG_LINE_RANGE) as u32)?;
+
+                    info.line_numbers.push(LineNumber {
+                        address,
+                        line: u32::try_from(line).ok()?,
+                        source_file: source_file.clone()
+                    });
+                }
+            }
+        }
+
+        // The variables still live at the end are live until the end of the method
+        for register in 0..live.len() {
+            end_local(&mut live, &mut last, &mut info.local_variables, register, code.insns_size);
+        }
+
+        info.local_variables.sort_by_key(|variable| (variable.start_address, variable.register));
+
+        Some(info)
+    }
+
+    /// Return a reference to the line number table, sorted by address
+    pub fn line_numbers(&self) -> &[LineNumber] {
+        &self.line_numbers
+    }
+
+    /// Return a reference to the local variables, including `this` and the
+    /// named parameters, sorted by start address
+    pub fn local_variables(&self) -> &[LocalVariable] {
+        &self.local_variables
+    }
+
+    /// Return a reference to the names of the parameters, without `this`
+    ///
+    /// Names are `None` for the parameters stripped from the debug information.
+    pub fn parameter_names(&self) -> &[Option<String>] {
+        &self.parameter_names
+    }
+
+    /// Return the address of the end of the prologue
+    pub fn prologue_end(&self) -> Option<u32> {
+        self.prologue_end
+    }
+
+    /// Return a reference to the addresses of the beginning of the epilogues
+    pub fn epilogue_begins(&self) -> &[u32] {
+        &self.epilogue_begins
+    }
+
+    /// Get the source line of the instruction at an address
+    pub fn line_at(&self, address: u32) -> Option<u32> {
+        self.line_numbers
+            .iter()
+            .take_while(|entry| entry.address <= address)
+            .last()
+            .map(|entry| entry.line)
+    }
+
+    /// Get the local variables live at an address
+    pub fn locals_at(&self, address: u32) -> Vec<&LocalVariable> {
+        self.local_variables
+            .iter()
+            .filter(|variable| variable.is_live_at(address))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn parse(dex: &DexFile, full_name: &str, is_static: bool) -> Option<DebugInfo> {
+        let class_def = dex.class_def_by_name("DexParserTest").unwrap();
+        let method = dex.encoded_method(&class_def, full_name).unwrap();
+        let code = dex.code_item(method.code_off).unwrap();
+
+        DebugInfo::parse(dex, &code, full_name, is_static)
+    }
+
+    #[test]
+    fn test_line_numbers() {
+        let dex = DexFile::from_path(Path::new("test_files/DexParserTest.dex")).unwrap();
+        let info = parse(&dex, "LDexParserTest;->calculateSum(II)I", false).unwrap();
+
+        let lines = info.line_numbers()
+            .iter()
+            .map(|entry| (entry.address(), entry.line()))
+            .collect::<Vec<(u32, u32)>>();
+        assert_eq!(lines, vec![(0, 24), (2, 25), (46, 26)]);
+        assert_eq!(info.line_at(10), Some(25));
+
+        // D8 strips the names of the parameters, only `this` is left
+        assert_eq!(info.parameter_names(), &[None, None]);
+        assert_eq!(info.local_variables().len(), 1);
+        assert_eq!(info.local_variables()[0].name(), "this");
+        assert_eq!(info.local_variables()[0].register(), 4);
+        assert_eq!(info.local_variables()[0].end_address(), 47);
+    }
+
+    #[test]
+    fn test_local_variables() {
+        let mut data = std::fs::read("test_files/DexParserTest.dex").unwrap();
+        let debug_info_off = data.len() as u32;
+
+        // String 25 is `field1`, 26 `field2`, 28 `out` and 15 `Ljava/lang/String;`,
+        // type 0 is `I` and type 4 `Ljava/lang/String;`
+        data.extend_from_slice(&[
+            10, 2, 26, 0,
+            DBG_SET_PROLOGUE_END,
+            0x0e,
+            DBG_ADVANCE_PC, 2,
+            DBG_START_LOCAL_EXTENDED, 0, 29, 5, 16,
+            0x1e,
+            DBG_START_LOCAL, 1, 27, 1,
+            DBG_ADVANCE_PC, 3,
+            DBG_END_LOCAL, 0,
+            0x10,
+            DBG_RESTART_LOCAL, 0,
+            DBG_ADVANCE_PC, 4,
+            DBG_SET_EPILOGUE_BEGIN,
+            DBG_ADVANCE_LINE, 0x7f,
+            0x0f,
+            DBG_END_SEQUENCE
+        ]);
+
+        let dex = DexFile::from_bytes(data).unwrap();
+        let code = CodeItem {
+            registers_size: 5,
+            ins_size: 3,
+            outs_size: 0,
+            tries_size: 0,
+            debug_info_off,
+            insns_size: 12,
+            insns_off: 0
+        };
+        let info = DebugInfo::parse(&dex, &code, "LDexParserTest;->calculateSum(II)I", false).unwrap();
+
+        let lines = info.line_numbers()
+            .iter()
+            .map(|entry| (entry.address(), entry.line()))
+            .collect::<Vec<(u32, u32)>>();
+        assert_eq!(lines, vec![(0, 10), (3, 11), (6, 13), (10, 13)]);
+        assert_eq!(info.prologue_end(), Some(0));
+        assert_eq!(info.epilogue_begins(), &[10]);
+        assert_eq!(info.parameter_names(), &[Some(String::from("field1")), None]);
+
+        let locals = info.local_variables()
+            .iter()
+            .map(|local| (local.register(), local.name(), local.descriptor(), local.start_address(), local.end_address()))
+            .collect::<Vec<(u32, &str, &str, u32, u32)>>();
+        assert_eq!(locals, vec![
+            (2, "this", "LDexParserTest;", 0, 12),
+            (3, "field1", "I", 0, 12),
+            (0, "out", "Ljava/lang/String;", 2, 6),
+            (1, "field2", "I", 3, 12),
+            (0, "out", "Ljava/lang/String;", 6, 12)
+        ]);
+        assert_eq!(info.local_variables()[2].signature(), Some("Ljava/lang/String;"));
+
+        let live = info.locals_at(4)
+            .iter()
+            .map(|local| local.name())
+            .collect::<Vec<&str>>();
+        assert_eq!(live, vec!["this", "field1", "out", "field2"]);
+    }
+}
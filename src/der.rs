@@ -0,0 +1,170 @@
+//! Minimal DER reader
+//!
+//! Only what is needed to walk X.509 certificates and PKCS #7 signed data:
+//! tag-length-value parsing, object identifiers, strings and times.
+
+/// Tag of a `SEQUENCE`
+pub(crate) const TAG_SEQUENCE: u8 = 0x30;
+/// Tag of an `INTEGER`
+pub(crate) const TAG_INTEGER: u8 = 0x02;
+/// Tag of an `OBJECT IDENTIFIER`
+pub(crate) const TAG_OID: u8 = 0x06;
+/// Tag of an `UTCTime`
+const TAG_UTC_TIME: u8 = 0x17;
+/// Tag of a `GeneralizedTime`
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+
+/// Tag-length-value element
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Tlv<'a> {
+    pub(crate) tag: u8,
+    /// Content of the element
+    pub(crate) content: &'a [u8],
+    /// Whole encoding of the element, header included
+    pub(crate) raw: &'a [u8]
+}
+
+impl<'a> Tlv<'a> {
+    /// Read the element at the start of `data`, returning it with the remaining bytes
+    pub(crate) fn read(data: &'a [u8]) -> Option<(Self, &'a [u8])> {
+        let tag = *data.first()?;
+        let first = *data.get(1)? as usize;
+
+        let (len, header) = if first & 0x80 == 0 {
+            (first, 2)
+        } else {
+            let count = first & 0x7f;
+            if count == 0 || count > 4 {
+                return None;
+            }
+
+            let len = data.get(2..2 + count)?
+                .iter()
+                .fold(0usize, |len, byte| (len << 8) | *byte as usize);
+            (len, 2 + count)
+        };
+
+        let end = header.checked_add(len)?;
+        let content = data.get(header..end)?;
+
+        Some((Self { tag, content, raw: &data[..end] }, &data[end..]))
+    }
+
+    /// Read the element at the start of `data`, checking its tag
+    pub(crate) fn expect(data: &'a [u8], tag: u8) -> Option<(Self, &'a [u8])> {
+        Self::read(data).filter(|(tlv, _)| tlv.tag == tag)
+    }
+
+    /// Get the elements inside a constructed element
+    pub(crate) fn children(&self) -> Vec<Tlv<'a>> {
+        let mut children = Vec::new();
+        let mut rest = self.content;
+
+        while let Some((child, next)) = Self::read(rest) {
+            children.push(child);
+            rest = next;
+        }
+
+        children
+    }
+
+    /// Decode an object identifier in dotted notation
+    pub(crate) fn oid(&self) -> Option<String> {
+        if self.tag != TAG_OID || self.content.is_empty() {
+            return None;
+        }
+
+        let mut arcs: Vec<u64> = Vec::new();
+        let mut value = 0u64;
+        for byte in self.content {
+            value = (value << 7) | (byte & 0x7f) as u64;
+            if byte & 0x80 == 0 {
+                if arcs.is_empty() {
+                    let first = (value / 40).min(2);
+                    arcs.push(first);
+                    arcs.push(value - first * 40);
+                } else {
+                    arcs.push(value);
+                }
+                value = 0;
+            }
+        }
+
+        Some(arcs.iter()
+            .map(|arc| arc.to_string())
+            .collect::<Vec<String>>()
+            .join("."))
+    }
+
+    /// Decode a directory string (UTF-8, printable, IA5, BMP, ...)
+    pub(crate) fn string(&self) -> String {
+        match self.tag {
+            // BMPString, UTF-16 big-endian
+            0x1e => {
+                let units = self.content
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect::<Vec<u16>>();
+                String::from_utf16_lossy(&units)
+            },
+            // UniversalString, UTF-32 big-endian
+            0x1c => self.content
+                .chunks_exact(4)
+                .filter_map(|unit| char::from_u32(u32::from_be_bytes([unit[0], unit[1], unit[2], unit[3]])))
+                .collect(),
+            _ => String::from_utf8_lossy(self.content).into_owned()
+        }
+    }
+
+    /// Decode an `UTCTime` or a `GeneralizedTime` as `YYYY-MM-DD HH:MM:SS`
+    pub(crate) fn time(&self) -> Option<String> {
+        let text = std::str::from_utf8(self.content).ok()?;
+
+        let (year, rest) = match self.tag {
+            TAG_UTC_TIME => {
+                let year: u32 = text.get(0..2)?.parse().ok()?;
+                (if year < 50 { 2000 + year } else { 1900 + year }, text.get(2..)?)
+            },
+            TAG_GENERALIZED_TIME => (text.get(0..4)?.parse().ok()?, text.get(4..)?),
+            _ => return None
+        };
+
+        let field = |idx: usize| rest.get(idx * 2..idx * 2 + 2).unwrap_or("00");
+
+        Some(format!("{year:04}-{}-{} {}:{}:{}", field(0), field(1), field(2), field(3), field(4)))
+    }
+}
+
+/// Format bytes as lowercase hexadecimal
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_der() {
+        // SEQUENCE { OID 1.2.840.113549.1.1.11, UTCTime 240101120000Z }
+        let data = [
+            0x30, 0x1a,
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b,
+            0x17, 0x0d, b'2', b'4', b'0', b'1', b'0', b'1', b'1', b'2', b'0', b'0', b'0', b'0', b'Z',
+            0xff
+        ];
+
+        let (sequence, rest) = Tlv::expect(&data, TAG_SEQUENCE).unwrap();
+        assert_eq!(rest, &[0xff]);
+
+        let children = sequence.children();
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].oid().as_deref(), Some("1.2.840.113549.1.1.11"));
+        assert_eq!(children[1].time().as_deref(), Some("2024-01-01 12:00:00"));
+
+        assert!(Tlv::read(&[0x30, 0x05, 0x00]).is_none());
+        assert_eq!(to_hex(&[0x00, 0xab]), "00ab");
+    }
+}
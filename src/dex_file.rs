@@ -0,0 +1,692 @@
+//! Raw DEX file reader
+//!
+//! Shuriken's C API does not expose every section of a DEX file. This module
+//! reads the few structures we need directly from the file bytes so the
+//! binding structs can be completed with the missing information.
+//!
+//! See the official [`documentation`] for the format details.
+//!
+//! [`documentation`]: https://source.android.com/docs/core/runtime/dex-format
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Value of `NO_INDEX` in the DEX format
+pub(crate) const NO_INDEX: u32 = 0xffff_ffff;
+
+/// Map item type of the `call_site_ids` section
+const TYPE_CALL_SITE_ID_ITEM: u16 = 0x0007;
+/// Map item type of the `method_handles` section
+const TYPE_METHOD_HANDLE_ITEM: u16 = 0x0008;
+
+/// Little-endian cursor over a byte slice
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    /// Current offset in the underlying data
+    pub(crate) fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Option<u8> {
+        let byte = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    pub(crate) fn read_u16(&mut self) -> Option<u16> {
+        let bytes = self.read_bytes(2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Option<u32> {
+        let bytes = self.read_bytes(4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Option<u64> {
+        let bytes = self.read_bytes(8)?;
+        Some(u64::from_le_bytes(bytes.try_into().ok()?))
+    }
+
+    pub(crate) fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    /// Read an unsigned LEB128 value
+    pub(crate) fn read_uleb128(&mut self) -> Option<u32> {
+        let mut result = 0u32;
+
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_u8()?;
+            result |= ((byte & 0x7f) as u32) << shift;
+
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+
+        None
+    }
+
+    /// Read an unsigned LEB128 value encoded with a +1 bias
+    pub(crate) fn read_uleb128p1(&mut self) -> Option<u32> {
+        self.read_uleb128().map(|value| value.wrapping_sub(1))
+    }
+
+    /// Read a signed LEB128 value
+    pub(crate) fn read_sleb128(&mut self) -> Option<i32> {
+        let mut result = 0i32;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.read_u8()?;
+            result |= ((byte & 0x7f) as i32) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                if shift < 32 && byte & 0x40 != 0 {
+                    result |= -1 << shift;
+                }
+                return Some(result);
+            }
+
+            if shift >= 35 {
+                return None;
+            }
+        }
+    }
+}
+
+/// Raw `class_def_item`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ClassDef {
+    pub(crate) class_idx: u32,
+    pub(crate) access_flags: u32,
+    pub(crate) superclass_idx: u32,
+    pub(crate) interfaces_off: u32,
+    pub(crate) source_file_idx: u32,
+    pub(crate) annotations_off: u32,
+    pub(crate) class_data_off: u32,
+    pub(crate) static_values_off: u32
+}
+
+/// Field of a `class_data_item`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EncodedField {
+    pub(crate) field_idx: u32,
+    pub(crate) access_flags: u32
+}
+
+/// Method of a `class_data_item`
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EncodedMethod {
+    pub(crate) method_idx: u32,
+    pub(crate) access_flags: u32,
+    pub(crate) code_off: u32
+}
+
+/// Header of a raw `code_item`, `insns_off` is the offset of the instructions
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CodeItem {
+    pub(crate) registers_size: u16,
+    pub(crate) ins_size: u16,
+    pub(crate) outs_size: u16,
+    pub(crate) tries_size: u16,
+    pub(crate) debug_info_off: u32,
+    pub(crate) insns_size: u32,
+    pub(crate) insns_off: usize
+}
+
+/// Raw `annotations_directory_item`, with (index, offset) pairs for the
+/// fields, methods and parameters
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AnnotationsDirectory {
+    pub(crate) class_annotations_off: u32,
+    pub(crate) fields: Vec<(u32, u32)>,
+    pub(crate) methods: Vec<(u32, u32)>,
+    pub(crate) parameters: Vec<(u32, u32)>
+}
+
+/// In-memory copy of a DEX file
+pub(crate) struct DexFile {
+    data: Vec<u8>,
+    /// Class definitions by type descriptor, built on the first lookup
+    class_index: OnceLock<HashMap<String, ClassDef>>
+}
+
+impl fmt::Debug for DexFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DexFile")
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
+impl DexFile {
+    /// Size of a DEX header
+    const HEADER_SIZE: usize = 0x70;
+
+    /// Wrap the bytes of a DEX file, checking the magic and the header size
+    pub(crate) fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        if data.len() < Self::HEADER_SIZE || &data[0..4] != b"dex\n" {
+            return None;
+        }
+
+        Some(Self { data, class_index: OnceLock::new() })
+    }
+
+    /// Read a DEX file from disk
+    pub(crate) fn from_path(path: &Path) -> Option<Self> {
+        Self::from_bytes(fs::read(path).ok()?)
+    }
+
+    /// Raw bytes of the file
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get a cursor at a given offset of the file
+    pub(crate) fn reader(&self, offset: usize) -> Reader<'_> {
+        Reader::new(&self.data, offset)
+    }
+
+    pub(crate) fn u16_at(&self, offset: usize) -> Option<u16> {
+        self.reader(offset).read_u16()
+    }
+
+    pub(crate) fn u32_at(&self, offset: usize) -> Option<u32> {
+        self.reader(offset).read_u32()
+    }
+
+    /// Read the `size` and `off` header fields of a section
+    fn section(&self, header_offset: usize) -> (u32, usize) {
+        let size = self.u32_at(header_offset).unwrap_or(0);
+        let off = self.u32_at(header_offset + 4).unwrap_or(0) as usize;
+        (size, off)
+    }
+
+    pub(crate) fn string_ids(&self) -> (u32, usize) {
+        self.section(0x38)
+    }
+
+    pub(crate) fn type_ids(&self) -> (u32, usize) {
+        self.section(0x40)
+    }
+
+    pub(crate) fn proto_ids(&self) -> (u32, usize) {
+        self.section(0x48)
+    }
+
+    pub(crate) fn field_ids(&self) -> (u32, usize) {
+        self.section(0x50)
+    }
+
+    pub(crate) fn method_ids(&self) -> (u32, usize) {
+        self.section(0x58)
+    }
+
+    pub(crate) fn class_defs_section(&self) -> (u32, usize) {
+        self.section(0x60)
+    }
+
+    pub(crate) fn call_site_ids(&self) -> (u32, usize) {
+        self.map_section(TYPE_CALL_SITE_ID_ITEM)
+    }
+
+    pub(crate) fn method_handles(&self) -> (u32, usize) {
+        self.map_section(TYPE_METHOD_HANDLE_ITEM)
+    }
+
+    /// Read the `size` and `off` of a section only listed in the map, like the
+    /// sections added by DEX 038
+    fn map_section(&self, item_type: u16) -> (u32, usize) {
+        let mut reader = self.reader(self.u32_at(0x34).unwrap_or(0) as usize);
+
+        for _ in 0..reader.read_u32().unwrap_or(0) {
+            let (Some(map_type), Some(_), Some(size), Some(off)) =
+                (reader.read_u16(), reader.read_u16(), reader.read_u32(), reader.read_u32()) else {
+                break;
+            };
+
+            if map_type == item_type {
+                return (size, off as usize);
+            }
+        }
+
+        (0, 0)
+    }
+
+    /// Get a string given its index in `string_ids`
+    pub(crate) fn string(&self, idx: u32) -> Option<String> {
+        let (size, off) = self.string_ids();
+        if idx >= size {
+            return None;
+        }
+
+        let data_off = self.u32_at(off + idx as usize * 4)? as usize;
+        let mut reader = self.reader(data_off);
+        let _utf16_size = reader.read_uleb128()?;
+
+        let start = reader.pos();
+        let len = self.data.get(start..)?
+            .iter()
+            .position(|&byte| byte == 0)?;
+
+        Some(decode_mutf8(&self.data[start..start + len]))
+    }
+
+    /// Get a type descriptor given its index in `type_ids`
+    pub(crate) fn type_descriptor(&self, idx: u32) -> Option<String> {
+        let (size, off) = self.type_ids();
+        if idx >= size {
+            return None;
+        }
+
+        self.string(self.u32_at(off + idx as usize * 4)?)
+    }
+
+    /// Read a `type_list` and resolve every entry to a descriptor
+    pub(crate) fn type_list(&self, offset: u32) -> Vec<String> {
+        if offset == 0 {
+            return Vec::new();
+        }
+
+        let mut reader = self.reader(offset as usize);
+        let size = reader.read_u32().unwrap_or(0);
+
+        (0..size)
+            .map_while(|_| reader.read_u16())
+            .filter_map(|type_idx| self.type_descriptor(type_idx as u32))
+            .collect()
+    }
+
+    /// Get the descriptor of a prototype given its index in `proto_ids`,
+    /// like `(I[Ljava/lang/String;)V`
+    pub(crate) fn proto_descriptor(&self, idx: u32) -> Option<String> {
+        let (size, off) = self.proto_ids();
+        if idx >= size {
+            return None;
+        }
+
+        let mut reader = self.reader(off + idx as usize * 12);
+        let _shorty_idx = reader.read_u32()?;
+        let return_type = self.type_descriptor(reader.read_u32()?)?;
+        let parameters = self.type_list(reader.read_u32()?);
+
+        Some(format!("({}){return_type}", parameters.concat()))
+    }
+
+    /// Get the class, name and type descriptors of a field given its index in `field_ids`
+    pub(crate) fn field_id(&self, idx: u32) -> Option<(String, String, String)> {
+        let (size, off) = self.field_ids();
+        if idx >= size {
+            return None;
+        }
+
+        let mut reader = self.reader(off + idx as usize * 8);
+        let class = self.type_descriptor(reader.read_u16()? as u32)?;
+        let field_type = self.type_descriptor(reader.read_u16()? as u32)?;
+        let name = self.string(reader.read_u32()?)?;
+
+        Some((class, name, field_type))
+    }
+
+    /// Get the full name of a field given its index in `field_ids`,
+    /// like `Lcom/example/Foo;->bar:I`
+    pub(crate) fn field_full_name(&self, idx: u32) -> Option<String> {
+        let (class, name, field_type) = self.field_id(idx)?;
+        Some(format!("{class}->{name}:{field_type}"))
+    }
+
+    /// Get the full name of a method given its index in `method_ids`,
+    /// like `Lcom/example/Foo;->bar(I)V`
+    pub(crate) fn method_full_name(&self, idx: u32) -> Option<String> {
+        let (size, off) = self.method_ids();
+        if idx >= size {
+            return None;
+        }
+
+        let mut reader = self.reader(off + idx as usize * 8);
+        let class = self.type_descriptor(reader.read_u16()? as u32)?;
+        let proto = self.proto_descriptor(reader.read_u16()? as u32)?;
+        let name = self.string(reader.read_u32()?)?;
+
+        Some(format!("{class}->{name}{proto}"))
+    }
+
+    /// Get the direct and virtual methods of the `class_data_item` of a class
+    pub(crate) fn class_methods(&self, class_def: &ClassDef) -> Vec<EncodedMethod> {
+        if class_def.class_data_off == 0 {
+            return Vec::new();
+        }
+
+        let mut reader = self.reader(class_def.class_data_off as usize);
+        let sizes = (0..4)
+            .map_while(|_| reader.read_uleb128())
+            .collect::<Vec<u32>>();
+        let [static_fields, instance_fields, direct_methods, virtual_methods] = sizes[..] else {
+            return Vec::new();
+        };
+
+        // Fields are a (field_idx_diff, access_flags) pair
+        let Some(field_values) = static_fields.checked_add(instance_fields).and_then(|fields| fields.checked_mul(2)) else {
+            return Vec::new();
+        };
+        for _ in 0..field_values {
+            if reader.read_uleb128().is_none() {
+                return Vec::new();
+            }
+        }
+
+        let mut methods = Vec::new();
+        for count in [direct_methods, virtual_methods] {
+            // Indexes are encoded as differences from the previous one of the list
+            let mut method_idx = 0u32;
+
+            for _ in 0..count {
+                let (Some(diff), Some(access_flags), Some(code_off)) =
+                    (reader.read_uleb128(), reader.read_uleb128(), reader.read_uleb128()) else {
+                    return methods;
+                };
+
+                method_idx = method_idx.wrapping_add(diff);
+                methods.push(EncodedMethod { method_idx, access_flags, code_off });
+            }
+        }
+
+        methods
+    }
+
+    /// Get the static and instance fields of the `class_data_item` of a class
+    pub(crate) fn class_fields(&self, class_def: &ClassDef) -> Vec<EncodedField> {
+        if class_def.class_data_off == 0 {
+            return Vec::new();
+        }
+
+        let mut reader = self.reader(class_def.class_data_off as usize);
+        let sizes = (0..4)
+            .map_while(|_| reader.read_uleb128())
+            .collect::<Vec<u32>>();
+        let [static_fields, instance_fields, _, _] = sizes[..] else {
+            return Vec::new();
+        };
+
+        let mut fields = Vec::new();
+        for count in [static_fields, instance_fields] {
+            // Indexes are encoded as differences from the previous one of the list
+            let mut field_idx = 0u32;
+
+            for _ in 0..count {
+                let (Some(diff), Some(access_flags)) = (reader.read_uleb128(), reader.read_uleb128()) else {
+                    return fields;
+                };
+
+                field_idx = field_idx.wrapping_add(diff);
+                fields.push(EncodedField { field_idx, access_flags });
+            }
+        }
+
+        fields
+    }
+
+    /// Get the indexes of the static fields of the `class_data_item` of a
+    /// class, in the order of the `static_values` array
+    pub(crate) fn class_static_fields(&self, class_def: &ClassDef) -> Vec<u32> {
+        if class_def.class_data_off == 0 {
+            return Vec::new();
+        }
+
+        let mut reader = self.reader(class_def.class_data_off as usize);
+        let static_fields = reader.read_uleb128().unwrap_or(0);
+        // Skip the sizes of the other lists
+        for _ in 0..3 {
+            if reader.read_uleb128().is_none() {
+                return Vec::new();
+            }
+        }
+
+        let mut field_idx = 0u32;
+        (0..static_fields)
+            .map_while(|_| {
+                let diff = reader.read_uleb128()?;
+                let _access_flags = reader.read_uleb128()?;
+
+                field_idx = field_idx.wrapping_add(diff);
+                Some(field_idx)
+            })
+            .collect()
+    }
+
+    /// Find the encoded method of a class given the full name of the method
+    pub(crate) fn encoded_method(&self, class_def: &ClassDef, full_name: &str) -> Option<EncodedMethod> {
+        self.class_methods(class_def)
+            .into_iter()
+            .find(|method| self.method_full_name(method.method_idx).as_deref() == Some(full_name))
+    }
+
+    /// Read the header of a `code_item`
+    pub(crate) fn code_item(&self, offset: u32) -> Option<CodeItem> {
+        if offset == 0 {
+            return None;
+        }
+
+        let mut reader = self.reader(offset as usize);
+        Some(CodeItem {
+            registers_size: reader.read_u16()?,
+            ins_size: reader.read_u16()?,
+            outs_size: reader.read_u16()?,
+            tries_size: reader.read_u16()?,
+            debug_info_off: reader.read_u32()?,
+            insns_size: reader.read_u32()?,
+            insns_off: reader.pos()
+        })
+    }
+
+    /// Read the `annotations_directory_item` of a class
+    pub(crate) fn annotations_directory(&self, class_def: &ClassDef) -> Option<AnnotationsDirectory> {
+        if class_def.annotations_off == 0 {
+            return None;
+        }
+
+        let mut reader = self.reader(class_def.annotations_off as usize);
+        let class_annotations_off = reader.read_u32()?;
+        let sizes = [reader.read_u32()?, reader.read_u32()?, reader.read_u32()?];
+
+        let mut lists = sizes.iter().map(|size| {
+            (0..*size)
+                .map_while(|_| reader.read_u32().zip(reader.read_u32()))
+                .collect::<Vec<(u32, u32)>>()
+        });
+
+        Some(AnnotationsDirectory {
+            class_annotations_off,
+            fields: lists.next()?,
+            methods: lists.next()?,
+            parameters: lists.next()?
+        })
+    }
+
+    /// Get the class definitions of the file
+    pub(crate) fn class_defs(&self) -> Vec<ClassDef> {
+        let (size, off) = self.class_defs_section();
+
+        (0..size as usize)
+            .map_while(|idx| {
+                let mut reader = self.reader(off + idx * 0x20);
+                Some(ClassDef {
+                    class_idx: reader.read_u32()?,
+                    access_flags: reader.read_u32()?,
+                    superclass_idx: reader.read_u32()?,
+                    interfaces_off: reader.read_u32()?,
+                    source_file_idx: reader.read_u32()?,
+                    annotations_off: reader.read_u32()?,
+                    class_data_off: reader.read_u32()?,
+                    static_values_off: reader.read_u32()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Find a class definition given a class name or a type descriptor
+    pub(crate) fn class_def_by_name(&self, name: &str) -> Option<ClassDef> {
+        let index = self.class_index.get_or_init(|| {
+            self.class_defs()
+                .into_iter()
+                .filter_map(|class_def| Some((self.type_descriptor(class_def.class_idx)?, class_def)))
+                .collect()
+        });
+
+        index.get(&to_descriptor(name)).copied()
+    }
+
+    /// Get the descriptors of the interfaces implemented by a class
+    pub(crate) fn interfaces(&self, class_def: &ClassDef) -> Vec<String> {
+        self.type_list(class_def.interfaces_off)
+    }
+}
+
+/// Decode a MUTF-8 string as used in DEX files
+pub(crate) fn decode_mutf8(bytes: &[u8]) -> String {
+    let mut units: Vec<u16> = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let byte = bytes[idx] as u16;
+        let next = |n: usize| bytes.get(idx + n).map(|b| (*b & 0x3f) as u16).unwrap_or(0);
+
+        if byte & 0x80 == 0 {
+            units.push(byte);
+            idx += 1;
+        } else if byte & 0xe0 == 0xc0 {
+            units.push(((byte & 0x1f) << 6) | next(1));
+            idx += 2;
+        } else {
+            units.push(((byte & 0x0f) << 12) | (next(1) << 6) | next(2));
+            idx += 3;
+        }
+    }
+
+    String::from_utf16_lossy(&units)
+}
+
+/// Convert a class name (`com.example.Foo`) to a type descriptor (`Lcom/example/Foo;`)
+///
+/// Names already in descriptor form are returned unchanged.
+pub(crate) fn to_descriptor(name: &str) -> String {
+    if name.starts_with('[') || (name.starts_with('L') && name.ends_with(';')) {
+        name.to_string()
+    } else {
+        format!("L{};", name.replace('.', "/"))
+    }
+}
+
+/// Convert a type descriptor (`Lcom/example/Foo;`) to a class name (`com.example.Foo`)
+pub(crate) fn to_class_name(descriptor: &str) -> String {
+    match descriptor.chars().next() {
+        Some('[') => format!("{}[]", to_class_name(&descriptor[1..])),
+        Some('L') => descriptor[1..]
+            .trim_end_matches(';')
+            .replace('/', "."),
+        Some('Z') => String::from("boolean"),
+        Some('B') => String::from("byte"),
+        Some('S') => String::from("short"),
+        Some('C') => String::from("char"),
+        Some('I') => String::from("int"),
+        Some('J') => String::from("long"),
+        Some('F') => String::from("float"),
+        Some('D') => String::from("double"),
+        Some('V') => String::from("void"),
+        _ => descriptor.to_string()
+    }
+}
+
+/// Write a type descriptor using the same notation as `reference`
+///
+/// Shuriken uses both class names and descriptors depending on the structure,
+/// this keeps the values we add consistent with the ones it provides.
+pub(crate) fn same_notation_as(reference: &str, descriptor: &str) -> String {
+    if reference.starts_with('L') && reference.ends_with(';') {
+        descriptor.to_string()
+    } else {
+        to_class_name(descriptor)
+    }
+}
+
+/// Split a full method name (`Lcom/example/Foo;->bar(I)V`) into its class,
+/// method name and descriptor
+pub(crate) fn split_method_name(full_name: &str) -> Option<(&str, &str, &str)> {
+    let (class, rest) = full_name.split_once("->")?;
+    let paren = rest.find('(')?;
+
+    Some((class, &rest[..paren], &rest[paren..]))
+}
+
+/// Split a method descriptor (`(I[Ljava/lang/String;)V`) into the descriptors
+/// of its parameters and of its return type
+pub(crate) fn split_method_descriptor(descriptor: &str) -> Option<(Vec<String>, String)> {
+    let (parameters, return_type) = descriptor.strip_prefix('(')?.split_once(')')?;
+
+    let mut types = Vec::new();
+    let mut rest = parameters;
+
+    while !rest.is_empty() {
+        let dimensions = rest.len() - rest.trim_start_matches('[').len();
+        let len = match rest[dimensions..].chars().next()? {
+            'L' => rest[dimensions..].find(';')? + 1,
+            _ => 1
+        };
+
+        types.push(rest[..dimensions + len].to_string());
+        rest = &rest[dimensions + len..];
+    }
+
+    Some((types, return_type.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class_def_by_name() {
+        let dex = DexFile::from_path(Path::new("test_files/DexAnnotationsTest.dex")).unwrap();
+
+        let anonymous = dex.class_def_by_name("com.example.annotations.Bridge$1").unwrap();
+        assert_eq!(dex.type_descriptor(anonymous.class_idx).as_deref(), Some("Lcom/example/annotations/Bridge$1;"));
+        assert_eq!(dex.type_descriptor(anonymous.superclass_idx).as_deref(), Some("Ljava/lang/Object;"));
+        assert_eq!(dex.interfaces(&anonymous), vec!["Ljava/lang/Runnable;"]);
+
+        let by_descriptor = dex.class_def_by_name("Lcom/example/annotations/Bridge$1;").unwrap();
+        assert_eq!(by_descriptor.class_idx, anonymous.class_idx);
+
+        let bridge = dex.class_def_by_name("Lcom/example/annotations/Bridge;").unwrap();
+        assert!(dex.interfaces(&bridge).is_empty());
+        assert!(dex.class_def_by_name("Ljava/lang/Object;").is_none());
+    }
+
+    #[test]
+    fn test_malformed_class_data() {
+        let mut data = fs::read("test_files/DexParserTest.dex").unwrap();
+        let class_data_off = data.len() as u32;
+        // Field counts overflowing once doubled, then no methods
+        data.extend([0xff, 0xff, 0xff, 0xff, 0x0f, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x00, 0x00]);
+
+        let dex = DexFile::from_bytes(data).unwrap();
+        let class_def = ClassDef { class_data_off, ..dex.class_defs()[0] };
+        assert!(dex.class_methods(&class_def).is_empty());
+        assert!(dex.class_fields(&class_def).is_empty());
+    }
+}
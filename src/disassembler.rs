@@ -5,6 +5,11 @@ use std::slice::from_raw_parts;
 
 use crate::shuriken;
 use crate::parser::DvmMethod;
+use crate::dex_file::{ self, CodeItem, DexFile };
+use crate::literals::Literal;
+use crate::exceptions::TryRange;
+use crate::semantics::InstructionSemantics;
+use crate::code_payloads::{ ArrayData, SwitchTable };
 
 /// Type alias for Shuriken's `dexinsttype_e`
 ///
@@ -57,7 +62,9 @@ pub struct DvmInstruction {
     address: u64,
     // TODO: replace with a enum of all opcodes maybe?
     op: u32,
-    disassembly: String
+    disassembly: String,
+    literal: Option<Literal>,
+    semantics: Option<InstructionSemantics>
 }
 
 impl DvmInstruction {
@@ -111,7 +118,9 @@ impl DvmInstruction {
             instruction_length: ins.instruction_length as usize,
             address: ins.address,
             op: ins.op,
-            disassembly
+            disassembly,
+            literal: None,
+            semantics: None
         }
     }
 
@@ -139,6 +148,28 @@ impl DvmInstruction {
     pub fn disassembly(&self) -> &str {
         &self.disassembly
     }
+
+    /// Decode the operands of the instruction from the code of its method
+    pub(crate) fn resolve_from_code(&mut self, dex: &DexFile, code: &CodeItem) {
+        self.semantics = Some(InstructionSemantics::read(dex, code, self.address));
+    }
+
+    /// Return a reference to the registers accessed by the instruction and
+    /// the effects of its execution
+    ///
+    /// Only available for methods disassembled or analyzed through a
+    /// `DexContext` or an `ApkContext`.
+    pub fn semantics(&self) -> Option<&InstructionSemantics> {
+        self.semantics.as_ref()
+    }
+
+    /// Return a reference to the literal loaded by a const instruction
+    ///
+    /// Only available for methods disassembled through a `DexContext` or an
+    /// `ApkContext`.
+    pub fn literal(&self) -> Option<&Literal> {
+        self.literal.as_ref()
+    }
 }
 
 /// Type alias for Shuriken's `dvmhandler_data_t`
@@ -238,6 +269,9 @@ pub struct DvmDisassembledMethod {
     n_of_instructions: usize,
     instructions: Vec<DvmInstruction>,
     method_string: String,
+    switch_tables: Vec<SwitchTable>,
+    array_data: Vec<ArrayData>,
+    try_ranges: Vec<TryRange>,
 }
 
 impl DvmDisassembledMethod {
@@ -275,7 +309,10 @@ impl DvmDisassembledMethod {
             exception_information,
             n_of_instructions: dvm_disas.n_of_instructions,
             instructions,
-            method_string
+            method_string,
+            switch_tables: Vec::new(),
+            array_data: Vec::new(),
+            try_ranges: Vec::new()
         }
     }
 
@@ -295,6 +332,41 @@ impl DvmDisassembledMethod {
         DvmDisassembledMethod::from_dvmdisassembled_method_t(ptr, dvm_method)
     }
 
+    /// Complete the method with the information missing from Shuriken's C API
+    pub(crate) fn resolve_from_dex(&mut self, dex: &DexFile) {
+        self.method_id.resolve_from_dex(dex);
+
+        let code = dex.class_def_by_name(self.method_id.class_name())
+            .and_then(|class_def| dex.encoded_method(&class_def, self.method_id.dalvik_name()))
+            .and_then(|encoded| dex.code_item(encoded.code_off));
+        let Some(code) = code else {
+            return;
+        };
+
+        self.switch_tables = self.instructions
+            .iter()
+            .filter_map(|ins| SwitchTable::read(dex, &code, ins.op, ins.address))
+            .collect();
+        self.array_data = self.instructions
+            .iter()
+            .filter_map(|ins| ArrayData::read(dex, &code, ins.op, ins.address))
+            .collect();
+        self.try_ranges = TryRange::read_all(dex, &code);
+
+        let return_type = dex_file::split_method_descriptor(self.method_id.prototype())
+            .map(|(_, return_type)| return_type)
+            .unwrap_or_default();
+        for n in 0..self.instructions.len() {
+            self.instructions[n].resolve_from_code(dex, &code);
+            let literal = Literal::read(dex, &code, self.instructions[n].address).map(|mut literal| {
+                let following = self.instructions[n + 1..].iter().map(DvmInstruction::address);
+                literal.infer_type(dex, &code, following, &return_type);
+                literal
+            });
+            self.instructions[n].literal = literal;
+        }
+    }
+
     /// Return a reference to the method id
     pub fn method_id(&self) -> &DvmMethod {
         &self.method_id
@@ -315,6 +387,14 @@ impl DvmDisassembledMethod {
         &self.exception_information
     }
 
+    /// Return a reference to the try ranges with their resolved handlers
+    ///
+    /// Only available for methods disassembled through a `DexContext` or an
+    /// `ApkContext`.
+    pub fn try_ranges(&self) -> &[TryRange] {
+        &self.try_ranges
+    }
+
     /// Return the number of instructions
     pub fn n_of_instructions(&self) -> usize {
         self.n_of_instructions
@@ -329,4 +409,29 @@ impl DvmDisassembledMethod {
     pub fn instructions(&self) -> &[DvmInstruction] {
         &self.instructions
     }
+
+    /// Return a reference to the tables of the `packed-switch` and
+    /// `sparse-switch` instructions
+    ///
+    /// Only available for methods disassembled through a `DexContext` or an
+    /// `ApkContext`.
+    pub fn switch_tables(&self) -> &[SwitchTable] {
+        &self.switch_tables
+    }
+
+    /// Get the table of the switch instruction at a given address
+    pub fn switch_table(&self, address: u64) -> Option<&SwitchTable> {
+        self.switch_tables
+            .iter()
+            .find(|table| table.address() == address)
+    }
+
+    /// Return a reference to the elements of the `fill-array-data`
+    /// instructions
+    ///
+    /// Only available for methods disassembled through a `DexContext` or an
+    /// `ApkContext`.
+    pub fn array_data(&self) -> &[ArrayData] {
+        &self.array_data
+    }
 }
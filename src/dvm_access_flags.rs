@@ -54,7 +54,7 @@ impl fmt::Display for DvmAccessFlagType {
 /// Representation of the different access flag
 /// Bitfields of these flags are used to indicate the accessibility and overall properties of
 /// classes and class members.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DvmAccessFlag {
     /// Public: visible everywhere
     ACC_PUBLIC,
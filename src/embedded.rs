@@ -0,0 +1,316 @@
+//! Embedded payloads
+//!
+//! Packers ship the real code of an application as a DEX, JAR or APK hidden
+//! in the assets or the raw resources, sometimes encrypted, and load it at
+//! runtime. Shuriken only parses the `classesN.dex` files at the root of the
+//! APK, this module finds the other payloads, including the ones nested in
+//! archives and the encrypted-looking blobs detected by their entropy.
+//!
+//! Nested paths are written with `!/` between the archive and its entry,
+//! like `assets/payload.jar!/classes.dex`.
+
+use std::fmt;
+
+use crate::DexContext;
+use crate::zip_file::{ ZipArchive, ZipEntry };
+
+/// Separator between an archive and the path of a nested entry
+const NESTED_SEPARATOR: &str = "!/";
+/// Maximum depth of nested archives
+const MAX_DEPTH: usize = 4;
+/// Minimum entropy of encrypted-looking blobs, in bits per byte
+const MIN_ENTROPY: f64 = 7.2;
+/// Minimum size of encrypted-looking blobs, smaller ones have unreliable entropy
+const MIN_ENTROPY_SIZE: usize = 1024;
+/// Maximum size of an inflated entry, including the nested ones
+const MAX_ENTRY_SIZE: usize = 128 * 1024 * 1024;
+/// Maximum number of bytes inflated to scan an APK, including the nested archives
+const MAX_TOTAL_SIZE: usize = 1024 * 1024 * 1024;
+
+/// Kind of an embedded payload
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PayloadKind {
+    /// DEX file
+    Dex,
+    /// Archive holding DEX files, like a JAR
+    Jar,
+    /// Archive with an `AndroidManifest.xml`
+    Apk,
+    /// Unknown data with a high entropy, likely encrypted or compressed
+    Encrypted
+}
+
+impl fmt::Display for PayloadKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadKind::Dex => write!(f, "DEX"),
+            PayloadKind::Jar => write!(f, "JAR"),
+            PayloadKind::Apk => write!(f, "APK"),
+            PayloadKind::Encrypted => write!(f, "encrypted")
+        }
+    }
+}
+
+/// Payload found in an APK
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddedPayload {
+    /// Path of the payload, with `!/` between nested archives
+    path: String,
+    kind: PayloadKind,
+    size: usize,
+    /// Shannon entropy, in bits per byte
+    entropy: f64
+}
+
+impl EmbeddedPayload {
+    /// Return a reference to the path of the payload, with `!/` between nested archives
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Return the kind of payload
+    pub fn kind(&self) -> PayloadKind {
+        self.kind
+    }
+
+    /// Return the size of the uncompressed payload
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Return the Shannon entropy of the payload, in bits per byte
+    pub fn entropy(&self) -> f64 {
+        self.entropy
+    }
+
+    /// Whether the payload is inside another payload
+    pub fn is_nested(&self) -> bool {
+        self.path.contains(NESTED_SEPARATOR)
+    }
+
+    /// Find the payloads of an APK
+    ///
+    /// The `classesN.dex` files at the root of the archive are skipped, as
+    /// well as the known media and compressed formats when checking the
+    /// entropy. Entries larger than `MAX_ENTRY_SIZE` are skipped, and the
+    /// scan stops after inflating `MAX_TOTAL_SIZE` bytes.
+    pub(crate) fn find(archive: &ZipArchive) -> Vec<Self> {
+        let mut payloads = Vec::new();
+        let mut budget = MAX_TOTAL_SIZE;
+
+        for entry in archive.entries() {
+            let name = entry.name();
+            if entry.is_dir() || is_main_dex(name) || name.starts_with("META-INF/") {
+                continue;
+            }
+
+            if let Some(data) = Self::read_entry(archive, entry, &mut budget) {
+                Self::scan(name, &data, 0, &mut budget, &mut payloads);
+            }
+        }
+
+        payloads
+    }
+
+    /// Read an entry if it fits in the per-entry limit and the remaining
+    /// budget, which is reduced by its size
+    fn read_entry(archive: &ZipArchive, entry: &ZipEntry, budget: &mut usize) -> Option<Vec<u8>> {
+        let data = archive.read_with_limit(entry, MAX_ENTRY_SIZE.min(*budget))?;
+        *budget -= data.len();
+        Some(data)
+    }
+
+    /// Classify some data, then look into it if it is an archive
+    fn scan(path: &str, data: &[u8], depth: usize, budget: &mut usize, payloads: &mut Vec<Self>) {
+        let nested = match data {
+            [b'P', b'K', 3, 4, ..] => ZipArchive::from_bytes(data.to_vec()),
+            _ => None
+        };
+
+        let mut data_entropy = None;
+        let kind = match &nested {
+            Some(archive) if archive.entry("AndroidManifest.xml").is_some() => Some(PayloadKind::Apk),
+            Some(archive) if archive.entries().iter().any(|entry| entry.name().ends_with(".dex")) => Some(PayloadKind::Jar),
+            Some(_) => None,
+            None if data.starts_with(b"dex\n") => Some(PayloadKind::Dex),
+            None if data.len() >= MIN_ENTROPY_SIZE && !is_known_format(data) => {
+                let value = *data_entropy.insert(entropy(data));
+                (value >= MIN_ENTROPY).then_some(PayloadKind::Encrypted)
+            },
+            None => None
+        };
+
+        if let Some(kind) = kind {
+            payloads.push(Self {
+                path: path.to_string(),
+                kind,
+                size: data.len(),
+                entropy: data_entropy.unwrap_or_else(|| entropy(data))
+            });
+        }
+
+        // Archives without payloads are looked into as well, they may hold other archives
+        if let Some(archive) = nested.filter(|_| depth < MAX_DEPTH) {
+            for entry in archive.entries().iter().filter(|entry| !entry.is_dir()) {
+                if let Some(data) = Self::read_entry(&archive, entry, budget) {
+                    let path = format!("{path}{NESTED_SEPARATOR}{}", entry.name());
+                    Self::scan(&path, &data, depth + 1, budget, payloads);
+                }
+            }
+        }
+    }
+
+    /// Read the data of a payload given its path
+    pub(crate) fn read(archive: &ZipArchive, path: &str) -> Option<Vec<u8>> {
+        let mut parts = path.split(NESTED_SEPARATOR);
+        let mut data = archive.read_with_limit(archive.entry(parts.next()?)?, MAX_ENTRY_SIZE)?;
+
+        for part in parts {
+            let nested = ZipArchive::from_bytes(data)?;
+            data = nested.read_with_limit(nested.entry(part)?, MAX_ENTRY_SIZE)?;
+        }
+
+        Some(data)
+    }
+}
+
+/// Whether an entry is one of the DEX files loaded by Android, `classes.dex`,
+/// `classes2.dex`, ...
+pub(crate) fn is_main_dex(name: &str) -> bool {
+    name.strip_prefix("classes")
+        .and_then(|name| name.strip_suffix(".dex"))
+        .is_some_and(|number| number.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Whether data starts with the magic of a media or compressed format, whose
+/// entropy is high without being suspicious
+fn is_known_format(data: &[u8]) -> bool {
+    const MAGICS: [&[u8]; 12] = [
+        b"\x89PNG",
+        b"\xff\xd8\xff",
+        b"GIF8",
+        b"RIFF",
+        b"OggS",
+        b"ID3",
+        b"fLaC",
+        b"\x1f\x8b",
+        b"BZh",
+        b"\xfd7zXZ",
+        b"7z\xbc\xaf",
+        b"\x28\xb5\x2f\xfd"
+    ];
+
+    // MP4 and other ISO media files have their magic at offset 4
+    MAGICS.iter().any(|magic| data.starts_with(magic)) || data.get(4..8) == Some(b"ftyp")
+}
+
+/// Shannon entropy of some data, in bits per byte
+pub(crate) fn entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let mut counts = [0usize; 256];
+    for byte in data {
+        counts[*byte as usize] += 1;
+    }
+
+    counts.iter()
+        .filter(|count| **count != 0)
+        .map(|count| {
+            let probability = *count as f64 / data.len() as f64;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+/// Embedded DEX files opened with Shuriken
+#[derive(Debug)]
+pub struct EmbeddedDexFiles {
+    /// Contexts of the DEX files, with the path of their payload
+    contexts: Vec<(String, DexContext)>
+}
+
+impl EmbeddedDexFiles {
+    /// Open the valid DEX payloads of an APK
+    pub(crate) fn open(archive: &ZipArchive) -> Self {
+        let contexts = EmbeddedPayload::find(archive)
+            .into_iter()
+            .filter(|payload| payload.kind == PayloadKind::Dex)
+            .filter_map(|payload| {
+                let data = EmbeddedPayload::read(archive, &payload.path)?;
+                Some((payload.path, DexContext::parse_dex_bytes(&data)?))
+            })
+            .collect();
+
+        Self { contexts }
+    }
+
+    /// Return a reference to the contexts of the DEX files, with the path of their payload
+    ///
+    /// The analysis of every context still has to be created with
+    /// [`DexContext::create_dex_analysis`].
+    pub fn contexts(&self) -> &[(String, DexContext)] {
+        &self.contexts
+    }
+
+    /// Get the context of a DEX file given the path of its payload
+    pub fn get(&self, path: &str) -> Option<&DexContext> {
+        self.contexts
+            .iter()
+            .find(|(payload, _)| payload == path)
+            .map(|(_, context)| context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_find_payloads() {
+        let archive = ZipArchive::from_path(Path::new("test_files/test_packed.apk")).unwrap();
+        let payloads = EmbeddedPayload::find(&archive);
+
+        let found = payloads.iter()
+            .map(|payload| (payload.path(), payload.kind()))
+            .collect::<Vec<(&str, PayloadKind)>>();
+        assert_eq!(found, vec![
+            ("assets/payload.jar", PayloadKind::Jar),
+            ("assets/payload.jar!/classes.dex", PayloadKind::Dex),
+            ("assets/hidden.bin", PayloadKind::Dex),
+            ("assets/blob.dat", PayloadKind::Encrypted),
+            ("res/raw/nested.apk", PayloadKind::Apk),
+            ("res/raw/nested.apk!/classes.dex", PayloadKind::Dex)
+        ]);
+
+        assert!(payloads[1].is_nested());
+        assert!(payloads[3].entropy() > 7.9);
+        assert_eq!(payloads[3].size(), 4096);
+
+        let dex = EmbeddedPayload::read(&archive, "res/raw/nested.apk!/classes.dex").unwrap();
+        assert_eq!(dex, std::fs::read("test_files/_pi.dex").unwrap());
+        assert!(EmbeddedPayload::read(&archive, "assets/payload.jar!/missing.dex").is_none());
+
+        // Entries are only read within the remaining budget, which they consume
+        let blob = archive.entry("assets/blob.dat").unwrap();
+        let mut budget = 4095;
+        assert!(EmbeddedPayload::read_entry(&archive, blob, &mut budget).is_none());
+        budget = 5000;
+        assert!(EmbeddedPayload::read_entry(&archive, blob, &mut budget).is_some());
+        assert_eq!(budget, 904);
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(&[]), 0.0);
+        assert_eq!(entropy(&[7; 64]), 0.0);
+        assert_eq!(entropy(&(0..=255).collect::<Vec<u8>>()), 8.0);
+
+        assert!(is_main_dex("classes.dex"));
+        assert!(is_main_dex("classes12.dex"));
+        assert!(!is_main_dex("assets/classes.dex"));
+        assert!(!is_main_dex("classes-x.dex"));
+    }
+}
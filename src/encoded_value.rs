@@ -0,0 +1,339 @@
+//! Encoded values
+//!
+//! Constants of the DEX format, used by the elements of the annotations, the
+//! initial values of the static fields and the arguments of the call sites.
+//! References to strings, types, fields and methods are resolved to their
+//! names, with fields written `Lcom/example/Foo;->bar:I` and methods
+//! `Lcom/example/Foo;->bar(I)V`.
+
+use crate::dex_file::{ ClassDef, DexFile, Reader };
+
+const VALUE_BYTE: u8 = 0x00;
+const VALUE_SHORT: u8 = 0x02;
+const VALUE_CHAR: u8 = 0x03;
+const VALUE_INT: u8 = 0x04;
+const VALUE_LONG: u8 = 0x06;
+const VALUE_FLOAT: u8 = 0x10;
+const VALUE_DOUBLE: u8 = 0x11;
+const VALUE_METHOD_TYPE: u8 = 0x15;
+const VALUE_METHOD_HANDLE: u8 = 0x16;
+const VALUE_STRING: u8 = 0x17;
+const VALUE_TYPE: u8 = 0x18;
+const VALUE_FIELD: u8 = 0x19;
+const VALUE_METHOD: u8 = 0x1a;
+const VALUE_ENUM: u8 = 0x1b;
+const VALUE_ARRAY: u8 = 0x1c;
+const VALUE_ANNOTATION: u8 = 0x1d;
+const VALUE_NULL: u8 = 0x1e;
+const VALUE_BOOLEAN: u8 = 0x1f;
+
+/// Maximum nesting of arrays and annotations
+const MAX_DEPTH: usize = 32;
+
+/// Value of an `encoded_value`
+#[derive(Debug, Clone, PartialEq)]
+pub enum EncodedValue {
+    Byte(i8),
+    Short(i16),
+    Char(u16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    /// Descriptor of a prototype, like `(I)V`
+    MethodType(String),
+    /// Index of a method handle
+    MethodHandle(u32),
+    String(String),
+    /// Type descriptor
+    Type(String),
+    /// Full name of a field
+    Field(String),
+    /// Full name of a method
+    Method(String),
+    /// Full name of an enum constant field
+    Enum(String),
+    Array(Vec<EncodedValue>),
+    Annotation(EncodedAnnotation),
+    Null,
+    Boolean(bool)
+}
+
+impl EncodedValue {
+    /// Read an `encoded_value`
+    pub(crate) fn read(dex: &DexFile, reader: &mut Reader) -> Option<Self> {
+        Self::read_nested(dex, reader, 0)
+    }
+
+    fn read_nested(dex: &DexFile, reader: &mut Reader, depth: usize) -> Option<Self> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        let header = reader.read_u8()?;
+        let (value_type, value_arg) = (header & 0x1f, header >> 5);
+        let size = value_arg as usize + 1;
+
+        // Little-endian value of `size` bytes
+        let read_bits = |reader: &mut Reader| -> Option<u64> {
+            let bytes = reader.read_bytes(size)?;
+            Some(bytes.iter().rev().fold(0u64, |value, byte| value << 8 | *byte as u64))
+        };
+        let sign_extend = |value: u64| {
+            let shift = 64 - 8 * size as u32;
+            ((value << shift) as i64) >> shift
+        };
+        // Floating-point values are zero-extended to the right
+        let zero_extend_right = |value: u64, width: usize| value << (8 * (width - size));
+
+        let value = match value_type {
+            VALUE_BYTE => EncodedValue::Byte(sign_extend(read_bits(reader)?) as i8),
+            VALUE_SHORT => EncodedValue::Short(sign_extend(read_bits(reader)?) as i16),
+            VALUE_CHAR => EncodedValue::Char(read_bits(reader)? as u16),
+            VALUE_INT => EncodedValue::Int(sign_extend(read_bits(reader)?) as i32),
+            VALUE_LONG => EncodedValue::Long(sign_extend(read_bits(reader)?)),
+            VALUE_FLOAT if size <= 4 => {
+                EncodedValue::Float(f32::from_bits(zero_extend_right(read_bits(reader)?, 4) as u32))
+            },
+            VALUE_DOUBLE => EncodedValue::Double(f64::from_bits(zero_extend_right(read_bits(reader)?, 8))),
+            VALUE_METHOD_TYPE => EncodedValue::MethodType(dex.proto_descriptor(read_bits(reader)? as u32)?),
+            VALUE_METHOD_HANDLE => EncodedValue::MethodHandle(read_bits(reader)? as u32),
+            VALUE_STRING => EncodedValue::String(dex.string(read_bits(reader)? as u32)?),
+            VALUE_TYPE => EncodedValue::Type(dex.type_descriptor(read_bits(reader)? as u32)?),
+            VALUE_FIELD => EncodedValue::Field(dex.field_full_name(read_bits(reader)? as u32)?),
+            VALUE_METHOD => EncodedValue::Method(dex.method_full_name(read_bits(reader)? as u32)?),
+            VALUE_ENUM => EncodedValue::Enum(dex.field_full_name(read_bits(reader)? as u32)?),
+            VALUE_ARRAY => EncodedValue::Array(Self::read_array_nested(dex, reader, depth + 1)?),
+            VALUE_ANNOTATION => EncodedValue::Annotation(EncodedAnnotation::read_nested(dex, reader, depth + 1)?),
+            VALUE_NULL => EncodedValue::Null,
+            VALUE_BOOLEAN => EncodedValue::Boolean(value_arg != 0),
+            _ => return None
+        };
+
+        Some(value)
+    }
+
+    /// Read an `encoded_array`
+    pub(crate) fn read_array(dex: &DexFile, reader: &mut Reader) -> Option<Vec<Self>> {
+        Self::read_array_nested(dex, reader, 0)
+    }
+
+    fn read_array_nested(dex: &DexFile, reader: &mut Reader, depth: usize) -> Option<Vec<Self>> {
+        let size = reader.read_uleb128()?;
+
+        (0..size)
+            .map(|_| Self::read_nested(dex, reader, depth))
+            .collect()
+    }
+
+    /// Read the `static_values` array of a class, with the default value of
+    /// the type of the fields missing at the end of the array
+    ///
+    /// Returns `None` if the array or the fields cannot be read.
+    pub(crate) fn read_static_values(dex: &DexFile, class_def: &ClassDef) -> Option<Vec<(u32, Self)>> {
+        let fields = dex.class_static_fields(class_def);
+        let mut values = match class_def.static_values_off {
+            0 => Vec::new(),
+            offset => Self::read_array(dex, &mut dex.reader(offset as usize))?
+        };
+
+        // Missing values are the default value of the type of the field
+        for field_idx in fields.iter().skip(values.len()) {
+            let (_, _, field_type) = dex.field_id(*field_idx)?;
+            values.push(Self::default_for(&field_type));
+        }
+
+        Some(fields.into_iter().zip(values).collect())
+    }
+
+    /// Get the default value of a type, `0`, `false` or `null`
+    pub(crate) fn default_for(descriptor: &str) -> Self {
+        match descriptor {
+            "Z" => EncodedValue::Boolean(false),
+            "B" => EncodedValue::Byte(0),
+            "S" => EncodedValue::Short(0),
+            "C" => EncodedValue::Char(0),
+            "I" => EncodedValue::Int(0),
+            "J" => EncodedValue::Long(0),
+            "F" => EncodedValue::Float(0.0),
+            "D" => EncodedValue::Double(0.0),
+            _ => EncodedValue::Null
+        }
+    }
+
+    /// Get the value of a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            EncodedValue::String(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// Get the descriptor of a type
+    pub fn as_type(&self) -> Option<&str> {
+        match self {
+            EncodedValue::Type(value) => Some(value),
+            _ => None
+        }
+    }
+
+    /// Get the values of an array
+    pub fn as_array(&self) -> Option<&[EncodedValue]> {
+        match self {
+            EncodedValue::Array(values) => Some(values),
+            _ => None
+        }
+    }
+
+    /// Get the value of an integral type, including `char` and `boolean`
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            EncodedValue::Byte(value) => Some(*value as i64),
+            EncodedValue::Short(value) => Some(*value as i64),
+            EncodedValue::Char(value) => Some(*value as i64),
+            EncodedValue::Int(value) => Some(*value as i64),
+            EncodedValue::Long(value) => Some(*value),
+            EncodedValue::Boolean(value) => Some(*value as i64),
+            _ => None
+        }
+    }
+}
+
+/// Element of an annotation
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationElement {
+    name: String,
+    value: EncodedValue
+}
+
+impl AnnotationElement {
+    /// Return a reference to the name of the element
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the value of the element
+    pub fn value(&self) -> &EncodedValue {
+        &self.value
+    }
+}
+
+/// Value of an `encoded_annotation`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedAnnotation {
+    /// Descriptor of the annotation type
+    type_descriptor: String,
+    elements: Vec<AnnotationElement>
+}
+
+impl EncodedAnnotation {
+    /// Read an `encoded_annotation`
+    pub(crate) fn read(dex: &DexFile, reader: &mut Reader) -> Option<Self> {
+        Self::read_nested(dex, reader, 0)
+    }
+
+    fn read_nested(dex: &DexFile, reader: &mut Reader, depth: usize) -> Option<Self> {
+        let type_descriptor = dex.type_descriptor(reader.read_uleb128()?)?;
+        let size = reader.read_uleb128()?;
+
+        let elements = (0..size)
+            .map(|_| Some(AnnotationElement {
+                name: dex.string(reader.read_uleb128()?)?,
+                value: EncodedValue::read_nested(dex, reader, depth)?
+            }))
+            .collect::<Option<Vec<AnnotationElement>>>()?;
+
+        Some(Self { type_descriptor, elements })
+    }
+
+    /// Return a reference to the descriptor of the annotation type
+    pub fn type_descriptor(&self) -> &str {
+        &self.type_descriptor
+    }
+
+    /// Return a reference to the elements of the annotation
+    pub fn elements(&self) -> &[AnnotationElement] {
+        &self.elements
+    }
+
+    /// Get the value of an element given its name
+    pub fn element(&self, name: &str) -> Option<&EncodedValue> {
+        self.elements
+            .iter()
+            .find(|element| element.name == name)
+            .map(|element| &element.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn static_values(path: &str, class_name: &str) -> Vec<(String, EncodedValue)> {
+        let dex = DexFile::from_path(Path::new(path)).unwrap();
+        let class_def = dex.class_def_by_name(class_name).unwrap();
+
+        EncodedValue::read_static_values(&dex, &class_def)
+            .unwrap()
+            .into_iter()
+            .map(|(field_idx, value)| (dex.field_id(field_idx).unwrap().1, value))
+            .collect()
+    }
+
+    #[test]
+    fn test_static_values() {
+        let values = static_values("test_files/DexStaticValuesTest.dex", "com.example.statics.Config");
+        let value = |name: &str| values.iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+            .unwrap();
+
+        assert_eq!(values.len(), 15);
+        assert_eq!(value("API_KEY").as_str(), Some("sk_live_4f9a2c"));
+        assert_eq!(value("ENDPOINT").as_str(), Some("https://api.example.com/v1"));
+        assert_eq!(value("RETRIES"), EncodedValue::Int(3));
+        assert_eq!(value("TIMEOUT"), EncodedValue::Long(30000));
+        assert_eq!(value("RATIO"), EncodedValue::Float(0.75));
+        assert_eq!(value("SCALE"), EncodedValue::Double(2.5));
+        assert_eq!(value("ENABLED"), EncodedValue::Boolean(true));
+        assert_eq!(value("LETTER"), EncodedValue::Char(b'x' as u16));
+        assert_eq!(value("OFFSET"), EncodedValue::Short(-2));
+        assert_eq!(value("MASK"), EncodedValue::Byte(0x7f));
+        assert_eq!(value("MODE"), EncodedValue::Enum(String::from("Lcom/example/statics/Mode;->FAST:Lcom/example/statics/Mode;")));
+        assert_eq!(value("TARGET").as_type(), Some("Lcom/example/statics/Target;"));
+        assert_eq!(value("FALLBACK"), EncodedValue::Null);
+        assert_eq!(value("PRIMES"), EncodedValue::Array(vec![EncodedValue::Int(2), EncodedValue::Int(3), EncodedValue::Int(5)]));
+
+        // Missing at the end of the array
+        assert_eq!(value("counter"), EncodedValue::Int(0));
+    }
+
+    #[test]
+    fn test_default_static_values() {
+        // Every field is assigned by `<clinit>`, the class has no `static_values`
+        let dex = DexFile::from_path(Path::new("test_files/TestFieldsLifter.dex")).unwrap();
+        let class_def = dex.class_def_by_name("TestFieldsLifter").unwrap();
+        assert_eq!(class_def.static_values_off, 0);
+
+        let values = EncodedValue::read_static_values(&dex, &class_def).unwrap();
+        assert!(!values.is_empty());
+        for (field_idx, value) in values {
+            let (_, _, field_type) = dex.field_id(field_idx).unwrap();
+            assert_eq!(value, EncodedValue::default_for(&field_type));
+        }
+    }
+
+    #[test]
+    fn test_malformed_static_values() {
+        let mut data = std::fs::read("test_files/DexStaticValuesTest.dex").unwrap();
+        let static_values_off = data.len() as u32;
+        // An array of 3 values holding a single one
+        data.extend([0x03, 0x04, 0x01]);
+
+        let dex = DexFile::from_bytes(data).unwrap();
+        let class_def = dex.class_def_by_name("com.example.statics.Config").unwrap();
+        let class_def = ClassDef { static_values_off, ..class_def };
+        assert!(EncodedValue::read_static_values(&dex, &class_def).is_none());
+    }
+}
@@ -0,0 +1,416 @@
+//! Android entry points
+//!
+//! Android applications have no `main` method: their code is called by the
+//! framework through component lifecycle methods, callbacks and JNI. This
+//! module recognizes these entry points from the class hierarchy and the
+//! access flags, and returns them as a root set for reachability analyses.
+
+use crate::hierarchy::{ ClassHierarchy, HierarchyClass, HierarchyMethod };
+use crate::dvm_access_flags::DvmAccessFlag;
+
+/// Kind of entry point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryPointKind {
+    /// Lifecycle method of an `Activity`
+    Activity,
+    /// Lifecycle method of a `Service`
+    Service,
+    /// `onReceive` of a `BroadcastReceiver`
+    BroadcastReceiver,
+    /// Lifecycle and query methods of a `ContentProvider`
+    ContentProvider,
+    /// Lifecycle method of the `Application`
+    Application,
+    /// Callback of a UI listener
+    UiListener,
+    /// `run` or `call` of a `Runnable`, `Thread` or `Callable`
+    Runnable,
+    /// Callback of an `AsyncTask`
+    AsyncTask,
+    /// JNI `native` method
+    Native
+}
+
+/// Kind of entry point, framework super types and the callbacks they make,
+/// as (name, descriptor) pairs
+type FrameworkCallbacks = (EntryPointKind, &'static [&'static str], &'static [(&'static str, &'static str)]);
+
+/// Framework super types and the methods they call back, for each kind
+///
+/// Callbacks are given by name and descriptor, with the erased types of the
+/// generic ones.
+const FRAMEWORK_CALLBACKS: [FrameworkCallbacks; 7] = [
+    (
+        EntryPointKind::Activity,
+        &[
+            "Landroid/app/Activity;",
+            "Landroid/app/ListActivity;",
+            "Landroid/app/NativeActivity;",
+            "Landroid/preference/PreferenceActivity;",
+            "Landroidx/activity/ComponentActivity;",
+            "Landroidx/core/app/ComponentActivity;",
+            "Landroidx/fragment/app/FragmentActivity;",
+            "Landroidx/appcompat/app/AppCompatActivity;",
+            "Landroid/support/v4/app/FragmentActivity;",
+            "Landroid/support/v7/app/AppCompatActivity;",
+        ],
+        &[
+            ("<init>", "()V"),
+            ("onCreate", "(Landroid/os/Bundle;)V"),
+            ("onCreate", "(Landroid/os/Bundle;Landroid/os/PersistableBundle;)V"),
+            ("onStart", "()V"),
+            ("onRestart", "()V"),
+            ("onResume", "()V"),
+            ("onPause", "()V"),
+            ("onStop", "()V"),
+            ("onDestroy", "()V"),
+            ("onPostCreate", "(Landroid/os/Bundle;)V"),
+            ("onPostResume", "()V"),
+            ("onNewIntent", "(Landroid/content/Intent;)V"),
+            ("onSaveInstanceState", "(Landroid/os/Bundle;)V"),
+            ("onRestoreInstanceState", "(Landroid/os/Bundle;)V"),
+            ("onActivityResult", "(IILandroid/content/Intent;)V"),
+            ("onRequestPermissionsResult", "(I[Ljava/lang/String;[I)V"),
+            ("onConfigurationChanged", "(Landroid/content/res/Configuration;)V"),
+            ("onBackPressed", "()V"),
+            ("onCreateOptionsMenu", "(Landroid/view/Menu;)Z"),
+            ("onOptionsItemSelected", "(Landroid/view/MenuItem;)Z"),
+            ("onWindowFocusChanged", "(Z)V"),
+            ("onUserLeaveHint", "()V"),
+        ]
+    ),
+    (
+        EntryPointKind::Service,
+        &[
+            "Landroid/app/Service;",
+            "Landroid/app/IntentService;",
+            "Landroid/app/job/JobService;",
+            "Landroid/accessibilityservice/AccessibilityService;",
+            "Landroid/service/notification/NotificationListenerService;",
+            "Landroidx/core/app/JobIntentService;",
+            "Landroidx/lifecycle/LifecycleService;",
+        ],
+        &[
+            ("<init>", "()V"),
+            ("onCreate", "()V"),
+            ("onStart", "(Landroid/content/Intent;I)V"),
+            ("onStartCommand", "(Landroid/content/Intent;II)I"),
+            ("onBind", "(Landroid/content/Intent;)Landroid/os/IBinder;"),
+            ("onUnbind", "(Landroid/content/Intent;)Z"),
+            ("onRebind", "(Landroid/content/Intent;)V"),
+            ("onDestroy", "()V"),
+            ("onTaskRemoved", "(Landroid/content/Intent;)V"),
+            ("onHandleIntent", "(Landroid/content/Intent;)V"),
+            ("onHandleWork", "(Landroid/content/Intent;)V"),
+            ("onStartJob", "(Landroid/app/job/JobParameters;)Z"),
+            ("onStopJob", "(Landroid/app/job/JobParameters;)Z"),
+            ("onAccessibilityEvent", "(Landroid/view/accessibility/AccessibilityEvent;)V"),
+            ("onInterrupt", "()V"),
+            ("onServiceConnected", "()V"),
+            ("onNotificationPosted", "(Landroid/service/notification/StatusBarNotification;)V"),
+            ("onNotificationRemoved", "(Landroid/service/notification/StatusBarNotification;)V"),
+        ]
+    ),
+    (
+        EntryPointKind::BroadcastReceiver,
+        &[
+            "Landroid/content/BroadcastReceiver;",
+            "Landroid/app/admin/DeviceAdminReceiver;",
+            "Landroid/appwidget/AppWidgetProvider;",
+        ],
+        &[
+            ("<init>", "()V"),
+            ("onReceive", "(Landroid/content/Context;Landroid/content/Intent;)V"),
+            ("onEnabled", "(Landroid/content/Context;)V"),
+            ("onEnabled", "(Landroid/content/Context;Landroid/content/Intent;)V"),
+            ("onDisabled", "(Landroid/content/Context;)V"),
+            ("onDisabled", "(Landroid/content/Context;Landroid/content/Intent;)V"),
+            ("onUpdate", "(Landroid/content/Context;Landroid/appwidget/AppWidgetManager;[I)V"),
+            ("onDeleted", "(Landroid/content/Context;[I)V"),
+        ]
+    ),
+    (
+        EntryPointKind::ContentProvider,
+        &[
+            "Landroid/content/ContentProvider;",
+            "Landroidx/core/content/FileProvider;",
+        ],
+        &[
+            ("<init>", "()V"),
+            ("onCreate", "()Z"),
+            ("query", "(Landroid/net/Uri;[Ljava/lang/String;Ljava/lang/String;[Ljava/lang/String;Ljava/lang/String;)Landroid/database/Cursor;"),
+            ("insert", "(Landroid/net/Uri;Landroid/content/ContentValues;)Landroid/net/Uri;"),
+            ("bulkInsert", "(Landroid/net/Uri;[Landroid/content/ContentValues;)I"),
+            ("update", "(Landroid/net/Uri;Landroid/content/ContentValues;Ljava/lang/String;[Ljava/lang/String;)I"),
+            ("delete", "(Landroid/net/Uri;Ljava/lang/String;[Ljava/lang/String;)I"),
+            ("getType", "(Landroid/net/Uri;)Ljava/lang/String;"),
+            ("call", "(Ljava/lang/String;Ljava/lang/String;Landroid/os/Bundle;)Landroid/os/Bundle;"),
+            ("openFile", "(Landroid/net/Uri;Ljava/lang/String;)Landroid/os/ParcelFileDescriptor;"),
+            ("openAssetFile", "(Landroid/net/Uri;Ljava/lang/String;)Landroid/content/res/AssetFileDescriptor;"),
+            ("applyBatch", "(Ljava/util/ArrayList;)[Landroid/content/ContentProviderResult;"),
+            ("shutdown", "()V"),
+        ]
+    ),
+    (
+        EntryPointKind::Application,
+        &[
+            "Landroid/app/Application;",
+            "Landroidx/multidex/MultiDexApplication;",
+            "Landroid/support/multidex/MultiDexApplication;",
+        ],
+        &[
+            ("<init>", "()V"),
+            ("onCreate", "()V"),
+            ("attachBaseContext", "(Landroid/content/Context;)V"),
+            ("onTerminate", "()V"),
+            ("onConfigurationChanged", "(Landroid/content/res/Configuration;)V"),
+            ("onLowMemory", "()V"),
+            ("onTrimMemory", "(I)V"),
+        ]
+    ),
+    (
+        EntryPointKind::Runnable,
+        &[
+            "Ljava/lang/Runnable;",
+            "Ljava/lang/Thread;",
+            "Ljava/util/concurrent/Callable;",
+        ],
+        &[
+            ("run", "()V"),
+            ("call", "()Ljava/lang/Object;"),
+        ]
+    ),
+    (
+        EntryPointKind::AsyncTask,
+        &[
+            "Landroid/os/AsyncTask;",
+        ],
+        &[
+            ("doInBackground", "([Ljava/lang/Object;)Ljava/lang/Object;"),
+            ("onPreExecute", "()V"),
+            ("onPostExecute", "(Ljava/lang/Object;)V"),
+            ("onProgressUpdate", "([Ljava/lang/Object;)V"),
+            ("onCancelled", "()V"),
+            ("onCancelled", "(Ljava/lang/Object;)V"),
+        ]
+    ),
+];
+
+/// Callbacks of the common framework listeners, used when the listener is
+/// not part of the hierarchy or Shuriken does not know the method
+const LISTENER_CALLBACKS: [(&str, &str, &str); 20] = [
+    ("Landroid/view/View$OnClickListener;", "onClick", "(Landroid/view/View;)V"),
+    ("Landroid/view/View$OnLongClickListener;", "onLongClick", "(Landroid/view/View;)Z"),
+    ("Landroid/view/View$OnTouchListener;", "onTouch", "(Landroid/view/View;Landroid/view/MotionEvent;)Z"),
+    ("Landroid/view/View$OnKeyListener;", "onKey", "(Landroid/view/View;ILandroid/view/KeyEvent;)Z"),
+    ("Landroid/view/View$OnFocusChangeListener;", "onFocusChange", "(Landroid/view/View;Z)V"),
+    ("Landroid/widget/CompoundButton$OnCheckedChangeListener;", "onCheckedChanged", "(Landroid/widget/CompoundButton;Z)V"),
+    ("Landroid/widget/AdapterView$OnItemClickListener;", "onItemClick", "(Landroid/widget/AdapterView;Landroid/view/View;IJ)V"),
+    ("Landroid/widget/AdapterView$OnItemLongClickListener;", "onItemLongClick", "(Landroid/widget/AdapterView;Landroid/view/View;IJ)Z"),
+    ("Landroid/widget/AdapterView$OnItemSelectedListener;", "onItemSelected", "(Landroid/widget/AdapterView;Landroid/view/View;IJ)V"),
+    ("Landroid/widget/AdapterView$OnItemSelectedListener;", "onNothingSelected", "(Landroid/widget/AdapterView;)V"),
+    ("Landroid/widget/SeekBar$OnSeekBarChangeListener;", "onProgressChanged", "(Landroid/widget/SeekBar;IZ)V"),
+    ("Landroid/widget/SeekBar$OnSeekBarChangeListener;", "onStartTrackingTouch", "(Landroid/widget/SeekBar;)V"),
+    ("Landroid/widget/SeekBar$OnSeekBarChangeListener;", "onStopTrackingTouch", "(Landroid/widget/SeekBar;)V"),
+    ("Landroid/content/DialogInterface$OnClickListener;", "onClick", "(Landroid/content/DialogInterface;I)V"),
+    ("Landroid/content/DialogInterface$OnCancelListener;", "onCancel", "(Landroid/content/DialogInterface;)V"),
+    ("Landroid/content/DialogInterface$OnDismissListener;", "onDismiss", "(Landroid/content/DialogInterface;)V"),
+    ("Landroid/view/MenuItem$OnMenuItemClickListener;", "onMenuItemClick", "(Landroid/view/MenuItem;)Z"),
+    ("Landroid/text/TextWatcher;", "beforeTextChanged", "(Ljava/lang/CharSequence;III)V"),
+    ("Landroid/text/TextWatcher;", "onTextChanged", "(Ljava/lang/CharSequence;III)V"),
+    ("Landroid/text/TextWatcher;", "afterTextChanged", "(Landroid/text/Editable;)V"),
+];
+
+/// Entry point of the application
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPoint {
+    /// Full name of the method
+    method: String,
+    /// Descriptor of the class defining the method
+    class: String,
+    /// Kind of entry point
+    kind: EntryPointKind
+}
+
+impl EntryPoint {
+    /// Return a reference to the full name of the method
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Return a reference to the descriptor of the class defining the method
+    pub fn class(&self) -> &str {
+        &self.class
+    }
+
+    /// Return the kind of entry point
+    pub fn kind(&self) -> EntryPointKind {
+        self.kind
+    }
+}
+
+/// Set of entry points of an application
+#[derive(Debug, Default, PartialEq)]
+pub struct EntryPoints {
+    entry_points: Vec<EntryPoint>
+}
+
+impl EntryPoints {
+    /// Find the entry points among the internal classes of a hierarchy
+    pub fn find(hierarchy: &ClassHierarchy) -> Self {
+        let mut entry_points = Vec::new();
+
+        let mut classes = hierarchy.classes()
+            .filter(|class| !class.is_external())
+            .collect::<Vec<&HierarchyClass>>();
+        classes.sort_by(|a, b| a.name().cmp(b.name()));
+
+        for class in classes {
+            let supertypes = hierarchy.supertypes(class.name());
+
+            for method in class.methods() {
+                if let Some(kind) = Self::kind_of(hierarchy, &supertypes, method) {
+                    entry_points.push(EntryPoint {
+                        method: method.full_name().to_string(),
+                        class: class.name().to_string(),
+                        kind
+                    });
+                }
+            }
+        }
+
+        Self { entry_points }
+    }
+
+    /// Find how a method is called by the framework, if it is
+    ///
+    /// Callbacks are matched by name and descriptor. Static and private
+    /// methods never override a framework method.
+    fn kind_of(hierarchy: &ClassHierarchy, supertypes: &[String], method: &HierarchyMethod) -> Option<EntryPointKind> {
+        let flags = method.access_flags();
+        if flags.contains(&DvmAccessFlag::ACC_NATIVE) {
+            return Some(EntryPointKind::Native);
+        }
+        if flags.contains(&DvmAccessFlag::ACC_STATIC) || flags.contains(&DvmAccessFlag::ACC_PRIVATE) {
+            return None;
+        }
+
+        let signature = (method.name(), method.descriptor());
+        for (kind, framework_types, callbacks) in FRAMEWORK_CALLBACKS.iter() {
+            if callbacks.contains(&signature)
+                && supertypes.iter().any(|supertype| framework_types.contains(&supertype.as_str()))
+            {
+                return Some(*kind);
+            }
+        }
+
+        if !method.is_virtual() {
+            return None;
+        }
+
+        supertypes.iter()
+            .filter(|supertype| Self::is_ui_listener(supertype))
+            .any(|listener| LISTENER_CALLBACKS.contains(&(listener.as_str(), signature.0, signature.1))
+                || hierarchy.class(listener)
+                            .and_then(|class| class.method(method.name(), method.descriptor()))
+                            .is_some())
+            .then_some(EntryPointKind::UiListener)
+    }
+
+    /// Whether a type is a framework listener or callback interface
+    fn is_ui_listener(descriptor: &str) -> bool {
+        let is_framework = descriptor.starts_with("Landroid/")
+            || descriptor.starts_with("Landroidx/");
+        let simple_name = descriptor.trim_end_matches(';')
+            .rsplit(['/', '$'])
+            .next()
+            .unwrap_or_default();
+
+        is_framework
+            && (simple_name.ends_with("Listener")
+                || simple_name.ends_with("Callback")
+                || simple_name == "TextWatcher")
+    }
+
+    /// Return a reference to the entry points
+    pub fn entry_points(&self) -> &[EntryPoint] {
+        &self.entry_points
+    }
+
+    /// Get the entry points of a given kind
+    pub fn of_kind(&self, kind: EntryPointKind) -> Vec<&EntryPoint> {
+        self.entry_points
+            .iter()
+            .filter(|entry_point| entry_point.kind == kind)
+            .collect()
+    }
+
+    /// Get the full names of the entry point methods, to use as roots of a
+    /// reachability analysis
+    pub fn roots(&self) -> Vec<&str> {
+        self.entry_points
+            .iter()
+            .map(|entry_point| entry_point.method.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn method(class: &str, name: &str, descriptor: &str, flags: &[DvmAccessFlag]) -> HierarchyMethod {
+        HierarchyMethod::new(name, descriptor, &format!("{class}->{name}{descriptor}"), flags)
+    }
+
+    #[test]
+    fn test_find_entry_points() {
+        let mut hierarchy = ClassHierarchy::new();
+
+        hierarchy.add_class(HierarchyClass::new("Landroidx/appcompat/app/AppCompatActivity;", None, &[], true));
+
+        let mut base = HierarchyClass::new("Lcom/app/BaseActivity;", Some("Landroidx/appcompat/app/AppCompatActivity;"), &[], false);
+        base.add_method(method("Lcom/app/BaseActivity;", "onResume", "()V", &[DvmAccessFlag::ACC_PROTECTED]));
+        hierarchy.add_class(base);
+
+        let mut main = HierarchyClass::new(
+            "Lcom/app/MainActivity;",
+            Some("Lcom/app/BaseActivity;"),
+            &[String::from("Landroid/view/View$OnClickListener;")],
+            false
+        );
+        main.add_method(method("Lcom/app/MainActivity;", "onCreate", "(Landroid/os/Bundle;)V", &[DvmAccessFlag::ACC_PROTECTED]));
+        main.add_method(method("Lcom/app/MainActivity;", "onClick", "(Landroid/view/View;)V", &[DvmAccessFlag::ACC_PUBLIC]));
+        main.add_method(method("Lcom/app/MainActivity;", "a", "()V", &[DvmAccessFlag::ACC_PRIVATE]));
+        main.add_method(method("Lcom/app/MainActivity;", "onSwipe", "(Landroid/view/View;)V", &[DvmAccessFlag::ACC_PUBLIC]));
+        main.add_method(method("Lcom/app/MainActivity;", "onStop", "(I)V", &[DvmAccessFlag::ACC_PUBLIC]));
+        main.add_method(method("Lcom/app/MainActivity;", "onPause", "()V", &[DvmAccessFlag::ACC_PRIVATE]));
+        main.add_method(method("Lcom/app/MainActivity;", "onStart", "()V", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_STATIC]));
+        main.add_method(method("Lcom/app/MainActivity;", "decrypt", "([B)[B", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_NATIVE]));
+        hierarchy.add_class(main);
+
+        let mut task = HierarchyClass::new("Lcom/app/Task;", Some("Ljava/lang/Object;"), &[String::from("Ljava/lang/Runnable;")], false);
+        task.add_method(method("Lcom/app/Task;", "run", "()V", &[DvmAccessFlag::ACC_PUBLIC]));
+        hierarchy.add_class(task);
+
+        let entry_points = EntryPoints::find(&hierarchy);
+        let kind = |name: &str| entry_points.entry_points()
+            .iter()
+            .find(|entry_point| entry_point.method() == name)
+            .map(|entry_point| entry_point.kind());
+
+        assert_eq!(kind("Lcom/app/BaseActivity;->onResume()V"), Some(EntryPointKind::Activity));
+        assert_eq!(kind("Lcom/app/MainActivity;->onCreate(Landroid/os/Bundle;)V"), Some(EntryPointKind::Activity));
+        assert_eq!(kind("Lcom/app/MainActivity;->onClick(Landroid/view/View;)V"), Some(EntryPointKind::UiListener));
+        assert_eq!(kind("Lcom/app/MainActivity;->decrypt([B)[B"), Some(EntryPointKind::Native));
+        assert_eq!(kind("Lcom/app/Task;->run()V"), Some(EntryPointKind::Runnable));
+        assert_eq!(kind("Lcom/app/MainActivity;->a()V"), None);
+        assert_eq!(kind("Lcom/app/MainActivity;->onSwipe(Landroid/view/View;)V"), None);
+        assert_eq!(kind("Lcom/app/MainActivity;->onStop(I)V"), None);
+        assert_eq!(kind("Lcom/app/MainActivity;->onPause()V"), None);
+        assert_eq!(kind("Lcom/app/MainActivity;->onStart()V"), None);
+
+        assert_eq!(entry_points.roots().len(), 5);
+        assert_eq!(entry_points.of_kind(EntryPointKind::Activity).len(), 2);
+    }
+}
@@ -0,0 +1,240 @@
+//! Exception handlers
+//!
+//! Shuriken's handler types are not usable: the one of the basic blocks leads
+//! to a segmentation fault (see [`issue 153`]). The `try_item`s and the
+//! `encoded_catch_handler_list` of a method are read from the DEX file instead,
+//! to map the try ranges to their handlers and to follow the exceptions
+//! thrown by a method.
+//!
+//! [`issue 153`]: https://github.com/Shuriken-Group/Shuriken-Analyzer/issues/153
+
+use crate::dex_file::{ CodeItem, DexFile };
+use crate::hierarchy::ClassHierarchy;
+use crate::semantics::{ self, InstructionSemantics };
+
+/// Type caught by catch-all handlers, like the ones of `finally` blocks
+pub const THROWABLE: &str = "Ljava/lang/Throwable;";
+
+/// Opcode of `move-exception`
+const MOVE_EXCEPTION: u16 = 0x0d;
+/// Opcode of `check-cast`
+const CHECK_CAST: u16 = 0x1f;
+/// Opcode of `new-instance`
+const NEW_INSTANCE: u16 = 0x22;
+/// Opcode of `throw`
+pub(crate) const THROW: u32 = 0x27;
+
+/// Handler of a try range
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatchHandler {
+    /// Descriptor of the exception caught, `None` for a catch-all handler
+    exception_type: Option<String>,
+    /// Address of the first instruction of the handler
+    address: u64
+}
+
+impl CatchHandler {
+    /// Return a reference to the descriptor of the exception caught, `None`
+    /// for a catch-all handler
+    pub fn exception_type(&self) -> Option<&str> {
+        self.exception_type.as_deref()
+    }
+
+    /// Whether the handler catches every exception
+    pub fn is_catch_all(&self) -> bool {
+        self.exception_type.is_none()
+    }
+
+    /// Return the address of the first instruction of the handler
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Check if the handler catches an exception given its descriptor, `None`
+    /// if the type is unknown
+    ///
+    /// Exceptions outside of the hierarchy are only caught by a handler of
+    /// their exact type, of `Throwable`, or by a catch-all handler.
+    pub fn catches(&self, exception_type: Option<&str>, hierarchy: &ClassHierarchy) -> bool {
+        match (self.exception_type.as_deref(), exception_type) {
+            (None, _) | (Some(THROWABLE), _) => true,
+            (Some(caught), Some(thrown)) => hierarchy.is_subtype_of(thrown, caught),
+            (Some(_), None) => false
+        }
+    }
+}
+
+/// Range of instructions protected by handlers, from a `try_item`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TryRange {
+    /// Address of the first instruction covered
+    start_address: u64,
+    /// Address following the last instruction covered
+    end_address: u64,
+    /// Handlers in the order they are tried, the catch-all handler last
+    handlers: Vec<CatchHandler>
+}
+
+impl TryRange {
+    /// Read the try ranges of a `code_item`
+    pub(crate) fn read_all(dex: &DexFile, code: &CodeItem) -> Vec<Self> {
+        if code.tries_size == 0 {
+            return Vec::new();
+        }
+
+        // The try items are 4-byte aligned after the instructions
+        let tries_off = (code.insns_off + code.insns_size as usize * 2 + 3) & !3;
+        let handlers_off = tries_off + code.tries_size as usize * 8;
+
+        (0..code.tries_size as usize)
+            .filter_map(|n| {
+                let mut reader = dex.reader(tries_off + n * 8);
+                let start_addr = reader.read_u32()? as u64;
+                let insn_count = reader.read_u16()? as u64;
+                let handler_off = reader.read_u16()? as usize;
+
+                Some(Self {
+                    start_address: start_addr * 2,
+                    end_address: (start_addr + insn_count) * 2,
+                    handlers: Self::read_handlers(dex, handlers_off + handler_off)?
+                })
+            })
+            .collect()
+    }
+
+    /// Read an `encoded_catch_handler`
+    fn read_handlers(dex: &DexFile, offset: usize) -> Option<Vec<CatchHandler>> {
+        let mut reader = dex.reader(offset);
+        let size = reader.read_sleb128()?;
+
+        let mut handlers = (0..size.unsigned_abs())
+            .map(|_| {
+                let exception_type = dex.type_descriptor(reader.read_uleb128()?)?;
+                let address = reader.read_uleb128()? as u64 * 2;
+                Some(CatchHandler { exception_type: Some(exception_type), address })
+            })
+            .collect::<Option<Vec<CatchHandler>>>()?;
+
+        if size <= 0 {
+            let address = reader.read_uleb128()? as u64 * 2;
+            handlers.push(CatchHandler { exception_type: None, address });
+        }
+
+        Some(handlers)
+    }
+
+    /// Return the address of the first instruction covered
+    pub fn start_address(&self) -> u64 {
+        self.start_address
+    }
+
+    /// Return the address following the last instruction covered
+    pub fn end_address(&self) -> u64 {
+        self.end_address
+    }
+
+    /// Return a reference to the handlers, in the order they are tried
+    pub fn handlers(&self) -> &[CatchHandler] {
+        &self.handlers
+    }
+
+    /// Whether the instruction at a given address is covered by the range
+    pub fn covers(&self, address: u64) -> bool {
+        (self.start_address..self.end_address).contains(&address)
+    }
+
+    /// Get the handler receiving an exception thrown in the range, given its
+    /// descriptor or `None` if the type is unknown
+    pub fn handler_for(&self, exception_type: Option<&str>, hierarchy: &ClassHierarchy) -> Option<&CatchHandler> {
+        self.handlers
+            .iter()
+            .find(|handler| handler.catches(exception_type, hierarchy))
+    }
+}
+
+/// Exception thrown by a `throw` instruction
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrownException {
+    /// Address of the `throw` instruction
+    address: u64,
+    /// Descriptor of the exception, if known
+    exception_type: Option<String>
+}
+
+impl ThrownException {
+    pub(crate) fn new(address: u64, exception_type: Option<String>) -> Self {
+        Self { address, exception_type }
+    }
+
+    /// Return the address of the `throw` instruction
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Return a reference to the descriptor of the exception, `None` if it
+    /// cannot be resolved from the instructions before the `throw`
+    pub fn exception_type(&self) -> Option<&str> {
+        self.exception_type.as_deref()
+    }
+}
+
+/// Resolve the type of the exception thrown by a `throw` instruction from the
+/// instructions preceding it, latest first
+///
+/// The register is followed back to the `new-instance` creating it, a
+/// `check-cast`, or the `move-exception` of a handler catching `caught`.
+pub(crate) fn thrown_type(
+    dex: &DexFile,
+    code: &CodeItem,
+    throw_address: u64,
+    preceding: impl IntoIterator<Item = u64>,
+    caught: Option<&CatchHandler>
+) -> Option<String> {
+    let register = semantics::code_units(dex, code, throw_address)[0] >> 8;
+
+    for address in preceding {
+        let units = semantics::code_units(dex, code, address);
+        if !InstructionSemantics::decode(&units, address).writes().contains(&register) {
+            continue;
+        }
+
+        return match units[0] & 0xff {
+            NEW_INSTANCE | CHECK_CAST => dex.type_descriptor(units[1] as u32),
+            MOVE_EXCEPTION => caught.and_then(|handler| handler.exception_type.clone()),
+            _ => None
+        };
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catch_handlers() {
+        let mut hierarchy = ClassHierarchy::new();
+        hierarchy.add_class(crate::hierarchy::HierarchyClass::new(
+            "Lcom/example/ParseException;", Some("Ljava/io/IOException;"), &[], false
+        ));
+
+        let handler = |exception_type: Option<&str>| CatchHandler {
+            exception_type: exception_type.map(str::to_string),
+            address: 0x20
+        };
+        let range = TryRange {
+            start_address: 4,
+            end_address: 0x10,
+            handlers: vec![handler(Some("Ljava/io/IOException;")), handler(None)]
+        };
+
+        assert!(range.covers(4) && range.covers(0xe) && !range.covers(0x10));
+        assert_eq!(range.handler_for(Some("Lcom/example/ParseException;"), &hierarchy), Some(&range.handlers[0]));
+        assert_eq!(range.handler_for(Some("Ljava/lang/RuntimeException;"), &hierarchy), Some(&range.handlers[1]));
+        assert_eq!(range.handler_for(None, &hierarchy), Some(&range.handlers[1]));
+
+        assert!(handler(Some(THROWABLE)).catches(None, &hierarchy));
+        assert!(!handler(Some("Ljava/io/IOException;")).catches(Some("Ljava/lang/Exception;"), &hierarchy));
+    }
+}
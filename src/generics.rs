@@ -0,0 +1,542 @@
+//! Generic signatures
+//!
+//! The descriptors of a DEX file are erased, the generic types only survive
+//! in the `Signature` system annotations, written with the grammar of the
+//! JVM signatures. This module parses them and renders the types as Java
+//! source, like `java.util.Map<java.lang.String, java.util.List<java.lang.Integer>>`.
+//!
+//! See the [`JVM specification`] for the grammar.
+//!
+//! [`JVM specification`]: https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.9.1
+
+use std::fmt;
+
+use crate::dex_file::to_class_name;
+
+/// Type of a generic signature
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenericType {
+    /// Primitive type or `void`, as a descriptor like `I`
+    Primitive(char),
+    /// Class type with the type arguments of the class and of its outer classes
+    Class(Vec<ClassTypeSegment>),
+    /// Type variable, like `T`
+    TypeVariable(String),
+    /// Array of a type
+    Array(Box<GenericType>)
+}
+
+/// Class of a class type, with its type arguments
+///
+/// Inner classes of generic classes are a segment each, like
+/// `Outer<T>.Inner<U>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassTypeSegment {
+    /// Qualified name for the first segment, simple name for the inner classes
+    name: String,
+    arguments: Vec<TypeArgument>
+}
+
+impl ClassTypeSegment {
+    /// Return a reference to the name of the class, qualified for the outermost class
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the type arguments
+    pub fn arguments(&self) -> &[TypeArgument] {
+        &self.arguments
+    }
+}
+
+/// Type argument of a class type
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeArgument {
+    /// Exact type
+    Type(GenericType),
+    /// Unbounded wildcard `?`
+    Wildcard,
+    /// Wildcard with an upper bound, `? extends T`
+    Extends(GenericType),
+    /// Wildcard with a lower bound, `? super T`
+    Super(GenericType)
+}
+
+/// Type parameter of a generic class or method, with its bounds
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeParameter {
+    name: String,
+    /// Bounds, the class bound first if any
+    bounds: Vec<GenericType>
+}
+
+impl TypeParameter {
+    /// Return a reference to the name of the parameter
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the bounds, the class bound first if any
+    pub fn bounds(&self) -> &[GenericType] {
+        &self.bounds
+    }
+}
+
+/// Generic signature of a class
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassSignature {
+    type_parameters: Vec<TypeParameter>,
+    super_class: GenericType,
+    interfaces: Vec<GenericType>
+}
+
+impl ClassSignature {
+    /// Parse the signature of a class, like `<T:Ljava/lang/Object;>Ljava/lang/Object;`
+    pub fn parse(signature: &str) -> Option<Self> {
+        let mut parser = Parser::new(signature);
+        let type_parameters = parser.type_parameters()?;
+        let super_class = parser.class_type()?;
+
+        let mut interfaces = Vec::new();
+        while !parser.is_done() {
+            interfaces.push(parser.class_type()?);
+        }
+
+        Some(Self { type_parameters, super_class, interfaces })
+    }
+
+    /// Return a reference to the type parameters
+    pub fn type_parameters(&self) -> &[TypeParameter] {
+        &self.type_parameters
+    }
+
+    /// Return a reference to the generic super class
+    pub fn super_class(&self) -> &GenericType {
+        &self.super_class
+    }
+
+    /// Return a reference to the generic interfaces
+    pub fn interfaces(&self) -> &[GenericType] {
+        &self.interfaces
+    }
+
+    /// Render the declaration of a class as Java source, like
+    /// `Box<T> extends java.lang.Object implements java.lang.Comparable<Box<T>>`
+    pub fn to_java(&self, name: &str) -> String {
+        let mut java = format!("{name}{}", TypeParameters(&self.type_parameters));
+        java.push_str(&format!(" extends {}", self.super_class));
+
+        if !self.interfaces.is_empty() {
+            java.push_str(&format!(" implements {}", join(&self.interfaces)));
+        }
+
+        java
+    }
+}
+
+/// Generic signature of a method
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSignature {
+    type_parameters: Vec<TypeParameter>,
+    parameters: Vec<GenericType>,
+    return_type: GenericType,
+    throws: Vec<GenericType>
+}
+
+impl MethodSignature {
+    /// Parse the signature of a method, like `<T:Ljava/lang/Object;>(TT;)TT;`
+    pub fn parse(signature: &str) -> Option<Self> {
+        let mut parser = Parser::new(signature);
+        let type_parameters = parser.type_parameters()?;
+
+        parser.expect('(')?;
+        let mut parameters = Vec::new();
+        while !parser.eat(')') {
+            parameters.push(parser.java_type()?);
+        }
+        let return_type = parser.java_type()?;
+
+        let mut throws = Vec::new();
+        while parser.eat('^') {
+            throws.push(parser.reference_type()?);
+        }
+
+        parser.is_done().then_some(Self { type_parameters, parameters, return_type, throws })
+    }
+
+    /// Return a reference to the type parameters
+    pub fn type_parameters(&self) -> &[TypeParameter] {
+        &self.type_parameters
+    }
+
+    /// Return a reference to the generic types of the parameters
+    pub fn parameters(&self) -> &[GenericType] {
+        &self.parameters
+    }
+
+    /// Return a reference to the generic return type
+    pub fn return_type(&self) -> &GenericType {
+        &self.return_type
+    }
+
+    /// Return a reference to the generic exceptions, only present in the
+    /// signature when one of them is a type variable
+    pub fn throws(&self) -> &[GenericType] {
+        &self.throws
+    }
+
+    /// Render the declaration of a method as Java source, like
+    /// `<T> T identity(T)`
+    pub fn to_java(&self, name: &str) -> String {
+        let mut java = match self.type_parameters.is_empty() {
+            true => String::new(),
+            false => format!("{} ", TypeParameters(&self.type_parameters))
+        };
+        java.push_str(&format!("{} {name}({})", self.return_type, join(&self.parameters)));
+
+        if !self.throws.is_empty() {
+            java.push_str(&format!(" throws {}", join(&self.throws)));
+        }
+
+        java
+    }
+}
+
+impl GenericType {
+    /// Parse the signature of a field, like `Ljava/util/List<Ljava/lang/String;>;`
+    ///
+    /// Plain descriptors are valid signatures as well.
+    pub fn parse(signature: &str) -> Option<Self> {
+        let mut parser = Parser::new(signature);
+        let generic_type = parser.java_type()?;
+
+        parser.is_done().then_some(generic_type)
+    }
+
+    /// Get the erased type descriptor, like `Ljava/util/List;`
+    ///
+    /// Type variables are erased to `Ljava/lang/Object;`, not to their bound.
+    pub fn erasure(&self) -> String {
+        match self {
+            GenericType::Primitive(descriptor) => descriptor.to_string(),
+            GenericType::Class(segments) => {
+                let name = segments.iter()
+                    .map(|segment| segment.name.replace('.', "/"))
+                    .collect::<Vec<String>>()
+                    .join("$");
+                format!("L{name};")
+            },
+            GenericType::TypeVariable(_) => String::from("Ljava/lang/Object;"),
+            GenericType::Array(component) => format!("[{}", component.erasure())
+        }
+    }
+}
+
+impl fmt::Display for GenericType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenericType::Primitive(descriptor) => write!(f, "{}", to_class_name(&descriptor.to_string())),
+            GenericType::Class(segments) => {
+                for (idx, segment) in segments.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ".")?;
+                    }
+                    write!(f, "{}", segment.name)?;
+
+                    if !segment.arguments.is_empty() {
+                        write!(f, "<{}>", join(&segment.arguments))?;
+                    }
+                }
+                Ok(())
+            },
+            GenericType::TypeVariable(name) => write!(f, "{name}"),
+            GenericType::Array(component) => write!(f, "{component}[]")
+        }
+    }
+}
+
+impl fmt::Display for TypeArgument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeArgument::Type(generic_type) => write!(f, "{generic_type}"),
+            TypeArgument::Wildcard => write!(f, "?"),
+            TypeArgument::Extends(bound) => write!(f, "? extends {bound}"),
+            TypeArgument::Super(bound) => write!(f, "? super {bound}")
+        }
+    }
+}
+
+impl fmt::Display for TypeParameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+
+        // `Object` alone is the implicit bound
+        let is_implicit = match self.bounds.as_slice() {
+            [] => true,
+            [GenericType::TypeVariable(_)] => false,
+            [bound] => bound.erasure() == "Ljava/lang/Object;",
+            _ => false
+        };
+        if !is_implicit {
+            let bounds = self.bounds.iter()
+                .map(|bound| bound.to_string())
+                .collect::<Vec<String>>();
+            write!(f, " extends {}", bounds.join(" & "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Type parameters between angle brackets, nothing if there is none
+struct TypeParameters<'a>(&'a [TypeParameter]);
+
+impl fmt::Display for TypeParameters<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.is_empty() {
+            true => Ok(()),
+            false => write!(f, "<{}>", join(self.0))
+        }
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items.iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Recursive descent parser of the signatures
+struct Parser<'a> {
+    signature: &'a str,
+    pos: usize,
+    /// Number of reference types being parsed, one inside the other
+    depth: usize
+}
+
+impl<'a> Parser<'a> {
+    /// Maximum nesting of arrays and type arguments, deeper signatures are rejected
+    const MAX_DEPTH: usize = 256;
+
+    fn new(signature: &'a str) -> Self {
+        Self { signature, pos: 0, depth: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.signature[self.pos..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    /// Consume a character if it is the next one
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.pos += expected.len_utf8();
+        }
+        found
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        self.eat(expected).then_some(())
+    }
+
+    fn is_done(&self) -> bool {
+        self.pos == self.signature.len()
+    }
+
+    /// Read an identifier up to one of the delimiters
+    fn identifier(&mut self, delimiters: &[char]) -> Option<&'a str> {
+        let rest = &self.signature[self.pos..];
+        let len = rest.find(|c| delimiters.contains(&c)).unwrap_or(rest.len());
+        if len == 0 {
+            return None;
+        }
+
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn type_parameters(&mut self) -> Option<Vec<TypeParameter>> {
+        let mut parameters = Vec::new();
+        if !self.eat('<') {
+            return Some(parameters);
+        }
+
+        while !self.eat('>') {
+            let name = self.identifier(&[':', '>'])?.to_string();
+            let mut bounds = Vec::new();
+
+            // The class bound may be empty when there are only interface bounds
+            self.expect(':')?;
+            if !matches!(self.peek(), Some(':')) {
+                bounds.push(self.reference_type()?);
+            }
+            while self.eat(':') {
+                bounds.push(self.reference_type()?);
+            }
+
+            parameters.push(TypeParameter { name, bounds });
+        }
+
+        (!parameters.is_empty()).then_some(parameters)
+    }
+
+    fn java_type(&mut self) -> Option<GenericType> {
+        match self.peek()? {
+            c @ ('Z' | 'B' | 'S' | 'C' | 'I' | 'J' | 'F' | 'D' | 'V') => {
+                self.next();
+                Some(GenericType::Primitive(c))
+            },
+            _ => self.reference_type()
+        }
+    }
+
+    fn reference_type(&mut self) -> Option<GenericType> {
+        if self.depth >= Self::MAX_DEPTH {
+            return None;
+        }
+
+        self.depth += 1;
+        let reference_type = self.nested_reference_type();
+        self.depth -= 1;
+        reference_type
+    }
+
+    fn nested_reference_type(&mut self) -> Option<GenericType> {
+        match self.peek()? {
+            'L' => self.class_type(),
+            'T' => {
+                self.next();
+                let name = self.identifier(&[';'])?.to_string();
+                self.expect(';')?;
+                Some(GenericType::TypeVariable(name))
+            },
+            '[' => {
+                self.next();
+                Some(GenericType::Array(Box::new(self.java_type()?)))
+            },
+            _ => None
+        }
+    }
+
+    fn class_type(&mut self) -> Option<GenericType> {
+        self.expect('L')?;
+
+        let mut segments = Vec::new();
+        loop {
+            let name = self.identifier(&['<', '.', ';'])?.replace('/', ".");
+            let mut arguments = Vec::new();
+
+            if self.eat('<') {
+                while !self.eat('>') {
+                    arguments.push(self.type_argument()?);
+                }
+            }
+
+            segments.push(ClassTypeSegment { name, arguments });
+
+            match self.next()? {
+                '.' => continue,
+                ';' => break,
+                _ => return None
+            }
+        }
+
+        Some(GenericType::Class(segments))
+    }
+
+    fn type_argument(&mut self) -> Option<TypeArgument> {
+        match self.peek()? {
+            '*' => {
+                self.next();
+                Some(TypeArgument::Wildcard)
+            },
+            '+' => {
+                self.next();
+                Some(TypeArgument::Extends(self.reference_type()?))
+            },
+            '-' => {
+                self.next();
+                Some(TypeArgument::Super(self.reference_type()?))
+            },
+            _ => Some(TypeArgument::Type(self.reference_type()?))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_signatures() {
+        let map = GenericType::parse("Ljava/util/Map<Ljava/lang/String;Ljava/util/List<Ljava/lang/Integer;>;>;").unwrap();
+        assert_eq!(map.to_string(), "java.util.Map<java.lang.String, java.util.List<java.lang.Integer>>");
+        assert_eq!(map.erasure(), "Ljava/util/Map;");
+
+        let wildcards = GenericType::parse("Ljava/util/Map<*+Ljava/lang/Number;>;").unwrap();
+        assert_eq!(wildcards.to_string(), "java.util.Map<?, ? extends java.lang.Number>");
+
+        let inner = GenericType::parse("Lcom/example/Outer<TT;>.Inner<-TU;>;").unwrap();
+        assert_eq!(inner.to_string(), "com.example.Outer<T>.Inner<? super U>");
+        assert_eq!(inner.erasure(), "Lcom/example/Outer$Inner;");
+
+        let array = GenericType::parse("[[Ljava/util/List<TE;>;").unwrap();
+        assert_eq!(array.to_string(), "java.util.List<E>[][]");
+        assert_eq!(GenericType::parse("J").unwrap().to_string(), "long");
+
+        assert!(GenericType::parse("Ljava/util/List<Ljava/lang/String;>").is_none());
+        assert!(GenericType::parse("Ljava/lang/String;I").is_none());
+    }
+
+    #[test]
+    fn test_class_signatures() {
+        let signature = ClassSignature::parse(
+            "<K::Ljava/lang/Comparable<TK;>;V:Ljava/lang/Object;>Ljava/util/AbstractMap<TK;TV;>;Ljava/io/Serializable;"
+        ).unwrap();
+
+        assert_eq!(signature.type_parameters().len(), 2);
+        assert_eq!(signature.type_parameters()[0].bounds().len(), 1);
+        assert_eq!(
+            signature.to_java("SortedMap"),
+            "SortedMap<K extends java.lang.Comparable<K>, V> extends java.util.AbstractMap<K, V> implements java.io.Serializable"
+        );
+    }
+
+    #[test]
+    fn test_method_signatures() {
+        let signature = MethodSignature::parse("<T:Ljava/lang/Object;X:Ljava/lang/Exception;>(TT;[ILjava/util/List<+TT;>;)TT;^TX;").unwrap();
+
+        assert_eq!(signature.parameters().len(), 3);
+        assert_eq!(signature.return_type(), &GenericType::TypeVariable(String::from("T")));
+        assert_eq!(
+            signature.to_java("first"),
+            "<T, X extends java.lang.Exception> T first(T, int[], java.util.List<? extends T>) throws X"
+        );
+
+        let signature = MethodSignature::parse("(Ljava/util/Map<Ljava/lang/String;*>;)V").unwrap();
+        assert!(signature.type_parameters().is_empty());
+        assert_eq!(signature.to_java("load"), "void load(java.util.Map<java.lang.String, ?>)");
+
+        assert!(MethodSignature::parse("(TT;").is_none());
+        assert!(MethodSignature::parse("<>()V").is_none());
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let arrays = |depth: usize| format!("{}I", "[".repeat(depth));
+        assert!(GenericType::parse(&arrays(Parser::MAX_DEPTH)).is_some());
+        assert!(GenericType::parse(&arrays(Parser::MAX_DEPTH + 1)).is_none());
+        assert!(GenericType::parse(&arrays(100_000)).is_none());
+
+        let arguments = |depth: usize| format!("{}Ljava/lang/Object;{}", "Ljava/util/List<".repeat(depth), ">;".repeat(depth));
+        assert!(GenericType::parse(&arguments(Parser::MAX_DEPTH - 1)).is_some());
+        assert!(GenericType::parse(&arguments(100_000)).is_none());
+    }
+}
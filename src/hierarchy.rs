@@ -0,0 +1,688 @@
+//! Class hierarchy
+//!
+//! Shuriken records calls to the declared target only. This module keeps the
+//! super class and interfaces of every analyzed class so that virtual calls can
+//! be resolved to their possible concrete implementations, either with Class
+//! Hierarchy Analysis (CHA) or Rapid Type Analysis (RTA).
+//!
+//! Class names are stored as type descriptors (`Lcom/example/Foo;`), but every
+//! lookup also accepts class names (`com.example.Foo`).
+
+use std::collections::{ HashMap, HashSet, VecDeque };
+
+use crate::dex_file::{ self, DexFile };
+use crate::analysis::{ DvmClassAnalysis, DvmMethodAnalysis };
+use crate::dvm_access_flags::{ DvmAccessFlag, DvmAccessFlagType };
+
+/// Algorithm used to resolve the targets of a virtual call
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DispatchResolution {
+    /// Only keep the declared target
+    Declared,
+    /// Class Hierarchy Analysis: every subtype of the declared class
+    Cha,
+    /// Rapid Type Analysis: every instantiated subtype of the declared class
+    Rta
+}
+
+/// Method of a class in the hierarchy
+#[derive(Debug, Clone, PartialEq)]
+pub struct HierarchyMethod {
+    /// Name of the method
+    name: String,
+    /// Descriptor of the method
+    descriptor: String,
+    /// Full name of the method including class name and descriptor
+    full_name: String,
+    /// Access flags
+    access_flags: Vec<DvmAccessFlag>
+}
+
+impl HierarchyMethod {
+    /// Create a method given its name, descriptor, full name and access flags
+    pub fn new(name: &str, descriptor: &str, full_name: &str, access_flags: &[DvmAccessFlag]) -> Self {
+        Self {
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            full_name: full_name.to_string(),
+            access_flags: access_flags.to_vec()
+        }
+    }
+
+    /// Build a hierarchy method from its analysis
+    pub fn from_method_analysis(method: &DvmMethodAnalysis) -> Self {
+        Self::new(method.name(), method.descriptor(), method.full_name(), method.access_flags())
+    }
+
+    /// Return a reference to the name of the method
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the descriptor of the method
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// Return a reference to the full name of the method
+    pub fn full_name(&self) -> &str {
+        &self.full_name
+    }
+
+    /// Return a reference to the access flags
+    pub fn access_flags(&self) -> &[DvmAccessFlag] {
+        &self.access_flags
+    }
+
+    /// Whether the method takes part in virtual dispatch
+    ///
+    /// Static methods, private methods and constructors are never dispatched.
+    pub fn is_virtual(&self) -> bool {
+        !self.access_flags.iter().any(|flag| matches!(flag,
+            DvmAccessFlag::ACC_STATIC | DvmAccessFlag::ACC_PRIVATE | DvmAccessFlag::ACC_CONSTRUCTOR
+        )) && !self.name.starts_with('<')
+    }
+
+    /// Whether the method has an implementation
+    pub fn is_abstract(&self) -> bool {
+        self.access_flags.contains(&DvmAccessFlag::ACC_ABSTRACT)
+    }
+}
+
+/// Field of a class in the hierarchy
+#[derive(Debug, Clone, PartialEq)]
+pub struct HierarchyField {
+    /// Name of the field
+    name: String,
+    /// Type descriptor of the field
+    descriptor: String,
+    /// Access flags
+    access_flags: Vec<DvmAccessFlag>
+}
+
+impl HierarchyField {
+    /// Create a field given its name, type descriptor and access flags
+    pub fn new(name: &str, descriptor: &str, access_flags: &[DvmAccessFlag]) -> Self {
+        Self {
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            access_flags: access_flags.to_vec()
+        }
+    }
+
+    /// Return a reference to the name of the field
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the type descriptor of the field
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// Return a reference to the access flags
+    pub fn access_flags(&self) -> &[DvmAccessFlag] {
+        &self.access_flags
+    }
+}
+
+/// Class in the hierarchy
+#[derive(Debug, Clone, PartialEq)]
+pub struct HierarchyClass {
+    /// Descriptor of the class
+    name: String,
+    /// Descriptor of the super class, if any
+    super_class: Option<String>,
+    /// Descriptors of the implemented interfaces
+    interfaces: Vec<String>,
+    /// Methods defined in the class
+    methods: Vec<HierarchyMethod>,
+    /// Fields defined in the class, only known when read from the DEX file
+    fields: Vec<HierarchyField>,
+    /// Is it an external class?
+    is_external: bool,
+    /// Is the class instantiated somewhere in the code?
+    is_instantiated: bool,
+    /// Descriptors of the classes referencing this class
+    referenced_by: Vec<String>
+}
+
+impl HierarchyClass {
+    /// Create a class without methods nor fields given its name, super class,
+    /// interfaces and whether it is external
+    pub fn new(name: &str, super_class: Option<&str>, interfaces: &[String], is_external: bool) -> Self {
+        Self {
+            name: dex_file::to_descriptor(name),
+            super_class: super_class
+                .filter(|super_class| !super_class.is_empty())
+                .map(dex_file::to_descriptor),
+            interfaces: interfaces.iter()
+                .map(|interface| dex_file::to_descriptor(interface))
+                .collect(),
+            methods: Vec::new(),
+            fields: Vec::new(),
+            is_external,
+            is_instantiated: false,
+            referenced_by: Vec::new()
+        }
+    }
+
+    /// Build a hierarchy class from its analysis
+    ///
+    /// The class is considered instantiated if a `new-instance` of it was found.
+    pub fn from_class_analysis(class: &DvmClassAnalysis) -> Self {
+        let mut hierarchy_class = Self::new(
+            class.name(),
+            Some(class.extends()),
+            class.interfaces(),
+            class.is_external()
+        );
+
+        hierarchy_class.methods = class.methods()
+            .iter()
+            .map(HierarchyMethod::from_method_analysis)
+            .collect();
+        hierarchy_class.is_instantiated = class.n_of_xrefnewinstance() > 0;
+
+        let referencing_methods = class.xrefnewinstance()
+            .iter()
+            .chain(class.xrefconstclass())
+            .filter_map(|xref| dex_file::split_method_name(xref.method()))
+            .map(|(class_name, _, _)| class_name);
+        let referencing_classes = class.xreffrom()
+            .iter()
+            .map(|xref| xref.class());
+
+        for referencing in referencing_classes.chain(referencing_methods) {
+            hierarchy_class.add_reference(referencing);
+        }
+
+        hierarchy_class
+    }
+
+    /// Add a method to the class
+    pub fn add_method(&mut self, method: HierarchyMethod) {
+        self.methods.push(method);
+    }
+
+    /// Add a field to the class
+    pub fn add_field(&mut self, field: HierarchyField) {
+        self.fields.push(field);
+    }
+
+    /// Record that a class references this class
+    pub fn add_reference(&mut self, class_name: &str) {
+        let descriptor = dex_file::to_descriptor(class_name);
+
+        if !self.referenced_by.contains(&descriptor) {
+            self.referenced_by.push(descriptor);
+        }
+    }
+
+    /// Mark the class as instantiated
+    pub fn set_instantiated(&mut self, is_instantiated: bool) {
+        self.is_instantiated = is_instantiated;
+    }
+
+    /// Return a reference to the descriptor of the class
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the descriptor of the super class
+    pub fn super_class(&self) -> Option<&str> {
+        self.super_class.as_deref()
+    }
+
+    /// Return a reference to the descriptors of the implemented interfaces
+    pub fn interfaces(&self) -> &[String] {
+        &self.interfaces
+    }
+
+    /// Return a reference to the methods defined in the class
+    pub fn methods(&self) -> &[HierarchyMethod] {
+        &self.methods
+    }
+
+    /// Return a reference to the fields defined in the class
+    pub fn fields(&self) -> &[HierarchyField] {
+        &self.fields
+    }
+
+    /// Return the is external class?
+    pub fn is_external(&self) -> bool {
+        self.is_external
+    }
+
+    /// Return the is the class instantiated?
+    pub fn is_instantiated(&self) -> bool {
+        self.is_instantiated
+    }
+
+    /// Return a reference to the descriptors of the classes referencing this class
+    ///
+    /// This includes calls, instantiations and `const-class` uses.
+    pub fn referenced_by(&self) -> &[String] {
+        &self.referenced_by
+    }
+
+    /// Find a method defined in this class
+    pub fn method(&self, name: &str, descriptor: &str) -> Option<&HierarchyMethod> {
+        self.methods
+            .iter()
+            .find(|method| method.name == name && method.descriptor == descriptor)
+    }
+}
+
+/// Class hierarchy of a set of analyzed classes
+#[derive(Debug, Default)]
+pub struct ClassHierarchy {
+    /// Classes indexed by descriptor
+    classes: HashMap<String, HierarchyClass>,
+    /// Direct subtypes (sub classes and implementing classes) of a class
+    subtypes: HashMap<String, Vec<String>>
+}
+
+impl ClassHierarchy {
+    /// Create an empty hierarchy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the hierarchy from a list of analyzed classes
+    pub fn from_classes(classes: &[DvmClassAnalysis]) -> Self {
+        let mut hierarchy = Self::new();
+
+        for class in classes {
+            hierarchy.add_class(HierarchyClass::from_class_analysis(class));
+        }
+
+        hierarchy
+    }
+
+    /// Add a class to the hierarchy
+    ///
+    /// If the class is already present it is replaced.
+    pub fn add_class(&mut self, class: HierarchyClass) {
+        if let Some(previous) = self.classes.remove(&class.name) {
+            for parent in previous.super_class.iter().chain(previous.interfaces.iter()) {
+                if let Some(subtypes) = self.subtypes.get_mut(parent) {
+                    subtypes.retain(|subtype| *subtype != previous.name);
+                }
+            }
+        }
+
+        for parent in class.super_class.iter().chain(class.interfaces.iter()) {
+            self.subtypes
+                .entry(parent.clone())
+                .or_default()
+                .push(class.name.clone());
+        }
+
+        self.classes.insert(class.name.clone(), class);
+    }
+
+    /// Read the fields of the classes defined in a DEX file, which Shuriken
+    /// does not describe
+    pub(crate) fn resolve_from_dex(&mut self, dex: &DexFile) {
+        for class in self.classes.values_mut().filter(|class| !class.is_external) {
+            let Some(class_def) = dex.class_def_by_name(&class.name) else {
+                continue;
+            };
+
+            class.fields = dex.class_fields(&class_def)
+                .into_iter()
+                .filter_map(|field| {
+                    let (_, name, descriptor) = dex.field_id(field.field_idx)?;
+                    let access_flags = DvmAccessFlag::parse(field.access_flags, DvmAccessFlagType::Field);
+                    Some(HierarchyField::new(&name, &descriptor, &access_flags))
+                })
+                .collect();
+        }
+    }
+
+    /// Get a class given its name or descriptor
+    pub fn class(&self, name: &str) -> Option<&HierarchyClass> {
+        self.classes.get(&dex_file::to_descriptor(name))
+    }
+
+    /// Get all the classes of the hierarchy
+    pub fn classes(&self) -> impl Iterator<Item = &HierarchyClass> {
+        self.classes.values()
+    }
+
+    /// Get the chain of super classes of a class, closest first
+    ///
+    /// The chain stops at the first class that is not part of the hierarchy.
+    pub fn superclasses(&self, name: &str) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut current = self.class(name).and_then(|class| class.super_class.clone());
+
+        while let Some(super_class) = current {
+            if chain.contains(&super_class) {
+                break;
+            }
+
+            current = self.classes.get(&super_class).and_then(|class| class.super_class.clone());
+            chain.push(super_class);
+        }
+
+        chain
+    }
+
+    /// Get all the super types (super classes and interfaces) of a class
+    pub fn supertypes(&self, name: &str) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut worklist = vec![dex_file::to_descriptor(name)];
+        let mut supertypes = Vec::new();
+
+        while let Some(current) = worklist.pop() {
+            if let Some(class) = self.classes.get(&current) {
+                for parent in class.super_class.iter().chain(class.interfaces.iter()) {
+                    if seen.insert(parent.clone()) {
+                        supertypes.push(parent.clone());
+                        worklist.push(parent.clone());
+                    }
+                }
+            }
+        }
+
+        supertypes
+    }
+
+    /// Get all the subtypes of a class or interface, transitively
+    pub fn subtypes(&self, name: &str) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut worklist = vec![dex_file::to_descriptor(name)];
+        let mut subtypes = Vec::new();
+
+        while let Some(current) = worklist.pop() {
+            for subtype in self.subtypes.get(&current).into_iter().flatten() {
+                if seen.insert(subtype.clone()) {
+                    subtypes.push(subtype.clone());
+                    worklist.push(subtype.clone());
+                }
+            }
+        }
+
+        subtypes
+    }
+
+    /// Check if `name` is `parent` or one of its subtypes
+    pub fn is_subtype_of(&self, name: &str, parent: &str) -> bool {
+        let name = dex_file::to_descriptor(name);
+        let parent = dex_file::to_descriptor(parent);
+
+        name == parent || self.supertypes(&name).contains(&parent)
+    }
+
+    /// Find the implementation of a method invoked on an object of type `name`
+    ///
+    /// The method is looked up in the class and then in its super classes,
+    /// up to the first class outside of the hierarchy. If none of them
+    /// defines it, the default methods of their super interfaces are
+    /// searched, closest interfaces first. Returns `None` if the method is
+    /// abstract or not found.
+    pub fn resolve_method(&self, name: &str, method_name: &str, descriptor: &str) -> Option<&HierarchyMethod> {
+        let mut current = Some(dex_file::to_descriptor(name));
+        let mut seen = HashSet::new();
+        let mut chain = Vec::new();
+
+        while let Some(class_name) = current {
+            if !seen.insert(class_name.clone()) {
+                break;
+            }
+
+            let Some(class) = self.classes.get(&class_name) else { break };
+            if let Some(method) = class.method(method_name, descriptor)
+                && method.is_virtual()
+            {
+                return (!method.is_abstract()).then_some(method);
+            }
+
+            chain.push(class);
+            current = class.super_class.clone();
+        }
+
+        let mut worklist = chain.iter()
+            .flat_map(|class| class.interfaces.iter().cloned())
+            .collect::<VecDeque<String>>();
+        let mut seen = HashSet::new();
+
+        while let Some(interface) = worklist.pop_front() {
+            if !seen.insert(interface.clone()) {
+                continue;
+            }
+
+            let Some(class) = self.classes.get(&interface) else { continue };
+            if let Some(method) = class.method(method_name, descriptor)
+                && method.is_virtual()
+                && !method.is_abstract()
+            {
+                return Some(method);
+            }
+
+            worklist.extend(class.interfaces.iter().cloned());
+        }
+
+        None
+    }
+
+    /// Get the possible concrete targets of a virtual call
+    ///
+    /// `class_name` is the class declared in the `invoke` instruction. With
+    /// [`DispatchResolution::Declared`] no target is resolved.
+    pub fn dispatch_targets(
+        &self,
+        class_name: &str,
+        method_name: &str,
+        descriptor: &str,
+        resolution: DispatchResolution
+    ) -> Vec<&HierarchyMethod> {
+        if resolution == DispatchResolution::Declared {
+            return Vec::new();
+        }
+
+        let declared = dex_file::to_descriptor(class_name);
+        let mut receivers = vec![declared.clone()];
+        receivers.extend(self.subtypes(&declared));
+
+        let mut targets: Vec<&HierarchyMethod> = Vec::new();
+
+        for receiver in receivers {
+            let Some(class) = self.classes.get(&receiver) else { continue };
+
+            if resolution == DispatchResolution::Rta && !class.is_instantiated {
+                continue;
+            }
+
+            if let Some(method) = self.resolve_method(&receiver, method_name, descriptor)
+                && !targets.iter().any(|target| target.full_name == method.full_name)
+            {
+                targets.push(method);
+            }
+        }
+
+        targets
+    }
+
+    /// Get the methods of the super types of a class that a method overrides
+    ///
+    /// Only the super types part of the hierarchy are searched. External
+    /// classes are only known through the methods the code references.
+    pub fn overridden_methods(&self, class_name: &str, method_name: &str, descriptor: &str) -> Vec<&HierarchyMethod> {
+        self.supertypes(class_name)
+            .iter()
+            .filter_map(|supertype| self.classes.get(supertype))
+            .filter_map(|class| class.method(method_name, descriptor))
+            .filter(|method| method.is_virtual())
+            .collect()
+    }
+
+    /// Get the methods of the subtypes of a class that override a method
+    pub fn overriding_methods(&self, class_name: &str, method_name: &str, descriptor: &str) -> Vec<&HierarchyMethod> {
+        self.subtypes(class_name)
+            .iter()
+            .filter_map(|subtype| self.classes.get(subtype))
+            .filter_map(|class| class.method(method_name, descriptor))
+            .filter(|method| method.is_virtual())
+            .collect()
+    }
+
+    /// Get the methods of a class with the same name but a different descriptor
+    pub fn overloads(&self, class_name: &str, method_name: &str, descriptor: &str) -> Vec<&HierarchyMethod> {
+        self.class(class_name)
+            .map(|class| class.methods
+                .iter()
+                .filter(|method| method.name == method_name && method.descriptor != descriptor)
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// Add the super types missing from the hierarchy
+    ///
+    /// `lookup` is called with the class name (`com.example.Foo`) of every
+    /// missing super class or interface, typically to fetch the analysis of an
+    /// external class.
+    pub fn add_missing_supertypes<F>(&mut self, lookup: F)
+    where
+        F: Fn(&str) -> Option<DvmClassAnalysis>
+    {
+        let mut queried = HashSet::new();
+
+        loop {
+            let missing = self.classes
+                .values()
+                .flat_map(|class| class.super_class.iter().chain(class.interfaces.iter()))
+                .filter(|parent| !self.classes.contains_key(*parent) && !queried.contains(*parent))
+                .cloned()
+                .collect::<HashSet<String>>();
+
+            if missing.is_empty() {
+                break;
+            }
+
+            for parent in missing {
+                if let Some(class) = lookup(&dex_file::to_class_name(&parent)) {
+                    let mut hierarchy_class = HierarchyClass::from_class_analysis(&class);
+                    hierarchy_class.name = parent.clone();
+                    self.add_class(hierarchy_class);
+                }
+                queried.insert(parent);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn method(class: &str, name: &str, flags: &[DvmAccessFlag]) -> HierarchyMethod {
+        HierarchyMethod::new(name, "()V", &format!("{class}->{name}()V"), flags)
+    }
+
+    /// `Listener` is implemented by `Base`, `Sub` overrides `onClick`, and
+    /// `Other` inherits it from `Base`. Only `Sub` is instantiated.
+    fn hierarchy() -> ClassHierarchy {
+        let mut hierarchy = ClassHierarchy::new();
+
+        let mut listener = HierarchyClass::new("LListener;", Some("Ljava/lang/Object;"), &[], false);
+        listener.add_method(method("LListener;", "onClick", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_ABSTRACT]));
+        hierarchy.add_class(listener);
+
+        let mut base = HierarchyClass::new("LBase;", Some("Ljava/lang/Object;"), &[String::from("LListener;")], false);
+        base.add_method(method("LBase;", "onClick", &[DvmAccessFlag::ACC_PUBLIC]));
+        base.add_method(method("LBase;", "<init>", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_CONSTRUCTOR]));
+        hierarchy.add_class(base);
+
+        let mut sub = HierarchyClass::new("Sub", Some("Base"), &[], false);
+        sub.add_method(method("LSub;", "onClick", &[DvmAccessFlag::ACC_PUBLIC]));
+        sub.set_instantiated(true);
+        hierarchy.add_class(sub);
+
+        hierarchy.add_class(HierarchyClass::new("LOther;", Some("LBase;"), &[], false));
+
+        hierarchy
+    }
+
+    fn names(targets: Vec<&HierarchyMethod>) -> Vec<&str> {
+        let mut names = targets.iter().map(|target| target.full_name()).collect::<Vec<&str>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_supertypes_and_subtypes() {
+        let hierarchy = hierarchy();
+
+        assert_eq!(hierarchy.superclasses("Sub"), vec!["LBase;", "Ljava/lang/Object;"]);
+        assert!(hierarchy.is_subtype_of("LOther;", "LListener;"));
+        assert!(!hierarchy.is_subtype_of("LListener;", "LSub;"));
+
+        let mut subtypes = hierarchy.subtypes("LListener;");
+        subtypes.sort();
+        assert_eq!(subtypes, vec!["LBase;", "LOther;", "LSub;"]);
+    }
+
+    #[test]
+    fn test_overrides_and_overloads() {
+        let mut hierarchy = hierarchy();
+
+        let mut other = HierarchyClass::new("LOther;", Some("LBase;"), &[], false);
+        other.add_method(HierarchyMethod::new("onClick", "(I)V", "LOther;->onClick(I)V", &[DvmAccessFlag::ACC_PUBLIC]));
+        other.add_method(method("LOther;", "onClick", &[DvmAccessFlag::ACC_PUBLIC]));
+        hierarchy.add_class(other);
+
+        assert_eq!(
+            names(hierarchy.overridden_methods("LSub;", "onClick", "()V")),
+            vec!["LBase;->onClick()V", "LListener;->onClick()V"]
+        );
+        assert_eq!(
+            names(hierarchy.overriding_methods("LBase;", "onClick", "()V")),
+            vec!["LOther;->onClick()V", "LSub;->onClick()V"]
+        );
+        assert!(hierarchy.overriding_methods("LBase;", "<init>", "()V").is_empty());
+        assert_eq!(names(hierarchy.overloads("LOther;", "onClick", "()V")), vec!["LOther;->onClick(I)V"]);
+    }
+
+    #[test]
+    fn test_dispatch_targets() {
+        let hierarchy = hierarchy();
+
+        assert_eq!(hierarchy.resolve_method("LOther;", "onClick", "()V").unwrap().full_name(), "LBase;->onClick()V");
+        assert!(hierarchy.resolve_method("LListener;", "onClick", "()V").is_none());
+        assert!(hierarchy.resolve_method("LBase;", "<init>", "()V").is_none());
+
+        assert!(hierarchy.dispatch_targets("LListener;", "onClick", "()V", DispatchResolution::Declared).is_empty());
+        assert_eq!(
+            names(hierarchy.dispatch_targets("LListener;", "onClick", "()V", DispatchResolution::Cha)),
+            vec!["LBase;->onClick()V", "LSub;->onClick()V"]
+        );
+        assert_eq!(
+            names(hierarchy.dispatch_targets("Listener", "onClick", "()V", DispatchResolution::Rta)),
+            vec!["LSub;->onClick()V"]
+        );
+    }
+
+    #[test]
+    fn test_default_methods() {
+        let mut hierarchy = hierarchy();
+
+        let mut named = HierarchyClass::new("LNamed;", Some("Ljava/lang/Object;"), &[], false);
+        named.add_method(method("LNamed;", "describe", &[DvmAccessFlag::ACC_PUBLIC]));
+        named.add_method(method("LNamed;", "name", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_ABSTRACT]));
+        hierarchy.add_class(named);
+        hierarchy.add_class(HierarchyClass::new("LTagged;", Some("Ljava/lang/Object;"), &[String::from("LNamed;")], false));
+
+        let mut item = HierarchyClass::new("LItem;", Some("LBase;"), &[String::from("LTagged;")], false);
+        item.add_method(method("LItem;", "name", &[DvmAccessFlag::ACC_PUBLIC]));
+        hierarchy.add_class(item);
+
+        assert_eq!(hierarchy.resolve_method("LItem;", "describe", "()V").unwrap().full_name(), "LNamed;->describe()V");
+        assert_eq!(hierarchy.resolve_method("LItem;", "name", "()V").unwrap().full_name(), "LItem;->name()V");
+        assert_eq!(hierarchy.resolve_method("LItem;", "onClick", "()V").unwrap().full_name(), "LBase;->onClick()V");
+        assert!(hierarchy.resolve_method("LTagged;", "name", "()V").is_none());
+        assert!(hierarchy.resolve_method("LSub;", "describe", "()V").is_none());
+    }
+}
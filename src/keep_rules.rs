@@ -0,0 +1,861 @@
+//! ProGuard keep rules
+//!
+//! Parse the `-keep` options of a ProGuard or R8 configuration and match them
+//! against the classes, methods and fields of a [`ClassHierarchy`]. Other
+//! options are ignored. See the ProGuard [`manual`] for the syntax.
+//!
+//! Annotation conditions cannot be checked and are considered satisfied.
+//! A keep option preceded by `-if` only applies if some class of the
+//! hierarchy matches the condition; back references like `<1>` are not bound
+//! to the condition and match any name.
+//!
+//! # Example
+//!
+//! ```
+//! use shuriken_bindings::keep_rules::{ KeepRule, KeepRuleKind };
+//!
+//! let rules = KeepRule::parse_rules("
+//!     # Reflection targets
+//!     -keep class com.example.plugins.** { public <init>(); }
+//!     -keepnames class com.example.Model
+//! ");
+//!
+//! assert_eq!(rules.len(), 2);
+//! assert_eq!(rules[0].kind(), KeepRuleKind::Keep);
+//! assert!(rules[0].prevents_shrinking());
+//! assert!(!rules[1].prevents_shrinking());
+//! ```
+//!
+//! [`manual`]: https://www.guardsquare.com/manual/configuration/usage#keepoptions
+
+use crate::dex_file;
+use crate::hierarchy::{ ClassHierarchy, HierarchyClass, HierarchyField, HierarchyMethod };
+use crate::dvm_access_flags::DvmAccessFlag;
+
+/// Kind of keep option
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeepRuleKind {
+    /// `-keep`: keep the classes and the members
+    Keep,
+    /// `-keepclassmembers`: keep the members only
+    KeepClassMembers,
+    /// `-keepclasseswithmembers`: keep the classes and the members, if all the members are present
+    KeepClassesWithMembers,
+    /// `-keepnames`: only prevent the classes and members from being renamed
+    KeepNames,
+    /// `-keepclassmembernames`: only prevent the members from being renamed
+    KeepClassMemberNames,
+    /// `-keepclasseswithmembernames`: only prevent the classes and members from being renamed
+    KeepClassesWithMemberNames
+}
+
+impl KeepRuleKind {
+    fn from_option(option: &str) -> Option<Self> {
+        match option {
+            "-keep" => Some(KeepRuleKind::Keep),
+            "-keepclassmembers" => Some(KeepRuleKind::KeepClassMembers),
+            "-keepclasseswithmembers" => Some(KeepRuleKind::KeepClassesWithMembers),
+            "-keepnames" => Some(KeepRuleKind::KeepNames),
+            "-keepclassmembernames" => Some(KeepRuleKind::KeepClassMemberNames),
+            "-keepclasseswithmembernames" => Some(KeepRuleKind::KeepClassesWithMemberNames),
+            _ => None
+        }
+    }
+}
+
+/// Modifier of a class or member specification, possibly negated
+#[derive(Debug, Clone, PartialEq)]
+struct Modifier {
+    flag: DvmAccessFlag,
+    negated: bool
+}
+
+impl Modifier {
+    fn parse(token: &str, negated: bool) -> Option<Self> {
+        let flag = match token {
+            "public" => DvmAccessFlag::ACC_PUBLIC,
+            "private" => DvmAccessFlag::ACC_PRIVATE,
+            "protected" => DvmAccessFlag::ACC_PROTECTED,
+            "static" => DvmAccessFlag::ACC_STATIC,
+            "final" => DvmAccessFlag::ACC_FINAL,
+            "synchronized" => DvmAccessFlag::ACC_SYNCHRONIZED,
+            "volatile" => DvmAccessFlag::ACC_VOLATILE,
+            "transient" => DvmAccessFlag::ACC_TRANSIENT,
+            "bridge" => DvmAccessFlag::ACC_BRIDGE,
+            "varargs" => DvmAccessFlag::ACC_VARARGS,
+            "native" => DvmAccessFlag::ACC_NATIVE,
+            "abstract" => DvmAccessFlag::ACC_ABSTRACT,
+            "strictfp" => DvmAccessFlag::ACC_STRICT,
+            "synthetic" => DvmAccessFlag::ACC_SYNTHETIC,
+            _ => return None
+        };
+
+        Some(Self { flag, negated })
+    }
+
+    fn matches(&self, access_flags: &[DvmAccessFlag]) -> bool {
+        access_flags.contains(&self.flag) != self.negated
+    }
+}
+
+/// Member specification between the braces of a keep option
+#[derive(Debug, Clone, PartialEq)]
+enum MemberSpec {
+    /// `*`: any field or method
+    Any {
+        modifiers: Vec<Modifier>
+    },
+    /// `<methods>` or an explicit method
+    Method {
+        modifiers: Vec<Modifier>,
+        /// Return type pattern, `None` for constructors
+        return_type: Option<String>,
+        /// Name pattern
+        name: String,
+        /// Parameter type patterns, `None` for any parameters
+        parameters: Option<Vec<String>>
+    },
+    /// `<fields>` or an explicit field
+    Field {
+        modifiers: Vec<Modifier>,
+        /// Type pattern, `None` for any type
+        field_type: Option<String>,
+        /// Name pattern
+        name: String
+    }
+}
+
+impl MemberSpec {
+    fn matches_method(&self, method: &HierarchyMethod) -> bool {
+        let (modifiers, return_type, name, parameters) = match self {
+            MemberSpec::Any { modifiers } => (modifiers, &None, "<methods>", &None),
+            MemberSpec::Method { modifiers, return_type, name, parameters } => (modifiers, return_type, name.as_str(), parameters),
+            MemberSpec::Field { .. } => return false
+        };
+
+        if !modifiers.iter().all(|modifier| modifier.matches(method.access_flags())) {
+            return false;
+        }
+
+        let Some((parameter_types, method_return_type)) = dex_file::split_method_descriptor(method.descriptor()) else {
+            return false;
+        };
+
+        if let Some(return_type) = return_type
+            && !type_matches(return_type, &method_return_type)
+        {
+            return false;
+        }
+
+        if name == "<init>" {
+            if method.name() != "<init>" {
+                return false;
+            }
+        } else if name != "<methods>" && (method.name().starts_with('<') || !wildcard_matches(name, method.name(), false)) {
+            return false;
+        }
+
+        match parameters {
+            None => true,
+            Some(patterns) => parameters_match(patterns, &parameter_types)
+        }
+    }
+
+    fn matches_field(&self, field: &HierarchyField) -> bool {
+        let (modifiers, field_type, name) = match self {
+            MemberSpec::Any { modifiers } => (modifiers, &None, "<fields>"),
+            MemberSpec::Field { modifiers, field_type, name } => (modifiers, field_type, name.as_str()),
+            MemberSpec::Method { .. } => return false
+        };
+
+        modifiers.iter().all(|modifier| modifier.matches(field.access_flags()))
+            && field_type.as_ref().is_none_or(|field_type| type_matches(field_type, field.descriptor()))
+            && (name == "<fields>" || wildcard_matches(name, field.name(), false))
+    }
+
+    fn matches_any(&self, class: &HierarchyClass) -> bool {
+        class.methods().iter().any(|method| self.matches_method(method))
+            || class.fields().iter().any(|field| self.matches_field(field))
+    }
+}
+
+/// Class specification of a keep option or of its `-if` condition
+#[derive(Debug, Clone, PartialEq)]
+struct ClassSpec {
+    /// Modifiers of the class specification
+    class_modifiers: Vec<Modifier>,
+    /// Class name patterns, possibly negated
+    class_names: Vec<(String, bool)>,
+    /// `extends` or `implements` pattern
+    extends: Option<String>,
+    /// Member specifications
+    members: Vec<MemberSpec>
+}
+
+impl ClassSpec {
+    fn matches_class(&self, hierarchy: &ClassHierarchy, class: &HierarchyClass) -> bool {
+        let class_name = dex_file::to_class_name(class.name());
+
+        // All the negated patterns must fail and one of the others must match
+        let mut matched = false;
+        for (pattern, negated) in &self.class_names {
+            // A single `*` matches any class, in any package
+            let is_match = pattern == "*" || wildcard_matches(pattern, &class_name, true);
+            if *negated && is_match {
+                return false;
+            }
+            matched |= !negated && is_match;
+        }
+
+        if !matched && self.class_names.iter().any(|(_, negated)| !negated) {
+            return false;
+        }
+
+        if let Some(extends) = &self.extends {
+            let has_parent = hierarchy.supertypes(class.name())
+                .iter()
+                .any(|parent| wildcard_matches(extends, &dex_file::to_class_name(parent), true));
+
+            if !has_parent {
+                return false;
+            }
+        }
+
+        // Class access flags are not available in the hierarchy, only check
+        // the modifiers we can infer
+        self.class_modifiers.iter().all(|modifier| match modifier.flag {
+            DvmAccessFlag::ACC_ABSTRACT => {
+                let is_abstract = class.methods().iter().any(|method| method.is_abstract());
+                is_abstract != modifier.negated
+            },
+            _ => true
+        })
+    }
+
+    /// Check if every member specification matches at least one member of the class
+    fn has_all_members(&self, class: &HierarchyClass) -> bool {
+        self.members.iter().all(|member| member.matches_any(class))
+    }
+}
+
+/// Keep option of a ProGuard configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeepRule {
+    /// Kind of option
+    kind: KeepRuleKind,
+    /// `allowshrinking` modifier of the option
+    allow_shrinking: bool,
+    /// Class specification of the preceding `-if` option
+    condition: Option<ClassSpec>,
+    /// Class specification of the option
+    spec: ClassSpec
+}
+
+impl KeepRule {
+    /// Parse a single keep option, possibly preceded by an `-if` option
+    ///
+    /// Returns `None` if the text is not a keep option or cannot be parsed.
+    pub fn parse(rule: &str) -> Option<Self> {
+        let tokens = tokenize(rule);
+        let mut parser = RuleParser { tokens: &tokens, pos: 0 };
+
+        let rule = parser.parse_rule()?;
+        parser.at_end().then_some(rule)
+    }
+
+    /// Parse every keep option of a configuration
+    ///
+    /// Other options and the keep options that cannot be parsed are skipped.
+    pub fn parse_rules(config: &str) -> Vec<Self> {
+        let tokens = tokenize(config);
+
+        // Split the configuration on options, an `-if` option belonging to
+        // the next one
+        let mut starts = Vec::new();
+        let mut after_if = false;
+        for (idx, token) in tokens.iter().enumerate() {
+            if !token.starts_with('-') || token.len() == 1 {
+                continue;
+            }
+            if !after_if {
+                starts.push(idx);
+            }
+            after_if = token == "-if";
+        }
+        starts.push(tokens.len());
+
+        starts.windows(2)
+            .filter_map(|window| {
+                let mut parser = RuleParser { tokens: &tokens[window[0]..window[1]], pos: 0 };
+                let rule = parser.parse_rule()?;
+                parser.at_end().then_some(rule)
+            })
+            .collect()
+    }
+
+    /// Return the kind of option
+    pub fn kind(&self) -> KeepRuleKind {
+        self.kind
+    }
+
+    /// Whether the option is preceded by an `-if` condition
+    pub fn is_conditional(&self) -> bool {
+        self.condition.is_some()
+    }
+
+    /// Whether the rule prevents the matched classes and members from being removed
+    ///
+    /// The `*names` options and the `allowshrinking` modifier only prevent renaming.
+    pub fn prevents_shrinking(&self) -> bool {
+        !self.allow_shrinking && matches!(self.kind,
+            KeepRuleKind::Keep | KeepRuleKind::KeepClassMembers | KeepRuleKind::KeepClassesWithMembers
+        )
+    }
+
+    /// Check if the class specification of the rule matches a class
+    pub fn matches_class(&self, hierarchy: &ClassHierarchy, class: &HierarchyClass) -> bool {
+        self.spec.matches_class(hierarchy, class)
+    }
+
+    /// Check if the `-if` condition of the rule holds, which is the case if
+    /// an internal class matches it with all its members
+    pub fn condition_holds(&self, hierarchy: &ClassHierarchy) -> bool {
+        let Some(condition) = &self.condition else {
+            return true;
+        };
+
+        hierarchy.classes()
+            .filter(|class| !class.is_external())
+            .any(|class| condition.matches_class(hierarchy, class) && condition.has_all_members(class))
+    }
+
+    /// Check if the rule keeps a class
+    ///
+    /// `-keepclassmembers` options never keep classes, and
+    /// `-keepclasseswithmembers` options only keep classes having all the
+    /// specified members.
+    pub fn keeps_class(&self, hierarchy: &ClassHierarchy, class: &HierarchyClass) -> bool {
+        if !self.applies_to(hierarchy, class) {
+            return false;
+        }
+
+        match self.kind {
+            KeepRuleKind::KeepClassMembers => false,
+            KeepRuleKind::KeepClassesWithMembers => self.spec.has_all_members(class),
+            _ => true
+        }
+    }
+
+    /// Check if the rule keeps a method of a class
+    pub fn keeps_method(&self, hierarchy: &ClassHierarchy, class: &HierarchyClass, method: &HierarchyMethod) -> bool {
+        self.keeps_members_of(hierarchy, class)
+            && self.spec.members.iter().any(|member| member.matches_method(method))
+    }
+
+    /// Check if the rule keeps a field of a class
+    ///
+    /// Fields are only known for the classes read from a DEX file.
+    pub fn keeps_field(&self, hierarchy: &ClassHierarchy, class: &HierarchyClass, field: &HierarchyField) -> bool {
+        self.keeps_members_of(hierarchy, class)
+            && self.spec.members.iter().any(|member| member.matches_field(field))
+    }
+
+    /// Check if the rule prevents shrinking, its condition holds and it
+    /// matches the class
+    fn applies_to(&self, hierarchy: &ClassHierarchy, class: &HierarchyClass) -> bool {
+        self.prevents_shrinking()
+            && self.matches_class(hierarchy, class)
+            && self.condition_holds(hierarchy)
+    }
+
+    fn keeps_members_of(&self, hierarchy: &ClassHierarchy, class: &HierarchyClass) -> bool {
+        self.applies_to(hierarchy, class)
+            && (self.kind != KeepRuleKind::KeepClassesWithMembers || self.spec.has_all_members(class))
+    }
+}
+
+/// Split a configuration into tokens, removing the comments
+fn tokenize(config: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+
+    for line in config.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut current = String::new();
+
+        for c in line.chars() {
+            match c {
+                c if c.is_whitespace() => {
+                    if !current.is_empty() {
+                        tokens.push(std::mem::take(&mut current));
+                    }
+                },
+                '{' | '}' | ';' | '(' | ')' | ',' | '!' => {
+                    if !current.is_empty() {
+                        tokens.push(std::mem::take(&mut current));
+                    }
+                    tokens.push(c.to_string());
+                },
+                _ => current.push(c)
+            }
+        }
+
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+    }
+
+    tokens
+}
+
+/// Recursive descent parser over the tokens of a single option
+struct RuleParser<'a> {
+    tokens: &'a [String],
+    pos: usize
+}
+
+impl RuleParser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(|token| token.as_str())
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        Some(token.as_str())
+    }
+
+    fn eat(&mut self, expected: &str) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn parse_rule(&mut self) -> Option<KeepRule> {
+        let mut option = self.next()?;
+
+        let condition = if option == "-if" {
+            let condition = self.parse_class_spec()?;
+            option = self.next()?;
+            Some(condition)
+        } else {
+            None
+        };
+        let kind = KeepRuleKind::from_option(option)?;
+
+        let mut allow_shrinking = false;
+        while self.eat(",") {
+            allow_shrinking |= self.next()? == "allowshrinking";
+        }
+
+        Some(KeepRule {
+            kind,
+            allow_shrinking,
+            condition,
+            spec: self.parse_class_spec()?
+        })
+    }
+
+    fn parse_class_spec(&mut self) -> Option<ClassSpec> {
+        let class_modifiers = self.parse_modifiers();
+
+        match self.next()? {
+            "class" | "interface" | "enum" | "@interface" => { },
+            _ => return None
+        }
+
+        let mut class_names = Vec::new();
+        loop {
+            let negated = self.eat("!");
+            class_names.push((self.next()?.to_string(), negated));
+
+            if !self.eat(",") {
+                break;
+            }
+        }
+
+        let extends = match self.peek() {
+            Some("extends") | Some("implements") => {
+                self.pos += 1;
+                self.skip_annotation();
+                Some(self.next()?.to_string())
+            },
+            _ => None
+        };
+
+        let mut members = Vec::new();
+        if self.eat("{") {
+            while !self.eat("}") {
+                members.push(self.parse_member()?);
+            }
+        }
+
+        Some(ClassSpec {
+            class_modifiers,
+            class_names,
+            extends,
+            members
+        })
+    }
+
+    fn skip_annotation(&mut self) {
+        while self.peek().is_some_and(|token| token.starts_with('@') && token != "@interface") {
+            self.pos += 1;
+        }
+    }
+
+    fn parse_modifiers(&mut self) -> Vec<Modifier> {
+        let mut modifiers = Vec::new();
+
+        loop {
+            self.skip_annotation();
+
+            let start = self.pos;
+            let negated = self.eat("!");
+            match self.peek().and_then(|token| Modifier::parse(token, negated)) {
+                Some(modifier) => {
+                    modifiers.push(modifier);
+                    self.pos += 1;
+                },
+                None => {
+                    self.pos = start;
+                    return modifiers;
+                }
+            }
+        }
+    }
+
+    fn parse_member(&mut self) -> Option<MemberSpec> {
+        let modifiers = self.parse_modifiers();
+        let first = self.next()?.to_string();
+
+        let member = match first.as_str() {
+            "*" => MemberSpec::Any { modifiers },
+            "<fields>" => MemberSpec::Field {
+                modifiers,
+                field_type: None,
+                name: first
+            },
+            "<methods>" => MemberSpec::Method {
+                modifiers,
+                return_type: None,
+                name: String::from("<methods>"),
+                parameters: None
+            },
+            "<init>" => MemberSpec::Method {
+                modifiers,
+                return_type: None,
+                name: first,
+                parameters: self.parse_parameters()?
+            },
+            _ => {
+                let name = self.next()?.to_string();
+                match self.parse_parameters()? {
+                    None => MemberSpec::Field {
+                        modifiers,
+                        field_type: Some(first),
+                        name
+                    },
+                    Some(parameters) => MemberSpec::Method {
+                        modifiers,
+                        return_type: Some(first),
+                        name,
+                        parameters: Some(parameters)
+                    }
+                }
+            }
+        };
+
+        self.eat(";").then_some(member)
+    }
+
+    /// Parse an optional parameter list
+    ///
+    /// Returns `Some(None)` when there is no parameter list at all, and a
+    /// list containing `...` for any parameters.
+    fn parse_parameters(&mut self) -> Option<Option<Vec<String>>> {
+        if !self.eat("(") {
+            return Some(None);
+        }
+
+        let mut parameters = Vec::new();
+        while !self.eat(")") {
+            parameters.push(self.next()?.to_string());
+            self.eat(",");
+        }
+
+        Some(Some(parameters))
+    }
+}
+
+/// Match a ProGuard name pattern
+///
+/// `?` matches one character and `*` any part of a name, except package
+/// separators when `class_name` is set. `**` and the back references to an
+/// `-if` condition like `<1>` match anything.
+fn wildcard_matches(pattern: &str, text: &str, class_name: bool) -> bool {
+    let pattern = pattern.as_bytes();
+    let text = text.as_bytes();
+
+    fn matches(pattern: &[u8], text: &[u8], class_name: bool) -> bool {
+        match pattern {
+            [] => text.is_empty(),
+            [b'*', b'*', rest @ ..] => {
+                (0..=text.len()).any(|idx| matches(rest, &text[idx..], class_name))
+            },
+            [b'<', rest @ ..] if back_reference_len(rest) > 0 => {
+                let rest = &rest[back_reference_len(rest)..];
+                (0..=text.len()).any(|idx| matches(rest, &text[idx..], class_name))
+            },
+            [b'*', rest @ ..] => {
+                for idx in 0..=text.len() {
+                    if matches(rest, &text[idx..], class_name) {
+                        return true;
+                    }
+                    if idx < text.len() && class_name && text[idx] == b'.' {
+                        break;
+                    }
+                }
+                false
+            },
+            [b'?', rest @ ..] => {
+                match text.first() {
+                    Some(b'.') if class_name => false,
+                    Some(_) => matches(rest, &text[1..], class_name),
+                    None => false
+                }
+            },
+            [c, rest @ ..] => !text.is_empty() && text[0] == *c && matches(rest, &text[1..], class_name)
+        }
+    }
+
+    /// Length of the digits and closing `>` of a back reference, 0 if there is none
+    fn back_reference_len(pattern: &[u8]) -> usize {
+        let digits = pattern.iter().take_while(|c| c.is_ascii_digit()).count();
+        match pattern.get(digits) {
+            Some(b'>') if digits > 0 => digits + 1,
+            _ => 0
+        }
+    }
+
+    matches(pattern, text, class_name)
+}
+
+/// Match a type pattern against a type descriptor
+fn type_matches(pattern: &str, descriptor: &str) -> bool {
+    let type_name = dex_file::to_class_name(descriptor);
+    let is_primitive = !descriptor.starts_with('L') && !descriptor.starts_with('[');
+
+    match pattern {
+        "***" => true,
+        "%" => is_primitive && descriptor != "V",
+        "**" | "*" if is_primitive || descriptor.starts_with('[') => false,
+        _ => wildcard_matches(pattern, &type_name, true)
+    }
+}
+
+/// Match parameter patterns, `...` standing for any number of parameters
+fn parameters_match(patterns: &[String], descriptors: &[String]) -> bool {
+    match patterns.split_first() {
+        None => descriptors.is_empty(),
+        Some((first, rest)) if first == "..." => {
+            (0..=descriptors.len()).any(|idx| parameters_match(rest, &descriptors[idx..]))
+        },
+        Some((first, rest)) => {
+            !descriptors.is_empty()
+                && type_matches(first, &descriptors[0])
+                && parameters_match(rest, &descriptors[1..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(name: &str, super_class: &str, methods: &[(&str, &str, &[DvmAccessFlag])]) -> HierarchyClass {
+        let mut class = HierarchyClass::new(name, Some(super_class), &[], false);
+
+        for (method_name, descriptor, flags) in methods {
+            class.add_method(HierarchyMethod::new(
+                method_name,
+                descriptor,
+                &format!("{name}->{method_name}{descriptor}"),
+                flags
+            ));
+        }
+
+        class
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(wildcard_matches("com.example.*", "com.example.Foo", true));
+        assert!(!wildcard_matches("com.example.*", "com.example.sub.Foo", true));
+        assert!(wildcard_matches("com.example.**", "com.example.sub.Foo", true));
+        assert!(wildcard_matches("com.example.Fo?", "com.example.Foo", true));
+        assert!(wildcard_matches("get*", "getName", false));
+
+        assert!(type_matches("%", "I"));
+        assert!(!type_matches("%", "Ljava/lang/String;"));
+        assert!(type_matches("java.lang.String[]", "[Ljava/lang/String;"));
+        assert!(type_matches("***", "[I"));
+        assert!(!type_matches("**", "[I"));
+
+        let descriptors = [String::from("I"), String::from("Ljava/lang/String;")];
+        assert!(parameters_match(&[String::from("...")], &descriptors));
+        assert!(parameters_match(&[String::from("int"), String::from("...")], &descriptors));
+        assert!(!parameters_match(&[String::from("int")], &descriptors));
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = KeepRule::parse_rules("
+            -dontobfuscate
+            -keep,allowobfuscation class com.example.** extends android.app.Activity {
+                public <init>();
+                public void on*(android.view.View);
+            }
+            -keepclassmembernames class * { native <methods>; }
+            -keepclasseswithmembers class * { public static void main(java.lang.String[]); }
+            -keep,allowshrinking class com.example.Model
+        ");
+
+        assert_eq!(rules.len(), 4);
+        assert_eq!(rules[0].kind(), KeepRuleKind::Keep);
+        assert_eq!(rules[0].spec.members.len(), 2);
+        assert_eq!(rules[0].spec.extends.as_deref(), Some("android.app.Activity"));
+        assert!(rules[0].prevents_shrinking());
+        assert!(!rules[1].prevents_shrinking());
+        assert_eq!(rules[2].kind(), KeepRuleKind::KeepClassesWithMembers);
+        assert!(!rules[3].prevents_shrinking());
+
+        assert!(KeepRule::parse("-keep class com.example.Foo { *; }").is_some());
+        assert!(KeepRule::parse("-dontwarn com.example.**").is_none());
+        assert!(KeepRule::parse("-keep class com.example.Foo {").is_none());
+    }
+
+    #[test]
+    fn test_keep_methods() {
+        let mut hierarchy = ClassHierarchy::new();
+        hierarchy.add_class(HierarchyClass::new("Landroid/app/Activity;", None, &[], true));
+
+        let activity = class("Lcom/example/ui/Main;", "Landroid/app/Activity;", &[
+            ("<init>", "()V", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_CONSTRUCTOR]),
+            ("onTap", "(Landroid/view/View;)V", &[DvmAccessFlag::ACC_PUBLIC]),
+            ("onTap", "(I)V", &[DvmAccessFlag::ACC_PUBLIC]),
+            ("helper", "()V", &[DvmAccessFlag::ACC_PRIVATE]),
+        ]);
+        hierarchy.add_class(activity.clone());
+
+        let main = class("Lcom/example/Main;", "Ljava/lang/Object;", &[
+            ("main", "([Ljava/lang/String;)V", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_STATIC]),
+        ]);
+        hierarchy.add_class(main.clone());
+
+        let rule = KeepRule::parse("
+            -keep class com.example.** extends android.app.Activity {
+                public <init>();
+                public void on*(android.view.View);
+            }
+        ").unwrap();
+
+        assert!(rule.keeps_class(&hierarchy, &activity));
+        assert!(!rule.keeps_class(&hierarchy, &main));
+
+        let kept = activity.methods()
+            .iter()
+            .filter(|method| rule.keeps_method(&hierarchy, &activity, method))
+            .map(|method| method.full_name())
+            .collect::<Vec<&str>>();
+        assert_eq!(kept, vec![
+            "Lcom/example/ui/Main;-><init>()V",
+            "Lcom/example/ui/Main;->onTap(Landroid/view/View;)V"
+        ]);
+
+        let rule = KeepRule::parse(
+            "-keepclasseswithmembers class * { public static void main(java.lang.String[]); }"
+        ).unwrap();
+        assert!(rule.keeps_class(&hierarchy, &main));
+        assert!(rule.keeps_method(&hierarchy, &main, &main.methods()[0]));
+        assert!(!rule.keeps_class(&hierarchy, &activity));
+
+        let rule = KeepRule::parse("-keepclassmembers class !com.example.ui.*, com.example.* { *; }").unwrap();
+        assert!(!rule.keeps_class(&hierarchy, &main));
+        assert!(rule.keeps_method(&hierarchy, &main, &main.methods()[0]));
+        assert!(!rule.keeps_method(&hierarchy, &activity, &activity.methods()[1]));
+    }
+
+    #[test]
+    fn test_keep_fields() {
+        let mut hierarchy = ClassHierarchy::new();
+
+        let mut model = class("Lcom/example/Model;", "Ljava/lang/Object;", &[
+            ("<init>", "()V", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_CONSTRUCTOR]),
+        ]);
+        model.add_field(HierarchyField::new("id", "J", &[DvmAccessFlag::ACC_PRIVATE]));
+        model.add_field(HierarchyField::new("name", "Ljava/lang/String;", &[DvmAccessFlag::ACC_PUBLIC]));
+        model.add_field(HierarchyField::new("TAG", "Ljava/lang/String;", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_STATIC]));
+        hierarchy.add_class(model.clone());
+
+        let kept = |rule: &KeepRule| model.fields()
+            .iter()
+            .filter(|field| rule.keeps_field(&hierarchy, &model, field))
+            .map(|field| field.name())
+            .collect::<Vec<&str>>();
+
+        let rule = KeepRule::parse("-keepclassmembers class com.example.Model { <fields>; }").unwrap();
+        assert_eq!(kept(&rule), vec!["id", "name", "TAG"]);
+        assert!(!rule.keeps_method(&hierarchy, &model, &model.methods()[0]));
+
+        let rule = KeepRule::parse("-keep class com.example.Model { !static java.lang.String *; long id; }").unwrap();
+        assert_eq!(kept(&rule), vec!["id", "name"]);
+
+        let rule = KeepRule::parse("-keep class com.example.Model { *; }").unwrap();
+        assert_eq!(kept(&rule).len(), 3);
+        assert!(rule.keeps_method(&hierarchy, &model, &model.methods()[0]));
+
+        let rule = KeepRule::parse("-keepclasseswithmembers class * { int id; }").unwrap();
+        assert!(!rule.keeps_class(&hierarchy, &model));
+        let rule = KeepRule::parse("-keepclasseswithmembers class * { long id; }").unwrap();
+        assert!(rule.keeps_class(&hierarchy, &model));
+    }
+
+    #[test]
+    fn test_conditional_rules() {
+        let mut hierarchy = ClassHierarchy::new();
+
+        let adapter = class("Lcom/example/Model$$Adapter;", "Ljava/lang/Object;", &[]);
+        hierarchy.add_class(adapter.clone());
+        let model = class("Lcom/example/Model;", "Ljava/lang/Object;", &[
+            ("<init>", "()V", &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_CONSTRUCTOR]),
+        ]);
+        hierarchy.add_class(model.clone());
+
+        let rules = KeepRule::parse_rules("
+            -if class **$$Adapter
+            -keep class <1> { <init>(); }
+            -if class **$$Missing
+            -keep class com.example.Model
+            -keepnames class com.example.Model
+        ");
+        assert_eq!(rules.len(), 3);
+        assert!(rules[0].is_conditional());
+        assert!(!rules[2].is_conditional());
+
+        assert!(rules[0].condition_holds(&hierarchy));
+        assert!(rules[0].keeps_class(&hierarchy, &model));
+        assert!(rules[0].keeps_method(&hierarchy, &model, &model.methods()[0]));
+
+        assert!(!rules[1].condition_holds(&hierarchy));
+        assert!(!rules[1].keeps_class(&hierarchy, &model));
+
+        assert!(KeepRule::parse("-if class com.example.Model").is_none());
+    }
+}
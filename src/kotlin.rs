@@ -0,0 +1,1377 @@
+//! Kotlin metadata
+//!
+//! kotlinc keeps the declarations of the source code in the `kotlin.Metadata`
+//! runtime annotation of every class it compiles: `d1` holds a protobuf
+//! message, encoded into strings, and `d2` the strings it refers to. This
+//! module decodes the parts of it that the DEX view loses: the kind of class,
+//! the properties, the nullability of the types, the suspend functions and
+//! the Kotlin names of the functions.
+//!
+//! See [`metadata.proto`] and [`jvm_metadata.proto`] for the messages.
+//!
+//! [`metadata.proto`]: https://github.com/JetBrains/kotlin/blob/master/core/metadata/src/metadata.proto
+//! [`jvm_metadata.proto`]: https://github.com/JetBrains/kotlin/blob/master/core/metadata.jvm/src/jvm_metadata.proto
+
+use std::cell::Cell;
+use std::fmt;
+
+use crate::annotations::{ self, Annotation };
+use crate::encoded_value::EncodedValue;
+use crate::protobuf::{ self, ProtoFields, ProtoValue };
+
+/// Type of the Kotlin metadata annotation
+pub(crate) const METADATA: &str = "Lkotlin/Metadata;";
+
+/// Maximum nesting of types, deeper types are rejected
+const MAX_TYPE_DEPTH: usize = 64;
+
+/// Maximum number of types read in a scope, which bounds type tables whose
+/// entries refer to the same types many times
+const MAX_TYPES_READ: usize = 4096;
+
+/// Strings of the string table that are not stored in `d2`
+const PREDEFINED_STRINGS: [&str; 44] = [
+    "kotlin/Any", "kotlin/Nothing", "kotlin/Unit", "kotlin/Throwable", "kotlin/Number",
+    "kotlin/Byte", "kotlin/Double", "kotlin/Float", "kotlin/Int", "kotlin/Long",
+    "kotlin/Short", "kotlin/Boolean", "kotlin/Char", "kotlin/CharSequence", "kotlin/String",
+    "kotlin/Comparable", "kotlin/Enum", "kotlin/Array", "kotlin/ByteArray", "kotlin/DoubleArray",
+    "kotlin/FloatArray", "kotlin/IntArray", "kotlin/LongArray", "kotlin/ShortArray", "kotlin/BooleanArray",
+    "kotlin/CharArray", "kotlin/Cloneable", "kotlin/Annotation", "kotlin/collections/Iterable",
+    "kotlin/collections/MutableIterable", "kotlin/collections/Collection", "kotlin/collections/MutableCollection",
+    "kotlin/collections/List", "kotlin/collections/MutableList", "kotlin/collections/Set",
+    "kotlin/collections/MutableSet", "kotlin/collections/Map", "kotlin/collections/MutableMap",
+    "kotlin/collections/Map.Entry", "kotlin/collections/MutableMap.MutableEntry", "kotlin/collections/Iterator",
+    "kotlin/collections/MutableIterator", "kotlin/collections/ListIterator", "kotlin/collections/MutableListIterator"
+];
+
+/// Kind of class file described by the metadata, the `k` element
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KotlinMetadataKind {
+    /// Class, interface, object or any other classifier
+    Class,
+    /// Facade of the top level declarations of a file, like `MainKt`
+    File,
+    /// Synthetic class, like the class of a lambda
+    SyntheticClass,
+    /// Facade of a `@JvmMultifileClass`, without declarations of its own
+    MultiFileClassFacade,
+    /// Part of a `@JvmMultifileClass`
+    MultiFileClassPart,
+    /// Kind unknown to this version
+    Unknown(i64)
+}
+
+impl KotlinMetadataKind {
+    fn from_value(value: i64) -> Self {
+        match value {
+            1 => KotlinMetadataKind::Class,
+            2 => KotlinMetadataKind::File,
+            3 => KotlinMetadataKind::SyntheticClass,
+            4 => KotlinMetadataKind::MultiFileClassFacade,
+            5 => KotlinMetadataKind::MultiFileClassPart,
+            value => KotlinMetadataKind::Unknown(value)
+        }
+    }
+}
+
+/// Kind of a Kotlin class
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KotlinClassKind {
+    Class,
+    Interface,
+    EnumClass,
+    EnumEntry,
+    AnnotationClass,
+    Object,
+    CompanionObject
+}
+
+/// Visibility of a Kotlin declaration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KotlinVisibility {
+    Internal,
+    Private,
+    Protected,
+    Public,
+    PrivateToThis,
+    Local
+}
+
+/// Modality of a Kotlin declaration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KotlinModality {
+    Final,
+    Open,
+    Abstract,
+    Sealed
+}
+
+/// Flags shared by every declaration: visibility in bits 1-3 and modality
+/// in bits 4-5
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Flags(u64);
+
+impl Flags {
+    /// Convert flags from the layout used before Kotlin 1.4
+    fn from_old(old_flags: u64) -> Self {
+        Flags((old_flags & 0x3f) | ((old_flags >> 8) << 6))
+    }
+
+    fn bit(&self, bit: u32) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    fn bits(&self, first: u32, count: u32) -> u64 {
+        (self.0 >> first) & ((1 << count) - 1)
+    }
+
+    fn visibility(&self) -> KotlinVisibility {
+        match self.bits(1, 3) {
+            0 => KotlinVisibility::Internal,
+            1 => KotlinVisibility::Private,
+            2 => KotlinVisibility::Protected,
+            4 => KotlinVisibility::PrivateToThis,
+            5 => KotlinVisibility::Local,
+            _ => KotlinVisibility::Public
+        }
+    }
+
+    fn modality(&self) -> KotlinModality {
+        match self.bits(4, 2) {
+            0 => KotlinModality::Final,
+            1 => KotlinModality::Open,
+            2 => KotlinModality::Abstract,
+            _ => KotlinModality::Sealed
+        }
+    }
+}
+
+/// Type of a Kotlin declaration
+#[derive(Debug, Clone, PartialEq)]
+pub struct KotlinType {
+    /// Class name, like `kotlin.collections.List`, or name of a type parameter
+    classifier: String,
+    arguments: Vec<KotlinTypeProjection>,
+    nullable: bool,
+    is_suspend: bool
+}
+
+impl KotlinType {
+    /// Return a reference to the class name, like `kotlin.collections.List`,
+    /// or to the name of a type parameter
+    pub fn classifier(&self) -> &str {
+        &self.classifier
+    }
+
+    /// Return a reference to the type arguments
+    pub fn arguments(&self) -> &[KotlinTypeProjection] {
+        &self.arguments
+    }
+
+    /// Whether the type is nullable, like `String?`
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    /// Whether the type is a suspend function type
+    pub fn is_suspend(&self) -> bool {
+        self.is_suspend
+    }
+}
+
+impl fmt::Display for KotlinType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_suspend {
+            write!(f, "suspend ")?;
+        }
+        write!(f, "{}", self.classifier)?;
+
+        if !self.arguments.is_empty() {
+            let arguments = self.arguments.iter()
+                .map(|argument| argument.to_string())
+                .collect::<Vec<String>>();
+            write!(f, "<{}>", arguments.join(", "))?;
+        }
+
+        match self.nullable {
+            true => write!(f, "?"),
+            false => Ok(())
+        }
+    }
+}
+
+/// Type argument of a Kotlin type
+#[derive(Debug, Clone, PartialEq)]
+pub enum KotlinTypeProjection {
+    /// Star projection `*`
+    Star,
+    /// Invariant type argument
+    Invariant(KotlinType),
+    /// Contravariant type argument, `in T`
+    In(KotlinType),
+    /// Covariant type argument, `out T`
+    Out(KotlinType)
+}
+
+impl fmt::Display for KotlinTypeProjection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KotlinTypeProjection::Star => write!(f, "*"),
+            KotlinTypeProjection::Invariant(argument) => write!(f, "{argument}"),
+            KotlinTypeProjection::In(argument) => write!(f, "in {argument}"),
+            KotlinTypeProjection::Out(argument) => write!(f, "out {argument}")
+        }
+    }
+}
+
+/// Name and descriptor of the JVM member compiled from a Kotlin declaration
+#[derive(Debug, Clone, PartialEq)]
+pub struct JvmSignature {
+    name: String,
+    /// `None` when it is the one derived from the Kotlin types
+    descriptor: Option<String>
+}
+
+impl JvmSignature {
+    /// Return a reference to the name of the JVM member
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the descriptor of the JVM member, `None` when
+    /// kotlinc did not record it because it is the one derived from the
+    /// Kotlin types
+    pub fn descriptor(&self) -> Option<&str> {
+        self.descriptor.as_deref()
+    }
+}
+
+/// Parameter of a Kotlin function or constructor
+#[derive(Debug, Clone, PartialEq)]
+pub struct KotlinParameter {
+    name: String,
+    parameter_type: KotlinType,
+    has_default_value: bool,
+    is_vararg: bool
+}
+
+impl KotlinParameter {
+    /// Return a reference to the name of the parameter
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the type of the parameter, the type of the
+    /// elements for a `vararg` parameter
+    pub fn parameter_type(&self) -> &KotlinType {
+        &self.parameter_type
+    }
+
+    /// Whether the parameter declares a default value
+    pub fn has_default_value(&self) -> bool {
+        self.has_default_value
+    }
+
+    /// Whether the parameter is a `vararg`
+    pub fn is_vararg(&self) -> bool {
+        self.is_vararg
+    }
+}
+
+/// Kotlin function
+#[derive(Debug, Clone, PartialEq)]
+pub struct KotlinFunction {
+    name: String,
+    flags: Flags,
+    type_parameters: Vec<String>,
+    receiver_type: Option<KotlinType>,
+    parameters: Vec<KotlinParameter>,
+    return_type: KotlinType,
+    jvm_signature: JvmSignature
+}
+
+impl KotlinFunction {
+    /// Return a reference to the name of the function in the source code
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the visibility of the function
+    pub fn visibility(&self) -> KotlinVisibility {
+        self.flags.visibility()
+    }
+
+    /// Return the modality of the function
+    pub fn modality(&self) -> KotlinModality {
+        self.flags.modality()
+    }
+
+    /// Whether the function is an `operator`
+    pub fn is_operator(&self) -> bool {
+        self.flags.bit(8)
+    }
+
+    /// Whether the function is `infix`
+    pub fn is_infix(&self) -> bool {
+        self.flags.bit(9)
+    }
+
+    /// Whether the function is `inline`
+    pub fn is_inline(&self) -> bool {
+        self.flags.bit(10)
+    }
+
+    /// Whether the function is `tailrec`
+    pub fn is_tailrec(&self) -> bool {
+        self.flags.bit(11)
+    }
+
+    /// Whether the function is `external`
+    pub fn is_external(&self) -> bool {
+        self.flags.bit(12)
+    }
+
+    /// Whether the function is `suspend`, compiled with an additional
+    /// `Continuation` parameter
+    pub fn is_suspend(&self) -> bool {
+        self.flags.bit(13)
+    }
+
+    /// Return a reference to the names of the type parameters
+    pub fn type_parameters(&self) -> &[String] {
+        &self.type_parameters
+    }
+
+    /// Return a reference to the type of the receiver of an extension function
+    pub fn receiver_type(&self) -> Option<&KotlinType> {
+        self.receiver_type.as_ref()
+    }
+
+    /// Return a reference to the parameters
+    pub fn parameters(&self) -> &[KotlinParameter] {
+        &self.parameters
+    }
+
+    /// Return a reference to the return type
+    pub fn return_type(&self) -> &KotlinType {
+        &self.return_type
+    }
+
+    /// Return a reference to the name and the descriptor of the compiled
+    /// method, which differ from the source ones for `@JvmName`, mangled and
+    /// `suspend` functions
+    pub fn jvm_signature(&self) -> &JvmSignature {
+        &self.jvm_signature
+    }
+}
+
+/// Kotlin property
+#[derive(Debug, Clone, PartialEq)]
+pub struct KotlinProperty {
+    name: String,
+    flags: Flags,
+    property_type: KotlinType,
+    receiver_type: Option<KotlinType>,
+    field: Option<JvmSignature>,
+    getter: Option<JvmSignature>,
+    setter: Option<JvmSignature>
+}
+
+impl KotlinProperty {
+    /// Return a reference to the name of the property
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the visibility of the property
+    pub fn visibility(&self) -> KotlinVisibility {
+        self.flags.visibility()
+    }
+
+    /// Return the modality of the property
+    pub fn modality(&self) -> KotlinModality {
+        self.flags.modality()
+    }
+
+    /// Whether the property is a `var`
+    pub fn is_var(&self) -> bool {
+        self.flags.bit(8)
+    }
+
+    /// Whether the property is `const`
+    pub fn is_const(&self) -> bool {
+        self.flags.bit(11)
+    }
+
+    /// Whether the property is `lateinit`
+    pub fn is_lateinit(&self) -> bool {
+        self.flags.bit(12)
+    }
+
+    /// Whether the property is delegated, like `by lazy`
+    pub fn is_delegated(&self) -> bool {
+        self.flags.bit(15)
+    }
+
+    /// Return a reference to the type of the property
+    pub fn property_type(&self) -> &KotlinType {
+        &self.property_type
+    }
+
+    /// Return a reference to the type of the receiver of an extension property
+    pub fn receiver_type(&self) -> Option<&KotlinType> {
+        self.receiver_type.as_ref()
+    }
+
+    /// Return a reference to the backing field, if any
+    pub fn field(&self) -> Option<&JvmSignature> {
+        self.field.as_ref()
+    }
+
+    /// Return a reference to the getter method, if any
+    pub fn getter(&self) -> Option<&JvmSignature> {
+        self.getter.as_ref()
+    }
+
+    /// Return a reference to the setter method, if any
+    pub fn setter(&self) -> Option<&JvmSignature> {
+        self.setter.as_ref()
+    }
+}
+
+/// Kotlin constructor
+#[derive(Debug, Clone, PartialEq)]
+pub struct KotlinConstructor {
+    flags: Flags,
+    parameters: Vec<KotlinParameter>,
+    jvm_descriptor: Option<String>
+}
+
+impl KotlinConstructor {
+    /// Return the visibility of the constructor
+    pub fn visibility(&self) -> KotlinVisibility {
+        self.flags.visibility()
+    }
+
+    /// Whether the constructor is the primary one
+    pub fn is_primary(&self) -> bool {
+        !self.flags.bit(4)
+    }
+
+    /// Return a reference to the parameters
+    pub fn parameters(&self) -> &[KotlinParameter] {
+        &self.parameters
+    }
+
+    /// Return a reference to the descriptor of the compiled constructor,
+    /// `None` when it is the one derived from the Kotlin types
+    pub fn jvm_descriptor(&self) -> Option<&str> {
+        self.jvm_descriptor.as_deref()
+    }
+}
+
+/// Kotlin class declaration
+#[derive(Debug, Clone, PartialEq)]
+pub struct KotlinClass {
+    name: String,
+    flags: Flags,
+    type_parameters: Vec<String>,
+    supertypes: Vec<KotlinType>,
+    companion_object: Option<String>,
+    nested_classes: Vec<String>,
+    enum_entries: Vec<String>,
+    sealed_subclasses: Vec<String>,
+    constructors: Vec<KotlinConstructor>
+}
+
+impl KotlinClass {
+    /// Return a reference to the name of the class, like `com.example.Outer.Inner`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the kind of class
+    pub fn kind(&self) -> KotlinClassKind {
+        match self.flags.bits(6, 3) {
+            1 => KotlinClassKind::Interface,
+            2 => KotlinClassKind::EnumClass,
+            3 => KotlinClassKind::EnumEntry,
+            4 => KotlinClassKind::AnnotationClass,
+            5 => KotlinClassKind::Object,
+            6 => KotlinClassKind::CompanionObject,
+            _ => KotlinClassKind::Class
+        }
+    }
+
+    /// Return the visibility of the class
+    pub fn visibility(&self) -> KotlinVisibility {
+        self.flags.visibility()
+    }
+
+    /// Return the modality of the class
+    pub fn modality(&self) -> KotlinModality {
+        self.flags.modality()
+    }
+
+    /// Whether the class is an `inner` class
+    pub fn is_inner(&self) -> bool {
+        self.flags.bit(9)
+    }
+
+    /// Whether the class is a `data` class
+    pub fn is_data(&self) -> bool {
+        self.flags.bit(10)
+    }
+
+    /// Whether the class is a `value` class
+    pub fn is_value(&self) -> bool {
+        self.flags.bit(13)
+    }
+
+    /// Whether the class is a `fun interface`
+    pub fn is_fun_interface(&self) -> bool {
+        self.flags.bit(14)
+    }
+
+    /// Return a reference to the names of the type parameters
+    pub fn type_parameters(&self) -> &[String] {
+        &self.type_parameters
+    }
+
+    /// Return a reference to the supertypes, with their type arguments
+    pub fn supertypes(&self) -> &[KotlinType] {
+        &self.supertypes
+    }
+
+    /// Return a reference to the simple name of the companion object
+    pub fn companion_object(&self) -> Option<&str> {
+        self.companion_object.as_deref()
+    }
+
+    /// Return a reference to the simple names of the nested classes
+    pub fn nested_classes(&self) -> &[String] {
+        &self.nested_classes
+    }
+
+    /// Return a reference to the names of the entries of an enum class
+    pub fn enum_entries(&self) -> &[String] {
+        &self.enum_entries
+    }
+
+    /// Return a reference to the names of the subclasses of a sealed class
+    pub fn sealed_subclasses(&self) -> &[String] {
+        &self.sealed_subclasses
+    }
+
+    /// Return a reference to the constructors
+    pub fn constructors(&self) -> &[KotlinConstructor] {
+        &self.constructors
+    }
+}
+
+/// Decoded `kotlin.Metadata` annotation
+#[derive(Debug, Clone, PartialEq)]
+pub struct KotlinMetadata {
+    kind: KotlinMetadataKind,
+    version: Vec<i64>,
+    class: Option<KotlinClass>,
+    functions: Vec<KotlinFunction>,
+    properties: Vec<KotlinProperty>
+}
+
+impl KotlinMetadata {
+    /// Decode the `kotlin.Metadata` annotation of a class, if any
+    pub(crate) fn from_annotations(class_annotations: &[Annotation]) -> Option<Self> {
+        let annotation = annotations::find(class_annotations, METADATA)?;
+
+        let strings = |name: &str| annotation.element(name)
+            .and_then(EncodedValue::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|value| value.as_str().map(String::from))
+            .collect::<Vec<String>>();
+        let version = annotation.element("mv")
+            .and_then(EncodedValue::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(EncodedValue::as_i64)
+            .collect::<Vec<i64>>();
+        let kind = annotation.element("k")
+            .and_then(EncodedValue::as_i64)
+            .unwrap_or(1);
+
+        Self::decode(kind, version, &strings("d1"), &strings("d2"))
+    }
+
+    /// Decode the elements of a `kotlin.Metadata` annotation
+    ///
+    /// `None` if the `d1` message is malformed.
+    pub fn decode(kind: i64, version: Vec<i64>, d1: &[String], d2: &[String]) -> Option<Self> {
+        let kind = KotlinMetadataKind::from_value(kind);
+        let mut metadata = Self {
+            kind,
+            version,
+            class: None,
+            functions: Vec::new(),
+            properties: Vec::new()
+        };
+
+        // Facades only list their parts in `d1`
+        let has_message = matches!(
+            kind,
+            KotlinMetadataKind::Class | KotlinMetadataKind::File
+                | KotlinMetadataKind::SyntheticClass | KotlinMetadataKind::MultiFileClassPart
+        );
+        if !has_message || d1.is_empty() {
+            return Some(metadata);
+        }
+
+        let bytes = decode_bytes(d1);
+        let (string_table, message) = protobuf::read_delimited(&bytes)?;
+        let resolver = NameResolver::new(string_table, d2);
+        let scope = Scope::new(&resolver, message, 30, &[]);
+
+        match kind {
+            KotlinMetadataKind::Class => metadata.read_class(&scope, message),
+            KotlinMetadataKind::SyntheticClass => {
+                metadata.functions.push(read_function(&scope, message)?);
+            },
+            _ => metadata.read_package(&scope, message)
+        }
+
+        Some(metadata)
+    }
+
+    fn read_class(&mut self, scope: &Scope, message: &[u8]) {
+        let scope = scope.with_type_parameters(message, 5);
+        let mut class = KotlinClass {
+            name: String::new(),
+            flags: Flags(6),
+            type_parameters: scope.type_parameters.iter().map(|(_, name)| name.clone()).collect(),
+            supertypes: Vec::new(),
+            companion_object: None,
+            nested_classes: Vec::new(),
+            enum_entries: Vec::new(),
+            sealed_subclasses: Vec::new(),
+            constructors: Vec::new()
+        };
+
+        for (field, value) in ProtoFields::new(message) {
+            match (field, value) {
+                (1, ProtoValue::Varint(flags)) => class.flags = Flags(flags),
+                (3, ProtoValue::Varint(name)) => class.name = scope.resolver.class_name(name),
+                (4, ProtoValue::Varint(name)) => class.companion_object = Some(scope.resolver.string(name)),
+                (6, ProtoValue::Bytes(supertype)) => class.supertypes.extend(scope.read_type(supertype)),
+                (2, value) => class.supertypes.extend(value.as_packed().into_iter().filter_map(|id| scope.type_by_id(id))),
+                (7, value) => class.nested_classes.extend(value.as_packed().into_iter().map(|name| scope.resolver.string(name))),
+                (8, ProtoValue::Bytes(constructor)) => class.constructors.push(read_constructor(&scope, constructor)),
+                (9, ProtoValue::Bytes(function)) => self.functions.extend(read_function(&scope, function)),
+                (10, ProtoValue::Bytes(property)) => self.properties.extend(read_property(&scope, property)),
+                (13, ProtoValue::Bytes(entry)) => class.enum_entries.extend(
+                    ProtoFields::new(entry)
+                        .find(|(field, _)| *field == 1)
+                        .and_then(|(_, name)| name.as_u64())
+                        .map(|name| scope.resolver.string(name))
+                ),
+                (16, value) => class.sealed_subclasses.extend(value.as_packed().into_iter().map(|name| scope.resolver.class_name(name))),
+                _ => ()
+            }
+        }
+
+        self.class = Some(class);
+    }
+
+    fn read_package(&mut self, scope: &Scope, message: &[u8]) {
+        for (field, value) in ProtoFields::new(message) {
+            match (field, value) {
+                (3, ProtoValue::Bytes(function)) => self.functions.extend(read_function(scope, function)),
+                (4, ProtoValue::Bytes(property)) => self.properties.extend(read_property(scope, property)),
+                _ => ()
+            }
+        }
+    }
+
+    /// Return the kind of class file described by the metadata
+    pub fn kind(&self) -> KotlinMetadataKind {
+        self.kind
+    }
+
+    /// Return a reference to the version of the metadata, the `mv` element
+    pub fn version(&self) -> &[i64] {
+        &self.version
+    }
+
+    /// Return a reference to the class declaration, for metadata of kind `Class`
+    pub fn class(&self) -> Option<&KotlinClass> {
+        self.class.as_ref()
+    }
+
+    /// Return a reference to the functions declared in the class or the file
+    pub fn functions(&self) -> &[KotlinFunction] {
+        &self.functions
+    }
+
+    /// Return a reference to the properties declared in the class or the file
+    pub fn properties(&self) -> &[KotlinProperty] {
+        &self.properties
+    }
+
+    /// Find the Kotlin function compiled to a method, given the name and the
+    /// descriptor of the method
+    pub fn function_for_method(&self, name: &str, descriptor: &str) -> Option<&KotlinFunction> {
+        self.functions
+            .iter()
+            .filter(|function| function.jvm_signature.name == name)
+            .find(|function| function.jvm_signature.descriptor.as_deref().is_none_or(|jvm| jvm == descriptor))
+    }
+
+    /// Find the Kotlin property compiled to a field, a getter or a setter,
+    /// given the name of the member
+    pub fn property_for_member(&self, name: &str) -> Option<&KotlinProperty> {
+        self.properties
+            .iter()
+            .find(|property| [&property.field, &property.getter, &property.setter]
+                .iter()
+                .any(|member| member.as_ref().is_some_and(|member| member.name == name)))
+    }
+}
+
+/// Decode the bytes of `d1`
+///
+/// Recent versions of kotlinc store one byte per character, after a `\0`
+/// marker. Older ones store 7 bits per character, the unused bit keeping
+/// the characters away from `\0`.
+fn decode_bytes(d1: &[String]) -> Vec<u8> {
+    let mut chars = d1.iter().flat_map(|part| part.chars()).peekable();
+
+    match chars.peek() {
+        Some('\0') => return chars.skip(1).map(|c| c as u8).collect(),
+        Some('\u{1}') => { chars.next(); },
+        _ => ()
+    }
+
+    let data = chars.map(|c| (c as u8).wrapping_add(0x7f) & 0x7f).collect::<Vec<u8>>();
+    let mut bytes = Vec::with_capacity(data.len() * 7 / 8);
+    let (mut idx, mut bit) = (0, 0);
+
+    for _ in 0..data.len() * 7 / 8 {
+        let low = data[idx] >> bit;
+        idx += 1;
+        let high = (data[idx] & ((1 << (bit + 1)) - 1)) << (7 - bit);
+        bytes.push(low.wrapping_add(high));
+
+        if bit == 6 {
+            idx += 1;
+            bit = 0;
+        } else {
+            bit += 1;
+        }
+    }
+
+    bytes
+}
+
+/// Record of the string table, telling how to get a string and transform it
+#[derive(Debug, Clone, Default)]
+struct Record {
+    string: Option<String>,
+    predefined_index: Option<usize>,
+    operation: u64,
+    substring_index: Vec<u64>,
+    replace_char: Vec<u64>
+}
+
+/// String table of the metadata, the strings of `d2` and the records
+/// describing how to read them
+struct NameResolver<'a> {
+    strings: &'a [String],
+    /// Record of every string
+    records: Vec<Record>
+}
+
+impl<'a> NameResolver<'a> {
+    fn new(string_table: &[u8], strings: &'a [String]) -> Self {
+        let mut records = Vec::new();
+
+        for (_, value) in ProtoFields::new(string_table).filter(|(field, _)| *field == 1) {
+            let mut record = Record::default();
+            let mut range = 1;
+
+            for (field, value) in ProtoFields::new(value.as_bytes().unwrap_or_default()) {
+                match field {
+                    1 => range = value.as_u64().unwrap_or(1),
+                    2 => record.predefined_index = value.as_u64().map(|idx| idx as usize),
+                    3 => record.operation = value.as_u64().unwrap_or(0),
+                    4 => record.substring_index.extend(value.as_packed()),
+                    5 => record.replace_char.extend(value.as_packed()),
+                    6 => record.string = value.as_string(),
+                    _ => ()
+                }
+            }
+
+            records.extend(std::iter::repeat_n(record, range.min(strings.len() as u64) as usize));
+        }
+
+        Self { strings, records }
+    }
+
+    /// Get a string of the table
+    fn string(&self, idx: u64) -> String {
+        let idx = idx as usize;
+        let raw = self.strings.get(idx).cloned().unwrap_or_default();
+        let Some(record) = self.records.get(idx) else {
+            return raw;
+        };
+
+        let mut string = match (&record.string, record.predefined_index) {
+            (Some(string), _) => string.clone(),
+            (None, Some(predefined)) if predefined < PREDEFINED_STRINGS.len() => PREDEFINED_STRINGS[predefined].to_string(),
+            _ => raw
+        };
+
+        if let [begin, end, ..] = record.substring_index[..] {
+            let chars = string.chars().collect::<Vec<char>>();
+            if begin <= end && end as usize <= chars.len() {
+                string = chars[begin as usize..end as usize].iter().collect();
+            }
+        }
+        if let [from, to, ..] = record.replace_char[..]
+            && let (Some(from), Some(to)) = (char::from_u32(from as u32), char::from_u32(to as u32))
+        {
+            string = string.replace(from, &to.to_string());
+        }
+
+        match record.operation {
+            // Internal name to class id
+            1 => string.replace('$', "."),
+            // Descriptor to class id
+            2 => {
+                let mut chars = string.chars();
+                let stripped = match (chars.next(), chars.next_back()) {
+                    (Some(_), Some(_)) => chars.as_str(),
+                    _ => &string
+                };
+                stripped.replace('$', ".")
+            },
+            _ => string
+        }
+    }
+
+    /// Get a class name of the table, like `kotlin.collections.Map.Entry`
+    fn class_name(&self, idx: u64) -> String {
+        self.string(idx)
+            .trim_start_matches('.')
+            .replace('/', ".")
+    }
+}
+
+/// Declarations visible while reading a message: the type table and the
+/// type parameters
+struct Scope<'a> {
+    resolver: &'a NameResolver<'a>,
+    types: Vec<&'a [u8]>,
+    first_nullable: Option<u64>,
+    type_parameters: Vec<(u64, String)>,
+    depth: Cell<usize>,
+    types_read: Cell<usize>
+}
+
+impl<'a> Scope<'a> {
+    /// Create the scope of a message, with the type table in `table_field`
+    /// if any
+    fn new(resolver: &'a NameResolver<'a>, message: &'a [u8], table_field: u32, type_parameters: &[(u64, String)]) -> Self {
+        let mut types = Vec::new();
+        let mut first_nullable = None;
+
+        let table = ProtoFields::new(message)
+            .filter(|(field, _)| *field == table_field)
+            .find_map(|(_, value)| value.as_bytes());
+        for (field, value) in table.map(ProtoFields::new).into_iter().flatten() {
+            match (field, value) {
+                (1, ProtoValue::Bytes(bytes)) => types.push(bytes),
+                (2, ProtoValue::Varint(first)) => first_nullable = Some(first),
+                _ => ()
+            }
+        }
+
+        Self {
+            resolver,
+            types,
+            first_nullable,
+            type_parameters: type_parameters.to_vec(),
+            depth: Cell::new(0),
+            types_read: Cell::new(0)
+        }
+    }
+
+    /// Extend the scope with the type parameters of a declaration, in `field`,
+    /// and with its own type table
+    fn with_type_parameters(&self, message: &'a [u8], field: u32) -> Scope<'a> {
+        let mut scope = Scope::new(self.resolver, message, 30, &self.type_parameters);
+        if scope.types.is_empty() {
+            scope.types = self.types.clone();
+            scope.first_nullable = self.first_nullable;
+        }
+
+        for (_, value) in ProtoFields::new(message).filter(|(number, _)| *number == field) {
+            let fields = ProtoFields::new(value.as_bytes().unwrap_or_default()).collect::<Vec<(u32, ProtoValue)>>();
+            let value_of = |number: u32| fields.iter()
+                .find(|(field, _)| *field == number)
+                .and_then(|(_, value)| value.as_u64());
+
+            if let (Some(id), Some(name)) = (value_of(1), value_of(2)) {
+                scope.type_parameters.push((id, self.resolver.string(name)));
+            }
+        }
+
+        scope
+    }
+
+    /// Get a type of the type table
+    fn type_by_id(&self, id: u64) -> Option<KotlinType> {
+        let mut kotlin_type = self.read_type(self.types.get(id as usize)?)?;
+        if self.first_nullable.is_some_and(|first| id >= first) {
+            kotlin_type.nullable = true;
+        }
+        Some(kotlin_type)
+    }
+
+    /// Get a type given its message or its id in the type table, whichever
+    /// is present
+    fn type_of(&self, message: Option<&[u8]>, id: Option<u64>) -> Option<KotlinType> {
+        match message {
+            Some(message) => self.read_type(message),
+            None => self.type_by_id(id?)
+        }
+    }
+
+    /// Read a `Type` message, or `None` if the type is nested too deeply or
+    /// the scope has read too many types
+    fn read_type(&self, message: &[u8]) -> Option<KotlinType> {
+        if self.depth.get() >= MAX_TYPE_DEPTH || self.types_read.get() >= MAX_TYPES_READ {
+            return None;
+        }
+
+        self.depth.set(self.depth.get() + 1);
+        self.types_read.set(self.types_read.get() + 1);
+        let kotlin_type = self.read_nested_type(message);
+        self.depth.set(self.depth.get() - 1);
+        kotlin_type
+    }
+
+    fn read_nested_type(&self, message: &[u8]) -> Option<KotlinType> {
+        let mut kotlin_type = KotlinType {
+            classifier: String::new(),
+            arguments: Vec::new(),
+            nullable: false,
+            is_suspend: false
+        };
+
+        for (field, value) in ProtoFields::new(message) {
+            match (field, value) {
+                (1, ProtoValue::Varint(flags)) => kotlin_type.is_suspend = flags & 1 != 0,
+                (2, ProtoValue::Bytes(argument)) => kotlin_type.arguments.push(self.read_projection(argument)?),
+                (3, ProtoValue::Varint(nullable)) => kotlin_type.nullable = nullable != 0,
+                (6 | 12, ProtoValue::Varint(name)) => kotlin_type.classifier = self.resolver.class_name(name),
+                (7, ProtoValue::Varint(id)) => kotlin_type.classifier = self.type_parameters
+                    .iter()
+                    .rev()
+                    .find(|(parameter, _)| *parameter == id)
+                    .map(|(_, name)| name.clone())
+                    .unwrap_or_else(|| format!("T{id}")),
+                (9, ProtoValue::Varint(name)) => kotlin_type.classifier = self.resolver.string(name),
+                _ => ()
+            }
+        }
+
+        (!kotlin_type.classifier.is_empty()).then_some(kotlin_type)
+    }
+
+    fn read_projection(&self, message: &[u8]) -> Option<KotlinTypeProjection> {
+        let mut projection = 2;
+        let (mut argument, mut argument_id) = (None, None);
+
+        for (field, value) in ProtoFields::new(message) {
+            match (field, value) {
+                (1, ProtoValue::Varint(value)) => projection = value,
+                (2, ProtoValue::Bytes(bytes)) => argument = Some(bytes),
+                (3, ProtoValue::Varint(id)) => argument_id = Some(id),
+                _ => ()
+            }
+        }
+
+        if projection == 3 {
+            return Some(KotlinTypeProjection::Star);
+        }
+
+        let argument = self.type_of(argument, argument_id)?;
+        Some(match projection {
+            0 => KotlinTypeProjection::In(argument),
+            1 => KotlinTypeProjection::Out(argument),
+            _ => KotlinTypeProjection::Invariant(argument)
+        })
+    }
+
+    /// Read a `ValueParameter` message
+    fn read_parameter(&self, message: &[u8]) -> Option<KotlinParameter> {
+        let mut flags = 0;
+        let mut name = String::new();
+        let (mut parameter_type, mut type_id) = (None, None);
+        let (mut vararg_type, mut vararg_type_id) = (None, None);
+
+        for (field, value) in ProtoFields::new(message) {
+            match (field, value) {
+                (1, ProtoValue::Varint(value)) => flags = value,
+                (2, ProtoValue::Varint(value)) => name = self.resolver.string(value),
+                (3, ProtoValue::Bytes(bytes)) => parameter_type = Some(bytes),
+                (4, ProtoValue::Bytes(bytes)) => vararg_type = Some(bytes),
+                (5, ProtoValue::Varint(id)) => type_id = Some(id),
+                (6, ProtoValue::Varint(id)) => vararg_type_id = Some(id),
+                _ => ()
+            }
+        }
+
+        let is_vararg = vararg_type.is_some() || vararg_type_id.is_some();
+        let parameter_type = match is_vararg {
+            true => self.type_of(vararg_type, vararg_type_id)?,
+            false => self.type_of(parameter_type, type_id)?
+        };
+
+        Some(KotlinParameter { name, parameter_type, has_default_value: flags & 2 != 0, is_vararg })
+    }
+
+    /// Read a `JvmMethodSignature` or a `JvmFieldSignature` message, the name
+    /// defaults to the one of the declaration
+    fn read_jvm_signature(&self, message: &[u8], default_name: &str) -> JvmSignature {
+        let mut signature = JvmSignature { name: default_name.to_string(), descriptor: None };
+
+        for (field, value) in ProtoFields::new(message) {
+            match (field, value) {
+                (1, ProtoValue::Varint(name)) => signature.name = self.resolver.string(name),
+                (2, ProtoValue::Varint(descriptor)) => signature.descriptor = Some(self.resolver.string(descriptor)),
+                _ => ()
+            }
+        }
+
+        signature
+    }
+}
+
+/// Read the flags of a declaration, in `field` or in `old_field` with the
+/// layout used before Kotlin 1.4
+fn read_flags(message: &[u8], field: u32, old_field: u32, default: u64) -> Flags {
+    let fields = ProtoFields::new(message).collect::<Vec<(u32, ProtoValue)>>();
+    let value_of = |number: u32| fields.iter()
+        .find(|(field, _)| *field == number)
+        .and_then(|(_, value)| value.as_u64());
+
+    match (value_of(field), value_of(old_field)) {
+        (Some(flags), _) => Flags(flags),
+        (None, Some(old_flags)) => Flags::from_old(old_flags),
+        (None, None) => Flags(default)
+    }
+}
+
+/// Read a `Function` message
+fn read_function(scope: &Scope, message: &[u8]) -> Option<KotlinFunction> {
+    let scope = scope.with_type_parameters(message, 4);
+    let mut name = String::new();
+    let (mut return_type, mut return_type_id) = (None, None);
+    let (mut receiver_type, mut receiver_type_id) = (None, None);
+    let mut parameters = Vec::new();
+    let mut jvm_signature = None;
+
+    for (field, value) in ProtoFields::new(message) {
+        match (field, value) {
+            (2, ProtoValue::Varint(value)) => name = scope.resolver.string(value),
+            (3, ProtoValue::Bytes(bytes)) => return_type = Some(bytes),
+            (5, ProtoValue::Bytes(bytes)) => receiver_type = Some(bytes),
+            (6, ProtoValue::Bytes(bytes)) => parameters.push(scope.read_parameter(bytes)?),
+            (7, ProtoValue::Varint(id)) => return_type_id = Some(id),
+            (8, ProtoValue::Varint(id)) => receiver_type_id = Some(id),
+            (100, ProtoValue::Bytes(bytes)) => jvm_signature = Some(bytes),
+            _ => ()
+        }
+    }
+
+    Some(KotlinFunction {
+        flags: read_flags(message, 9, 1, 6),
+        type_parameters: scope.type_parameters.iter().map(|(_, name)| name.clone()).collect(),
+        receiver_type: scope.type_of(receiver_type, receiver_type_id),
+        parameters,
+        return_type: scope.type_of(return_type, return_type_id)?,
+        jvm_signature: scope.read_jvm_signature(jvm_signature.unwrap_or_default(), &name),
+        name
+    })
+}
+
+/// Read a `Property` message
+fn read_property(scope: &Scope, message: &[u8]) -> Option<KotlinProperty> {
+    let scope = scope.with_type_parameters(message, 4);
+    let mut name = String::new();
+    let (mut return_type, mut return_type_id) = (None, None);
+    let (mut receiver_type, mut receiver_type_id) = (None, None);
+    let mut jvm_signature = None;
+
+    for (field, value) in ProtoFields::new(message) {
+        match (field, value) {
+            (2, ProtoValue::Varint(value)) => name = scope.resolver.string(value),
+            (3, ProtoValue::Bytes(bytes)) => return_type = Some(bytes),
+            (5, ProtoValue::Bytes(bytes)) => receiver_type = Some(bytes),
+            (9, ProtoValue::Varint(id)) => return_type_id = Some(id),
+            (10, ProtoValue::Varint(id)) => receiver_type_id = Some(id),
+            (100, ProtoValue::Bytes(bytes)) => jvm_signature = Some(bytes),
+            _ => ()
+        }
+    }
+
+    let mut property = KotlinProperty {
+        flags: read_flags(message, 11, 1, 518),
+        property_type: scope.type_of(return_type, return_type_id)?,
+        receiver_type: scope.type_of(receiver_type, receiver_type_id),
+        field: None,
+        getter: None,
+        setter: None,
+        name
+    };
+
+    for (field, value) in ProtoFields::new(jvm_signature.unwrap_or_default()) {
+        let Some(bytes) = value.as_bytes() else {
+            continue;
+        };
+
+        match field {
+            1 => property.field = Some(scope.read_jvm_signature(bytes, &property.name)),
+            3 => property.getter = Some(scope.read_jvm_signature(bytes, &property.name)),
+            4 => property.setter = Some(scope.read_jvm_signature(bytes, &property.name)),
+            _ => ()
+        }
+    }
+
+    Some(property)
+}
+
+/// Read a `Constructor` message
+fn read_constructor(scope: &Scope, message: &[u8]) -> KotlinConstructor {
+    let mut constructor = KotlinConstructor {
+        flags: read_flags(message, 1, 1, 6),
+        parameters: Vec::new(),
+        jvm_descriptor: None
+    };
+
+    for (field, value) in ProtoFields::new(message) {
+        match (field, value) {
+            (2, ProtoValue::Bytes(bytes)) => constructor.parameters.extend(scope.read_parameter(bytes)),
+            (100, ProtoValue::Bytes(bytes)) => constructor.jvm_descriptor = scope.read_jvm_signature(bytes, "<init>").descriptor,
+            _ => ()
+        }
+    }
+
+    constructor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal protobuf writer for the messages of the tests
+    #[derive(Default)]
+    struct Message(Vec<u8>);
+
+    impl Message {
+        fn varint(mut value: u64, out: &mut Vec<u8>) {
+            while value >= 0x80 {
+                out.push((value as u8) | 0x80);
+                value >>= 7;
+            }
+            out.push(value as u8);
+        }
+
+        fn int(mut self, field: u64, value: u64) -> Self {
+            Self::varint(field << 3, &mut self.0);
+            Self::varint(value, &mut self.0);
+            self
+        }
+
+        fn bytes(mut self, field: u64, value: &[u8]) -> Self {
+            Self::varint((field << 3) | 2, &mut self.0);
+            Self::varint(value.len() as u64, &mut self.0);
+            self.0.extend_from_slice(value);
+            self
+        }
+
+        fn message(self, field: u64, message: Message) -> Self {
+            self.bytes(field, &message.0)
+        }
+    }
+
+    /// Encode `d1` the way recent versions of kotlinc do
+    fn d1(string_table: Message, message: Message) -> Vec<String> {
+        let mut bytes = Vec::new();
+        Message::varint(string_table.0.len() as u64, &mut bytes);
+        bytes.extend(string_table.0);
+        bytes.extend(message.0);
+
+        vec![std::iter::once('\0').chain(bytes.iter().map(|b| *b as char)).collect()]
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_decode_bytes() {
+        let bytes = vec![0x08, 0x96, 0x01, 0x00, 0xff];
+        let utf8 = std::iter::once('\0').chain(bytes.iter().map(|b| *b as char)).collect::<String>();
+        assert_eq!(decode_bytes(&[utf8]), bytes);
+
+        // 7 bits per character, shifted by one to avoid `\0`
+        let mut data = Vec::new();
+        let (mut acc, mut bits) = (0u32, 0);
+        for byte in &bytes {
+            acc |= (*byte as u32) << bits;
+            bits += 8;
+            while bits >= 7 {
+                data.push((acc & 0x7f) as u8);
+                acc >>= 7;
+                bits -= 7;
+            }
+        }
+        data.push(acc as u8);
+        let legacy = data.iter().map(|b| ((b + 1) & 0x7f) as char).collect::<String>();
+        assert_eq!(decode_bytes(&[legacy]), bytes);
+    }
+
+    #[test]
+    fn test_string_table() {
+        // 0: predefined `kotlin/String`, 1-2: `d2` as is, 3: descriptor to class id
+        let string_table = Message::default()
+            .message(1, Message::default().int(2, 14))
+            .message(1, Message::default().int(1, 2))
+            .message(1, Message::default().int(3, 2).int(1, 3));
+        let d2 = strings(&["", "name", "Lcom/example/Outer$Inner;", "Lcom/example/Outer$Inner;", "Lcom/exampleé/Ça;", "é"]);
+        let resolver = NameResolver::new(&string_table.0, &d2);
+
+        assert_eq!(resolver.class_name(0), "kotlin.String");
+        assert_eq!(resolver.string(1), "name");
+        assert_eq!(resolver.string(2), "Lcom/example/Outer$Inner;");
+        assert_eq!(resolver.class_name(3), "com.example.Outer.Inner");
+        assert_eq!(resolver.class_name(4), "com.exampleé.Ça");
+        assert_eq!(resolver.string(5), "é");
+    }
+
+    #[test]
+    fn test_recursive_type_table() {
+        let d2 = strings(&["kotlin/collections/Map"]);
+        let resolver = NameResolver::new(&[], &d2);
+
+        // Map<T0, T0> where T0 is the type itself
+        let argument = || Message::default().int(3, 0);
+        let message = Message::default()
+            .message(30, Message::default()
+                .message(1, Message::default().int(6, 0).message(2, argument()).message(2, argument())));
+        let scope = Scope::new(&resolver, &message.0, 30, &[]);
+        assert!(scope.type_by_id(0).is_none());
+
+        // Nested deeper than the limit
+        let mut nested = Message::default().int(6, 0);
+        for _ in 0..MAX_TYPE_DEPTH {
+            nested = Message::default().int(6, 0).message(2, Message::default().message(2, nested));
+        }
+        assert!(scope.read_type(&nested.0).is_none());
+    }
+
+    #[test]
+    fn test_class_metadata() {
+        let d2 = strings(&[
+            "Lcom/example/User;", "com/example/User", "kotlin/String", "name", "email", "load",
+            "Lkotlin/coroutines/Continuation;", "kotlin/collections/List", "force", "kotlin/Boolean",
+            "getEmail", "()Ljava/lang/String;"
+        ]);
+        let string_table = Message::default()
+            .message(1, Message::default().int(1, 12));
+
+        let string = |nullable: bool| Message::default().int(6, 2).int(3, nullable as u64);
+        let class = Message::default()
+            .int(1, 6 | (1 << 10))
+            .int(3, 1)
+            .message(8, Message::default()
+                .message(2, Message::default().int(2, 3).message(3, string(false))))
+            .message(9, Message::default()
+                .int(9, 6 | (1 << 13))
+                .int(2, 5)
+                .message(3, Message::default()
+                    .int(6, 7)
+                    .message(2, Message::default().message(2, string(false))))
+                .message(6, Message::default().int(1, 2).int(2, 8).message(3, Message::default().int(6, 9)))
+                .message(100, Message::default().int(1, 5)))
+            .message(10, Message::default()
+                .int(11, 6 | (1 << 8) | (1 << 9) | (1 << 10))
+                .int(2, 4)
+                .message(3, string(true))
+                .message(100, Message::default()
+                    .message(1, Message::default())
+                    .message(3, Message::default().int(1, 10).int(2, 11))));
+
+        let metadata = KotlinMetadata::decode(1, vec![1, 9, 0], &d1(string_table, class), &d2).unwrap();
+        assert_eq!(metadata.kind(), KotlinMetadataKind::Class);
+
+        let declared = metadata.class().unwrap();
+        assert_eq!(declared.name(), "com.example.User");
+        assert_eq!(declared.kind(), KotlinClassKind::Class);
+        assert!(declared.is_data());
+        assert_eq!(declared.constructors()[0].parameters()[0].name(), "name");
+
+        let load = &metadata.functions()[0];
+        assert_eq!(load.name(), "load");
+        assert!(load.is_suspend());
+        assert_eq!(load.return_type().to_string(), "kotlin.collections.List<kotlin.String>");
+        assert!(load.parameters()[0].has_default_value());
+        assert_eq!(load.parameters()[0].parameter_type().to_string(), "kotlin.Boolean");
+        assert_eq!(
+            metadata.function_for_method("load", "(ZLkotlin/coroutines/Continuation;)Ljava/lang/Object;"),
+            Some(load)
+        );
+
+        let email = &metadata.properties()[0];
+        assert_eq!(email.name(), "email");
+        assert!(email.is_var());
+        assert!(email.property_type().is_nullable());
+        assert_eq!(email.property_type().to_string(), "kotlin.String?");
+        assert_eq!(email.field().unwrap().name(), "email");
+        assert_eq!(email.getter().unwrap().descriptor(), Some("()Ljava/lang/String;"));
+        assert_eq!(metadata.property_for_member("getEmail"), Some(email));
+    }
+
+    #[test]
+    fn test_type_table_and_type_parameters() {
+        let d2 = strings(&["first", "T", "kotlin/collections/List", "items"]);
+        let string_table = Message::default();
+
+        // fun <T> first(items: List<out T>): T?, with the types in the table
+        let package = Message::default()
+            .message(3, Message::default()
+                .int(2, 0)
+                .message(4, Message::default().int(1, 0).int(2, 1))
+                .message(6, Message::default().int(2, 3).int(5, 0))
+                .int(7, 1))
+            .message(30, Message::default()
+                .message(1, Message::default()
+                    .int(6, 2)
+                    .message(2, Message::default().int(1, 1).message(2, Message::default().int(7, 0))))
+                .message(1, Message::default().int(7, 0))
+                .int(2, 1));
+
+        let metadata = KotlinMetadata::decode(2, Vec::new(), &d1(string_table, package), &d2).unwrap();
+        let first = &metadata.functions()[0];
+
+        assert_eq!(first.type_parameters(), ["T"]);
+        assert_eq!(first.parameters()[0].parameter_type().to_string(), "kotlin.collections.List<out T>");
+        assert_eq!(first.return_type().to_string(), "T?");
+        assert_eq!(first.jvm_signature().name(), "first");
+        assert!(first.jvm_signature().descriptor().is_none());
+
+        let facade = KotlinMetadata::decode(4, Vec::new(), &strings(&["com/example/UtilsKt__A"]), &[]).unwrap();
+        assert!(facade.functions().is_empty());
+    }
+}
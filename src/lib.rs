@@ -10,8 +10,38 @@ pub mod parser;
 pub mod disassembler;
 pub mod analysis;
 pub mod dvm_access_flags;
+pub mod hierarchy;
+pub mod call_graph;
+pub mod entry_points;
+pub mod keep_rules;
+pub mod dead_code;
+pub mod axml;
+pub mod manifest;
+pub mod resources;
+pub mod zip_file;
+pub mod signature;
+pub mod bundle;
+pub mod embedded;
+pub mod carving;
+pub mod native;
+pub mod debug_info;
+pub mod encoded_value;
+pub mod annotations;
+pub mod generics;
+pub mod nesting;
+pub mod kotlin;
+pub mod call_sites;
+pub mod code_payloads;
+pub mod literals;
+pub mod exceptions;
+pub mod semantics;
+pub mod dataflow;
+mod dex_file;
+mod der;
+mod protobuf;
 
 use std::path::Path;
+use std::collections::HashMap;
 use std::ffi::{ CStr, CString };
 
 use crate::parser::{
@@ -19,12 +49,28 @@ use crate::parser::{
     DvmMethod,
     DvmClass
 };
+use crate::dex_file::DexFile;
 use crate::disassembler::DvmDisassembledMethod;
 use crate::analysis::{
     DvmStringAnalysis,
     DvmMethodAnalysis,
     DvmClassAnalysis
 };
+use crate::hierarchy::{ ClassHierarchy, DispatchResolution };
+use crate::nesting::NestingTree;
+use crate::call_sites::{ CallSite, MethodHandle };
+use crate::call_graph::CallGraph;
+use crate::entry_points::EntryPoints;
+use crate::keep_rules::KeepRule;
+use crate::dead_code::DeadCodeReport;
+use crate::manifest::AndroidManifest;
+use crate::resources::ResourceTable;
+use crate::signature::ApkSignatures;
+use crate::embedded::{ EmbeddedDexFiles, EmbeddedPayload };
+use crate::carving::carve_dex;
+use crate::native::{ JniMap, NativeLibrary };
+use crate::bundle::ExtractDir;
+use crate::zip_file::{ ZipArchive, ZipEntry };
 
 mod shuriken {
     #[cfg(not(docsrs))]
@@ -41,7 +87,12 @@ mod shuriken {
 /// different analysis classes.
 #[derive(Debug)]
 pub struct DexContext {
-    ptr: shuriken::hDexContext
+    ptr: shuriken::hDexContext,
+    /// Raw copy of the DEX file, used for data Shuriken does not expose
+    dex_file: Option<DexFile>,
+    /// Directory of the file written for Shuriken when parsing bytes,
+    /// removed after the context is destroyed
+    _extract_dir: Option<ExtractDir>
 }
 
 // --------------------------- Parser API ---------------------------
@@ -64,8 +115,35 @@ impl DexContext {
         let c_world = c_str.as_ptr();
 
         let ptr = unsafe { shuriken::parse_dex(c_world) };
+        let dex_file = DexFile::from_path(filepath);
 
-        Self { ptr }
+        Self { ptr, dex_file, _extract_dir: None }
+    }
+
+    /// Parse a DEX file held in memory
+    ///
+    /// Shuriken only parses files, so the bytes are written to a temporary
+    /// file removed with the context. Returns `None` if the data does not
+    /// start with a DEX header or cannot be written.
+    pub fn parse_dex_bytes(data: &[u8]) -> Option<Self> {
+        let dex_file = DexFile::from_bytes(data.to_vec())?;
+        let extract_dir = ExtractDir::create()?;
+        let path = extract_dir.write("classes.dex", dex_file.data())?;
+
+        let mut context = Self::parse_dex(&path);
+        context._extract_dir = Some(extract_dir);
+        Some(context)
+    }
+
+    /// Carve the valid DEX files of a buffer, such as a memory dump, and
+    /// parse them with their offset
+    ///
+    /// See the [`carving`] module for the validation of the candidates.
+    pub fn parse_carved_dex(buffer: &[u8]) -> Vec<(usize, Self)> {
+        carve_dex(buffer)
+            .iter()
+            .filter_map(|dex| Some((dex.offset(), Self::parse_dex_bytes(dex.data())?)))
+            .collect()
     }
 
     /// Get the number of strings in the DEX file
@@ -114,7 +192,7 @@ impl DexContext {
 
         if ! dvm_class_ptr.is_null() {
             unsafe {
-                Some(DvmClass::from_ptr(*dvm_class_ptr))
+                Some(self.resolve_class(DvmClass::from_ptr(*dvm_class_ptr)))
             }
         } else {
             None
@@ -129,13 +207,21 @@ impl DexContext {
         let class_ptr = unsafe { shuriken::get_class_by_name(self.ptr, c_str.as_ptr()) };
         if ! class_ptr.is_null() {
             unsafe {
-                Some(DvmClass::from_ptr(*class_ptr))
+                Some(self.resolve_class(DvmClass::from_ptr(*class_ptr)))
             }
         } else {
             None
         }
     }
 
+    /// Complete a `DvmClass` with the data read from the raw DEX file
+    fn resolve_class(&self, mut class: DvmClass) -> DvmClass {
+        if let Some(dex_file) = &self.dex_file {
+            class.resolve_from_dex(dex_file);
+        }
+        class
+    }
+
     /// Get a method structure given a full dalvik name.
     pub fn get_method_by_name(&self, method_name: &str) -> Option<DvmMethod> {
         let c_str = CString::new(method_name)
@@ -143,14 +229,33 @@ impl DexContext {
 
         let method_ptr = unsafe { shuriken::get_method_by_name(self.ptr, c_str.as_ptr()) };
         if ! method_ptr.is_null() {
-            unsafe {
-                Some(DvmMethod::from_ptr(*method_ptr))
+            let mut method = unsafe { DvmMethod::from_ptr(*method_ptr) };
+            if let Some(dex_file) = &self.dex_file {
+                method.resolve_from_dex(dex_file);
             }
+            Some(method)
         } else {
             None
         }
     }
 
+    /// Get the call sites of the `call_site_ids` section, targets of the
+    /// `invoke-custom` instructions
+    pub fn get_call_sites(&self) -> Vec<CallSite> {
+        self.dex_file
+            .as_ref()
+            .map(CallSite::read_all)
+            .unwrap_or_default()
+    }
+
+    /// Get the method handles of the `method_handles` section
+    pub fn get_method_handles(&self) -> Vec<MethodHandle> {
+        self.dex_file
+            .as_ref()
+            .map(MethodHandle::read_all)
+            .unwrap_or_default()
+    }
+
     // --------------------------- Disassembler API ---------------------------
 
     /// Disassemble a DEX file and generate an internal DexDisassembler
@@ -170,7 +275,11 @@ impl DexContext {
         let dvm_disas = unsafe { shuriken::get_disassembled_method(self.ptr, c_str.as_ptr()) };
 
         if ! dvm_disas.is_null() {
-            Some(unsafe { DvmDisassembledMethod::from_dvmdisassembled_method_t(*dvm_disas, dvm_method) })
+            let mut method = unsafe { DvmDisassembledMethod::from_dvmdisassembled_method_t(*dvm_disas, dvm_method) };
+            if let Some(dex_file) = &self.dex_file {
+                method.resolve_from_dex(dex_file);
+            }
+            Some(method)
         } else {
             eprintln!("No disassembled method. Did you run `DexContext::disassemble_dex()`?");
             None
@@ -219,7 +328,10 @@ impl DexContext {
         match class_analysis_ptr.is_null() {
             true => None,
             false => {
-                let dvm_class_analysis = unsafe { DvmClassAnalysis::from_ptr(*class_analysis_ptr) };
+                let mut dvm_class_analysis = unsafe { DvmClassAnalysis::from_ptr(*class_analysis_ptr) };
+                if let Some(dex_file) = &self.dex_file {
+                    dvm_class_analysis.resolve_from_dex(dex_file);
+                }
                 Some(dvm_class_analysis)
             }
         }
@@ -242,11 +354,87 @@ impl DexContext {
         match method_analysis_ptr.is_null() {
             true => None,
             false => {
-                let dvm_method_analysis = unsafe { DvmMethodAnalysis::from_ptr(*method_analysis_ptr) };
+                let mut dvm_method_analysis = unsafe { DvmMethodAnalysis::from_ptr(*method_analysis_ptr) };
+                if let Some(dex_file) = &self.dex_file {
+                    dvm_method_analysis.resolve_from_dex(dex_file);
+                }
                 Some(dvm_method_analysis)
             }
         }
     }
+
+    /// Obtain the `DvmClassAnalysis` of every class defined in the DEX file
+    pub fn get_analyzed_classes(&self) -> Vec<DvmClassAnalysis> {
+        (0..self.get_number_of_classes())
+            .filter_map(|idx| {
+                // Only the name is needed, the class is not resolved
+                let dvm_class_ptr = unsafe { shuriken::get_class_by_id(self.ptr, idx as u16) };
+                match dvm_class_ptr.is_null() {
+                    true => None,
+                    false => Some(unsafe { DvmClass::from_ptr(*dvm_class_ptr) })
+                }
+            })
+            .filter_map(|class| self.get_analyzed_class_by_hdvmclass(&class))
+            .collect()
+    }
+
+    /// Build the class hierarchy of the classes defined in the DEX file
+    ///
+    /// External super classes and interfaces are added with the methods
+    /// Shuriken knows about.
+    pub fn get_class_hierarchy(&self) -> ClassHierarchy {
+        self.class_hierarchy_of(&self.get_analyzed_classes())
+    }
+
+    /// Build the class hierarchy of already analyzed classes
+    fn class_hierarchy_of(&self, classes: &[DvmClassAnalysis]) -> ClassHierarchy {
+        let mut hierarchy = ClassHierarchy::from_classes(classes);
+        hierarchy.add_missing_supertypes(|class_name| self.get_analyzed_class(class_name));
+        if let Some(dex_file) = &self.dex_file {
+            hierarchy.resolve_from_dex(dex_file);
+        }
+        hierarchy
+    }
+
+    /// Build the tree of the inner, anonymous and lambda classes defined in
+    /// the DEX file
+    ///
+    /// Lambda classes are only linked to the method they call if the
+    /// analysis was created with xrefs.
+    pub fn get_nesting_tree(&self) -> NestingTree {
+        NestingTree::from_classes(&self.get_analyzed_classes())
+    }
+
+    /// Build the call graph of the DEX file
+    ///
+    /// Virtual and interface calls are resolved to their possible concrete
+    /// targets according to `resolution`. The analysis must have been created
+    /// with xrefs.
+    pub fn get_call_graph(&self, resolution: DispatchResolution) -> CallGraph {
+        let classes = self.get_analyzed_classes();
+        let hierarchy = self.class_hierarchy_of(&classes);
+
+        CallGraph::build(&classes, &hierarchy, resolution)
+    }
+
+    /// Find the Android entry points of the classes defined in the DEX file
+    pub fn get_entry_points(&self) -> EntryPoints {
+        EntryPoints::find(&self.get_class_hierarchy())
+    }
+
+    /// Find the dead code of the classes defined in the DEX file
+    ///
+    /// The entry points and the methods kept by `keep_rules` are used as
+    /// roots, and virtual calls are resolved with CHA. The analysis must have
+    /// been created with xrefs.
+    pub fn get_dead_code_report(&self, keep_rules: &[KeepRule]) -> DeadCodeReport {
+        let classes = self.get_analyzed_classes();
+        let hierarchy = self.class_hierarchy_of(&classes);
+        let call_graph = CallGraph::build(&classes, &hierarchy, DispatchResolution::Cha);
+        let entry_points = EntryPoints::find(&hierarchy);
+
+        DeadCodeReport::analyze(&hierarchy, &call_graph, &entry_points.roots(), keep_rules)
+    }
 }
 
 // C - APK part of the CORE API from ShurikenLib
@@ -255,7 +443,11 @@ impl DexContext {
 /// Type alias for Shuriken's `hApkContext`
 #[derive(Debug)]
 pub struct ApkContext {
-    ptr: shuriken::hApkContext
+    ptr: shuriken::hApkContext,
+    /// Raw copy of the APK archive, used for the entries Shuriken does not expose
+    archive: Option<ZipArchive>,
+    /// Raw copies of the `classes*.dex` files of the APK, indexed by entry name
+    dex_files: HashMap<String, DexFile>
 }
 
 impl Drop for ApkContext {
@@ -282,7 +474,19 @@ impl ApkContext {
             shuriken::parse_apk(c_str.as_ptr(), xrefs)
         };
 
-        Self { ptr }
+        let archive = ZipArchive::from_path(filepath);
+        let dex_files = archive.iter()
+            .flat_map(|archive| archive.entries()
+                .iter()
+                .filter(|entry| embedded::is_main_dex(entry.name()))
+                .filter_map(|entry| Some((entry.name().to_string(), DexFile::from_bytes(archive.read(entry)?)?))))
+            .collect();
+
+        Self {
+            ptr,
+            archive,
+            dex_files
+        }
     }
 
     /// Get the number of DEX files in an APK
@@ -306,6 +510,120 @@ impl ApkContext {
         }
     }
 
+    /// Get the entries of the APK archive
+    ///
+    /// Every entry of the central directory is returned, not only the DEX
+    /// files. Returns an empty slice if the archive cannot be read.
+    pub fn get_entries_from_apk(&self) -> &[ZipEntry] {
+        self.archive
+            .as_ref()
+            .map(|archive| archive.entries())
+            .unwrap_or_default()
+    }
+
+    /// Get an entry of the APK archive given its name
+    pub fn get_entry_from_apk(&self, name: &str) -> Option<&ZipEntry> {
+        self.archive.as_ref()?.entry(name)
+    }
+
+    /// Read the uncompressed data of an entry of the APK archive given its name
+    pub fn read_entry_from_apk(&self, name: &str) -> Option<Vec<u8>> {
+        self.archive.as_ref()?.read_by_name(name)
+    }
+
+    /// Get the entries of the APK archive whose name starts with a prefix,
+    /// such as `lib/` or `assets/`
+    pub fn get_entries_with_prefix_from_apk(&self, prefix: &str) -> Vec<&ZipEntry> {
+        self.get_entries_from_apk()
+            .iter()
+            .filter(|entry| entry.name().starts_with(prefix))
+            .collect()
+    }
+
+    /// Get the raw DEX files in the order Android loads them: `classes.dex`,
+    /// `classes2.dex`, ... up to the first missing one
+    fn loaded_dex_files(&self) -> Vec<&DexFile> {
+        (1..)
+            .map(|n| match n {
+                1 => "classes.dex".to_string(),
+                n => format!("classes{n}.dex")
+            })
+            .map_while(|name| self.dex_files.get(&name))
+            .collect()
+    }
+
+    /// Find the raw DEX file defining a class, the first loaded one if
+    /// several do
+    fn dex_file_defining(&self, class_name: &str) -> Option<&DexFile> {
+        self.loaded_dex_files()
+            .into_iter()
+            .find(|dex_file| dex_file.class_def_by_name(class_name).is_some())
+    }
+
+    /// Decode the binary `AndroidManifest.xml` of the APK
+    ///
+    /// Returns `None` if the APK has no manifest or if it cannot be decoded.
+    pub fn get_android_manifest(&self) -> Option<AndroidManifest> {
+        let data = self.read_entry_from_apk("AndroidManifest.xml")?;
+        AndroidManifest::parse(&data)
+    }
+
+    /// Decode the `resources.arsc` table of the APK
+    ///
+    /// Returns `None` if the APK has no resource table or if it cannot be decoded.
+    pub fn get_resource_table(&self) -> Option<ResourceTable> {
+        let data = self.read_entry_from_apk("resources.arsc")?;
+        ResourceTable::parse(&data)
+    }
+
+    /// Parse the v1, v2, v3 and v3.1 signatures of the APK and check their digests
+    ///
+    /// The signatures themselves are not verified cryptographically, see
+    /// the [`signature`] module.
+    pub fn get_signatures_from_apk(&self) -> ApkSignatures {
+        self.archive
+            .as_ref()
+            .map(ApkSignatures::parse)
+            .unwrap_or_default()
+    }
+
+    /// Find the DEX, JAR, APK and encrypted-looking payloads hidden in the APK
+    ///
+    /// Every entry is checked except the `classesN.dex` files at the root,
+    /// and nested archives are looked into, see the [`embedded`] module.
+    pub fn get_embedded_payloads_from_apk(&self) -> Vec<EmbeddedPayload> {
+        self.archive
+            .as_ref()
+            .map(EmbeddedPayload::find)
+            .unwrap_or_default()
+    }
+
+    /// Open the valid embedded DEX files of the APK as `DexContext`s
+    pub fn get_embedded_dex_files_from_apk(&self) -> Option<EmbeddedDexFiles> {
+        Some(EmbeddedDexFiles::open(self.archive.as_ref()?))
+    }
+
+    /// Parse the ELF headers and the dynamic symbols of the `lib/<abi>/*.so`
+    /// libraries of the APK
+    ///
+    /// Entries that are not little-endian ELF files are skipped.
+    pub fn get_native_libraries_from_apk(&self) -> Vec<NativeLibrary> {
+        self.get_entries_with_prefix_from_apk("lib/")
+            .into_iter()
+            .filter(|entry| !entry.is_dir() && entry.name().ends_with(".so"))
+            .filter_map(|entry| {
+                let data = self.archive.as_ref()?.read(entry)?;
+                NativeLibrary::parse(entry.name(), &data)
+            })
+            .collect()
+    }
+
+    /// Match the `native` methods of the classes defined in the APK to the
+    /// JNI functions of its native libraries
+    pub fn get_jni_map_from_apk(&self) -> JniMap {
+        JniMap::build(&self.get_class_hierarchy_from_apk(), &self.get_native_libraries_from_apk())
+    }
+
     /// Get the number of classes in a DEX file
     ///
     /// Every DEX file contains a number of classes. This function retrieves the total number of
@@ -331,12 +649,30 @@ impl ApkContext {
 
         match ptr.is_null() {
             true => None,
-            false => unsafe {
-                Some(DvmClass::from_ptr(*ptr))
+            false => {
+                let mut class = unsafe { DvmClass::from_ptr(*ptr) };
+                if let Some(dex) = self.dex_files.get(dex_file) {
+                    class.resolve_from_dex(dex);
+                }
+                Some(class)
             }
         }
     }
 
+    /// Get the call sites of the `call_site_ids` section of a given DEX file
+    pub fn get_call_sites_from_dex(&self, dex_file: &str) -> Option<Vec<CallSite>> {
+        self.dex_files
+            .get(dex_file)
+            .map(CallSite::read_all)
+    }
+
+    /// Get the method handles of the `method_handles` section of a given DEX file
+    pub fn get_method_handles_from_dex(&self, dex_file: &str) -> Option<Vec<MethodHandle>> {
+        self.dex_files
+            .get(dex_file)
+            .map(MethodHandle::read_all)
+    }
+
     /// Get the header of a given DEX file
     pub fn get_header_from_dex(&self, dex_file: &str) -> Option<DvmHeader> {
         let dex_name = CString::new(dex_file)
@@ -397,8 +733,12 @@ impl ApkContext {
 
         match method_ptr.is_null() {
             true => None,
-            false => unsafe {
-                Some(DvmDisassembledMethod::from_ptr(*method_ptr))
+            false => {
+                let mut method = unsafe { DvmDisassembledMethod::from_ptr(*method_ptr) };
+                if let Some(dex) = self.dex_file_defining(method.method_id().class_name()) {
+                    method.resolve_from_dex(dex);
+                }
+                Some(method)
             }
         }
     }
@@ -421,8 +761,12 @@ impl ApkContext {
 
         match class_ptr.is_null() {
             true => None,
-            false => unsafe {
-                Some(DvmClassAnalysis::from_ptr(*class_ptr))
+            false => {
+                let mut class_analysis = unsafe { DvmClassAnalysis::from_ptr(*class_ptr) };
+                if let Some(dex) = self.dex_file_defining(class_analysis.name()) {
+                    class_analysis.resolve_from_dex(dex);
+                }
+                Some(class_analysis)
             }
         }
     }
@@ -443,8 +787,12 @@ impl ApkContext {
 
         match method_ptr.is_null() {
             true => None,
-            false => unsafe {
-                Some(DvmMethodAnalysis::from_ptr(*method_ptr))
+            false => {
+                let mut method_analysis = unsafe { DvmMethodAnalysis::from_ptr(*method_ptr) };
+                if let Some(dex) = self.dex_file_defining(method_analysis.class_name()) {
+                    method_analysis.resolve_from_dex(dex);
+                }
+                Some(method_analysis)
             }
         }
     }
@@ -464,12 +812,99 @@ impl ApkContext {
 
         match method_ptr.is_null() {
             true => None,
-            false => unsafe {
-                Some(DvmMethodAnalysis::from_ptr(*method_ptr))
+            false => {
+                let mut method_analysis = unsafe { DvmMethodAnalysis::from_ptr(*method_ptr) };
+                if let Some(dex) = self.dex_file_defining(method_analysis.class_name()) {
+                    method_analysis.resolve_from_dex(dex);
+                }
+                Some(method_analysis)
             }
         }
     }
 
+    /// Obtain the `DvmClassAnalysis` of every class defined in the APK
+    pub fn get_analyzed_classes_from_apk(&self) -> Vec<DvmClassAnalysis> {
+        (0..self.get_number_of_dex_files())
+            .filter_map(|idx| self.get_dex_file_by_index(idx))
+            .flat_map(|dex_file| {
+                let nb_classes = self.get_number_of_classes_from_dex(&dex_file).unwrap_or(0);
+                // Only the names are needed, the classes are not resolved
+                (0..nb_classes)
+                    .filter_map(|idx| {
+                        let dex_name = CString::new(dex_file.as_str()).ok()?;
+                        let ptr = unsafe {
+                            shuriken::get_hdvmclass_from_dex_by_index(self.ptr, dex_name.as_ptr(), idx as u32)
+                        };
+                        match ptr.is_null() {
+                            true => None,
+                            false => Some(unsafe { DvmClass::from_ptr(*ptr) })
+                        }
+                    })
+                    .collect::<Vec<DvmClass>>()
+            })
+            .filter_map(|class| self.get_analyzed_class_by_hdvmclass_from_apk(&class))
+            .collect()
+    }
+
+    /// Build the class hierarchy of the classes defined in the APK
+    ///
+    /// External super classes and interfaces are added with the methods
+    /// Shuriken knows about.
+    pub fn get_class_hierarchy_from_apk(&self) -> ClassHierarchy {
+        self.class_hierarchy_of(&self.get_analyzed_classes_from_apk())
+    }
+
+    /// Build the class hierarchy of already analyzed classes
+    fn class_hierarchy_of(&self, classes: &[DvmClassAnalysis]) -> ClassHierarchy {
+        let mut hierarchy = ClassHierarchy::from_classes(classes);
+        hierarchy.add_missing_supertypes(|class_name| self.get_analyzed_class_from_apk(class_name));
+        // The first loaded definition of a class wins
+        for dex_file in self.loaded_dex_files().into_iter().rev() {
+            hierarchy.resolve_from_dex(dex_file);
+        }
+        hierarchy
+    }
+
+    /// Build the tree of the inner, anonymous and lambda classes defined in
+    /// the APK
+    ///
+    /// Lambda classes are only linked to the method they call if the APK was
+    /// parsed with xrefs.
+    pub fn get_nesting_tree_from_apk(&self) -> NestingTree {
+        NestingTree::from_classes(&self.get_analyzed_classes_from_apk())
+    }
+
+    /// Build the call graph of the APK
+    ///
+    /// Virtual and interface calls are resolved to their possible concrete
+    /// targets according to `resolution`. The APK must have been parsed with
+    /// xrefs.
+    pub fn get_call_graph_from_apk(&self, resolution: DispatchResolution) -> CallGraph {
+        let classes = self.get_analyzed_classes_from_apk();
+        let hierarchy = self.class_hierarchy_of(&classes);
+
+        CallGraph::build(&classes, &hierarchy, resolution)
+    }
+
+    /// Find the Android entry points of the classes defined in the APK
+    pub fn get_entry_points_from_apk(&self) -> EntryPoints {
+        EntryPoints::find(&self.get_class_hierarchy_from_apk())
+    }
+
+    /// Find the dead code of the classes defined in the APK
+    ///
+    /// The entry points and the methods kept by `keep_rules` are used as
+    /// roots, and virtual calls are resolved with CHA. The APK must have been
+    /// parsed with xrefs.
+    pub fn get_dead_code_report_from_apk(&self, keep_rules: &[KeepRule]) -> DeadCodeReport {
+        let classes = self.get_analyzed_classes_from_apk();
+        let hierarchy = self.class_hierarchy_of(&classes);
+        let call_graph = CallGraph::build(&classes, &hierarchy, DispatchResolution::Cha);
+        let entry_points = EntryPoints::find(&hierarchy);
+
+        DeadCodeReport::analyze(&hierarchy, &call_graph, &entry_points.roots(), keep_rules)
+    }
+
     /// Obtain a `DvmStringAnalysis` given a string
     pub fn get_analyzed_string_from_apk(&self, string: &str) -> Option<DvmStringAnalysis> {
         let string = CString::new(string)
@@ -509,7 +944,7 @@ mod tests {
                 let path = path.unwrap().path();
 
                 // Only testing DEX files
-                if path.extension().unwrap() == "apk" {
+                if path.extension().unwrap() != "dex" {
                     continue;
                 }
 
@@ -566,7 +1001,15 @@ mod tests {
                 ("test_files/TestFieldsLifter.dex", 44),
                 ("test_files/_instance.dex", 28),
                 ("test_files/_switch.dex", 33),
-                ("test_files/_int.dex", 27)
+                ("test_files/_int.dex", 27),
+                ("test_files/DexAnnotationsTest.dex", 56),
+                ("test_files/DexStaticValuesTest.dex", 37),
+                ("test_files/DexKotlinTest.dex", 27),
+                ("test_files/DexCallSitesTest.dex", 20),
+                ("test_files/DexEntryPointsTest.dex", 26),
+                ("test_files/DexFieldOverloadsTest.dex", 13),
+                ("test_files/DexNestingTest.dex", 22),
+                ("test_files/DexSliceTest.dex", 13)
             ]);
 
             let paths = fs::read_dir(TEST_FILES_PATH).unwrap();
@@ -575,7 +1018,7 @@ mod tests {
                 let path = path.unwrap().path();
 
                 // Only testing DEX files
-                if path.extension().unwrap() == "apk" {
+                if path.extension().unwrap() != "dex" {
                     continue;
                 }
 
@@ -751,6 +1194,34 @@ mod tests {
             assert_eq!(class.as_ref().unwrap().access_flags(), vec![DvmAccessFlag::ACC_PUBLIC]);
         }
 
+        #[test]
+        fn test_class_interfaces() {
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexParserTest.dex"));
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+
+            let class = context.get_class_by_name("DexParserTest");
+            assert!(class.is_some());
+            assert!(class.unwrap().interfaces().is_empty());
+
+            let class_analysis = context.get_analyzed_class("DexParserTest");
+            assert!(class_analysis.is_some());
+            assert!(class_analysis.unwrap().interfaces().is_empty());
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexAnnotationsTest.dex"));
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+
+            let class = context.get_class_by_name("com.example.annotations.Bridge$1").unwrap();
+            assert_eq!(class.interfaces(), &["java.lang.Runnable"]);
+
+            let class_analysis = context.get_analyzed_class_by_hdvmclass(&class).unwrap();
+            let runnable = dex_file::same_notation_as(class_analysis.extends(), "Ljava/lang/Runnable;");
+            assert_eq!(class_analysis.interfaces(), &[runnable]);
+        }
+
         #[test]
         fn test_get_method_by_name() {
             let context = DexContext::parse_dex(&PathBuf::from("test_files/DexParserTest.dex"));
@@ -763,6 +1234,238 @@ mod tests {
             assert_eq!(method.as_ref().unwrap().access_flags(), vec![DvmAccessFlag::ACC_PRIVATE]);
         }
 
+        #[test]
+        fn test_method_debug_info() {
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexParserTest.dex"));
+            let method = context.get_method_by_name("LDexParserTest;->calculateSum(II)I").unwrap();
+
+            let debug_info = method.debug_info().unwrap();
+            assert_eq!(debug_info.line_at(0), Some(24));
+            assert_eq!(debug_info.local_variables()[0].name(), "this");
+
+            let class = context.get_class_by_name("DexParserTest").unwrap();
+            let lines = class.direct_methods()
+                .iter()
+                .map(|method| method.debug_info().and_then(|debug_info| debug_info.line_at(0)))
+                .collect::<Vec<Option<u32>>>();
+            assert!(lines.iter().all(Option::is_some));
+        }
+
+        #[test]
+        fn test_annotations() {
+            use crate::annotations::AnnotationVisibility;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexAnnotationsTest.dex"));
+            let class = context.get_class_by_name("com.example.annotations.Bridge").unwrap();
+
+            assert_eq!(class.annotations().len(), 4);
+            assert_eq!(class.signature().as_deref(), Some("<T:Ljava/lang/Object;>Ljava/lang/Object;"));
+            assert_eq!(class.member_classes(), vec!["Lcom/example/annotations/Bridge$Inner;"]);
+            assert!(class.inner_class().is_none());
+
+            let exposed = class.virtual_methods()
+                .iter()
+                .find(|method| method.method_name() == "exposed")
+                .unwrap();
+            assert_eq!(exposed.annotations()[0].type_descriptor(), "Landroid/webkit/JavascriptInterface;");
+            assert_eq!(exposed.annotations()[0].visibility(), AnnotationVisibility::Runtime);
+            assert_eq!(exposed.throws(), vec!["Ljava/io/IOException;"]);
+            assert_eq!(exposed.parameter_annotations()[0][0].element("value").unwrap().as_str(), Some("message"));
+
+            let items = class.instance_fields()
+                .iter()
+                .find(|field| field.name() == "items")
+                .unwrap();
+            assert_eq!(items.signature().as_deref(), Some("Ljava/util/List<Ljava/lang/String;>;"));
+
+            let anonymous = context.get_class_by_name("com.example.annotations.Bridge$1").unwrap();
+            assert!(anonymous.inner_class().unwrap().is_anonymous());
+            assert_eq!(
+                anonymous.enclosing_method().as_deref(),
+                Some("Lcom/example/annotations/Bridge;->exposed(Ljava/lang/String;)V")
+            );
+        }
+
+        #[test]
+        fn test_field_overloads_annotations() {
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexFieldOverloadsTest.dex"));
+            let class = context.get_class_by_name("com.example.fields.Obfuscated").unwrap();
+
+            // Both fields are named `a`, their annotations are told apart by type
+            let tag = |descriptor: &str| class.static_fields()
+                .iter()
+                .find(|field| field.name() == "a" && field.type_value() == descriptor)
+                .and_then(|field| field.annotations().first())
+                .and_then(|annotation| annotation.element("value"))
+                .and_then(|value| value.as_str().map(str::to_string));
+            assert_eq!(tag("I").as_deref(), Some("int"));
+            assert_eq!(tag("Ljava/lang/String;").as_deref(), Some("string"));
+        }
+
+        #[test]
+        fn test_generic_signatures() {
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexAnnotationsTest.dex"));
+            let class = context.get_class_by_name("com.example.annotations.Bridge").unwrap();
+
+            let signature = class.generic_signature().unwrap();
+            assert_eq!(signature.type_parameters()[0].name(), "T");
+            assert_eq!(signature.to_java("Bridge"), "Bridge<T> extends java.lang.Object");
+
+            let method = |name: &str| class.virtual_methods()
+                .iter()
+                .find(|method| method.method_name() == name)
+                .unwrap();
+            assert_eq!(method("identity").generic_prototype().unwrap().to_java("identity"), "T identity(T)");
+            assert_eq!(method("exposed").generic_prototype().unwrap().to_java("exposed"), "void exposed(java.lang.String)");
+
+            let items = class.instance_fields()
+                .iter()
+                .find(|field| field.name() == "items")
+                .unwrap();
+            assert_eq!(items.generic_type().unwrap().to_string(), "java.util.List<java.lang.String>");
+            assert_eq!(items.generic_type().unwrap().erasure(), items.type_value());
+
+            let inner = context.get_class_by_name("com.example.annotations.Bridge$Inner").unwrap();
+            assert_eq!(inner.generic_signature().unwrap().to_java("Inner"), "Inner extends java.lang.Object");
+        }
+
+        #[test]
+        fn test_nesting_tree() {
+            use crate::nesting::NestingKind;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexNestingTest.dex"));
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+
+            let tree = context.get_nesting_tree();
+            let outer = "Lcom/example/nesting/Outer;";
+            let exposed = "Lcom/example/nesting/Outer;->exposed(Ljava/lang/String;)V";
+
+            let inner = tree.class("com.example.nesting.Outer$Inner").unwrap();
+            assert_eq!(inner.kind(), NestingKind::Member);
+            assert_eq!(inner.simple_name(), Some("Inner"));
+            assert_eq!(inner.outer_class(), Some(outer));
+
+            let anonymous = tree.class("com.example.nesting.Outer$1").unwrap();
+            assert_eq!(anonymous.kind(), NestingKind::Anonymous);
+            assert_eq!(anonymous.enclosing_method(), Some(exposed));
+
+            let lambda = tree.class("com.example.nesting.Outer$$ExternalSyntheticLambda0").unwrap();
+            assert_eq!(lambda.kind(), NestingKind::Lambda);
+            assert_eq!(lambda.functional_interface(), Some("Ljava/lang/Runnable;"));
+            assert_eq!(lambda.implementation(), Some("Lcom/example/nesting/Outer;->lambda$exposed$0()V"));
+            assert_eq!(lambda.enclosing_method(), Some(exposed));
+
+            assert_eq!(tree.nested_classes(outer).len(), 3);
+            assert_eq!(tree.classes_declared_in(exposed).len(), 2);
+
+            let analyses = context.get_analyzed_classes();
+            let lambda_analysis = analyses.iter()
+                .find(|class| class.nesting().kind() == NestingKind::Lambda)
+                .unwrap();
+            assert_eq!(lambda_analysis.outer_class(), Some(outer));
+            assert!(lambda_analysis.nested_classes(&tree).is_empty());
+        }
+
+        #[test]
+        fn test_kotlin_metadata() {
+            use crate::kotlin::{ KotlinClassKind, KotlinMetadataKind };
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexKotlinTest.dex"));
+            let class = context.get_class_by_name("com.example.kotlin.User").unwrap();
+
+            let metadata = class.kotlin_metadata().unwrap();
+            assert_eq!(metadata.kind(), KotlinMetadataKind::Class);
+            assert_eq!(metadata.version(), [1, 9, 0]);
+
+            let declared = metadata.class().unwrap();
+            assert_eq!(declared.name(), "com.example.kotlin.User");
+            assert_eq!(declared.kind(), KotlinClassKind::Class);
+            assert!(declared.is_data());
+
+            let load = class.virtual_methods()
+                .iter()
+                .find(|method| method.method_name() == "load")
+                .unwrap();
+            let function = metadata.function_for_method(load.method_name(), load.prototype()).unwrap();
+            assert!(function.is_suspend());
+            assert_eq!(function.return_type().to_string(), "kotlin.collections.List<kotlin.String>");
+
+            let email = metadata.property_for_member("getEmail").unwrap();
+            assert_eq!(email.name(), "email");
+            assert!(email.property_type().is_nullable());
+
+            let plain = DexContext::parse_dex(&PathBuf::from("test_files/DexParserTest.dex"));
+            assert!(plain.get_class_by_name("DexParserTest").unwrap().kotlin_metadata().is_none());
+        }
+
+        #[test]
+        fn test_call_sites() {
+            use crate::call_sites::{ DynamicXref, MethodHandleKind };
+
+            let lambda = "Lcom/example/callsites/Handles;->lambda$run$0()V";
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexCallSitesTest.dex"));
+
+            let call_sites = context.get_call_sites();
+            assert_eq!(call_sites.len(), 1);
+            assert!(call_sites[0].is_lambda_metafactory());
+            assert_eq!(call_sites[0].method_name(), "run");
+            assert_eq!(call_sites[0].method_type(), "()Ljava/lang/Runnable;");
+            assert_eq!(call_sites[0].implementation().map(|handle| handle.target()), Some(lambda));
+            assert!(context.get_method_handles()
+                .iter()
+                .any(|handle| handle.kind() == MethodHandleKind::InvokeStatic && handle.target() == lambda));
+
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+
+            let method = context.get_analyzed_method("Lcom/example/callsites/Handles;->run()V").unwrap();
+            let xref = |idx: u64| method.xrefdynamic().iter().find(|xref| xref.idx() == idx).unwrap();
+            assert_eq!(method.xrefdynamic().len(), 4);
+            assert!(matches!(xref(0), DynamicXref::CallSite { call_site_idx: 0, .. }));
+            assert_eq!(xref(0).target(), Some(lambda));
+            assert_eq!(xref(14).target(), Some(lambda));
+            assert!(matches!(xref(18), DynamicXref::MethodType { prototype, .. } if prototype == "()V"));
+            assert!(matches!(xref(22), DynamicXref::Polymorphic { prototype, .. } if prototype == "()V"));
+            assert!(method.xrefto()
+                .iter()
+                .any(|xref| xref.idx() == 0 && xref.method() == lambda));
+        }
+
+        #[test]
+        fn test_static_values() {
+            use crate::encoded_value::EncodedValue;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexStaticValuesTest.dex"));
+            let class = context.get_class_by_name("com.example.statics.Config").unwrap();
+
+            let value = |name: &str| class.static_fields()
+                .iter()
+                .find(|field| field.name() == name)
+                .and_then(|field| field.initial_value());
+            assert_eq!(value("ENDPOINT").and_then(EncodedValue::as_str), Some("https://api.example.com/v1"));
+            assert_eq!(value("RETRIES"), Some(&EncodedValue::Int(3)));
+            assert_eq!(value("counter"), Some(&EncodedValue::Int(0)));
+            assert!(class.instance_fields()[0].initial_value().is_none());
+        }
+
+        #[test]
+        fn test_field_overloads_static_values() {
+            use crate::encoded_value::EncodedValue;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexFieldOverloadsTest.dex"));
+            let class = context.get_class_by_name("com.example.fields.Obfuscated").unwrap();
+
+            let value = |descriptor: &str| class.static_fields()
+                .iter()
+                .find(|field| field.name() == "a" && field.type_value() == descriptor)
+                .and_then(|field| field.initial_value().cloned());
+            assert_eq!(value("I"), Some(EncodedValue::Int(7)));
+            assert_eq!(value("Ljava/lang/String;"), Some(EncodedValue::String(String::from("seven"))));
+        }
+
         #[test]
         fn test_disassemble_dex() {
             let paths = fs::read_dir(TEST_FILES_PATH).unwrap();
@@ -771,7 +1474,7 @@ mod tests {
                 let path = path.unwrap().path();
 
                 // Only testing DEX files
-                if path.extension().unwrap() == "apk" {
+                if path.extension().unwrap() != "dex" {
                     continue;
                 }
 
@@ -780,6 +1483,218 @@ mod tests {
             }
         }
 
+        #[test]
+        fn test_instruction_semantics() {
+            use crate::semantics::ControlFlow;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/_exception.dex"));
+            context.disassemble_dex();
+
+            let method = context.get_disassembled_method("Lcom/dexbox/_exception;->test_throw()V").unwrap();
+            let semantics = |address: u64| method.instructions()
+                .iter()
+                .find(|ins| ins.address() == address)
+                .and_then(|ins| ins.semantics())
+                .unwrap();
+
+            // new-instance v1, Ljava/lang/NullPointerException;
+            assert_eq!(semantics(18).writes(), &[1]);
+            assert!(semantics(18).can_throw());
+
+            // invoke-direct {v1}, Ljava/lang/NullPointerException;-><init>()V
+            assert_eq!(semantics(22).reads(), &[1]);
+            assert!(semantics(22).writes().is_empty());
+            assert!(semantics(22).has_side_effects());
+
+            // throw v1
+            assert_eq!(semantics(28).control_flow(), ControlFlow::Throw);
+            assert!(semantics(28).is_terminator());
+
+            // goto to the rethrow of the finally block
+            assert_eq!(semantics(32).control_flow(), ControlFlow::Goto(64));
+            assert_eq!(semantics(62).control_flow(), ControlFlow::Return);
+        }
+
+        #[test]
+        fn test_liveness_def_use() {
+            use std::collections::BTreeSet;
+            use crate::dataflow::{ ControlFlowGraph, DefUseChains, DefinitionSite, Liveness, ParameterRegisters };
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/_exception.dex"));
+            context.disassemble_dex();
+
+            let method = context.get_disassembled_method("Lcom/dexbox/_exception;->test_throw()V").unwrap();
+            let cfg = ControlFlowGraph::build(&method);
+            assert!(ParameterRegisters::of(&method).is_empty());
+
+            // The try block reaches its handlers only through exceptions
+            let try_block = cfg.block_containing(&method, 18).unwrap();
+            assert!(cfg.blocks()[try_block].successors().is_empty());
+            assert_eq!(cfg.blocks()[try_block].handlers().len(), 2);
+
+            // v0 holds the string printed by the handlers and the finally block
+            let liveness = Liveness::build(&method, &cfg);
+            assert!(liveness.live_in(0).is_empty());
+            assert_eq!(liveness.live_in(try_block), &BTreeSet::from([0]));
+            let finally_block = cfg.block_containing(&method, 64).unwrap();
+            assert_eq!(liveness.live_in(finally_block), &BTreeSet::from([0, 1]));
+
+            let chains = DefUseChains::build(&method, &cfg);
+            let uses = chains.definitions()
+                .find(|definition| definition.site() == DefinitionSite::Instruction(18))
+                .map(|definition| chains.uses_of(definition))
+                .unwrap();
+            assert_eq!(uses.iter().map(|register_use| register_use.address()).collect::<Vec<u64>>(), vec![22, 28]);
+            assert_eq!(chains.definitions_of(68, 0)[0].site(), DefinitionSite::Instruction(0));
+
+            // The exception rethrown by the finally block comes from its move-exception
+            let (instructions, parameters) = chains.backward_slice(74, 1);
+            assert_eq!(instructions, BTreeSet::from([30]));
+            assert!(parameters.is_empty());
+
+            let method = context.get_disassembled_method("Lcom/dexbox/_exception;-><init>()V").unwrap();
+            let cfg = ControlFlowGraph::build(&method);
+            let parameters = ParameterRegisters::of(&method);
+            assert_eq!(parameters.len(), 1);
+            assert_eq!(parameters[0].descriptor(), "Lcom/dexbox/_exception;");
+            assert_eq!(parameters[0].registers(), &[0]);
+
+            let (instructions, parameters) = DefUseChains::build(&method, &cfg).backward_slice(0, 0);
+            assert!(instructions.is_empty());
+            assert_eq!(parameters.len(), 1);
+        }
+
+        #[test]
+        fn test_backward_slice_results() {
+            use std::collections::BTreeSet;
+            use crate::dataflow::{ ControlFlowGraph, DefUseChains };
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexSliceTest.dex"));
+            context.disassemble_dex();
+
+            let method = context.get_disassembled_method("Lcom/example/slice/Sender;->send()V").unwrap();
+            let chains = DefUseChains::build(&method, &ControlFlowGraph::build(&method));
+
+            // The message sent comes from the move-result of format(), which
+            // depends on the two strings given to it
+            let (instructions, parameters) = chains.backward_slice(16, 1);
+            assert_eq!(instructions, BTreeSet::from([0, 4, 8, 14]));
+            assert!(parameters.is_empty());
+
+            let (instructions, _) = chains.backward_slice(16, 0);
+            assert_eq!(instructions, BTreeSet::from([0]));
+        }
+
+        #[test]
+        fn test_exception_flow() {
+            use crate::hierarchy::ClassHierarchy;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/_exception.dex"));
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+            let hierarchy = ClassHierarchy::new();
+
+            let method = context.get_analyzed_method("Lcom/dexbox/_exception;->test_throw()V").unwrap();
+            let try_ranges = method.try_ranges();
+            assert_eq!(try_ranges.len(), 2);
+            assert_eq!((try_ranges[0].start_address(), try_ranges[0].end_address()), (4, 30));
+
+            let handlers = try_ranges[0].handlers();
+            assert_eq!(handlers[0].exception_type(), Some("Ljava/lang/NullPointerException;"));
+            assert!(handlers[1].is_catch_all());
+
+            let handler_block = method.handler_block(&handlers[0]).unwrap();
+            assert_eq!(handler_block.handler_type(), "Ljava/lang/NullPointerException;");
+            assert_eq!(method.handler_block(&handlers[1]).unwrap().handler_type(), "Ljava/lang/Throwable;");
+            assert!(!method.blocks_covered_by(&handlers[1]).is_empty());
+
+            // The NullPointerException is caught, the exception rethrown by the
+            // finally block escapes
+            assert_eq!(method.thrown_exceptions().len(), 2);
+            let thrown = method.thrown_exceptions()
+                .iter()
+                .find(|thrown| thrown.address() == 28)
+                .unwrap();
+            assert_eq!(thrown.exception_type(), Some("Ljava/lang/NullPointerException;"));
+            let escaping = method.escaping_thrown_exceptions(&hierarchy);
+            assert_eq!(escaping.len(), 1);
+            assert_eq!(escaping[0].address(), 74);
+
+            let method = context.get_analyzed_method("Lcom/dexbox/_exception;->test_nest()V").unwrap();
+            assert!(method.escaping_thrown_exceptions(&hierarchy).is_empty());
+        }
+
+        #[test]
+        fn test_literals() {
+            use crate::literals::{ LiteralType, LiteralValue };
+
+            let literals = |file: &str, method: &str| {
+                let context = DexContext::parse_dex(&PathBuf::from(file));
+                context.disassemble_dex();
+
+                context.get_disassembled_method(method)
+                    .unwrap()
+                    .instructions()
+                    .iter()
+                    .filter_map(|ins| ins.literal().map(|literal| literal.value()))
+                    .collect::<Vec<LiteralValue>>()
+            };
+
+            assert_eq!(
+                literals("test_files/_float.dex", "Lcom/dexbox/_float;->test_constant()V"),
+                vec![LiteralValue::Float(0.0), LiteralValue::Float(1.0), LiteralValue::Float(2.0), LiteralValue::Float(3.0)]
+            );
+            assert_eq!(
+                literals("test_files/_double.dex", "Lcom/dexbox/_double;->test_calculate()V"),
+                vec![
+                    LiteralValue::Double(6.0), LiteralValue::Double(2.0), LiteralValue::Double(8.0),
+                    LiteralValue::Double(0.0), LiteralValue::Double(-4.0)
+                ]
+            );
+            assert_eq!(
+                literals("test_files/_long.dex", "Lcom/dexbox/_long;->main([Ljava/lang/String;)I")[..3],
+                [LiteralValue::Long(10), LiteralValue::Long(6), LiteralValue::Long(16)]
+            );
+            assert!(literals("test_files/_pi.dex", "Lcom/dexbox/_pi;->main([Ljava/lang/String;)I")
+                .contains(&LiteralValue::Double(0.001)));
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/_float.dex"));
+            context.disassemble_dex();
+            let method = context.get_disassembled_method("Lcom/dexbox/_float;->test_constant()V").unwrap();
+            let literal = method.instructions()[4].literal().unwrap();
+            assert_eq!(literal.register(), 1);
+            assert_eq!(literal.int_value(), 0x3f800000);
+            assert_eq!(literal.float_value(), Some(1.0));
+            assert_eq!(literal.inferred_type(), Some(LiteralType::Float));
+        }
+
+        #[test]
+        fn test_switch_tables() {
+            use crate::code_payloads::SwitchKind;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/_switch.dex"));
+            context.disassemble_dex();
+
+            let method = context.get_disassembled_method("Lcom/dexbox/_switch;->performAction(I)V").unwrap();
+            assert_eq!(method.switch_tables().len(), 1);
+            assert!(method.array_data().is_empty());
+
+            let table = method.switch_table(0).unwrap();
+            assert_eq!(table.kind(), SwitchKind::Packed);
+            assert_eq!(table.payload_address(), 272);
+            assert_eq!(table.cases().len(), 15);
+            assert_eq!(table.target(1), Some(252));
+            assert_eq!(table.target(15), Some(24));
+            assert_eq!(table.target(16), None);
+
+            for case in table.cases() {
+                assert!(method.instructions()
+                    .iter()
+                    .any(|ins| ins.address() == case.target()));
+            }
+        }
+
         #[test]
         fn test_get_disassembled_method() {
             use std::collections::HashMap;
@@ -1084,6 +1999,159 @@ mod tests {
             assert_eq!(class_analysis.xreffrom(), class_analysis_by_hdvmclass.xreffrom());
         }
 
+        #[test]
+        fn test_call_graph() {
+            use call_graph::CallEdgeKind;
+            use hierarchy::DispatchResolution;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexParserTest.dex"));
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+
+            let hierarchy = context.get_class_hierarchy();
+            assert!(hierarchy.class("DexParserTest").is_some());
+
+            let call_graph = context.get_call_graph(DispatchResolution::Rta);
+
+            let callees = call_graph.callees("LDexParserTest;->main([Ljava/lang/String;)V");
+            assert!(callees.iter().any(|edge| edge.callee() == "LDexParserTest;->printMessage()V"
+                                            && edge.kind() == CallEdgeKind::Direct));
+
+            let callees = call_graph.callees("LDexParserTest;->printMessage()V");
+            assert!(callees.iter().any(|edge| edge.callee() == "Ljava/io/PrintStream;->println(Ljava/lang/String;)V"
+                                            && edge.kind() == CallEdgeKind::Virtual));
+            assert!(callees.iter().all(|edge| edge.kind() != CallEdgeKind::Dispatched));
+
+            let reachable = call_graph.reachable_from(["LDexParserTest;->main([Ljava/lang/String;)V"]);
+            assert!(reachable.contains("LDexParserTest;->calculateSum(II)I"));
+        }
+
+        #[test]
+        fn test_method_overrides() {
+            use hierarchy::HierarchyMethod;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexEntryPointsTest.dex"));
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+
+            let hierarchy = context.get_class_hierarchy();
+            let names = |methods: Vec<&HierarchyMethod>| methods.iter()
+                .map(|method| method.full_name().to_string())
+                .collect::<Vec<String>>();
+
+            let greet = context.get_analyzed_method("Lcom/example/entry/MainActivity;->greet()V").unwrap();
+            assert!(greet.overridden_methods(&hierarchy).is_empty());
+            assert_eq!(names(greet.overriding_methods(&hierarchy)),
+                       vec!["Lcom/example/entry/DetailActivity;->greet()V"]);
+            assert_eq!(names(greet.overloads(&hierarchy)),
+                       vec!["Lcom/example/entry/MainActivity;->greet(Ljava/lang/String;)V"]);
+
+            let greet = context.get_analyzed_method("Lcom/example/entry/DetailActivity;->greet()V").unwrap();
+            assert_eq!(names(greet.overridden_methods(&hierarchy)),
+                       vec!["Lcom/example/entry/MainActivity;->greet()V"]);
+            assert!(greet.overriding_methods(&hierarchy).is_empty());
+            assert!(greet.overloads(&hierarchy).is_empty());
+
+            // Static methods are not overridden
+            let on_resume = context.get_analyzed_method("Lcom/example/entry/DetailActivity;->onResume()V").unwrap();
+            assert!(names(on_resume.overridden_methods(&hierarchy))
+                .iter()
+                .all(|name| name != "Lcom/example/entry/MainActivity;->onResume()V"));
+            let on_resume = context.get_analyzed_method("Lcom/example/entry/MainActivity;->onResume()V").unwrap();
+            assert!(on_resume.overriding_methods(&hierarchy).is_empty());
+
+            // Static and private methods, and constructors, override nothing
+            // even with the signature of a super class method
+            for method in [
+                "Lcom/example/entry/DetailActivity;->onStop(I)V",
+                "Lcom/example/entry/DetailActivity;->onSwipe(Landroid/view/View;)V",
+                "Lcom/example/entry/DetailActivity;-><init>()V"
+            ] {
+                let method = context.get_analyzed_method(method).unwrap();
+                assert!(method.overridden_methods(&hierarchy).is_empty());
+            }
+            let on_stop = context.get_analyzed_method("Lcom/example/entry/MainActivity;->onStop(I)V").unwrap();
+            assert!(on_stop.overriding_methods(&hierarchy).is_empty());
+            let init = context.get_analyzed_method("Lcom/example/entry/MainActivity;-><init>()V").unwrap();
+            assert!(init.overriding_methods(&hierarchy).is_empty());
+        }
+
+        #[test]
+        fn test_entry_points() {
+            use entry_points::EntryPointKind;
+
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexEntryPointsTest.dex"));
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+
+            let entry_points = context.get_entry_points();
+            let mut found = entry_points.entry_points()
+                .iter()
+                .map(|entry_point| (entry_point.method(), entry_point.kind()))
+                .collect::<Vec<_>>();
+            found.sort_by_key(|(method, _)| method.to_string());
+
+            // Static, private and unknown `on*` methods are not called back
+            assert_eq!(found, vec![
+                ("Lcom/example/entry/DetailActivity;-><init>()V", EntryPointKind::Activity),
+                ("Lcom/example/entry/DetailActivity;->onResume()V", EntryPointKind::Activity),
+                ("Lcom/example/entry/MainActivity;-><init>()V", EntryPointKind::Activity),
+                ("Lcom/example/entry/MainActivity;->onClick(Landroid/view/View;)V", EntryPointKind::UiListener),
+                ("Lcom/example/entry/MainActivity;->onCreate(Landroid/os/Bundle;)V", EntryPointKind::Activity),
+                ("Lcom/example/entry/Worker;->run()V", EntryPointKind::Runnable),
+            ]);
+            assert_eq!(entry_points.roots().len(), 6);
+            assert_eq!(entry_points.of_kind(EntryPointKind::Activity).len(), 4);
+
+            // Plain Java class: nothing is called by the Android framework
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexParserTest.dex"));
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+            assert!(context.get_entry_points().entry_points().is_empty());
+        }
+
+        #[test]
+        fn test_parse_carved_dex() {
+            let mut dump = vec![0u8; 64];
+            dump.extend(fs::read("test_files/_int.dex").unwrap());
+            dump.extend([0xff; 16]);
+
+            let carved = DexContext::parse_carved_dex(&dump);
+            assert_eq!(carved.len(), 1);
+            assert_eq!(carved[0].0, 64);
+            assert_eq!(carved[0].1.get_number_of_strings(), 27);
+
+            assert!(DexContext::parse_dex_bytes(b"not a dex").is_none());
+        }
+
+        #[test]
+        fn test_dead_code_report() {
+            let context = DexContext::parse_dex(&PathBuf::from("test_files/DexParserTest.dex"));
+            context.disassemble_dex();
+            context.create_dex_analysis(true);
+            context.analyze_classes();
+
+            // Without entry points nothing is reachable
+            let report = context.get_dead_code_report(&[]);
+            assert_eq!(report.total_classes(), 1);
+            assert_eq!(report.total_methods(), 4);
+            assert_eq!(report.unreachable_methods().len(), 4);
+            assert_eq!(report.unreachable_classes(), &["LDexParserTest;"]);
+            assert!(report.unreferenced_methods().contains(&String::from("LDexParserTest;->main([Ljava/lang/String;)V")));
+            assert!(!report.unreferenced_methods().contains(&String::from("LDexParserTest;->printMessage()V")));
+
+            let keep_rules = KeepRule::parse_rules(
+                "-keepclasseswithmembers class * { public static void main(java.lang.String[]); }"
+            );
+            let report = context.get_dead_code_report(&keep_rules);
+            assert!(report.unreachable_methods().is_empty());
+            assert!(report.unreachable_classes().is_empty());
+        }
+
         #[test]
         fn test_get_analyzed_method() {
             let context = DexContext::parse_dex(&PathBuf::from("test_files/DexParserTest.dex"));
@@ -1126,4 +2194,154 @@ mod tests {
             assert_eq!(method_analysis.method_string(), method_analysis_by_hdvmmethod.method_string());
         }
     }
+
+    mod apk {
+        use super::super::*;
+
+        use std::path::PathBuf;
+
+        use crate::signature::SignatureScheme;
+
+        #[test]
+        fn test_apk_entries() {
+            let context = ApkContext::parse_apk(&PathBuf::from("test_files/test_resources.apk"), false);
+
+            let names = context.get_entries_from_apk()
+                .iter()
+                .map(|entry| entry.name())
+                .collect::<Vec<&str>>();
+            assert_eq!(names, vec!["AndroidManifest.xml", "classes.dex", "resources.arsc"]);
+
+            let entry = context.get_entry_from_apk("resources.arsc").unwrap();
+            assert!(!entry.is_compressed());
+            assert_eq!(entry.compressed_size(), entry.uncompressed_size());
+
+            let data = context.read_entry_from_apk("classes.dex").unwrap();
+            assert_eq!(data, std::fs::read("test_files/DexParserTest.dex").unwrap());
+            assert!(context.read_entry_from_apk("assets/missing").is_none());
+
+            assert_eq!(context.get_entries_with_prefix_from_apk("classes").len(), 1);
+        }
+
+        #[test]
+        fn test_apk_manifest_and_resources() {
+            let context = ApkContext::parse_apk(&PathBuf::from("test_files/test_resources.apk"), false);
+
+            let manifest = context.get_android_manifest().unwrap();
+            assert_eq!(manifest.package(), "com.example.test");
+
+            let resources = context.get_resource_table().unwrap();
+            assert_eq!(resources.get_name(0x7f020001).unwrap().to_java(), "R.string.api_key");
+
+            let context = ApkContext::parse_apk(&PathBuf::from("test_files/test_zip.apk"), false);
+            assert!(context.get_android_manifest().is_none());
+            assert!(context.get_resource_table().is_none());
+        }
+
+        #[test]
+        fn test_loaded_dex_files() {
+            let context = ApkContext::parse_apk(&PathBuf::from("test_files/test_multidex.apk"), false);
+
+            // assets/shadow.dex is not loaded, classes4.dex follows a missing classes3.dex
+            let mut names = context.dex_files.keys().map(String::as_str).collect::<Vec<&str>>();
+            names.sort_unstable();
+            assert_eq!(names, vec!["classes.dex", "classes2.dex", "classes4.dex"]);
+            assert_eq!(context.loaded_dex_files().len(), 2);
+
+            let defining = context.dex_file_defining("Lcom/example/fields/Obfuscated;").unwrap();
+            assert!(std::ptr::eq(defining, &context.dex_files["classes2.dex"]));
+            assert!(context.dex_file_defining("LDexParserTest;").is_none());
+        }
+
+        #[test]
+        fn test_apk_signatures() {
+            let context = ApkContext::parse_apk(&PathBuf::from("test_files/test_signed.apk"), false);
+            let signatures = context.get_signatures_from_apk();
+            assert!(signatures.is_verified());
+            assert!(signatures.has_scheme(SignatureScheme::V2));
+            assert_eq!(signatures.certificates()[0].subject(), "CN=Shuriken Test, OU=Analysis, O=Example, L=Paris, C=FR");
+
+            let context = ApkContext::parse_apk(&PathBuf::from("test_files/test_resources.apk"), false);
+            assert!(context.get_signatures_from_apk().signers().is_empty());
+        }
+
+        #[test]
+        fn test_embedded_payloads() {
+            use crate::embedded::PayloadKind;
+
+            let context = ApkContext::parse_apk(&PathBuf::from("test_files/test_packed.apk"), false);
+            let payloads = context.get_embedded_payloads_from_apk();
+            assert_eq!(payloads.len(), 6);
+            assert_eq!(payloads[3].kind(), PayloadKind::Encrypted);
+
+            let dex_files = context.get_embedded_dex_files_from_apk().unwrap();
+            assert_eq!(dex_files.contexts().len(), 3);
+
+            let dex = dex_files.get("assets/hidden.bin").unwrap();
+            assert_eq!(dex.get_number_of_strings(), 23);
+        }
+
+        #[test]
+        fn test_native_libraries() {
+            let context = ApkContext::parse_apk(&PathBuf::from("test_files/test_native.apk"), false);
+            let libraries = context.get_native_libraries_from_apk();
+            let paths = libraries.iter()
+                .map(|library| library.path())
+                .collect::<Vec<&str>>();
+            assert_eq!(paths, vec!["lib/x86_64/libnative.so", "lib/x86_64/libplain.so"]);
+
+            // DexParserTest has no native method
+            let map = context.get_jni_map_from_apk();
+            assert!(map.methods().is_empty());
+            assert_eq!(map.unmatched_exports().len(), 5);
+        }
+
+        #[test]
+        fn test_apk_bundles() {
+            use crate::bundle::{ ApkBundle, BundleFormat };
+
+            for path in ["test_files/test_bundle.apks", "test_files/test_bundle.xapk"] {
+                let bundle = ApkBundle::parse(&PathBuf::from(path), false).unwrap();
+                let names = bundle.splits()
+                    .iter()
+                    .map(|split| split.name())
+                    .collect::<Vec<&str>>();
+                assert_eq!(names, vec!["base", "config.de"]);
+
+                assert_eq!(bundle.get_android_manifest().unwrap().package(), "com.example.test");
+                assert_eq!(bundle.get_resource_table().unwrap().get_entries(0x7f020000).len(), 3);
+                assert!(bundle.get_analyzed_class("DexParserTest").is_some());
+            }
+
+            let bundle = ApkBundle::parse(&PathBuf::from("test_files/test_bundle.aab"), false).unwrap();
+            assert_eq!(bundle.format(), BundleFormat::Aab);
+            assert_eq!(bundle.split("dynamic").unwrap().dex_contexts()[0].0, "dynamic/dex/classes.dex");
+            assert!(bundle.get_resource_table().is_none());
+
+            let class_names = bundle.get_analyzed_classes()
+                .iter()
+                .map(|class| class.name().to_string())
+                .collect::<Vec<String>>();
+            assert!(class_names.contains(&String::from("DexParserTest")));
+            assert!(class_names.len() > 1);
+
+            // Fields are read from the DEX file of the module
+            let hierarchy = bundle.get_class_hierarchy();
+            let fields = hierarchy.class("DexParserTest")
+                .unwrap()
+                .fields()
+                .iter()
+                .map(|field| field.name())
+                .collect::<Vec<&str>>();
+            assert_eq!(fields, vec!["field1", "field2"]);
+
+            let split_apks = [
+                PathBuf::from("test_files/test_zip.apk"),
+                PathBuf::from("test_files/test_resources.apk")
+            ];
+            let bundle = ApkBundle::from_split_apks(&[&split_apks[0], &split_apks[1]], false).unwrap();
+            assert_eq!(bundle.format(), BundleFormat::SplitApks);
+            assert!(bundle.base().is_some());
+        }
+    }
 }
@@ -0,0 +1,355 @@
+//! Literals of the const instructions
+//!
+//! `const`, `const/high16`, `const-wide`, `const-wide/high16` and the shorter
+//! forms load raw bits into a register: the same instruction loads an `int` or
+//! a `float`, a `long` or a `double`. The type of the literal is only known
+//! from the instruction that first reads the register, so it is inferred from
+//! the instructions following the const instruction.
+
+use crate::dex_file::{ self, CodeItem, DexFile };
+use crate::semantics::{ self, ControlFlow, InstructionSemantics };
+
+/// Opcode of `const/4`
+const CONST_4: u16 = 0x12;
+/// Opcode of `const/16`
+const CONST_16: u16 = 0x13;
+/// Opcode of `const`
+const CONST: u16 = 0x14;
+/// Opcode of `const/high16`
+const CONST_HIGH16: u16 = 0x15;
+/// Opcode of `const-wide/16`
+const CONST_WIDE_16: u16 = 0x16;
+/// Opcode of `const-wide/32`
+const CONST_WIDE_32: u16 = 0x17;
+/// Opcode of `const-wide`
+const CONST_WIDE: u16 = 0x18;
+/// Opcode of `const-wide/high16`
+const CONST_WIDE_HIGH16: u16 = 0x19;
+
+/// Java type of a literal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LiteralType {
+    /// `int`, and the narrower `boolean`, `byte`, `short` and `char`
+    Int,
+    Long,
+    Float,
+    Double
+}
+
+impl LiteralType {
+    /// Get the type of a literal stored in a given type descriptor, `None`
+    /// for references
+    fn from_descriptor(descriptor: &str) -> Option<Self> {
+        match descriptor {
+            "I" | "Z" | "B" | "S" | "C" => Some(LiteralType::Int),
+            "J" => Some(LiteralType::Long),
+            "F" => Some(LiteralType::Float),
+            "D" => Some(LiteralType::Double),
+            _ => None
+        }
+    }
+}
+
+/// Value of a literal interpreted with its type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LiteralValue {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64)
+}
+
+/// Literal loaded by a const instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Literal {
+    /// Destination register, the first of the pair for wide literals
+    register: u16,
+    /// Integer value, sign-extended
+    value: i64,
+    wide: bool,
+    inferred_type: Option<LiteralType>
+}
+
+impl Literal {
+    /// Decode the literal of a const instruction given its address in bytes,
+    /// `None` for other instructions
+    pub(crate) fn read(dex: &DexFile, code: &CodeItem, address: u64) -> Option<Self> {
+        Self::parse(&semantics::code_units(dex, code, address))
+    }
+
+    /// Decode the literal of the code units of a const instruction
+    fn parse(units: &[u16; 5]) -> Option<Self> {
+        let op = units[0] & 0xff;
+        let aa = units[0] >> 8;
+        let high32 = |low: u16, high: u16| (low as u32 | (high as u32) << 16) as i32 as i64;
+
+        let (register, value) = match op {
+            CONST_4 => (aa & 0xf, ((units[0] as i16) >> 12) as i64),
+            CONST_16 | CONST_WIDE_16 => (aa, units[1] as i16 as i64),
+            CONST | CONST_WIDE_32 => (aa, high32(units[1], units[2])),
+            CONST_HIGH16 => (aa, ((units[1] as i32) << 16) as i64),
+            CONST_WIDE => (aa, (high32(units[1], units[2]) as u32 as u64 | (high32(units[3], units[4]) as u64) << 32) as i64),
+            CONST_WIDE_HIGH16 => (aa, ((units[1] as u64) << 48) as i64),
+            _ => return None
+        };
+        let wide = (CONST_WIDE_16..=CONST_WIDE_HIGH16).contains(&op);
+
+        Some(Self { register, value, wide, inferred_type: None })
+    }
+
+    /// Infer the type of the literal from the first instruction reading it,
+    /// following the addresses of the next instructions in order
+    ///
+    /// The inference stops at the first jump, or when the register is
+    /// overwritten or read by an instruction not telling its type.
+    pub(crate) fn infer_type(
+        &mut self,
+        dex: &DexFile,
+        code: &CodeItem,
+        following: impl IntoIterator<Item = u64>,
+        return_type: &str
+    ) {
+        let width = if self.wide { 2 } else { 1 };
+        let overlaps = |register: u16, other_width: u16| {
+            registers_overlap((self.register, width), (register, other_width))
+        };
+
+        for address in following {
+            let units = semantics::code_units(dex, code, address);
+
+            let reads = typed_reads(dex, &units, return_type);
+            if let Some((_, literal_type)) = reads.iter().find(|(register, _)| overlaps(*register, 1)) {
+                self.inferred_type = literal_type.filter(|literal_type| {
+                    matches!(literal_type, LiteralType::Long | LiteralType::Double) == self.wide
+                });
+                return;
+            }
+
+            let semantics = InstructionSemantics::decode(&units, address);
+            let overwritten = semantics.writes().iter().any(|register| overlaps(*register, 1));
+            if overwritten || !matches!(semantics.control_flow(), ControlFlow::FallThrough | ControlFlow::Branch(_)) {
+                return;
+            }
+        }
+    }
+
+    /// Return the destination register, the first of the pair for wide
+    /// literals
+    pub fn register(&self) -> u16 {
+        self.register
+    }
+
+    /// Whether the literal is a `long` or a `double` stored in a register pair
+    pub fn is_wide(&self) -> bool {
+        self.wide
+    }
+
+    /// Return the integer value, sign-extended: an `int` for narrow literals
+    /// and a `long` for wide literals
+    pub fn int_value(&self) -> i64 {
+        self.value
+    }
+
+    /// Get the bits of a narrow literal interpreted as an IEEE-754 `float`
+    pub fn float_value(&self) -> Option<f32> {
+        (!self.wide).then(|| f32::from_bits(self.value as u32))
+    }
+
+    /// Get the bits of a wide literal interpreted as an IEEE-754 `double`
+    pub fn double_value(&self) -> Option<f64> {
+        self.wide.then(|| f64::from_bits(self.value as u64))
+    }
+
+    /// Return the type of the literal inferred from its first use, if known
+    pub fn inferred_type(&self) -> Option<LiteralType> {
+        self.inferred_type
+    }
+
+    /// Get the value of the literal with its inferred type, as an `int` or a
+    /// `long` if the type is unknown
+    pub fn value(&self) -> LiteralValue {
+        match (self.inferred_type, self.wide) {
+            (Some(LiteralType::Float), false) => LiteralValue::Float(f32::from_bits(self.value as u32)),
+            (Some(LiteralType::Double), true) => LiteralValue::Double(f64::from_bits(self.value as u64)),
+            (_, false) => LiteralValue::Int(self.value as i32),
+            (_, true) => LiteralValue::Long(self.value)
+        }
+    }
+}
+
+/// Get the registers read by an instruction holding a primitive value, with
+/// the type of the value when the instruction tells it
+fn typed_reads(dex: &DexFile, units: &[u16; 5], return_type: &str) -> Vec<(u16, Option<LiteralType>)> {
+    use LiteralType::*;
+
+    let op = units[0] & 0xff;
+    let a = (units[0] >> 8) & 0xf;
+    let b = units[0] >> 12;
+    let aa = units[0] >> 8;
+    let bb = units[1] & 0xff;
+    let cc = units[1] >> 8;
+
+    let field_type = || dex.field_id(units[1] as u32).and_then(|(_, _, field_type)| LiteralType::from_descriptor(&field_type));
+
+    match op {
+        // move, move/from16, move/16 and their wide versions
+        0x01 | 0x04 => vec![(b, None)],
+        0x02 | 0x05 => vec![(units[1], None)],
+        0x03 | 0x06 => vec![(units[2], None)],
+        // return and return-wide
+        0x0f | 0x10 => vec![(aa, LiteralType::from_descriptor(return_type))],
+        // cmp-kind
+        0x2d | 0x2e => vec![(bb, Some(Float)), (cc, Some(Float))],
+        0x2f | 0x30 => vec![(bb, Some(Double)), (cc, Some(Double))],
+        0x31 => vec![(bb, Some(Long)), (cc, Some(Long))],
+        // if-test and if-testz, packed-switch and sparse-switch, new-array
+        0x32..=0x37 => vec![(a, Some(Int)), (b, Some(Int))],
+        0x38..=0x3d | 0x2b | 0x2c => vec![(aa, Some(Int))],
+        0x23 => vec![(b, Some(Int))],
+        // aget, and aput whose value is not typed for int and float arrays
+        0x44..=0x4a => vec![(cc, Some(Int))],
+        0x4b | 0x4c => vec![(aa, None), (cc, Some(Int))],
+        0x4e..=0x51 => vec![(aa, Some(Int)), (cc, Some(Int))],
+        0x4d => vec![(cc, Some(Int))],
+        // iput and sput
+        0x59 | 0x5a => vec![(a, field_type())],
+        0x5c..=0x5f => vec![(a, Some(Int))],
+        0x67 | 0x68 => vec![(aa, field_type())],
+        0x6a..=0x6d => vec![(aa, Some(Int))],
+        // invoke-kind and invoke-kind/range
+        0x6e..=0x72 => {
+            let registers = [units[2] & 0xf, (units[2] >> 4) & 0xf, (units[2] >> 8) & 0xf, units[2] >> 12, a];
+            invoke_reads(dex, op == 0x71, units[1] as u32, &registers[..(b as usize).min(5)])
+        },
+        0x74..=0x78 => {
+            let registers = (0..aa).map(|n| units[2].wrapping_add(n)).collect::<Vec<u16>>();
+            invoke_reads(dex, op == 0x77, units[1] as u32, &registers)
+        },
+        // unop
+        0x7b..=0x8f => vec![(b, Some(match op {
+            0x7d | 0x7e | 0x84..=0x86 => Long,
+            0x7f | 0x87..=0x89 => Float,
+            0x80 | 0x8a..=0x8c => Double,
+            _ => Int
+        }))],
+        // binop, the shift distance of longs being an int
+        0x90..=0xaf => {
+            let operand = binop_type(op - 0x90);
+            let shift = if (0xa3..=0xa5).contains(&op) { Int } else { operand };
+            vec![(bb, Some(operand)), (cc, Some(shift))]
+        },
+        0xb0..=0xcf => {
+            let operand = binop_type(op - 0xb0);
+            let shift = if (0xc3..=0xc5).contains(&op) { Int } else { operand };
+            vec![(a, Some(operand)), (b, Some(shift))]
+        },
+        // binop/lit16 and binop/lit8
+        0xd0..=0xd7 => vec![(b, Some(Int))],
+        0xd8..=0xe2 => vec![(bb, Some(Int))],
+        _ => Vec::new()
+    }
+}
+
+/// Get the type of the operands of a binop given its offset from `add-int`
+fn binop_type(offset: u16) -> LiteralType {
+    match offset {
+        0x00..=0x0a => LiteralType::Int,
+        0x0b..=0x15 => LiteralType::Long,
+        0x16..=0x1a => LiteralType::Float,
+        _ => LiteralType::Double
+    }
+}
+
+/// Get the registers passed to an invoke with the type of their parameter
+fn invoke_reads(dex: &DexFile, is_static: bool, method_idx: u32, registers: &[u16]) -> Vec<(u16, Option<LiteralType>)> {
+    let parameters = dex.method_full_name(method_idx)
+        .and_then(|full_name| dex_file::split_method_descriptor(dex_file::split_method_name(&full_name)?.2))
+        .map(|(parameters, _)| parameters)
+        .unwrap_or_default();
+
+    let mut reads = Vec::new();
+    let mut registers = registers.iter();
+    if !is_static {
+        registers.next();
+    }
+
+    for parameter in parameters {
+        let Some(register) = registers.next() else {
+            break;
+        };
+        let literal_type = LiteralType::from_descriptor(&parameter);
+        reads.push((*register, literal_type));
+
+        if matches!(literal_type, Some(LiteralType::Long | LiteralType::Double)) {
+            registers.next();
+        }
+    }
+
+    reads
+}
+
+/// Whether two ranges of registers, given by their first register and their
+/// width, share a register
+fn registers_overlap((first, width): (u16, u16), (other, other_width): (u16, u16)) -> bool {
+    let end = |register: u16, width: u16| register as u32 + width as u32;
+    (other as u32) < end(first, width) && (first as u32) < end(other, other_width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(units: &[u16]) -> Literal {
+        let mut padded = [0; 5];
+        padded[..units.len()].copy_from_slice(units);
+        Literal::parse(&padded).unwrap()
+    }
+
+    #[test]
+    fn test_parse_literals() {
+        let literal = parse(&[0xf112]);
+        assert_eq!((literal.register(), literal.int_value()), (1, -1));
+
+        let literal = parse(&[0x0115, 0x3f80]);
+        assert_eq!(literal.int_value(), 0x3f800000);
+        assert_eq!(literal.float_value(), Some(1.0));
+        assert_eq!(literal.double_value(), None);
+
+        let literal = parse(&[0x0214, 0xffff, 0x7fff]);
+        assert_eq!(literal.int_value(), i32::MAX as i64);
+
+        let literal = parse(&[0x0219, 0x4000]);
+        assert!(literal.is_wide());
+        assert_eq!(literal.double_value(), Some(2.0));
+
+        let literal = parse(&[0x0818, 0xa9fc, 0xd2f1, 0x624d, 0x3f50]);
+        assert_eq!((literal.register(), literal.double_value()), (8, Some(0.001)));
+
+        let literal = parse(&[0x0716, 0xfff8]);
+        assert_eq!(literal.int_value(), -8);
+
+        assert!(Literal::parse(&[0x000e, 0, 0, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn test_literal_value() {
+        let mut literal = parse(&[0x0115, 0x4040]);
+        assert_eq!(literal.value(), LiteralValue::Int(0x40400000));
+
+        literal.inferred_type = Some(LiteralType::Float);
+        assert_eq!(literal.value(), LiteralValue::Float(3.0));
+
+        let mut literal = parse(&[0x0216, 0x000a]);
+        literal.inferred_type = Some(LiteralType::Long);
+        assert_eq!(literal.value(), LiteralValue::Long(10));
+    }
+
+    #[test]
+    fn test_registers_overlap() {
+        assert!(registers_overlap((1, 2), (2, 1)));
+        assert!(!registers_overlap((1, 2), (3, 1)));
+        assert!(!registers_overlap((1, 1), (0, 1)));
+        assert!(registers_overlap((u16::MAX - 1, 2), (u16::MAX, 1)));
+        assert!(!registers_overlap((1, 2), (u16::MAX, 1)));
+    }
+}
@@ -0,0 +1,519 @@
+//! Android manifest
+//!
+//! Typed view of the binary `AndroidManifest.xml` of an APK: package,
+//! versions, SDK levels, permissions and the components with their intent
+//! filters. The full decoded document remains available through
+//! [`AndroidManifest::document`].
+
+use crate::axml::{ XmlDocument, XmlElement, XmlValue };
+
+/// Kind of application component
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComponentKind {
+    /// `<activity>`
+    Activity,
+    /// `<activity-alias>`
+    ActivityAlias,
+    /// `<service>`
+    Service,
+    /// `<receiver>`
+    Receiver,
+    /// `<provider>`
+    Provider
+}
+
+impl ComponentKind {
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "activity" => Some(ComponentKind::Activity),
+            "activity-alias" => Some(ComponentKind::ActivityAlias),
+            "service" => Some(ComponentKind::Service),
+            "receiver" => Some(ComponentKind::Receiver),
+            "provider" => Some(ComponentKind::Provider),
+            _ => None
+        }
+    }
+}
+
+/// `<data>` element of an intent filter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntentData {
+    scheme: Option<String>,
+    host: Option<String>,
+    port: Option<String>,
+    path: Option<String>,
+    path_prefix: Option<String>,
+    path_pattern: Option<String>,
+    mime_type: Option<String>
+}
+
+impl IntentData {
+    fn from_element(element: &XmlElement) -> Self {
+        let string = |name: &str| element.attribute(name).map(|value| value.to_string());
+
+        Self {
+            scheme: string("scheme"),
+            host: string("host"),
+            port: string("port"),
+            path: string("path"),
+            path_prefix: string("pathPrefix"),
+            path_pattern: string("pathPattern"),
+            mime_type: string("mimeType")
+        }
+    }
+
+    /// Return a reference to the URI scheme
+    pub fn scheme(&self) -> Option<&str> {
+        self.scheme.as_deref()
+    }
+
+    /// Return a reference to the URI host
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// Return a reference to the URI port
+    pub fn port(&self) -> Option<&str> {
+        self.port.as_deref()
+    }
+
+    /// Return a reference to the URI path
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Return a reference to the URI path prefix
+    pub fn path_prefix(&self) -> Option<&str> {
+        self.path_prefix.as_deref()
+    }
+
+    /// Return a reference to the URI path pattern
+    pub fn path_pattern(&self) -> Option<&str> {
+        self.path_pattern.as_deref()
+    }
+
+    /// Return a reference to the MIME type
+    pub fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+}
+
+/// Intent filter of a component
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntentFilter {
+    actions: Vec<String>,
+    categories: Vec<String>,
+    data: Vec<IntentData>,
+    priority: Option<i64>
+}
+
+impl IntentFilter {
+    fn from_element(element: &XmlElement) -> Self {
+        let names = |tag: &str| element.children_named(tag)
+            .filter_map(|child| child.attribute("name"))
+            .map(|name| name.to_string())
+            .collect();
+
+        Self {
+            actions: names("action"),
+            categories: names("category"),
+            data: element.children_named("data")
+                .map(IntentData::from_element)
+                .collect(),
+            priority: element.attribute("priority")
+                .and_then(|priority| priority.as_int())
+        }
+    }
+
+    /// Return a reference to the actions
+    pub fn actions(&self) -> &[String] {
+        &self.actions
+    }
+
+    /// Return a reference to the categories
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    /// Return a reference to the data specifications
+    pub fn data(&self) -> &[IntentData] {
+        &self.data
+    }
+
+    /// Return the priority of the filter
+    pub fn priority(&self) -> Option<i64> {
+        self.priority
+    }
+}
+
+/// Activity, service, receiver or provider declared in the manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    kind: ComponentKind,
+    /// Fully qualified class name
+    name: String,
+    /// Value of `android:exported`, if set
+    exported: Option<bool>,
+    /// Value of `android:enabled`, if set
+    enabled: Option<bool>,
+    /// Permission required to interact with the component
+    permission: Option<String>,
+    /// Authorities of a provider
+    authorities: Vec<String>,
+    /// Activity an alias points to
+    target_activity: Option<String>,
+    intent_filters: Vec<IntentFilter>
+}
+
+impl Component {
+    fn from_element(kind: ComponentKind, element: &XmlElement, package: &str) -> Self {
+        let class_name = |name: &str| element.attribute(name)
+            .map(|value| resolve_class_name(package, &value.to_string()));
+
+        Self {
+            kind,
+            name: class_name("name").unwrap_or_default(),
+            exported: element.attribute("exported").and_then(|value| value.as_bool()),
+            enabled: element.attribute("enabled").and_then(|value| value.as_bool()),
+            permission: element.attribute("permission").map(|value| value.to_string()),
+            authorities: element.attribute("authorities")
+                .map(|value| value.to_string()
+                    .split(';')
+                    .map(String::from)
+                    .collect())
+                .unwrap_or_default(),
+            target_activity: class_name("targetActivity"),
+            intent_filters: element.children_named("intent-filter")
+                .map(IntentFilter::from_element)
+                .collect()
+        }
+    }
+
+    /// Return the kind of component
+    pub fn kind(&self) -> ComponentKind {
+        self.kind
+    }
+
+    /// Return a reference to the fully qualified class name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the value of `android:exported`, if set
+    pub fn exported(&self) -> Option<bool> {
+        self.exported
+    }
+
+    /// Whether other applications can reach the component
+    ///
+    /// Without an explicit `android:exported`, components with an intent
+    /// filter are exported, as on Android versions before 12.
+    pub fn is_exported(&self) -> bool {
+        self.exported.unwrap_or(!self.intent_filters.is_empty())
+    }
+
+    /// Return the value of `android:enabled`, if set
+    pub fn enabled(&self) -> Option<bool> {
+        self.enabled
+    }
+
+    /// Return a reference to the permission required to interact with the component
+    pub fn permission(&self) -> Option<&str> {
+        self.permission.as_deref()
+    }
+
+    /// Return a reference to the authorities of a provider
+    pub fn authorities(&self) -> &[String] {
+        &self.authorities
+    }
+
+    /// Return a reference to the activity an alias points to
+    pub fn target_activity(&self) -> Option<&str> {
+        self.target_activity.as_deref()
+    }
+
+    /// Return a reference to the intent filters
+    pub fn intent_filters(&self) -> &[IntentFilter] {
+        &self.intent_filters
+    }
+}
+
+/// Decoded `AndroidManifest.xml`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AndroidManifest {
+    package: String,
+    /// Name of the split, `None` for base APKs
+    split_name: Option<String>,
+    version_code: Option<i64>,
+    version_name: Option<String>,
+    min_sdk_version: Option<i64>,
+    target_sdk_version: Option<i64>,
+    max_sdk_version: Option<i64>,
+    /// Permissions requested with `<uses-permission>`
+    permissions: Vec<String>,
+    /// Permissions defined with `<permission>`
+    declared_permissions: Vec<String>,
+    /// Class name of the `Application`
+    application_name: Option<String>,
+    debuggable: bool,
+    allow_backup: Option<bool>,
+    components: Vec<Component>,
+    document: XmlDocument
+}
+
+impl AndroidManifest {
+    /// Decode a binary `AndroidManifest.xml`
+    ///
+    /// Returns `None` if the data is not binary XML or if the root element is
+    /// not `<manifest>`.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        Self::from_document(XmlDocument::parse(data)?)
+    }
+
+    /// Build the manifest from a decoded document
+    pub fn from_document(document: XmlDocument) -> Option<Self> {
+        let root = document.root();
+        if root.name() != "manifest" {
+            return None;
+        }
+
+        let package = root.attribute("package")
+            .map(|package| package.to_string())
+            .unwrap_or_default();
+
+        let uses_sdk = root.children_named("uses-sdk").next();
+        let sdk_version = |name: &str| uses_sdk
+            .and_then(|uses_sdk| uses_sdk.attribute(name))
+            .and_then(|version| version.as_int());
+
+        let names = |tags: &[&str]| root.children()
+            .iter()
+            .filter(|child| tags.contains(&child.name()))
+            .filter_map(|child| child.attribute("name"))
+            .map(|name| name.to_string())
+            .collect::<Vec<String>>();
+
+        let application = root.children_named("application").next();
+        let components = application
+            .map(|application| application.children()
+                .iter()
+                .filter_map(|child| ComponentKind::from_tag(child.name())
+                    .map(|kind| Component::from_element(kind, child, &package)))
+                .collect())
+            .unwrap_or_default();
+
+        Some(Self {
+            split_name: root.attribute("split").map(|split| split.to_string()),
+            version_code: root.attribute("versionCode").and_then(|code| code.as_int()),
+            version_name: root.attribute("versionName").map(|name| name.to_string()),
+            min_sdk_version: sdk_version("minSdkVersion"),
+            target_sdk_version: sdk_version("targetSdkVersion"),
+            max_sdk_version: sdk_version("maxSdkVersion"),
+            permissions: names(&["uses-permission", "uses-permission-sdk-23"]),
+            declared_permissions: names(&["permission"]),
+            application_name: application
+                .and_then(|application| application.attribute("name"))
+                .map(|name| resolve_class_name(&package, &name.to_string())),
+            debuggable: application
+                .and_then(|application| application.attribute("debuggable"))
+                .and_then(XmlValue::as_bool)
+                .unwrap_or(false),
+            allow_backup: application
+                .and_then(|application| application.attribute("allowBackup"))
+                .and_then(XmlValue::as_bool),
+            package,
+            components,
+            document
+        })
+    }
+
+    /// Return a reference to the package name
+    pub fn package(&self) -> &str {
+        &self.package
+    }
+
+    /// Return a reference to the name of the split, such as `config.arm64_v8a`
+    ///
+    /// `None` for base APKs.
+    pub fn split_name(&self) -> Option<&str> {
+        self.split_name.as_deref()
+    }
+
+    /// Return the version code
+    pub fn version_code(&self) -> Option<i64> {
+        self.version_code
+    }
+
+    /// Return a reference to the version name
+    ///
+    /// References to string resources are returned as `@0x7f...`.
+    pub fn version_name(&self) -> Option<&str> {
+        self.version_name.as_deref()
+    }
+
+    /// Return the minimum SDK version
+    pub fn min_sdk_version(&self) -> Option<i64> {
+        self.min_sdk_version
+    }
+
+    /// Return the target SDK version
+    pub fn target_sdk_version(&self) -> Option<i64> {
+        self.target_sdk_version
+    }
+
+    /// Return the maximum SDK version
+    pub fn max_sdk_version(&self) -> Option<i64> {
+        self.max_sdk_version
+    }
+
+    /// Return a reference to the requested permissions
+    pub fn permissions(&self) -> &[String] {
+        &self.permissions
+    }
+
+    /// Return a reference to the permissions defined by the application
+    pub fn declared_permissions(&self) -> &[String] {
+        &self.declared_permissions
+    }
+
+    /// Return a reference to the class name of the `Application`
+    pub fn application_name(&self) -> Option<&str> {
+        self.application_name.as_deref()
+    }
+
+    /// Return whether the application is debuggable
+    pub fn is_debuggable(&self) -> bool {
+        self.debuggable
+    }
+
+    /// Return the value of `android:allowBackup`, if set
+    pub fn allow_backup(&self) -> Option<bool> {
+        self.allow_backup
+    }
+
+    /// Return a reference to every component
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    /// Get the components of a given kind
+    pub fn components_of_kind(&self, kind: ComponentKind) -> Vec<&Component> {
+        self.components
+            .iter()
+            .filter(|component| component.kind == kind)
+            .collect()
+    }
+
+    /// Get the activities, aliases included
+    pub fn activities(&self) -> Vec<&Component> {
+        self.components
+            .iter()
+            .filter(|component| matches!(component.kind, ComponentKind::Activity | ComponentKind::ActivityAlias))
+            .collect()
+    }
+
+    /// Get the services
+    pub fn services(&self) -> Vec<&Component> {
+        self.components_of_kind(ComponentKind::Service)
+    }
+
+    /// Get the broadcast receivers
+    pub fn receivers(&self) -> Vec<&Component> {
+        self.components_of_kind(ComponentKind::Receiver)
+    }
+
+    /// Get the content providers
+    pub fn providers(&self) -> Vec<&Component> {
+        self.components_of_kind(ComponentKind::Provider)
+    }
+
+    /// Get the exported components
+    pub fn exported_components(&self) -> Vec<&Component> {
+        self.components
+            .iter()
+            .filter(|component| component.is_exported())
+            .collect()
+    }
+
+    /// Return a reference to the decoded XML document
+    pub fn document(&self) -> &XmlDocument {
+        &self.document
+    }
+}
+
+/// Resolve a class name relative to the package (`.Foo` or `Foo`)
+fn resolve_class_name(package: &str, name: &str) -> String {
+    if name.starts_with('.') {
+        format!("{package}{name}")
+    } else if !name.contains('.') && !package.is_empty() {
+        format!("{package}.{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use crate::zip_file::ZipArchive;
+
+    #[test]
+    fn test_parse_manifest() {
+        let archive = ZipArchive::from_path(Path::new("test_files/test_manifest.apk")).unwrap();
+        let data = archive.read_by_name("AndroidManifest.xml").unwrap();
+        let manifest = AndroidManifest::parse(&data).unwrap();
+
+        assert_eq!(manifest.package(), "com.example.test");
+        assert_eq!(manifest.version_code(), Some(42));
+        assert_eq!(manifest.version_name(), Some("1.2.3"));
+        assert_eq!(manifest.min_sdk_version(), Some(21));
+        assert_eq!(manifest.target_sdk_version(), Some(33));
+        assert_eq!(manifest.max_sdk_version(), None);
+        assert_eq!(manifest.permissions(), &[
+            "android.permission.INTERNET",
+            "android.permission.RECEIVE_SMS"
+        ]);
+        assert_eq!(manifest.declared_permissions(), &["com.example.test.permission.C2D"]);
+        assert_eq!(manifest.application_name(), Some("com.example.test.App"));
+        assert!(manifest.is_debuggable());
+        assert_eq!(manifest.allow_backup(), Some(false));
+
+        let activities = manifest.activities();
+        assert_eq!(activities.len(), 1);
+        assert_eq!(activities[0].name(), "com.example.test.MainActivity");
+        assert!(activities[0].is_exported());
+        let filter = &activities[0].intent_filters()[0];
+        assert_eq!(filter.actions(), &["android.intent.action.MAIN"]);
+        assert_eq!(filter.categories(), &["android.intent.category.LAUNCHER"]);
+        assert_eq!(filter.data()[0].scheme(), Some("https"));
+        assert_eq!(filter.data()[0].host(), Some("example.com"));
+
+        let services = manifest.services();
+        assert_eq!(services[0].name(), "com.example.test.SyncService");
+        assert_eq!(services[0].exported(), Some(false));
+        assert!(!services[0].is_exported());
+
+        let receivers = manifest.receivers();
+        assert_eq!(receivers[0].name(), "com.other.SmsReceiver");
+        assert_eq!(receivers[0].intent_filters()[0].priority(), Some(999));
+        assert!(receivers[0].is_exported());
+
+        let providers = manifest.providers();
+        assert_eq!(providers[0].authorities(), &["com.example.test.files"]);
+        assert_eq!(providers[0].permission(), Some("com.example.test.permission.C2D"));
+
+        assert_eq!(manifest.exported_components().len(), 2);
+
+        let xml = manifest.document().to_string();
+        assert!(xml.contains("<manifest xmlns:android=\"http://schemas.android.com/apk/res/android\""));
+        assert!(xml.contains("android:versionCode=\"42\""));
+    }
+
+    #[test]
+    fn test_resolve_class_name() {
+        assert_eq!(resolve_class_name("com.example", ".Main"), "com.example.Main");
+        assert_eq!(resolve_class_name("com.example", "Main"), "com.example.Main");
+        assert_eq!(resolve_class_name("com.example", "org.other.Main"), "org.other.Main");
+    }
+}
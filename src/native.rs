@@ -0,0 +1,588 @@
+//! Native libraries and JNI bindings
+//!
+//! Inventory of the `lib/<abi>/*.so` libraries of an APK from their ELF
+//! headers and dynamic symbols, and map of the Java `native` methods to their
+//! implementation:
+//!
+//! * statically bound methods are exported under their JNI name,
+//!   `Java_<class>_<method>` or `Java_<class>_<method>__<parameters>` for
+//!   overloads;
+//! * methods without an exported symbol are likely registered with
+//!   `RegisterNatives` when a library exports `JNI_OnLoad`;
+//! * the other ones are missing an implementation.
+//!
+//! Only little-endian ELF files are supported, like every Android ABI.
+
+use crate::dex_file::{ Reader, split_method_descriptor, to_descriptor };
+use crate::dvm_access_flags::DvmAccessFlag;
+use crate::hierarchy::ClassHierarchy;
+
+/// Section holding the symbol table
+const SHT_SYMTAB: u32 = 2;
+/// Section holding the dynamic linking information
+const SHT_DYNAMIC: u32 = 6;
+/// Section holding the dynamic symbols
+const SHT_DYNSYM: u32 = 11;
+/// Dynamic entry naming a needed library
+const DT_NEEDED: u64 = 1;
+/// Name of the function called when a library is loaded by the VM
+const JNI_ONLOAD: &str = "JNI_OnLoad";
+
+/// Section header of an ELF file
+struct Section {
+    kind: u32,
+    offset: usize,
+    size: usize,
+    /// Index of the associated string table
+    link: usize,
+    entry_size: usize
+}
+
+/// Native library of an APK
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeLibrary {
+    /// Path of the library in the APK
+    path: String,
+    /// ABI from the path of the library, like `arm64-v8a`
+    abi: Option<String>,
+    /// Architecture from the ELF header
+    machine: String,
+    is_64_bit: bool,
+    /// Libraries in the `DT_NEEDED` entries
+    needed: Vec<String>,
+    /// Defined global and weak symbols
+    exports: Vec<String>,
+    /// Undefined symbols
+    imports: Vec<String>
+}
+
+impl NativeLibrary {
+    /// Parse the ELF header and the symbols of a library
+    ///
+    /// Returns `None` if the data is not a little-endian ELF file. Libraries
+    /// whose section headers were stripped are returned without symbols.
+    pub fn parse(path: &str, data: &[u8]) -> Option<Self> {
+        if !data.starts_with(b"\x7fELF") || data.get(5) != Some(&1) {
+            return None;
+        }
+
+        let is_64_bit = *data.get(4)? == 2;
+        let machine = match Reader::new(data, 18).read_u16()? {
+            3 => "x86",
+            8 => "MIPS",
+            40 => "ARM",
+            62 => "x86-64",
+            183 => "AArch64",
+            243 => "RISC-V",
+            _ => "unknown"
+        };
+
+        let abi = path.strip_prefix("lib/")
+            .and_then(|rest| rest.split_once('/'))
+            .map(|(abi, _)| abi.to_string());
+
+        let mut library = Self {
+            path: path.to_string(),
+            abi,
+            machine: machine.to_string(),
+            is_64_bit,
+            needed: Vec::new(),
+            exports: Vec::new(),
+            imports: Vec::new()
+        };
+
+        let sections = Self::read_sections(data, is_64_bit).unwrap_or_default();
+
+        let symbols = sections.iter()
+            .find(|section| section.kind == SHT_DYNSYM)
+            .or_else(|| sections.iter().find(|section| section.kind == SHT_SYMTAB));
+        if let Some(symbols) = symbols {
+            library.read_symbols(data, symbols, sections.get(symbols.link), is_64_bit);
+        }
+
+        if let Some(dynamic) = sections.iter().find(|section| section.kind == SHT_DYNAMIC) {
+            library.read_needed(data, dynamic, sections.get(dynamic.link), is_64_bit);
+        }
+
+        Some(library)
+    }
+
+    fn read_sections(data: &[u8], is_64_bit: bool) -> Option<Vec<Section>> {
+        let (offset, header_size, count) = if is_64_bit {
+            let offset = Reader::new(data, 0x28).read_u64()? as usize;
+            let mut reader = Reader::new(data, 0x3a);
+            (offset, reader.read_u16()? as usize, reader.read_u16()? as usize)
+        } else {
+            let offset = Reader::new(data, 0x20).read_u32()? as usize;
+            let mut reader = Reader::new(data, 0x2e);
+            (offset, reader.read_u16()? as usize, reader.read_u16()? as usize)
+        };
+
+        (0..count)
+            .map(|idx| {
+                let mut reader = Reader::new(data, offset.checked_add(idx * header_size)?);
+                let _name = reader.read_u32()?;
+                let kind = reader.read_u32()?;
+
+                if is_64_bit {
+                    let _flags = reader.read_u64()?;
+                    let _address = reader.read_u64()?;
+                    let offset = reader.read_u64()? as usize;
+                    let size = reader.read_u64()? as usize;
+                    let link = reader.read_u32()? as usize;
+                    let _info = reader.read_u32()?;
+                    let _align = reader.read_u64()?;
+                    let entry_size = reader.read_u64()? as usize;
+                    Some(Section { kind, offset, size, link, entry_size })
+                } else {
+                    let _flags = reader.read_u32()?;
+                    let _address = reader.read_u32()?;
+                    let offset = reader.read_u32()? as usize;
+                    let size = reader.read_u32()? as usize;
+                    let link = reader.read_u32()? as usize;
+                    let _info = reader.read_u32()?;
+                    let _align = reader.read_u32()?;
+                    let entry_size = reader.read_u32()? as usize;
+                    Some(Section { kind, offset, size, link, entry_size })
+                }
+            })
+            .collect()
+    }
+
+    /// Read a null-terminated string of a string table
+    fn read_string(data: &[u8], strings: &Section, offset: usize) -> Option<String> {
+        let start = strings.offset.checked_add(offset)?;
+        let bytes = data.get(start..strings.offset.checked_add(strings.size)?)?;
+        let len = bytes.iter().position(|byte| *byte == 0)?;
+
+        Some(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+
+    fn read_symbols(&mut self, data: &[u8], symbols: &Section, strings: Option<&Section>, is_64_bit: bool) {
+        let Some(strings) = strings else { return };
+        let entry_size = match symbols.entry_size {
+            0 if is_64_bit => 24,
+            0 => 16,
+            size => size
+        };
+
+        // The first symbol is always the null symbol
+        for idx in 1..symbols.size / entry_size {
+            let Some(offset) = idx.checked_mul(entry_size).and_then(|offset| offset.checked_add(symbols.offset)) else {
+                break;
+            };
+            let mut reader = Reader::new(data, offset);
+            let Some(name) = reader.read_u32() else { break };
+
+            let (info, section) = if is_64_bit {
+                let info = reader.read_u8();
+                let _other = reader.read_u8();
+                (info, reader.read_u16())
+            } else {
+                let _value = reader.read_u32();
+                let _size = reader.read_u32();
+                let info = reader.read_u8();
+                let _other = reader.read_u8();
+                (info, reader.read_u16())
+            };
+            let (Some(info), Some(section)) = (info, section) else { break };
+
+            let Some(name) = Self::read_string(data, strings, name as usize).filter(|name| !name.is_empty()) else {
+                continue;
+            };
+
+            // Binding in the high nibble: 1 for global and 2 for weak symbols
+            match (section, info >> 4) {
+                (0, _) => self.imports.push(name),
+                (_, 1 | 2) => self.exports.push(name),
+                _ => { }
+            }
+        }
+    }
+
+    fn read_needed(&mut self, data: &[u8], dynamic: &Section, strings: Option<&Section>, is_64_bit: bool) {
+        let Some(strings) = strings else { return };
+        let Some(end) = dynamic.offset.checked_add(dynamic.size) else { return };
+        let mut reader = Reader::new(data, dynamic.offset);
+
+        while reader.pos() < end {
+            let entry = if is_64_bit {
+                reader.read_u64().zip(reader.read_u64())
+            } else {
+                reader.read_u32().zip(reader.read_u32())
+                    .map(|(tag, value)| (tag as u64, value as u64))
+            };
+
+            match entry {
+                Some((0, _)) | None => break,
+                Some((DT_NEEDED, value)) => self.needed.extend(Self::read_string(data, strings, value as usize)),
+                _ => { }
+            }
+        }
+    }
+
+    /// Return a reference to the path of the library in the APK
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Return a reference to the ABI from the path of the library, like `arm64-v8a`
+    pub fn abi(&self) -> Option<&str> {
+        self.abi.as_deref()
+    }
+
+    /// Return a reference to the architecture from the ELF header, like `AArch64`
+    pub fn machine(&self) -> &str {
+        &self.machine
+    }
+
+    /// Whether the library is a 64-bit ELF file
+    pub fn is_64_bit(&self) -> bool {
+        self.is_64_bit
+    }
+
+    /// Return a reference to the libraries needed by this one
+    pub fn needed(&self) -> &[String] {
+        &self.needed
+    }
+
+    /// Return a reference to the exported symbols
+    pub fn exports(&self) -> &[String] {
+        &self.exports
+    }
+
+    /// Return a reference to the imported symbols
+    pub fn imports(&self) -> &[String] {
+        &self.imports
+    }
+
+    /// Get the exported JNI functions, whose name starts with `Java_`
+    pub fn jni_exports(&self) -> Vec<&str> {
+        self.exports
+            .iter()
+            .filter(|name| name.starts_with("Java_"))
+            .map(|name| name.as_str())
+            .collect()
+    }
+
+    /// Whether the library exports `JNI_OnLoad`
+    pub fn has_jni_onload(&self) -> bool {
+        self.exports.iter().any(|name| name == JNI_ONLOAD)
+    }
+}
+
+/// Mangle a name for a JNI symbol
+fn mangle(name: &str) -> String {
+    let mut mangled = String::new();
+
+    for c in name.chars() {
+        match c {
+            '/' | '.' => mangled.push('_'),
+            '_' => mangled.push_str("_1"),
+            ';' => mangled.push_str("_2"),
+            '[' => mangled.push_str("_3"),
+            c if c.is_ascii_alphanumeric() => mangled.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    mangled.push_str(&format!("_0{unit:04x}"));
+                }
+            }
+        }
+    }
+
+    mangled
+}
+
+/// Get the short JNI name of a native method, `Java_<class>_<method>`
+///
+/// The class is either a name (`com.example.Foo`) or a descriptor.
+pub fn jni_short_name(class_name: &str, method_name: &str) -> String {
+    let descriptor = to_descriptor(class_name);
+    let class = descriptor.trim_start_matches('L').trim_end_matches(';');
+
+    format!("Java_{}_{}", mangle(class), mangle(method_name))
+}
+
+/// Get the long JNI name of a native method, with its mangled parameters
+/// for overloaded methods
+pub fn jni_long_name(class_name: &str, method_name: &str, descriptor: &str) -> String {
+    let parameters = split_method_descriptor(descriptor)
+        .map(|(parameters, _)| parameters.concat())
+        .unwrap_or_default();
+
+    format!("{}__{}", jni_short_name(class_name, method_name), mangle(&parameters))
+}
+
+/// How a native method is bound to its implementation
+#[derive(Debug, Clone, PartialEq)]
+pub enum JniBinding {
+    /// Exported under its JNI name by the libraries
+    Static {
+        symbol: String,
+        libraries: Vec<String>
+    },
+    /// No exported symbol, but the libraries export `JNI_OnLoad` and likely
+    /// register the method with `RegisterNatives`
+    Dynamic {
+        libraries: Vec<String>
+    },
+    /// No library implements the method
+    Missing
+}
+
+/// Native method of the application with its binding
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeMethod {
+    class_name: String,
+    name: String,
+    descriptor: String,
+    full_name: String,
+    binding: JniBinding
+}
+
+impl NativeMethod {
+    /// Return a reference to the name of the class of the method
+    pub fn class_name(&self) -> &str {
+        &self.class_name
+    }
+
+    /// Return a reference to the name of the method
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the descriptor of the method
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    /// Return a reference to the full name of the method
+    pub fn full_name(&self) -> &str {
+        &self.full_name
+    }
+
+    /// Return a reference to the binding of the method
+    pub fn binding(&self) -> &JniBinding {
+        &self.binding
+    }
+}
+
+/// Map of the native methods to the libraries implementing them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JniMap {
+    methods: Vec<NativeMethod>,
+    /// JNI functions matching no native method, as (library, symbol)
+    unmatched_exports: Vec<(String, String)>
+}
+
+impl JniMap {
+    /// Match the native methods of the hierarchy to the symbols of the libraries
+    pub fn build(hierarchy: &ClassHierarchy, libraries: &[NativeLibrary]) -> Self {
+        let exporting = |symbol: &str| libraries.iter()
+            .filter(|library| library.exports.iter().any(|export| export == symbol))
+            .map(|library| library.path.clone())
+            .collect::<Vec<String>>();
+
+        let onload_libraries = libraries.iter()
+            .filter(|library| library.has_jni_onload())
+            .map(|library| library.path.clone())
+            .collect::<Vec<String>>();
+
+        let mut methods = Vec::new();
+        let mut matched = Vec::new();
+
+        for class in hierarchy.classes().filter(|class| !class.is_external()) {
+            for method in class.methods() {
+                if !method.access_flags().contains(&DvmAccessFlag::ACC_NATIVE) {
+                    continue;
+                }
+
+                // The VM looks for the short name first, then the long name
+                let names = [
+                    jni_short_name(class.name(), method.name()),
+                    jni_long_name(class.name(), method.name(), method.descriptor())
+                ];
+                let binding = names.into_iter()
+                    .find_map(|symbol| {
+                        let libraries = exporting(&symbol);
+                        (!libraries.is_empty()).then_some(JniBinding::Static { symbol, libraries })
+                    })
+                    .unwrap_or_else(|| match onload_libraries.is_empty() {
+                        true => JniBinding::Missing,
+                        false => JniBinding::Dynamic { libraries: onload_libraries.clone() }
+                    });
+
+                if let JniBinding::Static { symbol, .. } = &binding {
+                    matched.push(symbol.clone());
+                }
+
+                methods.push(NativeMethod {
+                    class_name: class.name().to_string(),
+                    name: method.name().to_string(),
+                    descriptor: method.descriptor().to_string(),
+                    full_name: method.full_name().to_string(),
+                    binding
+                });
+            }
+        }
+
+        let unmatched_exports = libraries.iter()
+            .flat_map(|library| library.jni_exports()
+                .into_iter()
+                .filter(|symbol| !matched.iter().any(|matched| matched == symbol))
+                .map(|symbol| (library.path.clone(), symbol.to_string())))
+            .collect();
+
+        Self { methods, unmatched_exports }
+    }
+
+    /// Return a reference to the native methods with their binding
+    pub fn methods(&self) -> &[NativeMethod] {
+        &self.methods
+    }
+
+    /// Get the native methods exported under their JNI name
+    pub fn statically_bound(&self) -> Vec<&NativeMethod> {
+        self.methods
+            .iter()
+            .filter(|method| matches!(method.binding, JniBinding::Static { .. }))
+            .collect()
+    }
+
+    /// Get the native methods likely registered with `RegisterNatives`
+    pub fn dynamically_bound(&self) -> Vec<&NativeMethod> {
+        self.methods
+            .iter()
+            .filter(|method| matches!(method.binding, JniBinding::Dynamic { .. }))
+            .collect()
+    }
+
+    /// Get the native methods without an implementation
+    pub fn missing(&self) -> Vec<&NativeMethod> {
+        self.methods
+            .iter()
+            .filter(|method| method.binding == JniBinding::Missing)
+            .collect()
+    }
+
+    /// Return a reference to the JNI functions matching no native method,
+    /// as (library, symbol)
+    ///
+    /// They may belong to classes loaded at runtime or removed by a shrinker.
+    pub fn unmatched_exports(&self) -> &[(String, String)] {
+        &self.unmatched_exports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use crate::hierarchy::{ HierarchyClass, HierarchyMethod };
+    use crate::zip_file::ZipArchive;
+
+    fn libraries() -> Vec<NativeLibrary> {
+        let archive = ZipArchive::from_path(Path::new("test_files/test_native.apk")).unwrap();
+
+        ["lib/x86_64/libnative.so", "lib/x86_64/libplain.so"]
+            .iter()
+            .map(|path| NativeLibrary::parse(path, &archive.read_by_name(path).unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_library() {
+        let libraries = libraries();
+        let native = &libraries[0];
+
+        assert_eq!(native.abi(), Some("x86_64"));
+        assert_eq!(native.machine(), "x86-64");
+        assert!(native.is_64_bit());
+        assert!(native.has_jni_onload());
+        assert_eq!(native.jni_exports().len(), 5);
+        assert!(!native.exports().iter().any(|name| name == "hidden"));
+
+        let plain = &libraries[1];
+        assert_eq!(plain.needed(), &["libnative.so"]);
+        assert!(plain.imports().iter().any(|name| name == JNI_ONLOAD));
+        assert!(!plain.has_jni_onload());
+
+        assert!(NativeLibrary::parse("lib/x86/libfake.so", b"not an ELF file").is_none());
+
+        // Sections whose bounds overflow are skipped
+        let mut library = libraries[0].clone();
+        let strings = Section { kind: 3, offset: 0, size: 16, link: 0, entry_size: 0 };
+        let overflowing = Section { kind: 2, offset: usize::MAX - 8, size: 64, link: 0, entry_size: 16 };
+        library.read_symbols(&[0; 64], &overflowing, Some(&strings), true);
+        library.read_needed(&[0; 64], &overflowing, Some(&strings), true);
+        assert_eq!(library.exports(), libraries[0].exports());
+        assert_eq!(library.needed(), libraries[0].needed());
+    }
+
+    #[test]
+    fn test_jni_names() {
+        assert_eq!(jni_short_name("com.example.Foo", "bar"), "Java_com_example_Foo_bar");
+        assert_eq!(jni_short_name("Lcom/my_app/Foo$Inner;", "run"), "Java_com_my_1app_Foo_00024Inner_run");
+        assert_eq!(
+            jni_long_name("Lcom/example/Foo;", "bar", "(I[Ljava/lang/String;)V"),
+            "Java_com_example_Foo_bar__I_3Ljava_lang_String_2"
+        );
+    }
+
+    #[test]
+    fn test_jni_map() {
+        let native = |name: &str, descriptor: &str| HierarchyMethod::new(
+            name,
+            descriptor,
+            &format!("Lcom/example/test/Crypto;->{name}{descriptor}"),
+            &[DvmAccessFlag::ACC_PUBLIC, DvmAccessFlag::ACC_NATIVE]
+        );
+
+        let mut crypto = HierarchyClass::new("Lcom/example/test/Crypto;", Some("Ljava/lang/Object;"), &[], false);
+        crypto.add_method(native("decrypt", "([B)[B"));
+        crypto.add_method(native("sum", "(II)I"));
+        crypto.add_method(native("sum", "(III)I"));
+        crypto.add_method(native("init", "()V"));
+        crypto.add_method(HierarchyMethod::new("run", "()V", "Lcom/example/test/Crypto;->run()V", &[DvmAccessFlag::ACC_PUBLIC]));
+
+        let mut helper = HierarchyClass::new("com.example.test.my_util.Helper", None, &[], false);
+        helper.add_method(HierarchyMethod::new("check", "()Z", "Lcom/example/test/my_util/Helper;->check()Z", &[DvmAccessFlag::ACC_NATIVE]));
+
+        let mut hierarchy = ClassHierarchy::new();
+        hierarchy.add_class(crypto);
+        hierarchy.add_class(helper);
+
+        let libraries = libraries();
+        let map = JniMap::build(&hierarchy, &libraries);
+        assert_eq!(map.methods().len(), 5);
+
+        let mut bound = map.statically_bound()
+            .iter()
+            .map(|method| match method.binding() {
+                JniBinding::Static { symbol, .. } => symbol.as_str(),
+                _ => unreachable!()
+            })
+            .collect::<Vec<&str>>();
+        bound.sort();
+        assert_eq!(bound, vec![
+            "Java_com_example_test_Crypto_decrypt",
+            "Java_com_example_test_Crypto_sum__II",
+            "Java_com_example_test_Crypto_sum__III",
+            "Java_com_example_test_my_1util_Helper_check"
+        ]);
+
+        let dynamic = map.dynamically_bound();
+        assert_eq!(dynamic.len(), 1);
+        assert_eq!(dynamic[0].name(), "init");
+        assert_eq!(dynamic[0].binding(), &JniBinding::Dynamic { libraries: vec![String::from("lib/x86_64/libnative.so")] });
+
+        assert_eq!(map.unmatched_exports(), &[
+            (String::from("lib/x86_64/libnative.so"), String::from("Java_com_example_test_Removed_gone"))
+        ]);
+
+        // Without JNI_OnLoad, the unbound methods are missing
+        let map = JniMap::build(&hierarchy, &libraries[1..]);
+        assert_eq!(map.missing().len(), 5);
+    }
+}
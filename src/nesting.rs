@@ -0,0 +1,438 @@
+//! Nested classes
+//!
+//! Inner, local and anonymous classes are compiled to classes of their own,
+//! linked to their outer class by the `InnerClass`, `EnclosingClass`,
+//! `EnclosingMethod` and `MemberClasses` system annotations. Lambdas desugared
+//! by D8/R8 (`$$ExternalSyntheticLambda`) or by the older desugaring tools
+//! (`-$$Lambda$`, `$$Lambda$`) carry no annotation at all, they are recognized
+//! from their name and linked through the method they call.
+//!
+//! Class names are stored as type descriptors (`Lcom/example/Foo;`), but every
+//! lookup also accepts class names (`com.example.Foo`).
+
+use std::collections::HashMap;
+
+use crate::dex_file::{ self, DexFile };
+use crate::analysis::DvmClassAnalysis;
+use crate::annotations::{ self, Annotation };
+use crate::dvm_access_flags::DvmAccessFlag;
+
+/// Kind of class, according to where it is declared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NestingKind {
+    /// Class declared at the top level of a source file
+    TopLevel,
+    /// Class declared as a member of another class
+    Member,
+    /// Named class declared inside a method
+    Local,
+    /// Anonymous class
+    Anonymous,
+    /// Synthetic class generated by the desugaring of a lambda or a method reference
+    Lambda
+}
+
+/// Nesting information of a class
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassNesting {
+    /// Descriptor of the class
+    name: String,
+    /// Kind of class
+    kind: NestingKind,
+    /// Simple name declared in the source code, `None` for anonymous classes
+    simple_name: Option<String>,
+    /// Access flags declared in the source code
+    access_flags: Vec<DvmAccessFlag>,
+    /// Descriptor of the outer class
+    outer_class: Option<String>,
+    /// Full name of the method declaring the class
+    enclosing_method: Option<String>,
+    /// Descriptors of the member classes
+    member_classes: Vec<String>,
+    /// Descriptor of the interface implemented by a lambda
+    functional_interface: Option<String>,
+    /// Full name of the method called by a lambda
+    implementation: Option<String>
+}
+
+impl ClassNesting {
+    /// Create the nesting information of a class given its name
+    ///
+    /// Lambda classes are recognized from their name, every other class is
+    /// considered top level until its annotations are applied.
+    pub fn new(name: &str) -> Self {
+        let name = dex_file::to_descriptor(name);
+        let outer_class = lambda_outer_class(&name);
+
+        Self {
+            kind: match outer_class {
+                Some(_) => NestingKind::Lambda,
+                None => NestingKind::TopLevel
+            },
+            name,
+            simple_name: None,
+            access_flags: Vec::new(),
+            outer_class,
+            enclosing_method: None,
+            member_classes: Vec::new(),
+            functional_interface: None,
+            implementation: None
+        }
+    }
+
+    /// Apply the `InnerClass`, `EnclosingClass`, `EnclosingMethod` and
+    /// `MemberClasses` system annotations of the class
+    pub(crate) fn apply_annotations(&mut self, class_annotations: &[Annotation]) {
+        self.member_classes = annotations::types(class_annotations, annotations::MEMBER_CLASSES);
+
+        let Some(inner_class) = annotations::inner_class(class_annotations) else {
+            return;
+        };
+        self.enclosing_method = annotations::enclosing_method(class_annotations);
+        self.outer_class = annotations::enclosing_class(class_annotations)
+            .or_else(|| self.enclosing_method
+                .as_deref()
+                .and_then(dex_file::split_method_name)
+                .map(|(class_name, _, _)| class_name.to_string()));
+
+        self.kind = match (inner_class.name(), &self.enclosing_method) {
+            (None, _) => NestingKind::Anonymous,
+            (Some(_), Some(_)) => NestingKind::Local,
+            (Some(_), None) => NestingKind::Member
+        };
+        self.simple_name = inner_class.name().map(String::from);
+        self.access_flags = inner_class.access_flags().to_vec();
+    }
+
+    /// Link a lambda class to its functional interface and to the first
+    /// method it calls outside of itself
+    ///
+    /// Does nothing for classes that are not lambdas.
+    pub fn set_lambda_target<'a>(&mut self, interfaces: &[String], callees: impl IntoIterator<Item = &'a str>) {
+        if self.kind != NestingKind::Lambda {
+            return;
+        }
+
+        self.functional_interface = interfaces.first().map(|interface| dex_file::to_descriptor(interface));
+        self.implementation = callees.into_iter()
+            .filter(|callee| !callee.starts_with(&format!("{}->", self.name)))
+            .find(|callee| !callee.contains("-><init>("))
+            .map(String::from);
+    }
+
+    /// Find the method declaring a lambda from the name of its implementation
+    /// method, which also gives the outer class of the older lambda classes
+    pub(crate) fn resolve_lambda_enclosing_method(&mut self, dex: &DexFile) {
+        let Some((class_name, method_name, _)) = self.implementation
+            .as_deref()
+            .and_then(dex_file::split_method_name) else {
+            return;
+        };
+        let Some(enclosing_name) = lambda_enclosing_method_name(method_name) else {
+            return;
+        };
+
+        self.outer_class = Some(class_name.to_string());
+        self.enclosing_method = dex.class_def_by_name(class_name)
+            .map(|class_def| dex.class_methods(&class_def))
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|method| dex.method_full_name(method.method_idx))
+            .find(|full_name| dex_file::split_method_name(full_name)
+                .is_some_and(|(_, name, _)| name == enclosing_name));
+    }
+
+    /// Set the method declaring a lambda
+    pub fn set_enclosing_method(&mut self, enclosing_method: &str) {
+        self.enclosing_method = Some(enclosing_method.to_string());
+    }
+
+    /// Return a reference to the descriptor of the class
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the kind of class
+    pub fn kind(&self) -> NestingKind {
+        self.kind
+    }
+
+    /// Return a reference to the simple name declared in the source code,
+    /// `None` for top level, anonymous and lambda classes
+    pub fn simple_name(&self) -> Option<&str> {
+        self.simple_name.as_deref()
+    }
+
+    /// Return a reference to the access flags declared in the source code
+    pub fn access_flags(&self) -> &[DvmAccessFlag] {
+        &self.access_flags
+    }
+
+    /// Return a reference to the descriptor of the outer class
+    pub fn outer_class(&self) -> Option<&str> {
+        self.outer_class.as_deref()
+    }
+
+    /// Return a reference to the full name of the method declaring the class
+    ///
+    /// Set for local and anonymous classes, and for lambdas whose
+    /// implementation method follows the naming of javac or kotlinc.
+    pub fn enclosing_method(&self) -> Option<&str> {
+        self.enclosing_method.as_deref()
+    }
+
+    /// Return a reference to the descriptors of the member classes
+    pub fn member_classes(&self) -> &[String] {
+        &self.member_classes
+    }
+
+    /// Return a reference to the descriptor of the interface implemented by a lambda
+    pub fn functional_interface(&self) -> Option<&str> {
+        self.functional_interface.as_deref()
+    }
+
+    /// Return a reference to the full name of the method called by a lambda,
+    /// the body of the lambda or the target of a method reference
+    pub fn implementation(&self) -> Option<&str> {
+        self.implementation.as_deref()
+    }
+
+    /// Whether the class is nested in another one
+    pub fn is_nested(&self) -> bool {
+        self.kind != NestingKind::TopLevel
+    }
+}
+
+/// Get the outer class of a desugared lambda from its name
+///
+/// `Lcom/example/Foo$$ExternalSyntheticLambda0;` and `Lcom/example/Foo$$Lambda$1;`
+/// are declared in `Lcom/example/Foo;`, and so is `Lcom/example/-$$Lambda$Foo$1a2b;`.
+pub fn lambda_outer_class(name: &str) -> Option<String> {
+    let name = dex_file::to_descriptor(name);
+    let name = name.strip_suffix(';')?;
+
+    if let Some(idx) = name.find("-$$Lambda$") {
+        let (package, lambda) = (&name[..idx], &name[idx + "-$$Lambda$".len()..]);
+        let (outer, _) = lambda.rsplit_once('$')?;
+        return Some(format!("{package}{outer};"));
+    }
+
+    ["$$ExternalSyntheticLambda", "$$Lambda$"]
+        .iter()
+        .find_map(|marker| name.find(marker))
+        .map(|idx| format!("{};", &name[..idx]))
+}
+
+/// Get the name of the method declaring a lambda from the name of its
+/// implementation method, following the naming of javac and kotlinc
+///
+/// `lambda$onCreate$0` and `onCreate$lambda$0` are declared in `onCreate`,
+/// `lambda$new$0` in `<init>` and `lambda$static$0` in `<clinit>`.
+pub fn lambda_enclosing_method_name(implementation_name: &str) -> Option<&str> {
+    let name = match implementation_name.strip_prefix("lambda$") {
+        Some(rest) => rest.rsplit_once('$')?.0,
+        None => implementation_name.split_once("$lambda")?.0
+    };
+
+    match name {
+        "" => None,
+        "new" | "_init_" => Some("<init>"),
+        "static" => Some("<clinit>"),
+        name => Some(name)
+    }
+}
+
+/// Nesting relationships of a set of classes
+#[derive(Debug, Default)]
+pub struct NestingTree {
+    /// Nesting information of every class, by descriptor
+    classes: HashMap<String, ClassNesting>,
+    /// Descriptors of the classes nested in a class
+    nested: HashMap<String, Vec<String>>
+}
+
+impl NestingTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the tree from a list of analyzed classes
+    pub fn from_classes(classes: &[DvmClassAnalysis]) -> Self {
+        let mut tree = Self::new();
+
+        for class in classes {
+            tree.add_class(class.nesting().clone());
+        }
+
+        tree
+    }
+
+    /// Add a class to the tree
+    ///
+    /// If the class is already present it is replaced.
+    pub fn add_class(&mut self, class: ClassNesting) {
+        if let Some(previous) = self.classes.remove(&class.name)
+            && let Some(nested) = previous.outer_class.and_then(|outer| self.nested.get_mut(&outer))
+        {
+            nested.retain(|nested| *nested != previous.name);
+        }
+
+        let outer_classes = class.outer_class
+            .iter()
+            .map(|outer| (outer.clone(), class.name.clone()));
+        let member_classes = class.member_classes
+            .iter()
+            .map(|member| (class.name.clone(), member.clone()));
+
+        for (outer, nested) in outer_classes.chain(member_classes).collect::<Vec<(String, String)>>() {
+            let nested_classes = self.nested.entry(outer).or_default();
+            if !nested_classes.contains(&nested) {
+                nested_classes.push(nested);
+            }
+        }
+
+        self.classes.insert(class.name.clone(), class);
+    }
+
+    /// Get the nesting information of a class
+    pub fn class(&self, name: &str) -> Option<&ClassNesting> {
+        self.classes.get(&dex_file::to_descriptor(name))
+    }
+
+    /// Get an iterator over every class of the tree
+    pub fn classes(&self) -> impl Iterator<Item = &ClassNesting> {
+        self.classes.values()
+    }
+
+    /// Get the outer class of a class
+    pub fn outer_class(&self, name: &str) -> Option<&ClassNesting> {
+        self.class(self.class(name)?.outer_class()?)
+    }
+
+    /// Get the outermost class declaring a class, the class itself if it is
+    /// not nested
+    pub fn outermost_class(&self, name: &str) -> String {
+        let mut outermost = dex_file::to_descriptor(name);
+
+        // Bounded by the number of classes in case of a malformed cycle
+        for _ in 0..self.classes.len() {
+            match self.classes.get(&outermost).and_then(ClassNesting::outer_class) {
+                Some(outer) => outermost = outer.to_string(),
+                None => break
+            }
+        }
+
+        outermost
+    }
+
+    /// Get the classes directly nested in a class, including its local,
+    /// anonymous and lambda classes
+    pub fn nested_classes(&self, name: &str) -> Vec<&ClassNesting> {
+        self.nested
+            .get(&dex_file::to_descriptor(name))
+            .map(|nested| nested.iter().filter_map(|nested| self.classes.get(nested)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Get the local, anonymous and lambda classes declared in a method
+    pub fn classes_declared_in(&self, method_full_name: &str) -> Vec<&ClassNesting> {
+        self.classes
+            .values()
+            .filter(|class| class.enclosing_method() == Some(method_full_name))
+            .collect()
+    }
+
+    /// Get the lambda classes calling a method
+    pub fn lambdas_calling(&self, method_full_name: &str) -> Vec<&ClassNesting> {
+        self.classes
+            .values()
+            .filter(|class| class.implementation() == Some(method_full_name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lambda_names() {
+        assert_eq!(
+            lambda_outer_class("Lcom/example/Main$$ExternalSyntheticLambda0;").as_deref(),
+            Some("Lcom/example/Main;")
+        );
+        assert_eq!(
+            lambda_outer_class("com.example.Main$Inner$$Lambda$2").as_deref(),
+            Some("Lcom/example/Main$Inner;")
+        );
+        assert_eq!(
+            lambda_outer_class("Lcom/example/-$$Lambda$Main$Vx3kQ2;").as_deref(),
+            Some("Lcom/example/Main;")
+        );
+        assert!(lambda_outer_class("Lcom/example/Main$1;").is_none());
+
+        assert_eq!(lambda_enclosing_method_name("lambda$onCreate$0"), Some("onCreate"));
+        assert_eq!(lambda_enclosing_method_name("onCreate$lambda$1"), Some("onCreate"));
+        assert_eq!(lambda_enclosing_method_name("onCreate$lambda-1"), Some("onCreate"));
+        assert_eq!(lambda_enclosing_method_name("lambda$new$2"), Some("<init>"));
+        assert_eq!(lambda_enclosing_method_name("lambda$static$0"), Some("<clinit>"));
+        assert!(lambda_enclosing_method_name("onCreate").is_none());
+    }
+
+    #[test]
+    fn test_lambda_target() {
+        let mut lambda = ClassNesting::new("Lcom/example/Main$$ExternalSyntheticLambda0;");
+        lambda.set_lambda_target(
+            &[String::from("java.lang.Runnable")],
+            [
+                "Ljava/lang/Object;-><init>()V",
+                "Lcom/example/Main$$ExternalSyntheticLambda0;->run()V",
+                "Lcom/example/Main;->lambda$onCreate$0(Landroid/os/Bundle;)V"
+            ]
+        );
+
+        assert_eq!(lambda.kind(), NestingKind::Lambda);
+        assert_eq!(lambda.functional_interface(), Some("Ljava/lang/Runnable;"));
+        assert_eq!(lambda.implementation(), Some("Lcom/example/Main;->lambda$onCreate$0(Landroid/os/Bundle;)V"));
+
+        let mut top_level = ClassNesting::new("Lcom/example/Main;");
+        top_level.set_lambda_target(&[String::from("Ljava/lang/Runnable;")], ["Lcom/example/Other;->run()V"]);
+        assert!(top_level.functional_interface().is_none());
+        assert!(!top_level.is_nested());
+    }
+
+    #[test]
+    fn test_nesting_tree() {
+        let mut tree = NestingTree::new();
+        tree.add_class(ClassNesting::new("Lcom/example/Main;"));
+
+        let mut lambda = ClassNesting::new("Lcom/example/Main$$ExternalSyntheticLambda0;");
+        lambda.set_enclosing_method("Lcom/example/Main;->onCreate(Landroid/os/Bundle;)V");
+        tree.add_class(lambda);
+
+        let mut nested_lambda = ClassNesting::new("Lcom/example/Main$$ExternalSyntheticLambda0$$ExternalSyntheticLambda0;");
+        nested_lambda.outer_class = Some(String::from("Lcom/example/Main$$ExternalSyntheticLambda0;"));
+        tree.add_class(nested_lambda);
+
+        let nested = tree.nested_classes("com.example.Main");
+        assert_eq!(nested.len(), 1);
+        assert_eq!(nested[0].name(), "Lcom/example/Main$$ExternalSyntheticLambda0;");
+        assert_eq!(
+            tree.outer_class("Lcom/example/Main$$ExternalSyntheticLambda0;").unwrap().name(),
+            "Lcom/example/Main;"
+        );
+        assert_eq!(
+            tree.outermost_class("Lcom/example/Main$$ExternalSyntheticLambda0$$ExternalSyntheticLambda0;"),
+            "Lcom/example/Main;"
+        );
+        assert_eq!(tree.classes_declared_in("Lcom/example/Main;->onCreate(Landroid/os/Bundle;)V").len(), 1);
+
+        // Replacing a class drops its previous links
+        tree.add_class(ClassNesting {
+            outer_class: None,
+            kind: NestingKind::TopLevel,
+            ..ClassNesting::new("Lcom/example/Main$$ExternalSyntheticLambda0;")
+        });
+        assert!(tree.nested_classes("Lcom/example/Main;").is_empty());
+    }
+}
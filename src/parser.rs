@@ -1,9 +1,16 @@
 //! Parser data structs
 
+use std::collections::HashMap;
 use std::ffi::CStr;
 use std::slice::from_raw_parts;
 
 use crate::shuriken;
+use crate::dex_file::{ self, AnnotationsDirectory, DexFile, EncodedMethod };
+use crate::debug_info::DebugInfo;
+use crate::annotations::{ self, Annotation, InnerClass };
+use crate::encoded_value::EncodedValue;
+use crate::kotlin::KotlinMetadata;
+use crate::generics::{ ClassSignature, GenericType, MethodSignature };
 use crate::dvm_access_flags::{ DvmAccessFlag, DvmAccessFlagType };
 
 /// Type alias for Shuriken's `htype_e`
@@ -256,7 +263,10 @@ pub struct DvmField {
     /// String value of the type
     type_value: String,
     /// Access flags of the field
-    access_flags: Vec<DvmAccessFlag>
+    access_flags: Vec<DvmAccessFlag>,
+    annotations: Vec<Annotation>,
+    /// Initial value of a static field
+    initial_value: Option<EncodedValue>
 }
 
 impl DvmField {
@@ -318,10 +328,22 @@ impl DvmField {
             field_type,
             fundamental_value,
             type_value,
-            access_flags
+            access_flags,
+            annotations: Vec::new(),
+            initial_value: None
         }
     }
 
+    /// Read the annotations of the field from the annotations directory of its class
+    fn resolve_from_dex(&mut self, dex: &DexFile, directory: &AnnotationsDirectory) {
+        self.annotations = directory.fields
+            .iter()
+            .find(|(field_idx, _)| dex.field_id(*field_idx)
+                .is_some_and(|(_, name, descriptor)| name == self.name && descriptor == self.type_value))
+            .map(|(_, annotations_off)| Annotation::read_set(dex, *annotations_off))
+            .unwrap_or_default();
+    }
+
     /// Return a reference to the class name
     pub fn class_name(&self) -> &str {
         &self.class_name
@@ -351,6 +373,45 @@ impl DvmField {
     pub fn access_flags(&self) -> &[DvmAccessFlag] {
         &self.access_flags
     }
+
+    /// Return a reference to the annotations of the field
+    ///
+    /// Shuriken does not expose the annotations, they are read from the DEX
+    /// file instead. Only available for fields of classes obtained through a
+    /// `DexContext` or an `ApkContext`.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Get the generic signature of the field, from the `Signature` system
+    /// annotation, like `Ljava/util/List<Ljava/lang/String;>;`
+    pub fn signature(&self) -> Option<String> {
+        annotations::signature(&self.annotations)
+    }
+
+    /// Get the generic type of the field, like
+    /// `java.util.Map<java.lang.String, java.util.List<java.lang.Integer>>`
+    ///
+    /// Falls back to the erased type from `type_value()` when the field has no
+    /// `Signature` annotation.
+    pub fn generic_type(&self) -> Option<GenericType> {
+        match self.signature() {
+            Some(signature) => GenericType::parse(&signature),
+            None => GenericType::parse(&self.type_value)
+        }
+    }
+
+    /// Return a reference to the initial value of a static field, from the
+    /// `static_values` array of its class
+    ///
+    /// Fields without an initial value in the array start with the default
+    /// value of their type, `0`, `false` or `null`, and may be assigned by
+    /// `<clinit>`. Always `None` for instance fields and when the array is
+    /// malformed, and only available for fields of classes obtained through
+    /// a `DexContext` or an `ApkContext`.
+    pub fn initial_value(&self) -> Option<&EncodedValue> {
+        self.initial_value.as_ref()
+    }
 }
 
 /// Type alias for Shuriken's `hdvmmethod_t`
@@ -365,7 +426,10 @@ pub struct DvmMethod {
     code_size: usize,
     code: Vec<u8>,
     dalvik_name: String,
-    demangled_name: String
+    demangled_name: String,
+    debug_info: Option<DebugInfo>,
+    annotations: Vec<Annotation>,
+    parameter_annotations: Vec<Vec<Annotation>>
 }
 
 impl DvmMethod {
@@ -421,10 +485,44 @@ impl DvmMethod {
             code_size: method.code_size as usize,
             code,
             dalvik_name,
-            demangled_name
+            demangled_name,
+            debug_info: None,
+            annotations: Vec::new(),
+            parameter_annotations: Vec::new()
+        }
+    }
+
+    /// Complete the method with the information missing from Shuriken's C API
+    pub(crate) fn resolve_from_dex(&mut self, dex: &DexFile) {
+        let Some(class_def) = dex.class_def_by_name(&self.class_name) else {
+            return;
+        };
+
+        if let Some(encoded) = dex.encoded_method(&class_def, &self.dalvik_name) {
+            let directory = dex.annotations_directory(&class_def).unwrap_or_default();
+            self.resolve_from_encoded_method(dex, &encoded, &directory);
         }
     }
 
+    fn resolve_from_encoded_method(&mut self, dex: &DexFile, encoded: &EncodedMethod, directory: &AnnotationsDirectory) {
+        let is_static = self.access_flags.contains(&DvmAccessFlag::ACC_STATIC);
+
+        self.debug_info = dex.code_item(encoded.code_off)
+            .and_then(|code| DebugInfo::parse(dex, &code, &self.dalvik_name, is_static));
+
+        let annotations_off = |entries: &[(u32, u32)]| entries
+            .iter()
+            .find(|(method_idx, _)| *method_idx == encoded.method_idx)
+            .map(|(_, annotations_off)| *annotations_off);
+
+        self.annotations = annotations_off(&directory.methods)
+            .map(|annotations_off| Annotation::read_set(dex, annotations_off))
+            .unwrap_or_default();
+        self.parameter_annotations = annotations_off(&directory.parameters)
+            .map(|annotations_off| Annotation::read_set_ref_list(dex, annotations_off))
+            .unwrap_or_default();
+    }
+
     /// Return a reference to the class name
     pub fn class_name(&self) -> &str {
         &self.class_name
@@ -464,6 +562,56 @@ impl DvmMethod {
     pub fn demangled_name(&self) -> &str {
         &self.demangled_name
     }
+
+    /// Returns a reference to the debug information: line numbers, local
+    /// variables and parameter names
+    ///
+    /// Shuriken does not expose the debug information, it is read from the DEX
+    /// file instead. Only available for methods obtained through a `DexContext`
+    /// or an `ApkContext`, and for methods with code compiled with debug
+    /// information.
+    pub fn debug_info(&self) -> Option<&DebugInfo> {
+        self.debug_info.as_ref()
+    }
+
+    /// Return a reference to the annotations of the method
+    ///
+    /// Shuriken does not expose the annotations, they are read from the DEX
+    /// file instead. Only available for methods obtained through a `DexContext`
+    /// or an `ApkContext`.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Return a reference to the annotations of every parameter
+    ///
+    /// Empty if no parameter is annotated.
+    pub fn parameter_annotations(&self) -> &[Vec<Annotation>] {
+        &self.parameter_annotations
+    }
+
+    /// Get the generic signature of the method, from the `Signature` system
+    /// annotation, like `<T:Ljava/lang/Object;>(TT;)V`
+    pub fn signature(&self) -> Option<String> {
+        annotations::signature(&self.annotations)
+    }
+
+    /// Get the generic prototype of the method, with its type parameters
+    ///
+    /// Falls back to the erased prototype from `prototype()` when the method
+    /// has no `Signature` annotation.
+    pub fn generic_prototype(&self) -> Option<MethodSignature> {
+        match self.signature() {
+            Some(signature) => MethodSignature::parse(&signature),
+            None => MethodSignature::parse(&self.prototype)
+        }
+    }
+
+    /// Get the descriptors of the checked exceptions declared by the method,
+    /// from the `Throws` system annotation
+    pub fn throws(&self) -> Vec<String> {
+        annotations::types(&self.annotations, annotations::THROWS)
+    }
 }
 
 
@@ -474,6 +622,7 @@ impl DvmMethod {
 pub struct DvmClass {
     class_name: String,
     super_class: String,
+    interfaces: Vec<String>,
     source_file: String,
     access_flags: Vec<DvmAccessFlag>,
     direct_methods_size: usize,
@@ -483,7 +632,9 @@ pub struct DvmClass {
     instance_fields_size: usize,
     instance_fields: Vec<DvmField>,
     static_fields_size: usize,
-    static_fields: Vec<DvmField>
+    static_fields: Vec<DvmField>,
+    annotations: Vec<Annotation>,
+    kotlin_metadata: Option<KotlinMetadata>
 }
 
 impl DvmClass {
@@ -557,6 +708,7 @@ impl DvmClass {
         DvmClass {
             class_name,
             super_class,
+            interfaces: Vec::new(),
             source_file,
             access_flags,
             direct_methods_size: ptr.direct_methods_size as usize,
@@ -566,7 +718,52 @@ impl DvmClass {
             instance_fields_size: ptr.instance_fields_size as usize,
             instance_fields,
             static_fields_size: ptr.static_fields_size as usize,
-            static_fields
+            static_fields,
+            annotations: Vec::new(),
+            kotlin_metadata: None
+        }
+    }
+
+    /// Complete the class with the information missing from Shuriken's C API
+    pub(crate) fn resolve_from_dex(&mut self, dex: &DexFile) {
+        if let Some(class_def) = dex.class_def_by_name(&self.class_name) {
+            self.interfaces = dex.interfaces(&class_def)
+                .iter()
+                .map(|interface| dex_file::same_notation_as(&self.super_class, interface))
+                .collect();
+
+            let encoded_methods = dex.class_methods(&class_def)
+                .into_iter()
+                .filter_map(|method| Some((dex.method_full_name(method.method_idx)?, method)))
+                .collect::<HashMap<String, EncodedMethod>>();
+
+            let directory = dex.annotations_directory(&class_def).unwrap_or_default();
+            self.annotations = Annotation::read_set(dex, directory.class_annotations_off);
+            self.kotlin_metadata = KotlinMetadata::from_annotations(&self.annotations);
+
+            for method in self.direct_methods.iter_mut().chain(self.virtual_methods.iter_mut()) {
+                if let Some(encoded) = encoded_methods.get(&method.dalvik_name) {
+                    method.resolve_from_encoded_method(dex, encoded, &directory);
+                }
+            }
+
+            for field in self.static_fields.iter_mut().chain(self.instance_fields.iter_mut()) {
+                field.resolve_from_dex(dex, &directory);
+            }
+
+            // Fields are told apart by type too, obfuscators reuse the names
+            let mut static_values = EncodedValue::read_static_values(dex, &class_def)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(field_idx, value)| {
+                    let (_, name, descriptor) = dex.field_id(field_idx)?;
+                    Some(((name, descriptor), value))
+                })
+                .collect::<HashMap<(String, String), EncodedValue>>();
+
+            for field in self.static_fields.iter_mut() {
+                field.initial_value = static_values.remove(&(field.name.clone(), field.type_value.clone()));
+            }
         }
     }
 
@@ -580,6 +777,15 @@ impl DvmClass {
         &self.super_class
     }
 
+    /// Returns a reference to the interfaces implemented by the class
+    ///
+    /// Shuriken does not expose the interfaces list, it is read from the DEX file
+    /// instead. Only available for classes obtained through a `DexContext` or an
+    /// `ApkContext`.
+    pub fn interfaces(&self) -> &[String] {
+        &self.interfaces
+    }
+
     /// Returns a reference to the source file
     pub fn source_file(&self) -> &str {
         &self.source_file
@@ -629,4 +835,70 @@ impl DvmClass {
     pub fn static_fields(&self) -> &[DvmField] {
         &self.static_fields
     }
+
+    /// Returns a reference to the annotations of the class
+    ///
+    /// Shuriken does not expose the annotations, they are read from the DEX file
+    /// instead. Only available for classes obtained through a `DexContext` or an
+    /// `ApkContext`.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Get the generic signature of the class, from the `Signature` system
+    /// annotation, like `<T:Ljava/lang/Object;>Ljava/lang/Object;`
+    pub fn signature(&self) -> Option<String> {
+        annotations::signature(&self.annotations)
+    }
+
+    /// Get the generic signature of the class, with its type parameters, its
+    /// generic super class and interfaces
+    ///
+    /// Falls back to the erased super class and interfaces when the class has
+    /// no `Signature` annotation. `None` for `java.lang.Object`.
+    pub fn generic_signature(&self) -> Option<ClassSignature> {
+        match self.signature() {
+            Some(signature) => ClassSignature::parse(&signature),
+            None => {
+                let erased = std::iter::once(&self.super_class)
+                    .chain(&self.interfaces)
+                    .map(|name| dex_file::to_descriptor(name))
+                    .collect::<String>();
+                ClassSignature::parse(&erased)
+            }
+        }
+    }
+
+    /// Return a reference to the Kotlin declarations of the class, decoded
+    /// from its `kotlin.Metadata` annotation
+    ///
+    /// `None` for classes not compiled by kotlinc. Only available for classes
+    /// obtained through a `DexContext` or an `ApkContext`.
+    pub fn kotlin_metadata(&self) -> Option<&KotlinMetadata> {
+        self.kotlin_metadata.as_ref()
+    }
+
+    /// Get the name and the source access flags of an inner class, from the
+    /// `InnerClass` system annotation
+    pub fn inner_class(&self) -> Option<InnerClass> {
+        annotations::inner_class(&self.annotations)
+    }
+
+    /// Get the descriptor of the class declaring a member class, from the
+    /// `EnclosingClass` system annotation
+    pub fn enclosing_class(&self) -> Option<String> {
+        annotations::enclosing_class(&self.annotations)
+    }
+
+    /// Get the full name of the method declaring a local or anonymous class,
+    /// from the `EnclosingMethod` system annotation
+    pub fn enclosing_method(&self) -> Option<String> {
+        annotations::enclosing_method(&self.annotations)
+    }
+
+    /// Get the descriptors of the member classes, from the `MemberClasses`
+    /// system annotation
+    pub fn member_classes(&self) -> Vec<String> {
+        annotations::types(&self.annotations, annotations::MEMBER_CLASSES)
+    }
 }
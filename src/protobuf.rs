@@ -0,0 +1,165 @@
+//! Minimal protocol buffers reader
+//!
+//! Android App Bundles store their manifests and resources in the aapt2
+//! protobuf format instead of binary XML. Without the schemas, this only
+//! walks the fields of a message, the callers know what they mean.
+
+/// Value of a field
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ProtoValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    /// Length-delimited value: string, bytes or embedded message
+    Bytes(&'a [u8]),
+    Fixed32(u32)
+}
+
+impl<'a> ProtoValue<'a> {
+    /// Get the value of a varint or fixed-size field
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        match self {
+            ProtoValue::Varint(value) | ProtoValue::Fixed64(value) => Some(*value),
+            ProtoValue::Fixed32(value) => Some(*value as u64),
+            ProtoValue::Bytes(_) => None
+        }
+    }
+
+    /// Get the content of a length-delimited field
+    pub(crate) fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            ProtoValue::Bytes(bytes) => Some(bytes),
+            _ => None
+        }
+    }
+
+    /// Get a length-delimited field as a string
+    pub(crate) fn as_string(&self) -> Option<String> {
+        self.as_bytes()
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// Get the values of a repeated varint field, packed or not
+    pub(crate) fn as_packed(&self) -> Vec<u64> {
+        match self {
+            ProtoValue::Bytes(bytes) => {
+                let mut bytes = *bytes;
+                let mut values = Vec::new();
+                while let Some((value, len)) = read_varint(bytes) {
+                    values.push(value);
+                    bytes = &bytes[len..];
+                }
+                values
+            },
+            _ => self.as_u64().into_iter().collect()
+        }
+    }
+}
+
+/// Read a varint at the start of `data`, returns the value and its length
+pub(crate) fn read_varint(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+
+    for (idx, shift) in (0..64).step_by(7).enumerate() {
+        let byte = *data.get(idx)?;
+
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some((value, idx + 1));
+        }
+    }
+
+    None
+}
+
+/// Split a length-prefixed message from the start of `data`, as written by
+/// `writeDelimitedTo`, returns the message and the remaining bytes
+pub(crate) fn read_delimited(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (len, varint_len) = read_varint(data)?;
+    let end = varint_len.checked_add(usize::try_from(len).ok()?)?;
+
+    Some((data.get(varint_len..end)?, &data[end..]))
+}
+
+/// Iterator over the fields of a message, as (field number, value)
+///
+/// Iteration stops at the first malformed field.
+pub(crate) struct ProtoFields<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> ProtoFields<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn read_varint(&mut self) -> Option<u64> {
+        let (value, len) = read_varint(&self.data[self.pos..])?;
+        self.pos += len;
+        Some(value)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+}
+
+impl<'a> Iterator for ProtoFields<'a> {
+    type Item = (u32, ProtoValue<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+
+        let key = self.read_varint()?;
+        let value = match key & 7 {
+            0 => ProtoValue::Varint(self.read_varint()?),
+            1 => ProtoValue::Fixed64(u64::from_le_bytes(self.read_bytes(8)?.try_into().ok()?)),
+            2 => {
+                let len = usize::try_from(self.read_varint()?).ok()?;
+                ProtoValue::Bytes(self.read_bytes(len)?)
+            },
+            5 => ProtoValue::Fixed32(u32::from_le_bytes(self.read_bytes(4)?.try_into().ok()?)),
+            _ => {
+                // Groups are deprecated and unused by aapt2
+                self.pos = self.data.len();
+                return None;
+            }
+        };
+
+        Some(((key >> 3) as u32, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        // 1: 150, 2: "ab", 3: fixed32 1, 4: truncated string
+        let data = [0x08, 0x96, 0x01, 0x12, 0x02, b'a', b'b', 0x1d, 0x01, 0x00, 0x00, 0x00, 0x22, 0x05, b'x'];
+
+        let fields = ProtoFields::new(&data).collect::<Vec<(u32, ProtoValue)>>();
+        assert_eq!(fields, vec![
+            (1, ProtoValue::Varint(150)),
+            (2, ProtoValue::Bytes(b"ab")),
+            (3, ProtoValue::Fixed32(1))
+        ]);
+        assert_eq!(fields[1].1.as_string().as_deref(), Some("ab"));
+        assert_eq!(fields[2].1.as_u64(), Some(1));
+    }
+
+    #[test]
+    fn test_packed_and_delimited() {
+        assert_eq!(ProtoValue::Bytes(&[0x01, 0x96, 0x01, 0x03]).as_packed(), vec![1, 150, 3]);
+        assert_eq!(ProtoValue::Varint(7).as_packed(), vec![7]);
+
+        let data = [0x02, 0x08, 0x01, 0x10, 0x02];
+        assert_eq!(read_delimited(&data), Some((&data[1..3], &data[3..])));
+        assert_eq!(read_delimited(&[0x05, 0x08]), None);
+    }
+}
@@ -0,0 +1,729 @@
+//! Compiled resources
+//!
+//! Decode the `resources.arsc` table of an APK into packages, types and
+//! entries, each entry having one value per configuration (language,
+//! density, SDK level, ...). Resource IDs (`0xPPTTEEEE`) found in the code can
+//! then be resolved to their names and values.
+
+use std::fmt;
+use std::collections::HashMap;
+
+use crate::dex_file::Reader;
+use crate::disassembler::DvmInstruction;
+use crate::axml::{ ChunkHeader, StringPool, XmlValue, RES_STRING_POOL_TYPE };
+
+/// Type of the resource table chunk
+const RES_TABLE_TYPE: u16 = 0x0002;
+/// Type of a package chunk
+const RES_TABLE_PACKAGE_TYPE: u16 = 0x0200;
+/// Type of a type chunk, holding the entries of one configuration
+const RES_TABLE_TYPE_TYPE: u16 = 0x0201;
+
+/// Configuration an entry value applies to
+///
+/// Only the most common qualifiers are decoded, the others are kept in the
+/// raw bytes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceConfig {
+    mcc: u16,
+    mnc: u16,
+    language: String,
+    region: String,
+    orientation: u8,
+    density: u16,
+    sdk_version: u16,
+    ui_mode: u8,
+    smallest_screen_width_dp: u16,
+    screen_width_dp: u16,
+    screen_height_dp: u16,
+    /// Raw `ResTable_config`
+    raw: Vec<u8>
+}
+
+impl ResourceConfig {
+    fn parse(data: &[u8]) -> Self {
+        let mut config = Self {
+            raw: data.to_vec(),
+            ..Self::default()
+        };
+
+        let u8_at = |offset: usize| data.get(offset).copied().unwrap_or(0);
+        let u16_at = |offset: usize| Reader::new(data, offset).read_u16().unwrap_or(0);
+
+        config.mcc = u16_at(4);
+        config.mnc = u16_at(6);
+        config.language = Self::unpack_locale([u8_at(8), u8_at(9)], b'a');
+        config.region = Self::unpack_locale([u8_at(10), u8_at(11)], b'0');
+        config.orientation = u8_at(12);
+        config.density = u16_at(14);
+        config.sdk_version = u16_at(24);
+        config.ui_mode = u8_at(29);
+        config.smallest_screen_width_dp = u16_at(30);
+        config.screen_width_dp = u16_at(32);
+        config.screen_height_dp = u16_at(34);
+
+        config
+    }
+
+    /// Decode a language or region, stored on two bytes or packed on three letters
+    fn unpack_locale(bytes: [u8; 2], base: u8) -> String {
+        if bytes[0] & 0x80 != 0 {
+            let first = bytes[1] & 0x1f;
+            let second = ((bytes[1] & 0xe0) >> 5) | ((bytes[0] & 0x03) << 3);
+            let third = (bytes[0] & 0x7c) >> 2;
+
+            [first, second, third]
+                .iter()
+                .map(|letter| (letter + base) as char)
+                .collect()
+        } else {
+            bytes.iter()
+                .take_while(|byte| **byte != 0)
+                .map(|byte| *byte as char)
+                .collect()
+        }
+    }
+
+    /// Return the mobile country code
+    pub fn mcc(&self) -> u16 {
+        self.mcc
+    }
+
+    /// Return the mobile network code
+    pub fn mnc(&self) -> u16 {
+        self.mnc
+    }
+
+    /// Return a reference to the language code
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Return a reference to the region code
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+
+    /// Return the screen orientation
+    pub fn orientation(&self) -> u8 {
+        self.orientation
+    }
+
+    /// Return the screen density, in dpi
+    pub fn density(&self) -> u16 {
+        self.density
+    }
+
+    /// Return the minimum SDK version
+    pub fn sdk_version(&self) -> u16 {
+        self.sdk_version
+    }
+
+    /// Return the UI mode
+    pub fn ui_mode(&self) -> u8 {
+        self.ui_mode
+    }
+
+    /// Return the smallest screen width, in dp
+    pub fn smallest_screen_width_dp(&self) -> u16 {
+        self.smallest_screen_width_dp
+    }
+
+    /// Return the screen width, in dp
+    pub fn screen_width_dp(&self) -> u16 {
+        self.screen_width_dp
+    }
+
+    /// Return the screen height, in dp
+    pub fn screen_height_dp(&self) -> u16 {
+        self.screen_height_dp
+    }
+
+    /// Return a reference to the raw `ResTable_config`
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// Whether this is the default configuration, without any qualifier
+    pub fn is_default(&self) -> bool {
+        self.raw.iter().skip(4).all(|byte| *byte == 0)
+    }
+}
+
+impl fmt::Display for ResourceConfig {
+    /// Write the configuration as resource directory qualifiers (`en-rUS-hdpi-v21`)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut qualifiers: Vec<String> = Vec::new();
+
+        if self.mcc != 0 {
+            qualifiers.push(format!("mcc{}", self.mcc));
+        }
+        if self.mnc != 0 {
+            qualifiers.push(format!("mnc{}", self.mnc));
+        }
+        if !self.language.is_empty() {
+            qualifiers.push(self.language.clone());
+        }
+        if !self.region.is_empty() {
+            qualifiers.push(format!("r{}", self.region));
+        }
+        if self.smallest_screen_width_dp != 0 {
+            qualifiers.push(format!("sw{}dp", self.smallest_screen_width_dp));
+        }
+        if self.screen_width_dp != 0 {
+            qualifiers.push(format!("w{}dp", self.screen_width_dp));
+        }
+        if self.screen_height_dp != 0 {
+            qualifiers.push(format!("h{}dp", self.screen_height_dp));
+        }
+        match self.orientation {
+            1 => qualifiers.push(String::from("port")),
+            2 => qualifiers.push(String::from("land")),
+            _ => { }
+        }
+        match self.ui_mode & 0x30 {
+            0x10 => qualifiers.push(String::from("notnight")),
+            0x20 => qualifiers.push(String::from("night")),
+            _ => { }
+        }
+        match self.density {
+            0 => { },
+            120 => qualifiers.push(String::from("ldpi")),
+            160 => qualifiers.push(String::from("mdpi")),
+            213 => qualifiers.push(String::from("tvdpi")),
+            240 => qualifiers.push(String::from("hdpi")),
+            320 => qualifiers.push(String::from("xhdpi")),
+            480 => qualifiers.push(String::from("xxhdpi")),
+            640 => qualifiers.push(String::from("xxxhdpi")),
+            0xfffe => qualifiers.push(String::from("anydpi")),
+            0xffff => qualifiers.push(String::from("nodpi")),
+            density => qualifiers.push(format!("{density}dpi"))
+        }
+        if self.sdk_version != 0 {
+            qualifiers.push(format!("v{}", self.sdk_version));
+        }
+
+        write!(f, "{}", qualifiers.join("-"))
+    }
+}
+
+/// Value of a resource entry
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceValue {
+    /// Single value (string, integer, reference, ...)
+    Simple(XmlValue),
+    /// Bag of values (style, array, plurals, ...) given with the resource ID of their keys
+    Complex {
+        parent: u32,
+        values: Vec<(u32, XmlValue)>
+    }
+}
+
+/// Value of a resource for one configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceEntry {
+    /// Resource ID
+    id: u32,
+    /// Name of the entry
+    name: String,
+    /// Configuration of this value
+    config: ResourceConfig,
+    /// Is the entry public?
+    is_public: bool,
+    value: ResourceValue
+}
+
+impl ResourceEntry {
+    /// Flag of bag entries
+    const FLAG_COMPLEX: u16 = 0x0001;
+    /// Flag of public entries
+    const FLAG_PUBLIC: u16 = 0x0002;
+    /// Flag of compact entries, storing the value in the entry itself
+    const FLAG_COMPACT: u16 = 0x0008;
+
+    /// Return the resource ID
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Return a reference to the name of the entry
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the configuration of this value
+    pub fn config(&self) -> &ResourceConfig {
+        &self.config
+    }
+
+    /// Return the is entry public?
+    pub fn is_public(&self) -> bool {
+        self.is_public
+    }
+
+    /// Return a reference to the value
+    pub fn value(&self) -> &ResourceValue {
+        &self.value
+    }
+}
+
+/// Resource type (`string`, `layout`, ...) of a package
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceType {
+    /// Type ID, the `TT` of the resource IDs
+    id: u8,
+    name: String,
+    /// Entries of every configuration
+    entries: Vec<ResourceEntry>
+}
+
+impl ResourceType {
+    /// Return the type ID
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Return a reference to the name of the type
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the entries of every configuration
+    pub fn entries(&self) -> &[ResourceEntry] {
+        &self.entries
+    }
+
+    /// Get the distinct configurations of the type
+    pub fn configs(&self) -> Vec<&ResourceConfig> {
+        let mut configs: Vec<&ResourceConfig> = Vec::new();
+
+        for entry in &self.entries {
+            if !configs.contains(&&entry.config) {
+                configs.push(&entry.config);
+            }
+        }
+
+        configs
+    }
+}
+
+/// Resource package
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourcePackage {
+    /// Package ID, the `PP` of the resource IDs
+    id: u8,
+    name: String,
+    types: Vec<ResourceType>
+}
+
+impl ResourcePackage {
+    /// Return the package ID
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// Return a reference to the name of the package
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return a reference to the types of the package
+    pub fn types(&self) -> &[ResourceType] {
+        &self.types
+    }
+
+    /// Find a type given its name
+    pub fn resource_type(&self, name: &str) -> Option<&ResourceType> {
+        self.types
+            .iter()
+            .find(|resource_type| resource_type.name == name)
+    }
+}
+
+/// Name of a resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceName {
+    package: String,
+    type_name: String,
+    name: String
+}
+
+impl ResourceName {
+    /// Return a reference to the package name
+    pub fn package(&self) -> &str {
+        &self.package
+    }
+
+    /// Return a reference to the type name
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// Return a reference to the entry name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the name as used in Java code (`R.string.api_key`)
+    pub fn to_java(&self) -> String {
+        format!("R.{}.{}", self.type_name, self.name)
+    }
+}
+
+impl fmt::Display for ResourceName {
+    /// Write the name as `package:type/name`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}/{}", self.package, self.type_name, self.name)
+    }
+}
+
+/// Decoded `resources.arsc`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceTable {
+    packages: Vec<ResourcePackage>,
+    /// Location of the entries of every resource ID, as (package, type, entries) indexes
+    by_id: HashMap<u32, (usize, usize, Vec<usize>)>
+}
+
+impl ResourceTable {
+    /// Decode a resource table
+    ///
+    /// Returns `None` if the data is not a resource table.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let header = ChunkHeader::read(data, 0)?;
+        if header.chunk_type != RES_TABLE_TYPE {
+            return None;
+        }
+
+        let mut strings = StringPool::default();
+        let mut packages = Vec::new();
+
+        let mut offset = header.body();
+        while let Some(chunk) = ChunkHeader::read(data, offset) {
+            match chunk.chunk_type {
+                RES_STRING_POOL_TYPE => {
+                    strings = StringPool::parse(data, &chunk)?;
+                },
+                RES_TABLE_PACKAGE_TYPE => {
+                    if let Some(package) = Self::parse_package(data, &chunk, &strings) {
+                        packages.push(package);
+                    }
+                },
+                _ => { }
+            }
+
+            offset = chunk.end();
+        }
+
+        let by_id = Self::index(&packages);
+
+        Some(Self { packages, by_id })
+    }
+
+    /// Locate the entries of every resource ID
+    fn index(packages: &[ResourcePackage]) -> HashMap<u32, (usize, usize, Vec<usize>)> {
+        let mut by_id: HashMap<u32, (usize, usize, Vec<usize>)> = HashMap::new();
+        for (package_idx, package) in packages.iter().enumerate() {
+            for (type_idx, resource_type) in package.types.iter().enumerate() {
+                for (entry_idx, entry) in resource_type.entries.iter().enumerate() {
+                    by_id.entry(entry.id)
+                        .or_insert_with(|| (package_idx, type_idx, Vec::new()))
+                        .2
+                        .push(entry_idx);
+                }
+            }
+        }
+        by_id
+    }
+
+    /// Add the entries of another table, such as the table of a split APK
+    ///
+    /// Packages and types with the same ID are merged, so the configurations
+    /// of a split (densities, languages, ...) are added to the base values.
+    pub fn merge(&mut self, other: ResourceTable) {
+        for package in other.packages {
+            let Some(base) = self.packages.iter_mut().find(|base| base.id == package.id) else {
+                self.packages.push(package);
+                continue;
+            };
+
+            for resource_type in package.types {
+                match base.types.iter_mut().find(|base| base.id == resource_type.id) {
+                    Some(base) => base.entries.extend(resource_type.entries),
+                    None => base.types.push(resource_type)
+                }
+            }
+        }
+
+        self.by_id = Self::index(&self.packages);
+    }
+
+    fn parse_package(data: &[u8], chunk: &ChunkHeader, strings: &StringPool) -> Option<ResourcePackage> {
+        let mut reader = Reader::new(data, chunk.offset + 8);
+        let id = reader.read_u32()? as u8;
+
+        let name_units = reader.read_bytes(256)?
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .take_while(|unit| *unit != 0)
+            .collect::<Vec<u16>>();
+        let name = String::from_utf16_lossy(&name_units);
+
+        let type_strings = reader.read_u32()? as usize;
+        let _last_public_type = reader.read_u32()?;
+        let key_strings = reader.read_u32()? as usize;
+
+        let read_pool = |offset: usize| ChunkHeader::read(data, chunk.offset + offset)
+            .filter(|pool| pool.chunk_type == RES_STRING_POOL_TYPE)
+            .and_then(|pool| StringPool::parse(data, &pool))
+            .unwrap_or_default();
+        let type_names = read_pool(type_strings);
+        let keys = read_pool(key_strings);
+
+        let mut types: Vec<ResourceType> = Vec::new();
+
+        let mut offset = chunk.body();
+        while offset < chunk.end() {
+            let Some(type_chunk) = ChunkHeader::read(data, offset) else { break };
+
+            if type_chunk.chunk_type == RES_TABLE_TYPE_TYPE {
+                let Some(&type_id) = data.get(type_chunk.offset + 8) else { break };
+                let entries = Self::parse_type(data, &type_chunk, id, strings, &keys);
+
+                match types.iter_mut().find(|resource_type| resource_type.id == type_id) {
+                    Some(resource_type) => resource_type.entries.extend(entries),
+                    None => types.push(ResourceType {
+                        id: type_id,
+                        name: type_names.get(type_id.wrapping_sub(1) as u32).unwrap_or_default().to_string(),
+                        entries
+                    })
+                }
+            }
+
+            offset = type_chunk.end();
+        }
+
+        Some(ResourcePackage { id, name, types })
+    }
+
+    fn parse_type(data: &[u8], chunk: &ChunkHeader, package_id: u8, strings: &StringPool, keys: &StringPool) -> Vec<ResourceEntry> {
+        const FLAG_SPARSE: u8 = 0x01;
+        const FLAG_OFFSET16: u8 = 0x02;
+
+        let mut reader = Reader::new(data, chunk.offset + 8);
+        let (Some(type_id), Some(flags), Some(_reserved), Some(entry_count), Some(entries_start), Some(config_size)) = (
+            reader.read_u8(),
+            reader.read_u8(),
+            reader.read_u16(),
+            reader.read_u32(),
+            reader.read_u32(),
+            Reader::new(data, chunk.offset + 20).read_u32()
+        ) else {
+            return Vec::new();
+        };
+
+        let config_start = chunk.offset + 20;
+        let config = data.get(config_start..config_start.saturating_add(config_size as usize).min(chunk.end()))
+            .map(ResourceConfig::parse)
+            .unwrap_or_default();
+
+        // Entry index and offset of every entry present in the chunk
+        let mut offsets = Reader::new(data, chunk.body());
+        let locations = (0..entry_count)
+            .map_while(|idx| {
+                if flags & FLAG_SPARSE != 0 {
+                    let entry_idx = offsets.read_u16()? as u32;
+                    let offset = offsets.read_u16()? as u32 * 4;
+                    Some(Some((entry_idx, offset)))
+                } else if flags & FLAG_OFFSET16 != 0 {
+                    let offset = offsets.read_u16()?;
+                    Some((offset != 0xffff).then_some((idx, offset as u32 * 4)))
+                } else {
+                    let offset = offsets.read_u32()?;
+                    Some((offset != 0xffff_ffff).then_some((idx, offset)))
+                }
+            })
+            .flatten()
+            .collect::<Vec<(u32, u32)>>();
+
+        let entries_start = chunk.offset + entries_start as usize;
+
+        locations.into_iter()
+            .filter_map(|(entry_idx, offset)| {
+                let mut reader = Reader::new(data, entries_start + offset as usize);
+                let size = reader.read_u16()?;
+                let entry_flags = reader.read_u16()?;
+                let id = ((package_id as u32) << 24) | ((type_id as u32) << 16) | entry_idx;
+
+                let (key, value) = if entry_flags & ResourceEntry::FLAG_COMPACT != 0 {
+                    // The key is in the size field and the type in the upper flags
+                    let value = reader.read_u32()?;
+                    (size as u32, ResourceValue::Simple(Self::value((entry_flags >> 8) as u8, value, strings)))
+                } else {
+                    let key = reader.read_u32()?;
+                    if entry_flags & ResourceEntry::FLAG_COMPLEX != 0 {
+                        let parent = reader.read_u32()?;
+                        let count = reader.read_u32()?;
+                        let values = (0..count)
+                            .map_while(|_| {
+                                let name = reader.read_u32()?;
+                                Some((name, Self::read_value(&mut reader, strings)?))
+                            })
+                            .collect();
+
+                        (key, ResourceValue::Complex { parent, values })
+                    } else {
+                        let mut reader = Reader::new(data, entries_start + offset as usize + size as usize);
+                        (key, ResourceValue::Simple(Self::read_value(&mut reader, strings)?))
+                    }
+                };
+
+                Some(ResourceEntry {
+                    id,
+                    name: keys.get(key).unwrap_or_default().to_string(),
+                    config: config.clone(),
+                    is_public: entry_flags & ResourceEntry::FLAG_PUBLIC != 0,
+                    value
+                })
+            })
+            .collect()
+    }
+
+    /// Read a `Res_value`
+    fn read_value(reader: &mut Reader, strings: &StringPool) -> Option<XmlValue> {
+        let _size = reader.read_u16()?;
+        let _res0 = reader.read_u8()?;
+        let data_type = reader.read_u8()?;
+        let data = reader.read_u32()?;
+
+        Some(Self::value(data_type, data, strings))
+    }
+
+    fn value(data_type: u8, data: u32, strings: &StringPool) -> XmlValue {
+        XmlValue::from_res_value(data_type, data, strings.get(data))
+    }
+
+    /// Return a reference to the packages
+    pub fn packages(&self) -> &[ResourcePackage] {
+        &self.packages
+    }
+
+    /// Get the values of a resource for every configuration
+    pub fn get_entries(&self, id: u32) -> Vec<&ResourceEntry> {
+        let Some((package_idx, type_idx, entries)) = self.by_id.get(&id) else {
+            return Vec::new();
+        };
+
+        let resource_type = &self.packages[*package_idx].types[*type_idx];
+        entries.iter()
+            .map(|idx| &resource_type.entries[*idx])
+            .collect()
+    }
+
+    /// Get the value of a resource for the default configuration, or for the
+    /// first configuration if there is no default value
+    pub fn get_value(&self, id: u32) -> Option<&ResourceValue> {
+        let entries = self.get_entries(id);
+
+        entries.iter()
+            .find(|entry| entry.config.is_default())
+            .or(entries.first())
+            .map(|entry| &entry.value)
+    }
+
+    /// Get the name of a resource given its ID
+    pub fn get_name(&self, id: u32) -> Option<ResourceName> {
+        let (package_idx, type_idx, entries) = self.by_id.get(&id)?;
+        let package = &self.packages[*package_idx];
+        let resource_type = &package.types[*type_idx];
+
+        Some(ResourceName {
+            package: package.name.clone(),
+            type_name: resource_type.name.clone(),
+            name: resource_type.entries[*entries.first()?].name.clone()
+        })
+    }
+
+    /// Get the ID of a resource given its type and name
+    pub fn get_id(&self, type_name: &str, name: &str) -> Option<u32> {
+        self.packages
+            .iter()
+            .filter_map(|package| package.resource_type(type_name))
+            .flat_map(|resource_type| resource_type.entries.iter())
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.id)
+    }
+
+    /// Get the resource loaded by a `const` instruction, if its literal is a
+    /// resource ID of the table
+    ///
+    /// This allows annotating `const v0, 0x7f0b0012` as `R.string.api_key`.
+    /// Literals are only decoded when the method was disassembled from a
+    /// DEX file read by this crate.
+    pub fn resource_for_instruction(&self, instruction: &DvmInstruction) -> Option<ResourceName> {
+        let literal = instruction.literal().filter(|literal| !literal.is_wide())?;
+        self.get_name(literal.int_value() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use crate::zip_file::ZipArchive;
+
+    #[test]
+    fn test_parse_resources() {
+        let archive = ZipArchive::from_path(Path::new("test_files/test_resources.apk")).unwrap();
+        let data = archive.read_by_name("resources.arsc").unwrap();
+        let table = ResourceTable::parse(&data).unwrap();
+
+        assert_eq!(table.packages().len(), 1);
+        let package = &table.packages()[0];
+        assert_eq!(package.id(), 0x7f);
+        assert_eq!(package.name(), "com.example.test");
+        assert_eq!(package.types().len(), 2);
+
+        let strings = package.resource_type("string").unwrap();
+        assert_eq!(strings.id(), 2);
+        assert_eq!(strings.configs().len(), 2);
+        assert_eq!(strings.configs()[1].language(), "fr");
+        assert_eq!(strings.configs()[1].to_string(), "fr-rFR");
+
+        let name = table.get_name(0x7f020001).unwrap();
+        assert_eq!(name.to_string(), "com.example.test:string/api_key");
+        assert_eq!(name.to_java(), "R.string.api_key");
+        assert_eq!(table.get_id("string", "api_key"), Some(0x7f020001));
+
+        assert_eq!(table.get_entries(0x7f020000).len(), 2);
+        assert_eq!(
+            table.get_value(0x7f020000),
+            Some(&ResourceValue::Simple(XmlValue::String(String::from("Test"))))
+        );
+        assert_eq!(
+            table.get_value(0x7f020001),
+            Some(&ResourceValue::Simple(XmlValue::String(String::from("s3cr3t"))))
+        );
+        assert_eq!(
+            table.get_value(0x7f010000),
+            Some(&ResourceValue::Simple(XmlValue::Int(30)))
+        );
+
+        let entries = table.get_entries(0x7f020000);
+        assert_eq!(
+            entries[1].value(),
+            &ResourceValue::Simple(XmlValue::String(String::from("Essai")))
+        );
+
+        assert!(table.get_name(0x7f030000).is_none());
+        assert!(ResourceTable::parse(b"not a table").is_none());
+    }
+
+    #[test]
+    fn test_unpack_locale() {
+        assert_eq!(ResourceConfig::unpack_locale([b'e', b'n'], b'a'), "en");
+        assert_eq!(ResourceConfig::unpack_locale([0, 0], b'a'), "");
+        // "fil" packed on two bytes
+        assert_eq!(ResourceConfig::unpack_locale([0xad, 0x05], b'a'), "fil");
+    }
+}
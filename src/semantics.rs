@@ -0,0 +1,392 @@
+//! Semantics of the instructions
+//!
+//! Shuriken only reports the opcode of an instruction. The operands are
+//! decoded from the code units of the DEX file to know which registers an
+//! instruction reads and writes, where it may transfer control, whether it
+//! can throw and whether it has side effects.
+//!
+//! See the [`documentation`] for the formats and the opcodes.
+//!
+//! [`documentation`]: https://source.android.com/docs/core/runtime/dalvik-bytecode
+
+use crate::dex_file::{ CodeItem, DexFile };
+
+/// Transfer of control after an instruction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControlFlow {
+    /// Continue with the next instruction
+    FallThrough,
+    /// `goto` to the address
+    Goto(u64),
+    /// `if-test` to the address, or to the next instruction
+    Branch(u64),
+    /// `packed-switch` or `sparse-switch`, to the targets of its table or to
+    /// the next instruction
+    Switch,
+    /// `return`
+    Return,
+    /// `throw`
+    Throw
+}
+
+/// Registers accessed by an instruction and effects of its execution
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstructionSemantics {
+    /// Registers read, with both registers of wide values
+    reads: Vec<u16>,
+    /// Registers written, with both registers of wide values
+    writes: Vec<u16>,
+    control_flow: ControlFlow,
+    can_throw: bool,
+    has_side_effects: bool
+}
+
+impl InstructionSemantics {
+    /// Decode the semantics of the instruction at a given address in bytes
+    pub(crate) fn read(dex: &DexFile, code: &CodeItem, address: u64) -> Self {
+        Self::decode(&code_units(dex, code, address), address)
+    }
+
+    /// Decode the semantics of the code units of an instruction
+    pub(crate) fn decode(units: &[u16; 5], address: u64) -> Self {
+        let op = units[0] & 0xff;
+        let a = (units[0] >> 8) & 0xf;
+        let b = units[0] >> 12;
+        let aa = units[0] >> 8;
+        let bb = units[1] & 0xff;
+        let cc = units[1] >> 8;
+
+        let mut reads = Vec::new();
+        let mut writes = Vec::new();
+        let mut control_flow = ControlFlow::FallThrough;
+        let target = |offset: i64| address.wrapping_add_signed(offset * 2);
+
+        match op {
+            // move, move-wide and move-object
+            0x01 | 0x04 | 0x07 => {
+                let wide = op == 0x04;
+                access(&mut reads, b, wide);
+                access(&mut writes, a, wide);
+            },
+            0x02 | 0x05 | 0x08 => {
+                let wide = op == 0x05;
+                access(&mut reads, units[1], wide);
+                access(&mut writes, aa, wide);
+            },
+            0x03 | 0x06 | 0x09 => {
+                let wide = op == 0x06;
+                access(&mut reads, units[2], wide);
+                access(&mut writes, units[1], wide);
+            },
+            // move-result, of the previous instruction, and move-exception
+            0x0a..=0x0d => access(&mut writes, aa, op == 0x0b),
+            // return
+            0x0e => control_flow = ControlFlow::Return,
+            0x0f..=0x11 => {
+                access(&mut reads, aa, op == 0x10);
+                control_flow = ControlFlow::Return;
+            },
+            // const
+            0x12 => access(&mut writes, a, false),
+            0x13..=0x19 => access(&mut writes, aa, op >= 0x16),
+            0x1a..=0x1c | 0x22 | 0xfe | 0xff => access(&mut writes, aa, false),
+            // monitor-enter, monitor-exit and check-cast
+            0x1d | 0x1e => access(&mut reads, aa, false),
+            0x1f => {
+                access(&mut reads, aa, false);
+                access(&mut writes, aa, false);
+            },
+            // instance-of, array-length and new-array
+            0x20 | 0x21 | 0x23 => {
+                access(&mut reads, b, false);
+                access(&mut writes, a, false);
+            },
+            // filled-new-array and invoke-kind, with wide arguments in two registers
+            0x24 | 0x6e..=0x72 | 0xfa | 0xfc => {
+                let registers = [units[2] & 0xf, (units[2] >> 4) & 0xf, (units[2] >> 8) & 0xf, units[2] >> 12, a];
+                reads.extend_from_slice(&registers[..(b as usize).min(5)]);
+            },
+            0x25 | 0x74..=0x78 | 0xfb | 0xfd => reads.extend((0..aa).map(|n| units[2].wrapping_add(n))),
+            // fill-array-data and throw
+            0x26 => access(&mut reads, aa, false),
+            0x27 => {
+                access(&mut reads, aa, false);
+                control_flow = ControlFlow::Throw;
+            },
+            // goto
+            0x28 => control_flow = ControlFlow::Goto(target((aa as u8 as i8) as i64)),
+            0x29 => control_flow = ControlFlow::Goto(target(units[1] as i16 as i64)),
+            0x2a => control_flow = ControlFlow::Goto(target((units[1] as u32 | (units[2] as u32) << 16) as i32 as i64)),
+            // packed-switch and sparse-switch
+            0x2b | 0x2c => {
+                access(&mut reads, aa, false);
+                control_flow = ControlFlow::Switch;
+            },
+            // cmp-kind
+            0x2d..=0x31 => {
+                let wide = op >= 0x2f;
+                access(&mut reads, bb, wide);
+                access(&mut reads, cc, wide);
+                access(&mut writes, aa, false);
+            },
+            // if-test and if-testz
+            0x32..=0x37 => {
+                access(&mut reads, a, false);
+                access(&mut reads, b, false);
+                control_flow = ControlFlow::Branch(target(units[1] as i16 as i64));
+            },
+            0x38..=0x3d => {
+                access(&mut reads, aa, false);
+                control_flow = ControlFlow::Branch(target(units[1] as i16 as i64));
+            },
+            // aget and aput
+            0x44..=0x4a => {
+                access(&mut reads, bb, false);
+                access(&mut reads, cc, false);
+                access(&mut writes, aa, op == 0x45);
+            },
+            0x4b..=0x51 => {
+                access(&mut reads, aa, op == 0x4c);
+                access(&mut reads, bb, false);
+                access(&mut reads, cc, false);
+            },
+            // iget and iput
+            0x52..=0x58 => {
+                access(&mut reads, b, false);
+                access(&mut writes, a, op == 0x53);
+            },
+            0x59..=0x5f => {
+                access(&mut reads, a, op == 0x5a);
+                access(&mut reads, b, false);
+            },
+            // sget and sput
+            0x60..=0x66 => access(&mut writes, aa, op == 0x61),
+            0x67..=0x6d => access(&mut reads, aa, op == 0x68),
+            // unop
+            0x7b..=0x8f => {
+                let (source_wide, destination_wide) = unop_widths(op);
+                access(&mut reads, b, source_wide);
+                access(&mut writes, a, destination_wide);
+            },
+            // binop and binop/2addr
+            0x90..=0xaf => {
+                let (wide, second_wide) = binop_widths(op - 0x90);
+                access(&mut reads, bb, wide);
+                access(&mut reads, cc, second_wide);
+                access(&mut writes, aa, wide);
+            },
+            0xb0..=0xcf => {
+                let (wide, second_wide) = binop_widths(op - 0xb0);
+                access(&mut reads, a, wide);
+                access(&mut reads, b, second_wide);
+                access(&mut writes, a, wide);
+            },
+            // binop/lit16 and binop/lit8
+            0xd0..=0xd7 => {
+                access(&mut reads, b, false);
+                access(&mut writes, a, false);
+            },
+            0xd8..=0xe2 => {
+                access(&mut reads, bb, false);
+                access(&mut writes, aa, false);
+            },
+            _ => ()
+        }
+
+        Self {
+            reads,
+            writes,
+            control_flow,
+            can_throw: can_throw(op),
+            has_side_effects: has_side_effects(op)
+        }
+    }
+
+    /// Return a reference to the registers read, with both registers of a
+    /// wide value and every register of a range
+    pub fn reads(&self) -> &[u16] {
+        &self.reads
+    }
+
+    /// Return a reference to the registers written, with both registers of a
+    /// wide value
+    ///
+    /// The result of an invoke is written by the following `move-result`.
+    pub fn writes(&self) -> &[u16] {
+        &self.writes
+    }
+
+    /// Return the transfer of control after the instruction
+    pub fn control_flow(&self) -> ControlFlow {
+        self.control_flow
+    }
+
+    /// Get the address of the `goto` or `if-test` target
+    pub fn branch_target(&self) -> Option<u64> {
+        match self.control_flow {
+            ControlFlow::Goto(target) | ControlFlow::Branch(target) => Some(target),
+            _ => None
+        }
+    }
+
+    /// Whether the instruction never continues with the next instruction
+    pub fn is_terminator(&self) -> bool {
+        matches!(self.control_flow, ControlFlow::Goto(_) | ControlFlow::Return | ControlFlow::Throw)
+    }
+
+    /// Whether the instruction may raise an exception, like a null pointer,
+    /// a failed class resolution or an exception from an invoked method
+    pub fn can_throw(&self) -> bool {
+        self.can_throw
+    }
+
+    /// Whether the instruction has an effect besides writing registers:
+    /// writing memory, synchronizing or invoking a method
+    ///
+    /// Class initializers run by the first access to a class are not taken
+    /// into account.
+    pub fn has_side_effects(&self) -> bool {
+        self.has_side_effects
+    }
+}
+
+/// Read the code units of the instruction at a given address, the longest
+/// instructions having 5 units. Units past the end of the code are zero.
+pub(crate) fn code_units(dex: &DexFile, code: &CodeItem, address: u64) -> [u16; 5] {
+    let mut units = [0; 5];
+    for (n, unit) in units.iter_mut().enumerate() {
+        *unit = dex.u16_at(code.insns_off + address as usize + n * 2).unwrap_or_default();
+    }
+    units
+}
+
+/// Add a register to a list, with the second register of a wide value
+fn access(registers: &mut Vec<u16>, register: u16, wide: bool) {
+    registers.push(register);
+    if wide {
+        registers.push(register.wrapping_add(1));
+    }
+}
+
+/// Whether the source and the destination of a unop are wide
+fn unop_widths(op: u16) -> (bool, bool) {
+    match op {
+        0x7d | 0x7e | 0x80 | 0x86 | 0x8b => (true, true),
+        0x81 | 0x83 | 0x88 | 0x89 => (false, true),
+        0x84 | 0x85 | 0x8a | 0x8c => (true, false),
+        _ => (false, false)
+    }
+}
+
+/// Whether the first operand and the result, and the second operand of a
+/// binop are wide, given its offset from `add-int`
+fn binop_widths(offset: u16) -> (bool, bool) {
+    match offset {
+        // shl-long, shr-long and ushr-long shift by an int
+        0x13..=0x15 => (true, false),
+        0x0b..=0x15 | 0x1b..=0x1f => (true, true),
+        _ => (false, false)
+    }
+}
+
+/// Whether an opcode may raise an exception
+fn can_throw(op: u16) -> bool {
+    matches!(
+        op,
+        // const-string, const-class, monitors, check-cast, instance-of and allocations
+        0x1a..=0x27
+            // arrays, fields and invokes
+            | 0x44..=0x72 | 0x74..=0x78
+            // div-int, rem-int, div-long and rem-long, and their variants
+            | 0x93 | 0x94 | 0x9e | 0x9f | 0xb3 | 0xb4 | 0xbe | 0xbf | 0xd3 | 0xd4 | 0xdb | 0xdc
+            // invoke-polymorphic, invoke-custom and method handles
+            | 0xfa..=0xff
+    )
+}
+
+/// Whether an opcode leaves a result for a following `move-result`:
+/// `filled-new-array` and the invokes
+pub(crate) fn has_result(op: u16) -> bool {
+    matches!(op, 0x24 | 0x25 | 0x6e..=0x72 | 0x74..=0x78 | 0xfa..=0xfd)
+}
+
+/// Whether an opcode is a `move-result`, `move-result-wide` or
+/// `move-result-object`
+pub(crate) fn is_move_result(op: u16) -> bool {
+    matches!(op, 0x0a..=0x0c)
+}
+
+/// Whether an opcode writes memory, synchronizes or invokes a method
+fn has_side_effects(op: u16) -> bool {
+    matches!(op, 0x1d | 0x1e | 0x26 | 0x4b..=0x51 | 0x59..=0x5f | 0x67..=0x72 | 0x74..=0x78 | 0xfa..=0xfd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(units: &[u16], address: u64) -> InstructionSemantics {
+        let mut padded = [0; 5];
+        padded[..units.len()].copy_from_slice(units);
+        InstructionSemantics::decode(&padded, address)
+    }
+
+    #[test]
+    fn test_registers() {
+        // add-long v0, v2, v4
+        let add = decode(&[0x009b, 0x0402], 0);
+        assert_eq!((add.reads(), add.writes()), (&[2, 3, 4, 5][..], &[0, 1][..]));
+        assert!(!add.can_throw() && !add.has_side_effects());
+
+        // shl-long/2addr v2, v4
+        let shl = decode(&[0x42c3], 0);
+        assert_eq!((shl.reads(), shl.writes()), (&[2, 3, 4][..], &[2, 3][..]));
+
+        // invoke-virtual {v1, v2, v3}, with a wide argument
+        let invoke = decode(&[0x306e, 0x0005, 0x0321], 0);
+        assert_eq!(invoke.reads(), &[1, 2, 3]);
+        assert!(invoke.writes().is_empty());
+        assert!(invoke.can_throw() && invoke.has_side_effects());
+
+        // invoke-static/range {v6 .. v10}
+        let invoke = decode(&[0x0577, 0x0002, 0x0006], 0);
+        assert_eq!(invoke.reads(), &[6, 7, 8, 9, 10]);
+
+        // double-to-int v4, v2
+        let convert = decode(&[0x248a], 0);
+        assert_eq!((convert.reads(), convert.writes()), (&[2, 3][..], &[4][..]));
+
+        // iput-wide v2, v0
+        let iput = decode(&[0x025a, 0x0001], 0);
+        assert_eq!(iput.reads(), &[2, 3, 0]);
+
+        // move-result-object v1, after an invoke
+        assert!(is_move_result(0x0c) && has_result(0x71));
+        assert!(!is_move_result(0x0d) && !has_result(0x1a));
+
+        // move-wide/16 v65535, v0, the pair wraps around
+        let move_wide = decode(&[0x0006, 0xffff, 0x0000], 0);
+        assert_eq!((move_wide.reads(), move_wide.writes()), (&[0, 1][..], &[0xffff, 0][..]));
+    }
+
+    #[test]
+    fn test_control_flow() {
+        // goto -8 at 0x20
+        let goto = decode(&[0xf828], 0x20);
+        assert_eq!(goto.control_flow(), ControlFlow::Goto(0x10));
+        assert!(goto.is_terminator());
+
+        // if-eqz v0, +4 at 0x10
+        let branch = decode(&[0x0038, 0x0004], 0x10);
+        assert_eq!(branch.branch_target(), Some(0x18));
+        assert!(!branch.is_terminator());
+
+        // return-wide v2 and throw v1
+        assert_eq!(decode(&[0x0210], 0).reads(), &[2, 3]);
+        let throw = decode(&[0x0127], 0);
+        assert!(throw.is_terminator() && throw.can_throw());
+
+        assert_eq!(decode(&[0x002b, 0x0010, 0x0000], 0).control_flow(), ControlFlow::Switch);
+        assert_eq!(decode(&[0x000e], 0).control_flow(), ControlFlow::Return);
+    }
+}
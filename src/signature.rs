@@ -0,0 +1,903 @@
+//! APK signatures
+//!
+//! Parse the JAR signatures of `META-INF` (v1 scheme) and the APK Signing
+//! Block (v2, v3 and v3.1 schemes), check the digests they contain against
+//! the content of the archive and expose the signer certificates.
+//!
+//! Only the digests are verified: the signatures over the signed data are
+//! not checked cryptographically. An APK whose digests match has not been
+//! modified after signing, but nothing proves the signer owns the key.
+//!
+//! See the [`APK signature scheme`] documentation for the format details.
+//!
+//! [`APK signature scheme`]: https://source.android.com/docs/security/features/apksigning
+
+use std::collections::HashMap;
+use std::fmt;
+
+use sha1::Sha1;
+use sha2::{ Digest, Sha256, Sha384, Sha512 };
+
+use crate::der::{ self, Tlv, TAG_INTEGER, TAG_SEQUENCE };
+use crate::dex_file::Reader;
+use crate::zip_file::ZipArchive;
+
+/// Magic at the end of the APK Signing Block
+const SIGNING_BLOCK_MAGIC: &[u8; 16] = b"APK Sig Block 42";
+/// ID of the v2 scheme block
+const V2_BLOCK_ID: u32 = 0x7109_871a;
+/// ID of the v3 scheme block
+const V3_BLOCK_ID: u32 = 0xf053_68c0;
+/// ID of the v3.1 scheme block
+const V31_BLOCK_ID: u32 = 0x1b93_ad61;
+/// ID-value pairs of the APK Signing Block
+type BlockPairs<'a> = Vec<(u32, &'a [u8])>;
+
+/// Size of the chunks hashed by the v2 and v3 schemes
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// APK signature scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureScheme {
+    /// JAR signature in `META-INF`
+    V1,
+    /// APK Signature Scheme v2
+    V2,
+    /// APK Signature Scheme v3
+    V3,
+    /// APK Signature Scheme v3.1
+    V31
+}
+
+impl fmt::Display for SignatureScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SignatureScheme::V1 => write!(f, "v1"),
+            SignatureScheme::V2 => write!(f, "v2"),
+            SignatureScheme::V3 => write!(f, "v3"),
+            SignatureScheme::V31 => write!(f, "v3.1")
+        }
+    }
+}
+
+/// Hash function used by a digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512
+}
+
+impl DigestAlgorithm {
+    /// Algorithm of a `<name>-Digest` attribute of a JAR manifest
+    fn from_jar_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA1" | "SHA-1" => Some(DigestAlgorithm::Sha1),
+            "SHA-256" => Some(DigestAlgorithm::Sha256),
+            "SHA-384" => Some(DigestAlgorithm::Sha384),
+            "SHA-512" => Some(DigestAlgorithm::Sha512),
+            _ => None
+        }
+    }
+
+    /// Content digest algorithm of a v2 or v3 signature algorithm
+    ///
+    /// `None` for the verity algorithms, which are not supported.
+    fn from_signature_algorithm(algorithm: u32) -> Option<Self> {
+        match algorithm {
+            0x0101 | 0x0103 | 0x0201 | 0x0301 => Some(DigestAlgorithm::Sha256),
+            0x0102 | 0x0104 | 0x0202 => Some(DigestAlgorithm::Sha512),
+            _ => None
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::Sha1 => Sha1::digest(data).to_vec(),
+            DigestAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+            DigestAlgorithm::Sha384 => Sha384::digest(data).to_vec(),
+            DigestAlgorithm::Sha512 => Sha512::digest(data).to_vec()
+        }
+    }
+
+    /// Chunked digest of the v2 and v3 schemes over the sections of the archive
+    fn chunked_digest(&self, sections: &[&[u8]]) -> Vec<u8> {
+        let chunks = sections.iter()
+            .flat_map(|section| section.chunks(CHUNK_SIZE))
+            .collect::<Vec<&[u8]>>();
+
+        let mut top = vec![0x5a];
+        top.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+
+        for chunk in chunks {
+            let mut data = Vec::with_capacity(chunk.len() + 5);
+            data.push(0xa5);
+            data.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            data.extend_from_slice(chunk);
+            top.extend(self.digest(&data));
+        }
+
+        self.digest(&top)
+    }
+}
+
+/// X.509 certificate of a signer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Certificate {
+    der: Vec<u8>,
+    subject: String,
+    issuer: String,
+    serial_number: String,
+    not_before: String,
+    not_after: String,
+    signature_algorithm: String,
+    public_key_algorithm: String
+}
+
+impl Certificate {
+    /// Parse a DER-encoded certificate
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (certificate, _) = Tlv::expect(data, TAG_SEQUENCE)?;
+        let fields = certificate.children();
+        let signature_algorithm = Self::algorithm_name(fields.get(1)?)?;
+
+        let tbs = fields.first()?.children();
+        // The version is an optional explicit [0] field
+        let tbs = match tbs.first()? {
+            tlv if tlv.tag == 0xa0 => &tbs[1..],
+            _ => &tbs[..]
+        };
+
+        let serial = tbs.first().filter(|tlv| tlv.tag == TAG_INTEGER)?.content;
+        let serial = match serial {
+            [0, rest @ ..] if !rest.is_empty() => rest,
+            serial => serial
+        };
+
+        let validity = tbs.get(3)?.children();
+        let public_key = tbs.get(5)?.children();
+
+        Some(Self {
+            der: certificate.raw.to_vec(),
+            subject: Self::distinguished_name(tbs.get(4)?),
+            issuer: Self::distinguished_name(tbs.get(2)?),
+            serial_number: der::to_hex(serial),
+            not_before: validity.first()?.time()?,
+            not_after: validity.get(1)?.time()?,
+            signature_algorithm,
+            public_key_algorithm: Self::algorithm_name(public_key.first()?)?
+        })
+    }
+
+    /// Name of an `AlgorithmIdentifier`, or its OID when unknown
+    fn algorithm_name(identifier: &Tlv) -> Option<String> {
+        let oid = identifier.children().first()?.oid()?;
+
+        let name = match oid.as_str() {
+            "1.2.840.113549.1.1.1" => "RSA",
+            "1.2.840.113549.1.1.4" => "MD5withRSA",
+            "1.2.840.113549.1.1.5" => "SHA1withRSA",
+            "1.2.840.113549.1.1.11" => "SHA256withRSA",
+            "1.2.840.113549.1.1.12" => "SHA384withRSA",
+            "1.2.840.113549.1.1.13" => "SHA512withRSA",
+            "1.2.840.10040.4.1" => "DSA",
+            "1.2.840.10040.4.3" => "SHA1withDSA",
+            "2.16.840.1.101.3.4.3.2" => "SHA256withDSA",
+            "1.2.840.10045.2.1" => "EC",
+            "1.2.840.10045.4.1" => "SHA1withECDSA",
+            "1.2.840.10045.4.3.2" => "SHA256withECDSA",
+            "1.2.840.10045.4.3.3" => "SHA384withECDSA",
+            "1.2.840.10045.4.3.4" => "SHA512withECDSA",
+            _ => return Some(oid)
+        };
+
+        Some(name.to_owned())
+    }
+
+    /// Format a `Name` like RFC 4514, last RDN first
+    fn distinguished_name(name: &Tlv) -> String {
+        let mut attributes = Vec::new();
+
+        for rdn in name.children().iter().rev() {
+            for attribute in rdn.children() {
+                let fields = attribute.children();
+                let (Some(oid), Some(value)) = (fields.first().and_then(Tlv::oid), fields.get(1)) else {
+                    continue;
+                };
+
+                let key = match oid.as_str() {
+                    "2.5.4.3" => "CN",
+                    "2.5.4.5" => "SERIALNUMBER",
+                    "2.5.4.6" => "C",
+                    "2.5.4.7" => "L",
+                    "2.5.4.8" => "ST",
+                    "2.5.4.9" => "STREET",
+                    "2.5.4.10" => "O",
+                    "2.5.4.11" => "OU",
+                    "1.2.840.113549.1.9.1" => "EMAILADDRESS",
+                    "0.9.2342.19200300.100.1.1" => "UID",
+                    "0.9.2342.19200300.100.1.25" => "DC",
+                    _ => oid.as_str()
+                };
+
+                let mut escaped = String::new();
+                for c in value.string().chars() {
+                    if matches!(c, ',' | '+' | '"' | '\\' | '<' | '>' | ';') {
+                        escaped.push('\\');
+                    }
+                    escaped.push(c);
+                }
+
+                attributes.push(format!("{key}={escaped}"));
+            }
+        }
+
+        attributes.join(", ")
+    }
+
+    /// Return a reference to the DER encoding of the certificate
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// Return a reference to the subject, as `CN=..., O=..., C=...`
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Return a reference to the issuer, as `CN=..., O=..., C=...`
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Whether the certificate is issued by its own subject
+    pub fn is_self_signed(&self) -> bool {
+        self.subject == self.issuer
+    }
+
+    /// Return a reference to the serial number in hexadecimal
+    pub fn serial_number(&self) -> &str {
+        &self.serial_number
+    }
+
+    /// Return a reference to the start of the validity period, as `YYYY-MM-DD HH:MM:SS` UTC
+    pub fn not_before(&self) -> &str {
+        &self.not_before
+    }
+
+    /// Return a reference to the end of the validity period, as `YYYY-MM-DD HH:MM:SS` UTC
+    pub fn not_after(&self) -> &str {
+        &self.not_after
+    }
+
+    /// Return a reference to the name of the algorithm of the certificate signature
+    ///
+    /// The OID in dotted notation when the algorithm is unknown.
+    pub fn signature_algorithm(&self) -> &str {
+        &self.signature_algorithm
+    }
+
+    /// Return a reference to the name of the algorithm of the public key (`RSA`, `EC`, `DSA`)
+    pub fn public_key_algorithm(&self) -> &str {
+        &self.public_key_algorithm
+    }
+
+    /// Get the SHA-256 fingerprint of the certificate in hexadecimal
+    pub fn sha256_fingerprint(&self) -> String {
+        der::to_hex(&Sha256::digest(&self.der))
+    }
+
+    /// Get the SHA-1 fingerprint of the certificate in hexadecimal
+    pub fn sha1_fingerprint(&self) -> String {
+        der::to_hex(&Sha1::digest(&self.der))
+    }
+}
+
+/// Signer of an APK, for one signature scheme
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApkSigner {
+    scheme: SignatureScheme,
+    /// Base name of the `.SF` file for the v1 scheme
+    name: Option<String>,
+    certificates: Vec<Certificate>,
+    signature_algorithms: Vec<u32>,
+    min_sdk_version: Option<u32>,
+    max_sdk_version: Option<u32>,
+    verified: Option<bool>,
+    errors: Vec<String>
+}
+
+impl ApkSigner {
+    fn new(scheme: SignatureScheme) -> Self {
+        Self {
+            scheme,
+            name: None,
+            certificates: Vec::new(),
+            signature_algorithms: Vec::new(),
+            min_sdk_version: None,
+            max_sdk_version: None,
+            verified: None,
+            errors: Vec::new()
+        }
+    }
+
+    /// Return the signature scheme
+    pub fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    /// Return a reference to the name of the signer, the base name of its
+    /// `.SF` file in `META-INF`
+    ///
+    /// Only v1 signers have a name.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Return a reference to the certificates, the signing certificate first
+    pub fn certificates(&self) -> &[Certificate] {
+        &self.certificates
+    }
+
+    /// Return a reference to the signing certificate
+    pub fn certificate(&self) -> Option<&Certificate> {
+        self.certificates.first()
+    }
+
+    /// Return a reference to the IDs of the signature algorithms of a v2 or v3 signer
+    ///
+    /// For instance `0x0103` for RSASSA-PKCS1-v1_5 with SHA2-256.
+    pub fn signature_algorithms(&self) -> &[u32] {
+        &self.signature_algorithms
+    }
+
+    /// Return the minimum SDK version of a v3 signer
+    pub fn min_sdk_version(&self) -> Option<u32> {
+        self.min_sdk_version
+    }
+
+    /// Return the maximum SDK version of a v3 signer
+    pub fn max_sdk_version(&self) -> Option<u32> {
+        self.max_sdk_version
+    }
+
+    /// Whether the digests of the signer match the archive
+    ///
+    /// `None` if the signer has no digest with a supported algorithm.
+    pub fn digests_verified(&self) -> Option<bool> {
+        self.verified
+    }
+
+    /// Return a reference to the problems found while checking the signer
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    fn fail(&mut self, error: String) {
+        self.verified = Some(false);
+        self.errors.push(error);
+    }
+}
+
+/// Section of a JAR manifest or signature file
+struct JarSection<'a> {
+    /// Raw bytes of the section, trailing empty line included
+    raw: &'a [u8],
+    attributes: Vec<(String, String)>
+}
+
+impl<'a> JarSection<'a> {
+    /// Split a manifest into its sections, the main section first
+    fn parse_all(data: &'a [u8]) -> Vec<Self> {
+        let mut sections = Vec::new();
+        let mut start = 0;
+        let mut pos = 0;
+        let mut lines: Vec<String> = Vec::new();
+
+        while pos < data.len() {
+            let end = data[pos..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(data.len(), |idx| pos + idx + 1);
+            let line = String::from_utf8_lossy(&data[pos..end]);
+            let line = line.trim_end_matches(['\r', '\n']);
+            pos = end;
+
+            if line.is_empty() {
+                sections.push(Self::new(&data[start..pos], &lines));
+                lines.clear();
+                start = pos;
+            } else if let (Some(rest), Some(last)) = (line.strip_prefix(' '), lines.last_mut()) {
+                last.push_str(rest);
+            } else {
+                lines.push(line.to_owned());
+            }
+        }
+
+        if start < data.len() {
+            sections.push(Self::new(&data[start..], &lines));
+        }
+
+        sections
+    }
+
+    fn new(raw: &'a [u8], lines: &[String]) -> Self {
+        let attributes = lines.iter()
+            .filter_map(|line| line.split_once(": "))
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+
+        Self { raw, attributes }
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == "Name")
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Digests with a supported algorithm whose attribute ends with `suffix`
+    fn digests(&self, suffix: &str) -> Vec<(DigestAlgorithm, Vec<u8>)> {
+        self.attributes
+            .iter()
+            .filter_map(|(key, value)| {
+                let algorithm = DigestAlgorithm::from_jar_name(key.strip_suffix(suffix)?)?;
+                Some((algorithm, base64_decode(value)?))
+            })
+            .collect()
+    }
+}
+
+/// Decode standard base64
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for byte in text.trim().bytes().take_while(|&byte| byte != b'=') {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None
+        };
+
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            data.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(data)
+}
+
+/// Read a value prefixed by its `u32` length
+fn read_prefixed<'a>(reader: &mut Reader<'a>) -> Option<&'a [u8]> {
+    let len = reader.read_u32()? as usize;
+    reader.read_bytes(len)
+}
+
+/// Read a sequence of values prefixed by their `u32` length
+fn read_sequence(data: &[u8]) -> Option<Vec<&[u8]>> {
+    let mut reader = Reader::new(data, 0);
+    let mut items = Vec::new();
+
+    while reader.pos() < data.len() {
+        items.push(read_prefixed(&mut reader)?);
+    }
+
+    Some(items)
+}
+
+/// Signatures of an APK
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApkSignatures {
+    signers: Vec<ApkSigner>,
+    block_ids: Vec<u32>
+}
+
+impl ApkSignatures {
+    /// Parse the signatures of an archive and check their digests
+    pub(crate) fn parse(archive: &ZipArchive) -> Self {
+        let mut signatures = Self::default();
+
+        signatures.parse_jar_signatures(archive);
+
+        if let Some((block_start, pairs)) = Self::find_signing_block(archive) {
+            let mut cache = HashMap::new();
+
+            for (id, value) in pairs {
+                signatures.block_ids.push(id);
+
+                let scheme = match id {
+                    V2_BLOCK_ID => SignatureScheme::V2,
+                    V3_BLOCK_ID => SignatureScheme::V3,
+                    V31_BLOCK_ID => SignatureScheme::V31,
+                    _ => continue
+                };
+
+                let signers = read_prefixed(&mut Reader::new(value, 0))
+                    .and_then(read_sequence)
+                    .unwrap_or_default();
+
+                for data in signers {
+                    let mut signer = ApkSigner::new(scheme);
+                    match Self::read_block_signer(&mut signer, data) {
+                        Some(digests) => Self::verify_block_digests(archive, block_start, &mut signer, &digests, &mut cache),
+                        None => signer.fail(String::from("malformed signer"))
+                    }
+                    signatures.signers.push(signer);
+                }
+            }
+        }
+
+        signatures
+    }
+
+    /// Find the APK Signing Block, right before the central directory
+    ///
+    /// Returns the offset of the block and its ID-value pairs.
+    fn find_signing_block(archive: &ZipArchive) -> Option<(usize, BlockPairs<'_>)> {
+        let data = archive.data();
+        let footer = archive.central_directory_offset().checked_sub(24)?;
+
+        let mut reader = Reader::new(data, footer);
+        let size = usize::try_from(reader.read_u64()?).ok()?;
+        if reader.read_bytes(16)? != SIGNING_BLOCK_MAGIC {
+            return None;
+        }
+
+        let block_start = archive.central_directory_offset().checked_sub(size.checked_add(8)?)?;
+        let mut reader = Reader::new(data, block_start);
+        if reader.read_u64()? != size as u64 {
+            return None;
+        }
+
+        let mut pairs = Vec::new();
+        while reader.pos() < footer {
+            let len = usize::try_from(reader.read_u64()?).ok()?;
+            let mut pair = Reader::new(reader.read_bytes(len)?, 0);
+            pairs.push((pair.read_u32()?, pair.read_bytes(len.checked_sub(4)?)?));
+        }
+
+        Some((block_start, pairs))
+    }
+
+    /// Parse a signer of a v2, v3 or v3.1 block
+    ///
+    /// Returns the content digests of the signed data with their algorithm.
+    fn read_block_signer<'a>(signer: &mut ApkSigner, data: &'a [u8]) -> Option<Vec<(u32, &'a [u8])>> {
+        let mut reader = Reader::new(data, 0);
+        let signed_data = read_prefixed(&mut reader)?;
+        if signer.scheme != SignatureScheme::V2 {
+            signer.min_sdk_version = Some(reader.read_u32()?);
+            signer.max_sdk_version = Some(reader.read_u32()?);
+        }
+
+        for signature in read_sequence(read_prefixed(&mut reader)?)? {
+            signer.signature_algorithms.push(Reader::new(signature, 0).read_u32()?);
+        }
+
+        let mut reader = Reader::new(signed_data, 0);
+        let digests = read_prefixed(&mut reader)?;
+
+        for certificate in read_sequence(read_prefixed(&mut reader)?)? {
+            match Certificate::parse(certificate) {
+                Some(certificate) => signer.certificates.push(certificate),
+                None => signer.fail(String::from("malformed certificate"))
+            }
+        }
+
+        read_sequence(digests)?
+            .into_iter()
+            .map(|digest| {
+                let mut reader = Reader::new(digest, 0);
+                Some((reader.read_u32()?, read_prefixed(&mut reader)?))
+            })
+            .collect()
+    }
+
+    /// Check the content digests of a v2 or v3 signer
+    fn verify_block_digests(
+        archive: &ZipArchive,
+        block_start: usize,
+        signer: &mut ApkSigner,
+        digests: &[(u32, &[u8])],
+        cache: &mut HashMap<DigestAlgorithm, Option<Vec<u8>>>
+    ) {
+        for &(algorithm, expected) in digests {
+            let Some(digest_algorithm) = DigestAlgorithm::from_signature_algorithm(algorithm) else {
+                signer.errors.push(format!("unsupported digest algorithm 0x{algorithm:04x}"));
+                continue;
+            };
+
+            let actual = cache.entry(digest_algorithm)
+                .or_insert_with(|| Self::content_digest(archive, block_start, digest_algorithm));
+
+            match actual {
+                Some(actual) if actual.as_slice() == expected => {
+                    signer.verified.get_or_insert(true);
+                },
+                Some(_) => signer.fail(format!("content digest 0x{algorithm:04x} does not match")),
+                None => signer.fail(String::from("signed content is out of the archive"))
+            }
+        }
+    }
+
+    /// Compute the digest of the contents signed by a v2 or v3 signer:
+    /// the entries, the central directory and the EOCD
+    ///
+    /// Returns `None` if the sections overlap or are out of the archive.
+    fn content_digest(archive: &ZipArchive, block_start: usize, algorithm: DigestAlgorithm) -> Option<Vec<u8>> {
+        let data = archive.data();
+        let mut eocd = data.get(archive.eocd_offset()..)?.to_vec();
+        // The central directory offset of the EOCD points to the signing block
+        eocd.get_mut(16..20)?.copy_from_slice(&u32::try_from(block_start).ok()?.to_le_bytes());
+
+        Some(algorithm.chunked_digest(&[
+            data.get(..block_start)?,
+            data.get(archive.central_directory_offset()..archive.eocd_offset())?,
+            &eocd
+        ]))
+    }
+
+    /// Parse the JAR signatures of `META-INF` and check the manifest digests
+    fn parse_jar_signatures(&mut self, archive: &ZipArchive) {
+        let signature_files = archive.entries()
+            .iter()
+            .map(|entry| entry.name())
+            .filter(|name| name.starts_with("META-INF/") && name.ends_with(".SF") && name.matches('/').count() == 1)
+            .collect::<Vec<&str>>();
+
+        if signature_files.is_empty() {
+            return;
+        }
+
+        let manifest = archive.read_by_name("META-INF/MANIFEST.MF");
+        let manifest_sections = manifest.as_deref()
+            .map(JarSection::parse_all)
+            .unwrap_or_default();
+
+        // Entry digests are shared by all the signers
+        let mut entry_errors = Vec::new();
+        if manifest.is_none() {
+            entry_errors.push(String::from("missing META-INF/MANIFEST.MF"));
+        }
+
+        for section in manifest_sections.iter().skip(1) {
+            let Some(name) = section.name() else { continue };
+
+            let Some(data) = archive.read_by_name(name) else {
+                entry_errors.push(format!("{name} is signed but missing"));
+                continue;
+            };
+
+            for (algorithm, expected) in section.digests("-Digest") {
+                if algorithm.digest(&data) != expected {
+                    entry_errors.push(format!("{name} does not match its digest"));
+                }
+            }
+        }
+
+        for entry in archive.entries() {
+            let name = entry.name();
+            if !entry.is_dir()
+                && !name.starts_with("META-INF/")
+                && !manifest_sections.iter().any(|section| section.name() == Some(name))
+            {
+                entry_errors.push(format!("{name} is not signed"));
+            }
+        }
+
+        for signature_file in signature_files {
+            let stem = &signature_file[..signature_file.len() - 3];
+            let mut signer = ApkSigner::new(SignatureScheme::V1);
+            signer.name = Some(stem["META-INF/".len()..].to_owned());
+
+            let block = ["RSA", "DSA", "EC"]
+                .iter()
+                .find_map(|extension| archive.read_by_name(&format!("{stem}.{extension}")));
+            match block {
+                Some(block) => signer.certificates = Self::pkcs7_certificates(&block),
+                None => signer.fail(format!("missing signature block for {signature_file}"))
+            }
+
+            match (&manifest, archive.read_by_name(signature_file)) {
+                (Some(manifest), Some(data)) => {
+                    let sections = JarSection::parse_all(&data);
+                    Self::verify_signature_file(&mut signer, manifest, &manifest_sections, &sections);
+                },
+                (_, None) => signer.fail(format!("cannot read {signature_file}")),
+                _ => ()
+            }
+
+            for error in &entry_errors {
+                signer.fail(error.clone());
+            }
+            if signer.errors.is_empty() {
+                signer.verified = Some(true);
+            }
+
+            self.signers.push(signer);
+        }
+    }
+
+    /// Check the digests of a `.SF` file against the manifest
+    fn verify_signature_file(
+        signer: &mut ApkSigner,
+        manifest: &[u8],
+        manifest_sections: &[JarSection],
+        sections: &[JarSection]
+    ) {
+        let Some(main) = sections.first() else {
+            signer.fail(String::from("empty signature file"));
+            return;
+        };
+
+        // The digest of the whole manifest is enough when it matches
+        let whole = main.digests("-Digest-Manifest");
+        if !whole.is_empty() && whole.iter().all(|(algorithm, expected)| &algorithm.digest(manifest) == expected) {
+            return;
+        }
+
+        for section in sections.iter().skip(1) {
+            let Some(name) = section.name() else { continue };
+
+            let Some(manifest_section) = manifest_sections.iter().find(|section| section.name() == Some(name)) else {
+                signer.fail(format!("{name} is missing from the manifest"));
+                continue;
+            };
+
+            for (algorithm, expected) in section.digests("-Digest") {
+                if algorithm.digest(manifest_section.raw) != expected {
+                    signer.fail(format!("manifest section of {name} does not match its digest"));
+                }
+            }
+        }
+    }
+
+    /// Extract the certificates of a PKCS #7 `SignedData`
+    fn pkcs7_certificates(data: &[u8]) -> Vec<Certificate> {
+        let Some((content_info, _)) = Tlv::expect(data, TAG_SEQUENCE) else {
+            return Vec::new();
+        };
+
+        content_info.children()
+            .iter()
+            .filter(|tlv| tlv.tag == 0xa0)
+            .flat_map(|explicit| explicit.children())
+            .filter(|signed_data| signed_data.tag == TAG_SEQUENCE)
+            .flat_map(|signed_data| signed_data.children())
+            .filter(|field| field.tag == 0xa0)
+            .flat_map(|certificates| certificates.children())
+            .filter_map(|certificate| Certificate::parse(certificate.raw))
+            .collect()
+    }
+
+    /// Return a reference to the signers, v1 first
+    pub fn signers(&self) -> &[ApkSigner] {
+        &self.signers
+    }
+
+    /// Get the signers of a scheme
+    pub fn signers_of_scheme(&self, scheme: SignatureScheme) -> Vec<&ApkSigner> {
+        self.signers
+            .iter()
+            .filter(|signer| signer.scheme == scheme)
+            .collect()
+    }
+
+    /// Get the schemes the APK is signed with
+    pub fn schemes(&self) -> Vec<SignatureScheme> {
+        let mut schemes = Vec::new();
+        for signer in &self.signers {
+            if !schemes.contains(&signer.scheme) {
+                schemes.push(signer.scheme);
+            }
+        }
+        schemes
+    }
+
+    /// Whether the APK is signed with a scheme
+    pub fn has_scheme(&self, scheme: SignatureScheme) -> bool {
+        self.signers
+            .iter()
+            .any(|signer| signer.scheme == scheme)
+    }
+
+    /// Get the distinct certificates of all the signers
+    pub fn certificates(&self) -> Vec<&Certificate> {
+        let mut certificates: Vec<&Certificate> = Vec::new();
+        for certificate in self.signers.iter().flat_map(|signer| &signer.certificates) {
+            if !certificates.iter().any(|known| known.der == certificate.der) {
+                certificates.push(certificate);
+            }
+        }
+        certificates
+    }
+
+    /// Return a reference to the IDs of all the pairs of the APK Signing Block
+    ///
+    /// Besides the signature schemes, the block holds padding
+    /// (`0x42726577`), source stamps or Play Store metadata.
+    pub fn signing_block_ids(&self) -> &[u32] {
+        &self.block_ids
+    }
+
+    /// Whether the APK is signed and the digests of every signer match
+    pub fn is_verified(&self) -> bool {
+        !self.signers.is_empty() && self.signers
+            .iter()
+            .all(|signer| signer.verified == Some(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    #[test]
+    fn test_signatures() {
+        let archive = ZipArchive::from_path(Path::new("test_files/test_signed.apk")).unwrap();
+        let signatures = ApkSignatures::parse(&archive);
+
+        assert_eq!(signatures.schemes(), vec![SignatureScheme::V1, SignatureScheme::V2, SignatureScheme::V3]);
+        assert_eq!(signatures.signing_block_ids(), &[V2_BLOCK_ID, V3_BLOCK_ID]);
+        assert!(signatures.is_verified());
+
+        let v1 = signatures.signers_of_scheme(SignatureScheme::V1)[0];
+        assert_eq!(v1.name(), Some("TEST"));
+        assert!(v1.errors().is_empty());
+
+        let v3 = signatures.signers_of_scheme(SignatureScheme::V3)[0];
+        assert_eq!(v3.signature_algorithms(), &[0x0103]);
+        assert_eq!(v3.min_sdk_version(), Some(24));
+        assert_eq!(v3.max_sdk_version(), Some(0x7fffffff));
+
+        let certificates = signatures.certificates();
+        assert_eq!(certificates.len(), 1);
+
+        let certificate = certificates[0];
+        assert_eq!(certificate.subject(), "CN=Shuriken Test, OU=Analysis, O=Example, L=Paris, C=FR");
+        assert!(certificate.is_self_signed());
+        assert_eq!(certificate.serial_number(), "0aafc0139b55ab88");
+        assert_eq!(certificate.not_before(), "2024-01-01 00:00:00");
+        assert_eq!(certificate.not_after(), "2033-12-29 00:00:00");
+        assert_eq!(certificate.signature_algorithm(), "SHA256withRSA");
+        assert_eq!(certificate.public_key_algorithm(), "RSA");
+        assert_eq!(certificate.sha256_fingerprint(), "3c9034f7174357337b8d18dc941035be926ee8d8fc21a1e92800f9a45dd96cf2");
+
+        // Any change to the archive content breaks the v2 and v3 digests
+        let mut data = archive.data().to_vec();
+        let entry = archive.entry("classes.dex").unwrap();
+        data[entry.data_offset().unwrap() as usize + 8] ^= 0xff;
+
+        let tampered = ApkSignatures::parse(&ZipArchive::from_bytes(data).unwrap());
+        assert!(!tampered.is_verified());
+        assert_eq!(tampered.signers_of_scheme(SignatureScheme::V2)[0].digests_verified(), Some(false));
+
+        // Signed sections out of the archive are reported, not read
+        let too_far = archive.data().len() + 1;
+        assert!(ApkSignatures::content_digest(&archive, too_far, DigestAlgorithm::Sha256).is_none());
+
+        let unsigned = ZipArchive::from_path(Path::new("test_files/test_resources.apk")).unwrap();
+        assert!(ApkSignatures::parse(&unsigned).signers().is_empty());
+    }
+
+    #[test]
+    fn test_jar_manifest() {
+        let sections = JarSection::parse_all(b"Manifest-Version: 1.0\r\n\r\nName: a/very/long/na\r\n me.txt\r\nSHA1-Digest: qUqP5cyxm6YcTAhz05Hph5gvu9M=\r\n\r\n");
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[1].name(), Some("a/very/long/name.txt"));
+        assert_eq!(sections[1].digests("-Digest"), vec![(DigestAlgorithm::Sha1, DigestAlgorithm::Sha1.digest(b"test"))]);
+        assert!(sections[1].raw.starts_with(b"Name:"));
+    }
+}
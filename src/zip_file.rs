@@ -0,0 +1,374 @@
+//! ZIP archive entries
+//!
+//! Shuriken only gives access to the DEX files of an APK. This module reads
+//! the central directory of the archive so the other entries (manifest,
+//! resources, native libraries, assets, ...) can be listed and extracted as
+//! well, see [`ApkContext::get_entries_from_apk`](crate::ApkContext::get_entries_from_apk).
+//!
+//! See the [`APPNOTE`] for the format details.
+//!
+//! [`APPNOTE`]: https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::dex_file::Reader;
+
+/// Signature of the end of central directory record
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+/// Signature of a central directory file header
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+/// Signature of a local file header
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+/// Signature of the ZIP64 end of central directory record
+const ZIP64_EOCD_SIGNATURE: u32 = 0x0606_4b50;
+/// Signature of the ZIP64 end of central directory locator
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+
+/// Compression method of stored entries
+pub(crate) const METHOD_STORED: u16 = 0;
+/// Compression method of deflated entries
+pub(crate) const METHOD_DEFLATED: u16 = 8;
+
+/// Entry of the central directory of an archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZipEntry {
+    /// Name of the entry, with its path in the archive
+    pub(crate) name: String,
+    /// Compression method
+    pub(crate) method: u16,
+    /// General purpose flags
+    pub(crate) flags: u16,
+    /// CRC-32 of the uncompressed data, as stored in the archive
+    pub(crate) crc32: u32,
+    pub(crate) compressed_size: u64,
+    pub(crate) uncompressed_size: u64,
+    /// Offset of the local file header
+    pub(crate) local_header_offset: u64,
+    /// Offset of the entry data, `None` if the local header is invalid
+    pub(crate) data_offset: Option<u64>
+}
+
+impl ZipEntry {
+    /// Flag of encrypted entries
+    const FLAG_ENCRYPTED: u16 = 0x0001;
+
+    /// Return a reference to the name of the entry, with its path in the archive
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the compression method
+    ///
+    /// `0` for stored entries and `8` for deflated entries. Android reads
+    /// the entries with any other value as stored.
+    pub fn compression_method(&self) -> u16 {
+        self.method
+    }
+
+    /// Whether the entry data is deflated
+    pub fn is_compressed(&self) -> bool {
+        self.method == METHOD_DEFLATED
+    }
+
+    /// Whether the encryption flag is set
+    ///
+    /// Android ignores this flag, malware sets it to break other tools.
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & Self::FLAG_ENCRYPTED != 0
+    }
+
+    /// Return the general purpose flags
+    pub fn flags(&self) -> u16 {
+        self.flags
+    }
+
+    /// Return the CRC-32 of the uncompressed data, as stored in the archive
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// Return the size of the data in the archive
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    /// Return the size of the uncompressed data
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    /// Return the offset of the local file header in the archive
+    pub fn local_header_offset(&self) -> u64 {
+        self.local_header_offset
+    }
+
+    /// Return the offset of the entry data in the archive
+    pub fn data_offset(&self) -> Option<u64> {
+        self.data_offset
+    }
+
+    /// Whether the entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// In-memory copy of a ZIP archive
+pub(crate) struct ZipArchive {
+    data: Vec<u8>,
+    entries: Vec<ZipEntry>,
+    /// Offset of the central directory
+    central_directory_offset: usize,
+    /// Offset of the end of central directory record
+    eocd_offset: usize
+}
+
+impl fmt::Debug for ZipArchive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ZipArchive")
+            .field("size", &self.data.len())
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+impl ZipArchive {
+    /// Maximum size of the archive comment, bounding the EOCD search
+    const MAX_COMMENT_SIZE: usize = 0xffff;
+    /// Size of an EOCD record without the comment
+    const EOCD_SIZE: usize = 22;
+    /// Maximum size of an inflated entry, whatever its declared size
+    pub(crate) const MAX_ENTRY_SIZE: usize = 512 * 1024 * 1024;
+
+    /// Parse the central directory of an archive
+    pub(crate) fn from_bytes(data: Vec<u8>) -> Option<Self> {
+        let eocd_offset = Self::find_eocd(&data)?;
+
+        let mut reader = Reader::new(&data, eocd_offset + 10);
+        let mut entry_count = reader.read_u16()? as u64;
+        let _size = reader.read_u32()?;
+        let mut central_directory_offset = reader.read_u32()? as u64;
+
+        // ZIP64 archives store the real values in another record
+        if eocd_offset >= 20 {
+            let mut locator = Reader::new(&data, eocd_offset - 20);
+            if locator.read_u32()? == ZIP64_LOCATOR_SIGNATURE {
+                let _disk = locator.read_u32()?;
+                let zip64_offset = locator.read_u64()? as usize;
+
+                let mut record = Reader::new(&data, zip64_offset);
+                if record.read_u32()? == ZIP64_EOCD_SIGNATURE {
+                    let mut record = Reader::new(&data, zip64_offset + 32);
+                    entry_count = record.read_u64()?;
+                    let _size = record.read_u64()?;
+                    central_directory_offset = record.read_u64()?;
+                }
+            }
+        }
+
+        let central_directory_offset = usize::try_from(central_directory_offset).ok()?;
+        let mut reader = Reader::new(&data, central_directory_offset);
+        let mut entries = Vec::new();
+
+        for _ in 0..entry_count {
+            if reader.read_u32()? != CENTRAL_HEADER_SIGNATURE {
+                break;
+            }
+
+            let _version_made_by = reader.read_u16()?;
+            let _version_needed = reader.read_u16()?;
+            let flags = reader.read_u16()?;
+            let method = reader.read_u16()?;
+            let _time = reader.read_u16()?;
+            let _date = reader.read_u16()?;
+            let crc32 = reader.read_u32()?;
+            let mut compressed_size = reader.read_u32()? as u64;
+            let mut uncompressed_size = reader.read_u32()? as u64;
+            let name_len = reader.read_u16()? as usize;
+            let extra_len = reader.read_u16()? as usize;
+            let comment_len = reader.read_u16()? as usize;
+            let _disk = reader.read_u16()?;
+            let _internal_attributes = reader.read_u16()?;
+            let _external_attributes = reader.read_u32()?;
+            let mut local_header_offset = reader.read_u32()? as u64;
+
+            let name = String::from_utf8_lossy(reader.read_bytes(name_len)?).into_owned();
+            let extra = reader.read_bytes(extra_len)?;
+            let _comment = reader.read_bytes(comment_len)?;
+
+            // Values set to 0xffffffff are in the ZIP64 extra field, in order
+            let mut extra_reader = Reader::new(extra, 0);
+            while let (Some(tag), Some(len)) = (extra_reader.read_u16(), extra_reader.read_u16()) {
+                let Some(field) = extra_reader.read_bytes(len as usize) else { break };
+                if tag != 0x0001 {
+                    continue;
+                }
+
+                let mut field = Reader::new(field, 0);
+                for value in [&mut uncompressed_size, &mut compressed_size, &mut local_header_offset] {
+                    if *value == 0xffff_ffff {
+                        *value = field.read_u64().unwrap_or(*value);
+                    }
+                }
+            }
+
+            let data_offset = Self::find_data_offset(&data, local_header_offset);
+
+            entries.push(ZipEntry {
+                name,
+                method,
+                flags,
+                crc32,
+                compressed_size,
+                uncompressed_size,
+                local_header_offset,
+                data_offset
+            });
+        }
+
+        Some(Self { data, entries, central_directory_offset, eocd_offset })
+    }
+
+    /// Offset of the data of an entry, right after its local header
+    fn find_data_offset(data: &[u8], local_header_offset: u64) -> Option<u64> {
+        let offset = usize::try_from(local_header_offset).ok()?;
+        let mut reader = Reader::new(data, offset);
+
+        if reader.read_u32()? != LOCAL_HEADER_SIGNATURE {
+            return None;
+        }
+
+        let mut reader = Reader::new(data, offset + 26);
+        let name_len = reader.read_u16()? as u64;
+        let extra_len = reader.read_u16()? as u64;
+
+        Some(local_header_offset + 30 + name_len + extra_len)
+    }
+
+    /// Read an archive from disk
+    pub(crate) fn from_path(path: &Path) -> Option<Self> {
+        Self::from_bytes(fs::read(path).ok()?)
+    }
+
+    /// Find the end of central directory record, starting from the end
+    fn find_eocd(data: &[u8]) -> Option<usize> {
+        let last = data.len().checked_sub(Self::EOCD_SIZE)?;
+        let first = last.saturating_sub(Self::MAX_COMMENT_SIZE);
+
+        (first..=last)
+            .rev()
+            .find(|&offset| Reader::new(data, offset).read_u32() == Some(EOCD_SIGNATURE))
+    }
+
+    /// Raw bytes of the archive
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Entries of the central directory
+    pub(crate) fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    /// Offset of the central directory
+    pub(crate) fn central_directory_offset(&self) -> usize {
+        self.central_directory_offset
+    }
+
+    /// Offset of the end of central directory record
+    pub(crate) fn eocd_offset(&self) -> usize {
+        self.eocd_offset
+    }
+
+    /// Find an entry given its name
+    pub(crate) fn entry(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+    }
+
+    /// Get the raw, possibly compressed, data of an entry
+    pub(crate) fn raw_data(&self, entry: &ZipEntry) -> Option<&[u8]> {
+        let start = usize::try_from(entry.data_offset?).ok()?;
+        let len = usize::try_from(entry.compressed_size).ok()?;
+
+        self.data.get(start..start.checked_add(len)?)
+    }
+
+    /// Get the uncompressed data of an entry
+    ///
+    /// Like Android, entries with an unknown compression method are read as
+    /// stored, a trick used by malware to break other tools. Deflated entries
+    /// inflating to more than their declared size or `MAX_ENTRY_SIZE` are
+    /// rejected.
+    pub(crate) fn read(&self, entry: &ZipEntry) -> Option<Vec<u8>> {
+        let raw = self.raw_data(entry)?;
+
+        match entry.method {
+            METHOD_DEFLATED => {
+                let limit = usize::try_from(entry.uncompressed_size)
+                    .unwrap_or(usize::MAX)
+                    .min(Self::MAX_ENTRY_SIZE);
+                miniz_oxide::inflate::decompress_to_vec_with_limit(raw, limit).ok()
+            },
+            _ => Some(raw.to_vec())
+        }
+    }
+
+    /// Get the uncompressed data of an entry, if it is at most `limit` bytes
+    ///
+    /// Deflated entries declaring a larger size are not inflated at all.
+    pub(crate) fn read_with_limit(&self, entry: &ZipEntry, limit: usize) -> Option<Vec<u8>> {
+        if entry.method == METHOD_DEFLATED && usize::try_from(entry.uncompressed_size).ok()? > limit {
+            return None;
+        }
+
+        self.read(entry).filter(|data| data.len() <= limit)
+    }
+
+    /// Get the uncompressed data of an entry given its name
+    pub(crate) fn read_by_name(&self, name: &str) -> Option<Vec<u8>> {
+        self.read(self.entry(name)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_entries() {
+        let archive = ZipArchive::from_path(Path::new("test_files/test_zip.apk")).unwrap();
+
+        let names = archive.entries()
+            .iter()
+            .map(|entry| entry.name())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec![
+            "test_zip/",
+            "test_zip/DexParserTest.dex",
+            "test_zip/_int.dex",
+            "test_zip/_loop.dex"
+        ]);
+
+        let entry = archive.entry("test_zip/DexParserTest.dex").unwrap();
+        assert!(entry.is_compressed());
+        assert!(!entry.is_encrypted());
+        assert_eq!(entry.uncompressed_size(), 1624);
+        assert_eq!(entry.crc32(), 0xa95b31c5);
+        assert!(archive.entries()[0].is_dir());
+
+        let data = archive.read(entry).unwrap();
+        assert_eq!(data, fs::read("test_files/DexParserTest.dex").unwrap());
+
+        // Entries inflating past their declared size are rejected
+        let mut lying = entry.clone();
+        lying.uncompressed_size = 1000;
+        assert!(archive.read(&lying).is_none());
+
+        assert!(archive.read_by_name("AndroidManifest.xml").is_none());
+        assert!(ZipArchive::from_bytes(b"not a zip".to_vec()).is_none());
+    }
+}